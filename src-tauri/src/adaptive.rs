@@ -0,0 +1,233 @@
+use std::collections::{HashMap, HashSet};
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+use rayon::prelude::*;
+
+use crate::messages::Message;
+use crate::{run_greedy_meshing, triangle_aabb_intersect, ConvertOptions, McCube};
+
+// ================= ADAPTIVE (OCTREE) VOXELIZATION =================
+//
+// Works in voxel units (model space × scale). The model is covered with root
+// cells 2^levels voxels wide; a cell whose triangles all face the same way
+// (within FLAT_ANGLE_DEGREES) along one axis becomes one leaf, otherwise it is
+// split into eight children down to single voxels. A flat leaf keeps only the
+// voxels its triangles reach and is only kept when that is one voxel thick
+// along its normal, so a wall stays one voxel thick instead of filling the
+// cell; sloped planes refine down to voxels tested one by one. Leaves of each size and shape are greedy-meshed on their own grid, only
+// along the axes they fill, so a flat wall becomes a few large cubes while
+// curved detail keeps full resolution.
+
+const FLAT_ANGLE_DEGREES: f32 = 10.0;
+pub const MAX_LEVELS: u32 = 6;
+
+struct Triangle {
+    v: [Vec3; 3],
+    normal: Vec3,
+}
+
+/// `epsilon` widens the cell by that share of a voxel, as `sat_epsilon` does.
+fn intersects(t: &Triangle, origin: IVec3, size: i32, epsilon: f32) -> bool {
+    let half = size as f32 / 2.0;
+    let center = origin.as_vec3() + Vec3::splat(half);
+    triangle_aabb_intersect(t.v[0], t.v[1], t.v[2], center, half + epsilon)
+}
+
+/// The axis `candidates` all face along, when they are flat and that axis is
+/// within FLAT_ANGLE_DEGREES of their mean normal.
+fn flat_axis(triangles: &[Triangle], candidates: &[usize]) -> Option<usize> {
+    let mean = candidates.iter()
+        .map(|&i| triangles[i].normal)
+        .sum::<Vec3>()
+        .normalize_or_zero();
+    if mean == Vec3::ZERO { return None; }
+
+    let min_dot = FLAT_ANGLE_DEGREES.to_radians().cos();
+    if !candidates.iter().all(|&i| triangles[i].normal.dot(mean) >= min_dot) {
+        return None;
+    }
+    let a = mean.abs();
+    let axis = if a.x >= a.y && a.x >= a.z { 0 } else if a.y >= a.z { 1 } else { 2 };
+    (a[axis] >= min_dot).then_some(axis)
+}
+
+/// An octree cell kept whole, and the voxels of it its triangles reach.
+struct Leaf {
+    level: u32,
+    /// Position on the grid of cells of this level.
+    cell: IVec3,
+    /// Voxel offset of the reached part inside the cell.
+    min: IVec3,
+    /// Size of the reached part in voxels.
+    size: IVec3,
+}
+
+/// Voxels of the cell at `origin` that `candidates` reach, as an offset and size.
+fn reached(triangles: &[Triangle], candidates: &[usize], origin: IVec3, size: i32) -> (IVec3, IVec3) {
+    let (low, high) = (origin.as_vec3(), (origin + IVec3::splat(size)).as_vec3());
+    let (min, max) = candidates.iter()
+        .map(|&i| &triangles[i].v)
+        .fold((high, low), |(min, max), v| {
+            (min.min(v[0].min(v[1]).min(v[2])), max.max(v[0].max(v[1]).max(v[2])))
+        });
+    let min = min.max(low).floor().as_ivec3();
+    // A triangle lying on a voxel boundary still takes the voxel above it
+    let max = max.min(high).ceil().as_ivec3().max(min + IVec3::ONE).min(origin + IVec3::splat(size));
+    let min = min.min(max - IVec3::ONE);
+    (min - origin, max - min)
+}
+
+/// Level, offset and size of a leaf, and its cell position on the axes it doesn't fill.
+type LeafShape = (u32, [i32; 3], [i32; 3], [Option<i32>; 3]);
+
+fn refine(
+    triangles: &[Triangle],
+    candidates: &[usize],
+    origin: IVec3,
+    level: u32,
+    epsilon: f32,
+    leaves: &mut Vec<Leaf>,
+) {
+    let size = 1 << level;
+    let cell = origin.div_euclid(IVec3::splat(size));
+    if level == 0 {
+        leaves.push(Leaf { level, cell, min: IVec3::ZERO, size: IVec3::ONE });
+        return;
+    }
+    if let Some(axis) = flat_axis(triangles, candidates) {
+        let (min, extent) = reached(triangles, candidates, origin, size);
+        // A plane at an angle to the axis would fill its whole bounding box
+        if extent[axis] == 1 {
+            leaves.push(Leaf { level, cell, min, size: extent });
+            return;
+        }
+    }
+
+    let half = size / 2;
+    for child in 0..8 {
+        let offset = IVec3::new(child & 1, (child >> 1) & 1, (child >> 2) & 1) * half;
+        let child_origin = origin + offset;
+        let hits: Vec<usize> = candidates.iter()
+            .copied()
+            .filter(|&i| intersects(&triangles[i], child_origin, half, epsilon))
+            .collect();
+
+        if !hits.is_empty() {
+            refine(triangles, &hits, child_origin, level - 1, epsilon, leaves);
+        }
+    }
+}
+
+/// Returns the mixed-size cubes and the number of voxels they cover.
+/// With `double_sided`, faces count as flat regardless of which way they point.
+pub fn voxelize_adaptive(
+    vertices: &[Vec3],
+    indices: &[u32],
+    scale: f32,
+    levels: u32,
+    double_sided: bool,
+    epsilon: f32,
+) -> (Vec<McCube>, usize) {
+    let levels = levels.min(MAX_LEVELS);
+    let epsilon = epsilon.max(-0.5);
+    let root_size = 1 << levels;
+
+    let triangles: Vec<Triangle> = indices.chunks(3)
+        .filter_map(|chunk| {
+            let v = [0, 1, 2].map(|k| vertices[chunk[k] as usize] * scale);
+            let area_normal = (v[1] - v[0]).cross(v[2] - v[0]);
+            // Degenerate triangles have no surface to voxelize
+            if area_normal.length_squared() == 0.0 { return None; }
+            let mut normal = area_normal.normalize();
+            // Point every normal into the same half-space along its main axis
+            let a = normal.abs();
+            let main = if a.x >= a.y && a.x >= a.z { normal.x } else if a.y >= a.z { normal.y } else { normal.z };
+            if double_sided && main < 0.0 {
+                normal = -normal;
+            }
+            Some(Triangle { v, normal })
+        })
+        .collect();
+
+    let roots = triangles.par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, t)| {
+            let min = (t.v[0].min(t.v[1]).min(t.v[2]) / root_size as f32).floor().as_ivec3();
+            let max = (t.v[0].max(t.v[1]).max(t.v[2]) / root_size as f32).floor().as_ivec3();
+
+            let mut cells = Vec::new();
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        let origin = IVec3::new(x, y, z) * root_size;
+                        if intersects(t, origin, root_size, epsilon) {
+                            cells.push((origin, i));
+                        }
+                    }
+                }
+            }
+            cells
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .fold(HashMap::<IVec3, Vec<usize>, RandomState>::default(), |mut roots, (origin, i)| {
+            roots.entry(origin).or_default().push(i);
+            roots
+        });
+
+    let leaves: Vec<Leaf> = roots.par_iter()
+        .flat_map_iter(|(&origin, candidates)| {
+            let mut leaves = Vec::new();
+            refine(&triangles, candidates, origin, levels, epsilon, &mut leaves);
+            leaves
+        })
+        .collect();
+
+    // Leaves group by level and shape; on axes a shape doesn't fill, the cell
+    // position joins the key too, so cubes only merge along the filled axes
+    let mut groups: HashMap<LeafShape, HashSet<IVec3, RandomState>, RandomState> = HashMap::default();
+    for leaf in &leaves {
+        let full = 1 << leaf.level;
+        let fixed = [0, 1, 2].map(|a| (leaf.size[a] != full).then_some(leaf.cell[a]));
+        groups.entry((leaf.level, leaf.min.to_array(), leaf.size.to_array(), fixed)).or_default().insert(leaf.cell);
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_unstable_by_key(|(shape, _)| *shape);
+
+    let mut cubes = Vec::new();
+    for ((level, min, extent, fixed), cells) in groups {
+        let size = (1 << level) as f32;
+        cubes.extend(run_greedy_meshing(&cells).into_iter().map(|cube| McCube {
+            origin: [0, 1, 2].map(|a| cube.origin[a] * size + min[a] as f32),
+            size: [0, 1, 2].map(|a| match fixed[a] {
+                Some(_) => extent[a] as f32,
+                None => cube.size[a] * size,
+            }),
+            ..cube
+        }));
+    }
+
+    let voxel_count = leaves.iter().map(|l| (l.size.x * l.size.y * l.size.z) as usize).sum();
+    (cubes, voxel_count)
+}
+
+/// Warning naming the options `adaptive_levels` leaves out, which only work
+/// on the uniform grid.
+pub fn skipped_warning(options: &ConvertOptions) -> Option<Message> {
+    if options.adaptive_levels == 0 {
+        return None;
+    }
+    let skipped: Vec<&str> = [
+        ("fill_interior", options.fill_interior),
+        ("weld_gaps", options.weld_gaps),
+        ("script", options.script.is_some()),
+        ("normal_offset", options.normal_offset > 0.0),
+        ("instance_parts", options.instance_parts),
+        ("fit_slopes", options.fit_slopes),
+    ]
+    .into_iter()
+    .filter_map(|(option, set)| set.then_some(option))
+    .collect();
+    (!skipped.is_empty()).then(|| Message::new("warning.adaptive_skipped").with("options", skipped.join(", ")))
+}
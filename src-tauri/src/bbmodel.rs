@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::base64;
+use crate::messages::{failed, Message};
+use crate::texture::Texture;
+use crate::{McBone, McCube, McFaceUv, McUv};
+
+// ================= BLOCKBENCH PROJECT (.BBMODEL) =================
+//
+// A Bedrock-format Blockbench project of the converted model, for tweaking it
+// by hand: bones become nested groups, cubes become elements and locators
+// locator elements, and the atlas is embedded so the project opens textured.
+// Blockbench mirrors Bedrock space in X, so element corners, pivots and
+// rotations are mirrored as its Bedrock importer does. Element ids are UUIDs
+// derived from the model and element, so converting again gives the same file.
+
+const FORMAT_VERSION: &str = "4.10";
+
+fn element_uuid(model: &str, part: &str) -> String {
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, format!("obj2mc:{}:{}", model, part).as_bytes()).to_string()
+}
+
+/// `-v` without negative zeros.
+fn negate(v: f32) -> f32 {
+    0.0 - v
+}
+
+/// Bedrock point in Blockbench space.
+fn mirror(p: [f32; 3]) -> [f32; 3] {
+    [negate(p[0]), p[1], p[2]]
+}
+
+/// Bedrock rotation in Blockbench space; Z turns the same way.
+fn mirror_rotation(r: [f32; 3]) -> [f32; 3] {
+    [negate(r[0]), negate(r[1]), r[2]]
+}
+
+fn face(uv: &Option<McFaceUv>) -> Value {
+    match uv {
+        Some(f) => json!({ "uv": [f.uv[0], f.uv[1], f.uv[0] + f.uv_size[0], f.uv[1] + f.uv_size[1]], "texture": 0 }),
+        None => json!({ "uv": [0, 0, 0, 0], "texture": null }),
+    }
+}
+
+fn element(cube: &McCube, name: &str, uuid: String) -> Value {
+    let [x, y, z] = cube.origin;
+    let [w, h, d] = cube.size;
+    let mut element = Map::new();
+    element.insert("name".to_string(), json!(name));
+    element.insert("type".to_string(), json!("cube"));
+    element.insert("uuid".to_string(), json!(uuid));
+    element.insert("from".to_string(), json!([negate(x + w), y, z]));
+    element.insert("to".to_string(), json!([negate(x), y + h, z + d]));
+    element.insert("origin".to_string(), json!(mirror(cube.pivot.unwrap_or(cube.origin))));
+    element.insert("rotation".to_string(), json!(mirror_rotation(cube.rotation.unwrap_or_default())));
+    element.insert("inflate".to_string(), json!(cube.inflate.unwrap_or(0.0)));
+    element.insert("autouv".to_string(), json!(0));
+    match &cube.uv {
+        McUv::Box(offset) => {
+            element.insert("box_uv".to_string(), json!(true));
+            element.insert("uv_offset".to_string(), json!(offset));
+        }
+        McUv::PerFace(faces) => {
+            element.insert("box_uv".to_string(), json!(false));
+            element.insert("faces".to_string(), json!({
+                "north": face(&faces.north),
+                "east": face(&faces.east),
+                "south": face(&faces.south),
+                "west": face(&faces.west),
+                "up": face(&faces.up),
+                "down": face(&faces.down),
+            }));
+        }
+    }
+    Value::Object(element)
+}
+
+/// Writes `bones` as a Blockbench project at `path`, textured with `texture`.
+pub fn write_bbmodel(bones: &[McBone], texture: &Texture, model_name: &str, path: &Path) -> Result<(), Message> {
+    if bones.iter().any(|b| b.poly_mesh.is_some()) {
+        return Err(Message::new("error.bbmodel_needs_cubes"));
+    }
+
+    let mut elements = Vec::new();
+    // Each bone's group, its children filled in below
+    let mut groups: Vec<Map<String, Value>> = Vec::new();
+    for bone in bones {
+        let mut children = Vec::new();
+        for (i, cube) in bone.cubes.iter().enumerate() {
+            let uuid = element_uuid(model_name, &format!("{}/{}", bone.name, i));
+            elements.push(element(cube, &bone.name, uuid.clone()));
+            children.push(json!(uuid));
+        }
+        for (name, position) in &bone.locators {
+            let uuid = element_uuid(model_name, &format!("{}/locator/{}", bone.name, name));
+            elements.push(json!({ "name": name, "type": "locator", "uuid": uuid, "position": mirror(*position) }));
+            children.push(json!(uuid));
+        }
+
+        let mut group = Map::new();
+        group.insert("name".to_string(), json!(bone.name));
+        group.insert("uuid".to_string(), json!(element_uuid(model_name, &bone.name)));
+        group.insert("origin".to_string(), json!(mirror(bone.pivot.map(|c| c as f32))));
+        group.insert("rotation".to_string(), json!([0, 0, 0]));
+        if let Some(binding) = &bone.binding {
+            group.insert("bedrock_binding".to_string(), json!(binding));
+        }
+        group.insert("export".to_string(), json!(true));
+        group.insert("isOpen".to_string(), json!(false));
+        group.insert("visibility".to_string(), json!(true));
+        group.insert("children".to_string(), json!(children));
+        groups.push(group);
+    }
+
+    // Children go into their parents deepest first; a missing parent leaves the group at the top
+    let index_of = |name: &str| bones.iter().position(|b| b.name == name);
+    let depth = |mut i: usize| {
+        let mut depth = 0;
+        while let Some(parent) = bones[i].parent.as_deref().and_then(index_of) {
+            depth += 1;
+            i = parent;
+            if depth > bones.len() {
+                break;
+            }
+        }
+        depth
+    };
+    let mut order: Vec<usize> = (0..bones.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(depth(i)));
+    let mut placed = vec![false; bones.len()];
+    for i in order {
+        let Some(parent) = bones[i].parent.as_deref().and_then(index_of).filter(|&p| p != i) else { continue };
+        let group = Value::Object(std::mem::take(&mut groups[i]));
+        if let Some(Value::Array(children)) = groups[parent].get_mut("children") {
+            children.push(group);
+        }
+        placed[i] = true;
+    }
+    let outliner: Vec<Value> = groups.into_iter().zip(placed)
+        .filter(|(_, placed)| !placed)
+        .map(|(group, _)| Value::Object(group))
+        .collect();
+
+    let box_uv = bones.iter().flat_map(|b| &b.cubes).all(|c| matches!(c.uv, McUv::Box(_)));
+    let project = json!({
+        "meta": { "format_version": FORMAT_VERSION, "model_format": "bedrock", "box_uv": box_uv },
+        "name": model_name,
+        "model_identifier": model_name,
+        "visible_box": [1, 1, 0],
+        "resolution": { "width": texture.width, "height": texture.height },
+        "elements": elements,
+        "outliner": outliner,
+        "textures": [{
+            "name": format!("{}.png", model_name),
+            "id": "0",
+            "uuid": element_uuid(model_name, "texture"),
+            "width": texture.width,
+            "height": texture.height,
+            "uv_width": texture.width,
+            "uv_height": texture.height,
+            "source": format!("data:image/png;base64,{}", base64::encode(&texture.to_png())),
+        }],
+    });
+    fs::write(path, serde_json::to_string_pretty(&project).unwrap()).map_err(failed("error.write_file"))
+}
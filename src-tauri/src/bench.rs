@@ -0,0 +1,102 @@
+use std::f32::consts::TAU;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::messages::Message;
+use crate::{voxelize_model, ConvertOptions, Scene};
+
+// ================= BENCHMARK =================
+//
+// Voxelizes and meshes a built-in reference mesh (a torus, curved everywhere
+// so greedy meshing can't shortcut it) at a few scales and reports throughput,
+// so machines and settings can be compared on the same work. Nothing is
+// written to disk.
+
+const SCALES: [f32; 3] = [16.0, 32.0, 64.0];
+const RINGS: usize = 64;
+const SIDES: usize = 32;
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkRun {
+    pub scale: f32,
+    pub triangles: usize,
+    pub voxel_count: usize,
+    pub cube_count: usize,
+    pub seconds: f64,
+    pub triangles_per_second: f64,
+    pub voxels_per_second: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    /// Worker threads used for voxelization.
+    pub threads: usize,
+    /// SIMD instruction sets the build was compiled for.
+    pub simd: Vec<&'static str>,
+    pub runs: Vec<BenchmarkRun>,
+}
+
+/// Torus around the Y axis, one block across (before scaling), resting on y = 0.
+fn reference_mesh() -> tobj::Model {
+    let (major, minor) = (0.35, 0.15);
+    let mut positions = Vec::with_capacity(RINGS * SIDES * 3);
+    for ring in 0..RINGS {
+        let a = ring as f32 / RINGS as f32 * TAU;
+        for side in 0..SIDES {
+            let b = side as f32 / SIDES as f32 * TAU;
+            let r = major + minor * b.cos();
+            positions.extend([r * a.cos(), minor + minor * b.sin(), r * a.sin()]);
+        }
+    }
+
+    let index = |ring: usize, side: usize| ((ring % RINGS) * SIDES + side % SIDES) as u32;
+    let mut indices = Vec::with_capacity(RINGS * SIDES * 6);
+    for ring in 0..RINGS {
+        for side in 0..SIDES {
+            let (a, b) = (index(ring, side), index(ring + 1, side));
+            let (c, d) = (index(ring + 1, side + 1), index(ring, side + 1));
+            indices.extend([a, d, c, a, c, b]);
+        }
+    }
+
+    tobj::Model {
+        mesh: tobj::Mesh { positions, indices, ..Default::default() },
+        name: "benchmark".to_string(),
+    }
+}
+
+fn simd_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(target_feature = "sse4.2") { features.push("sse4.2"); }
+    if cfg!(target_feature = "avx2") { features.push("avx2"); }
+    if cfg!(target_feature = "avx512f") { features.push("avx512f"); }
+    if cfg!(target_feature = "neon") { features.push("neon"); }
+    features
+}
+
+/// Runs the benchmark with `options` (format and output settings don't matter).
+pub fn run_benchmark(options: &ConvertOptions) -> Result<BenchmarkReport, Message> {
+    let scene = Scene::new(vec![reference_mesh()], vec![]);
+    let triangles = scene.face_count;
+
+    let runs = SCALES.iter()
+        .map(|&scale| {
+            let start = Instant::now();
+            let (_, stats) = voxelize_model(&scene, scale, options, &|_, _| {})?;
+            let seconds = start.elapsed().as_secs_f64();
+            Ok(BenchmarkRun {
+                scale,
+                triangles,
+                voxel_count: stats.voxel_count,
+                cube_count: stats.cube_count,
+                seconds,
+                triangles_per_second: triangles as f64 / seconds,
+                voxels_per_second: stats.voxel_count as f64 / seconds,
+            })
+        })
+        .collect::<Result<_, Message>>()?;
+
+    let threads = if options.threads > 0 { options.threads } else { rayon::current_num_threads() };
+    Ok(BenchmarkReport { threads, simd: simd_features(), runs })
+}
@@ -0,0 +1,133 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use obj2mc_app_lib::{bench, job, report, ConvertOptions};
+
+fn usage() -> ExitCode {
+    eprintln!(
+        "Usage: obj2mc-cli run <job.toml | job.json> [--report <report.json | report.csv>] [--dry-run] \
+         [--threads <count>] [--low-priority]"
+    );
+    eprintln!("       obj2mc-cli bench");
+    ExitCode::from(2)
+}
+
+fn benchmark() -> ExitCode {
+    let report = match bench::run_benchmark(&ConvertOptions::default()) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let simd = if report.simd.is_empty() { "none".to_string() } else { report.simd.join(", ") };
+    println!("{} threads, SIMD: {}", report.threads, simd);
+    for run in &report.runs {
+        println!(
+            "scale {:>3}: {:>8} voxels, {:>6} cubes in {:.3}s ({:.0} triangles/s, {:.0} voxels/s)",
+            run.scale, run.voxel_count, run.cube_count, run.seconds, run.triangles_per_second, run.voxels_per_second,
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if matches!(args.as_slice(), [command] if command == "bench") {
+        return benchmark();
+    }
+
+    let [command, job_path, flags @ ..] = args.as_slice() else { return usage() };
+    if command != "run" {
+        return usage();
+    }
+    let job_path = Path::new(job_path);
+
+    let mut report_path = None;
+    let mut dry_run = false;
+    let mut threads = None;
+    let mut low_priority = false;
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--report" => match flags.next() {
+                Some(path) => report_path = Some(Path::new(path)),
+                None => return usage(),
+            },
+            "--dry-run" => dry_run = true,
+            "--threads" => match flags.next().and_then(|n| n.parse().ok()) {
+                Some(count) => threads = Some(count),
+                None => return usage(),
+            },
+            "--low-priority" => low_priority = true,
+            _ => return usage(),
+        }
+    }
+
+    let mut entries = match job::load_job(job_path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Dry runs neither resume nor leave progress behind
+    for entry in &mut entries {
+        entry.options.dry_run |= dry_run;
+        entry.options.threads = threads.unwrap_or(entry.options.threads);
+        entry.options.low_priority |= low_priority;
+    }
+    let progress = job::progress_file(job_path);
+    let done = if dry_run { Vec::new() } else { job::load_progress(&progress, &entries) };
+    if !done.is_empty() {
+        println!(
+            "Resuming after {} of {} inputs (delete {} to start over)",
+            done.len(), entries.len(), progress.display(),
+        );
+    }
+
+    let results = match job::run_job(&entries, done, (!dry_run).then_some(progress.as_path())) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut failed = 0;
+
+    for (entry, job) in entries.iter().zip(&results) {
+        let result = &job.result;
+        if !result.success {
+            failed += 1;
+            eprintln!("{}: {}", entry.path, result.message);
+        } else if let Some(output) = &result.output_path {
+            let outputs: Vec<&str> = std::iter::once(output.as_str())
+                .chain(result.other_outputs.iter().map(String::as_str))
+                .collect();
+            println!("{}: {} ({})", entry.path, result.message, outputs.join(", "));
+        } else if let Some(size) = &result.estimated_size {
+            println!(
+                "{}: {} (geo.json {} bytes, texture {} bytes)",
+                entry.path, result.message, size.geometry_bytes, size.texture_bytes,
+            );
+        } else {
+            println!("{}: {}", entry.path, result.message);
+        }
+        for warning in &result.warnings {
+            eprintln!("{}: warning: {}", entry.path, warning);
+        }
+    }
+
+    println!("{} converted, {} failed", results.len() - failed, failed);
+
+    if let Some(report_path) = report_path {
+        if let Err(e) = report::write_report(report_path, &entries, &results) {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if failed > 0 { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glam::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::cem::number;
+use crate::messages::{failed, Message};
+use crate::texture::Texture;
+use crate::{pieces, resource_name, McBone, McCube, McFaceUv, McUv};
+
+// ================= JAVA BLOCK MODEL =================
+//
+// Java Edition `models/block/*.json`: every cube becomes an element in block
+// space, Bedrock's X mirrored as Blockbench does and the geometry origin at
+// the bottom center of the block. Box UVs are unwrapped into face UVs, given
+// in Java's 0-16 over the atlas, which is padded to a square. Elements turn
+// about one axis by -45 to 45 degrees in 22.5 degree steps, so rotations are
+// snapped to the nearest step about their largest axis.
+//
+// Elements have to stay within -16 to 32 on every axis. A model that doesn't
+// fit is, with `block_model_fit = "scale"`, shrunk to 48 units and centered
+// on the block; with "split", cut on the block grid into one model per block
+// it covers, listed with the block offsets in `index.json`. The files go in
+// `assets/minecraft/models/block/` of a resource pack, the texture in
+// `textures/block/`.
+
+const RANGE_MIN: f32 = -16.0;
+const RANGE_MAX: f32 = 32.0;
+const BLOCK: f32 = 16.0;
+const ANGLES: [f32; 5] = [-45.0, -22.5, 0.0, 22.5, 45.0];
+const AXES: [&str; 3] = ["x", "y", "z"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockModelFit {
+    /// Shrink the model until it fits the element range.
+    #[default]
+    Scale,
+    /// Cut the model into one block model per block it covers.
+    Split,
+}
+
+const FACE_NAMES: [&str; 6] = ["north", "east", "south", "west", "up", "down"];
+
+/// Axis each face lies across, and whether on the element's high side (in
+/// Java space, where Bedrock east is +X).
+const FACE_SIDES: [(usize, bool); 6] = [(2, false), (0, true), (2, true), (0, false), (1, true), (1, false)];
+
+/// Axes along which each face's u and v run, and whether they run towards
+/// the high side, as Java orients face textures (north's u runs towards -X).
+const FACE_AXES: [[(usize, bool); 2]; 6] = [
+    [(0, false), (1, false)],
+    [(2, false), (1, false)],
+    [(0, true), (1, false)],
+    [(2, true), (1, false)],
+    [(0, true), (2, true)],
+    [(0, true), (2, false)],
+];
+
+struct Element {
+    from: Vec3,
+    to: Vec3,
+    /// Axis, angle and origin.
+    rotation: Option<(usize, f32, Vec3)>,
+    /// Face UVs as `[u1, v1, u2, v2]` in atlas texels, north to down.
+    faces: [Option<[f32; 4]>; 6],
+}
+
+/// Written block models.
+pub struct BlockModels {
+    /// The model file, or the folder of the pieces.
+    pub path: PathBuf,
+    pub elements: usize,
+    pub pieces: usize,
+    /// Factor the model was shrunk by to fit.
+    pub scale: f32,
+}
+
+/// Bedrock point in Java block space.
+fn java_point(p: Vec3) -> Vec3 {
+    Vec3::new(8.0 - p.x, p.y, p.z + 8.0)
+}
+
+/// Face UVs of `cube`, box UVs unwrapped as Blockbench does.
+fn face_uvs(cube: &McCube) -> [Option<[f32; 4]>; 6] {
+    match &cube.uv {
+        McUv::PerFace(f) => {
+            let rect = |f: &Option<McFaceUv>| f.map(|f| [f.uv[0], f.uv[1], f.uv[0] + f.uv_size[0], f.uv[1] + f.uv_size[1]]);
+            [rect(&f.north), rect(&f.east), rect(&f.south), rect(&f.west), rect(&f.up), rect(&f.down)]
+        }
+        &McUv::Box([u, v]) => {
+            let (u, v) = (u as f32, v as f32);
+            let [w, h, d] = cube.size;
+            let at = |x: f32, y: f32, width: f32, height: f32| Some([u + x, v + y, u + x + width, v + y + height]);
+            [
+                at(d, d, w, h),
+                at(0.0, d, d, h),
+                at(2.0 * d + w, d, w, h),
+                at(d + w, d, d, h),
+                at(d, 0.0, w, d),
+                // Down is flipped vertically
+                at(d + w, d, w, -d),
+            ]
+        }
+    }
+}
+
+fn element(cube: &McCube) -> Element {
+    let (origin, size) = (Vec3::from(cube.origin), Vec3::from(cube.size));
+    let inflate = Vec3::splat(cube.inflate.unwrap_or(0.0));
+    let from = java_point(origin + Vec3::new(size.x, 0.0, 0.0)) - inflate;
+    let to = java_point(origin + Vec3::new(0.0, size.y, size.z)) + inflate;
+    let rotation = cube.rotation.and_then(|[x, y, z]| {
+        let turn = [-x, -y, z];
+        let axis = (0..3).max_by(|&a, &b| turn[a].abs().total_cmp(&turn[b].abs())).unwrap();
+        let angle = ANGLES.into_iter().min_by(|a, b| (a - turn[axis]).abs().total_cmp(&(b - turn[axis]).abs())).unwrap();
+        (angle != 0.0).then(|| (axis, angle, java_point(Vec3::from(cube.pivot.unwrap_or(cube.origin)))))
+    });
+    Element { from, to, rotation, faces: face_uvs(cube) }
+}
+
+/// The parts of `element` in each block it covers. Faces inside the cut are
+/// dropped; rotated elements go whole to the block of their center.
+fn cut(element: Element) -> Vec<(IVec3, Element)> {
+    if element.rotation.is_some() {
+        let block = ((element.from + element.to) / 2.0 / BLOCK).floor().as_ivec3();
+        return vec![(block, element)];
+    }
+    let first = (element.from / BLOCK).floor().as_ivec3();
+    // Elements ending on a block edge don't reach into the next block
+    let last = ((element.to / BLOCK).ceil().as_ivec3() - IVec3::ONE).max(first);
+    let size = (element.to - element.from).max(Vec3::splat(f32::EPSILON));
+
+    let mut parts = Vec::new();
+    for x in first.x..=last.x {
+        for y in first.y..=last.y {
+            for z in first.z..=last.z {
+                let block = IVec3::new(x, y, z);
+                let lo = element.from.max(block.as_vec3() * BLOCK);
+                let hi = element.to.min((block + IVec3::ONE).as_vec3() * BLOCK);
+                let mut faces = [None; 6];
+                for (i, face) in faces.iter_mut().enumerate() {
+                    let (axis, high) = FACE_SIDES[i];
+                    let outside = if high { hi[axis] >= element.to[axis] } else { lo[axis] <= element.from[axis] };
+                    let Some([u1, v1, u2, v2]) = element.faces[i].filter(|_| outside) else { continue };
+                    // Where the part starts and ends along the face's u and v, 0 to 1
+                    let span = |(axis, up): (usize, bool)| {
+                        let t = |p: f32| (p - element.from[axis]) / size[axis];
+                        if up { (t(lo[axis]), t(hi[axis])) } else { (1.0 - t(hi[axis]), 1.0 - t(lo[axis])) }
+                    };
+                    let ((su, eu), (sv, ev)) = (span(FACE_AXES[i][0]), span(FACE_AXES[i][1]));
+                    *face = Some([u1 + (u2 - u1) * su, v1 + (v2 - v1) * sv, u1 + (u2 - u1) * eu, v1 + (v2 - v1) * ev]);
+                }
+                parts.push((block, Element { from: lo, to: hi, rotation: None, faces }));
+            }
+        }
+    }
+    parts
+}
+
+fn fits(elements: &[Element]) -> bool {
+    elements.iter().all(|e| e.from.min_element() >= RANGE_MIN && e.to.max_element() <= RANGE_MAX)
+}
+
+/// Shrinks `elements` to fit the range if needed and centers them on the
+/// block; returns the factor.
+fn scale_to_fit(elements: &mut [Element]) -> f32 {
+    if fits(elements) {
+        return 1.0;
+    }
+    let min = elements.iter().fold(Vec3::INFINITY, |m, e| m.min(e.from));
+    let max = elements.iter().fold(Vec3::NEG_INFINITY, |m, e| m.max(e.to));
+    let extent = max - min;
+    let scale = ((RANGE_MAX - RANGE_MIN) / extent.max_element()).min(1.0);
+    // Standing on the block's floor when it's low enough, else as high as it may go
+    let bottom = if extent.y * scale <= RANGE_MAX { 0.0 } else { RANGE_MAX - extent.y * scale };
+    let anchor = Vec3::new((min.x + max.x) / 2.0, min.y, (min.z + max.z) / 2.0);
+    let target = Vec3::new(BLOCK / 2.0, bottom, BLOCK / 2.0);
+    let place = |p: Vec3| (p - anchor) * scale + target;
+    for element in elements {
+        element.from = place(element.from);
+        element.to = place(element.to);
+        if let Some((_, _, origin)) = &mut element.rotation {
+            *origin = place(*origin);
+        }
+    }
+    scale
+}
+
+fn vec3(v: Vec3) -> Value {
+    json!([number(v.x), number(v.y), number(v.z)])
+}
+
+fn element_json(element: &Element, texture_size: f32, offset: Vec3) -> Value {
+    let mut faces = Map::new();
+    for (name, face) in FACE_NAMES.iter().zip(&element.faces) {
+        let Some(uv) = face else { continue };
+        let uv = uv.map(|c| number(c * BLOCK / texture_size));
+        faces.insert(name.to_string(), json!({ "uv": uv, "texture": "#0" }));
+    }
+    let mut json = Map::new();
+    json.insert("from".to_string(), vec3(element.from - offset));
+    json.insert("to".to_string(), vec3(element.to - offset));
+    if let Some((axis, angle, origin)) = element.rotation {
+        json.insert("rotation".to_string(), json!({
+            "angle": number(angle),
+            "axis": AXES[axis],
+            "origin": vec3(origin - offset),
+        }));
+    }
+    json.insert("faces".to_string(), Value::Object(faces));
+    Value::Object(json)
+}
+
+fn model_json(elements: &[&Element], texture: &str, texture_size: f32, offset: Vec3) -> String {
+    let model = json!({
+        "textures": { "0": texture, "particle": texture },
+        "elements": elements.iter().map(|e| element_json(e, texture_size, offset)).collect::<Vec<_>>(),
+    });
+    serde_json::to_string_pretty(&model).unwrap()
+}
+
+/// `texture` padded with transparent texels to a square; Java stretches UVs over
+/// the whole texture on both axes.
+fn square(texture: &Texture) -> Texture {
+    let side = texture.width.max(texture.height);
+    let mut pixels = vec![[0; 4]; (side * side) as usize];
+    for (row, texels) in texture.pixels.chunks(texture.width as usize).enumerate() {
+        let start = row * side as usize;
+        pixels[start..start + texels.len()].copy_from_slice(texels);
+    }
+    Texture { width: side, height: side, pixels }
+}
+
+/// Writes `bones` as a Java block model (or, split, one per block) into
+/// `output_dir`, textured with `texture`.
+pub fn write_block_models(
+    bones: &[McBone],
+    texture: &Texture,
+    model_name: &str,
+    output_dir: &Path,
+    fit: BlockModelFit,
+) -> Result<BlockModels, Message> {
+    if bones.iter().any(|b| b.poly_mesh.is_some()) {
+        return Err(Message::new("error.block_model_needs_cubes"));
+    }
+    let name = resource_name(model_name);
+    let texture = square(texture);
+    let texture_id = format!("block/{}", name);
+    let texture_size = texture.width as f32;
+    let write_texture = |path: PathBuf| fs::write(path, texture.to_png()).map_err(failed("error.write_texture"));
+
+    let mut elements: Vec<Element> = bones.iter().flat_map(|b| &b.cubes).map(element).collect();
+    if fit == BlockModelFit::Scale || fits(&elements) {
+        let scale = scale_to_fit(&mut elements);
+        let path = output_dir.join(format!("{}.json", name));
+        let all: Vec<&Element> = elements.iter().collect();
+        fs::write(&path, model_json(&all, &texture_id, texture_size, Vec3::ZERO)).map_err(failed("error.write_file"))?;
+        write_texture(output_dir.join(format!("{}.png", name)))?;
+        return Ok(BlockModels { path, elements: elements.len(), pieces: 1, scale });
+    }
+
+    let mut blocks: BTreeMap<[i32; 3], Vec<Element>> = BTreeMap::new();
+    for (block, part) in elements.into_iter().flat_map(cut) {
+        blocks.entry(block.to_array()).or_default().push(part);
+    }
+    let dir = pieces::pieces_dir(output_dir, model_name, "block_model")?;
+    let mut index = Vec::new();
+    for (&[x, y, z], parts) in &blocks {
+        let file = format!("{}_x{}_y{}_z{}.json", name, x, y, z);
+        let offset = IVec3::new(x, y, z).as_vec3() * BLOCK;
+        let parts: Vec<&Element> = parts.iter().collect();
+        fs::write(dir.join(&file), model_json(&parts, &texture_id, texture_size, offset)).map_err(failed("error.write_file"))?;
+        index.push(json!({ "file": file, "position": [x, y, z] }));
+    }
+    write_texture(dir.join(format!("{}.png", name)))?;
+    let index = json!({ "pieces": index });
+    fs::write(dir.join("index.json"), serde_json::to_string_pretty(&index).unwrap()).map_err(failed("error.write_index"))?;
+    Ok(BlockModels { path: dir, elements: blocks.values().map(Vec::len).sum(), pieces: blocks.len(), scale: 1.0 })
+}
@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet};
+
+use ahash::RandomState;
+use glam::{EulerRot, IVec3, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::{fill, ConvertOptions, McBone};
+
+// ================= BLOCK GRID =================
+//
+// World exports (structures, schematics) place one block per voxel cell. The
+// cells are recovered from the finished cubes, so every geometry option
+// (adaptive, boxes, slopes) carries over: a cell is filled when its center
+// lies inside a cube, rotated cubes included.
+//
+// With partial blocks, the top surface is smoothed per column: where the
+// heights on both sides climb by one (a 45° slope) the top block becomes
+// stairs, and where a flat tread meets a one-block riser (gentler slopes) a
+// bottom slab is added on top.
+//
+// The anchor picks the point of the model that lands on the placement
+// position (plus a fixed offset): its minimum corner, its center, or its
+// lowest block nearest to the middle, e.g. the foot of a statue.
+//
+// Scaffolding makes hollow builds reachable by hand in survival: inside the
+// enclosed space, every Nth layer gets a floor and every Nth column (in X and
+// Z) a pillar holding the floors up, all in a separate support block so
+// they're easy to spot and clear afterwards.
+//
+// Orientable blocks follow the shape of the model: logs and other pillars
+// lie along the direction the solid runs longest through them (up a trunk,
+// along a beam), so bark faces the surface; glazed terracotta faces the side
+// the block is exposed on.
+//
+// Empty cells are air in structures (replacing whatever is there when loaded,
+// water included, across the whole bounding box) and left alone by datapacks.
+// `keep` turns them into structure voids so terrain and water stay;
+// `dry_interior` does the same outside but places air in the enclosed inside,
+// so a hollow model pasted into an ocean isn't flooded.
+//
+// Block variation breaks up large flat areas: the grid is divided into
+// patches, and each patch of full blocks either keeps `block` or takes one of
+// the similar variation blocks, picked by a fixed hash so exports repeat.
+//
+// Decorations dress up terrain: a share of the columns whose top is a full
+// block get one of the decoration blocks (grass, flowers) placed on top.
+
+/// Direction a stair block ascends towards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Facing {
+    East,
+    West,
+    South,
+    North,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+const DIRECTIONS: [(Facing, IVec3); 4] = [
+    (Facing::East, IVec3::X),
+    (Facing::West, IVec3::NEG_X),
+    (Facing::South, IVec3::Z),
+    (Facing::North, IVec3::NEG_Z),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockShape {
+    Full,
+    Stairs(Facing),
+    /// Bottom half slab.
+    Slab,
+    /// Scaffolding inside the model.
+    Support,
+    /// Full pillar block (log, basalt, ...) along an axis.
+    Pillar(Axis),
+    /// Full block with a front (glazed terracotta) facing a side.
+    Rotated(Facing),
+    /// Air placed explicitly in the enclosed inside.
+    Air,
+    /// Full block of the `block_variation` entry with this index.
+    Variation(usize),
+    /// The `decorations` entry with this index, on top of the surface.
+    Decoration(usize),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyCells {
+    #[default]
+    Air,
+    /// Structure voids, for pasting over existing terrain.
+    #[serde(alias = "structure_void")]
+    Keep,
+    DryInterior,
+}
+
+const DEFAULT_DECORATION_DENSITY: f32 = 0.3;
+
+/// Longest solid run counted in each direction when orienting pillars.
+const MAX_RUN: i32 = 8;
+
+const PILLAR_SUFFIXES: &[&str] = &["_log", "_wood", "_stem", "_hyphae", "_pillar", "basalt"];
+const PILLAR_BLOCKS: &[&str] = &["bone_block", "hay_block", "deepslate", "muddy_mangrove_roots"];
+
+enum Orientable {
+    Pillar,
+    Rotated,
+}
+
+fn orientable(block: &str) -> Option<Orientable> {
+    let name = block.strip_prefix("minecraft:").unwrap_or(block);
+    if name.ends_with("_glazed_terracotta") {
+        Some(Orientable::Rotated)
+    } else if PILLAR_SUFFIXES.iter().any(|s| name.ends_with(s)) || PILLAR_BLOCKS.contains(&name) {
+        Some(Orientable::Pillar)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    #[default]
+    Corner,
+    Center,
+    Lowest,
+}
+
+pub struct BlockGrid {
+    /// Cells in each axis; block positions run from zero to `size - 1`.
+    pub size: IVec3,
+    pub blocks: HashMap<IVec3, BlockShape, RandomState>,
+    /// Position of block (0, 0, 0) relative to the placement position.
+    pub origin: IVec3,
+}
+
+impl BlockGrid {
+    pub fn from_bones(bones: &[McBone], options: &ConvertOptions) -> Option<Self> {
+        let cells = rasterize(bones);
+        let mut blocks: HashMap<IVec3, BlockShape, RandomState> =
+            cells.iter().map(|&c| (c, BlockShape::Full)).collect();
+        if options.partial_blocks {
+            shape_slopes(&mut blocks);
+        }
+        if let Some(kind) = options.orient_blocks.then(|| options.block.as_deref().and_then(orientable)).flatten() {
+            orient_blocks(&mut blocks, kind);
+        }
+        if !options.block_variation.is_empty() {
+            vary_blocks(&mut blocks, options);
+        }
+        if options.scaffolding > 0 {
+            add_supports(&mut blocks, &cells, options.scaffolding as i32);
+        }
+        if !options.decorations.is_empty() {
+            decorate(&mut blocks, options);
+        }
+        if options.empty_cells == EmptyCells::DryInterior {
+            let solid: HashSet<IVec3, RandomState> = blocks.keys().copied().collect();
+            blocks.extend(fill::interior_cells(&solid).into_iter().map(|p| (p, BlockShape::Air)));
+        }
+
+        let mut iter = blocks.keys();
+        let &first = iter.next()?;
+        let (min, max) = iter.fold((first, first), |(min, max), &v| (min.min(v), max.max(v)));
+
+        let mut grid = Self {
+            size: max - min + IVec3::ONE,
+            blocks: blocks.into_iter().map(|(p, shape)| (p - min, shape)).collect(),
+            origin: IVec3::ZERO,
+        };
+        grid.origin = IVec3::from(options.offset) - grid.anchor_point(options.anchor);
+        Some(grid)
+    }
+
+    pub fn get(&self, p: IVec3) -> Option<BlockShape> {
+        self.blocks.get(&p).copied()
+    }
+
+    /// Cells in the grid's box, counted in `usize` so large grids don't overflow.
+    pub fn volume(&self) -> usize {
+        self.size.x as usize * self.size.y as usize * self.size.z as usize
+    }
+
+    /// Placed blocks, not counting explicit air.
+    pub fn block_count(&self) -> usize {
+        self.blocks.values().filter(|&&s| s != BlockShape::Air).count()
+    }
+
+    fn anchor_point(&self, anchor: Anchor) -> IVec3 {
+        match anchor {
+            Anchor::Corner => IVec3::ZERO,
+            Anchor::Center => self.size / 2,
+            Anchor::Lowest => {
+                let middle = self.size / 2;
+                let horizontal_distance = |p: &IVec3| (*p - middle).with_y(0).length_squared();
+                self.blocks.iter()
+                    .filter(|(_, &s)| s != BlockShape::Air)
+                    .map(|(p, _)| p)
+                    .min_by_key(|p| (p.y, horizontal_distance(p), p.x, p.z))
+                    .copied()
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// Voxel cells whose centers fall inside any cube of `bones`.
+pub(crate) fn rasterize(bones: &[McBone]) -> HashSet<IVec3, RandomState> {
+    let mut cells = HashSet::default();
+
+    for cube in bones.iter().flat_map(|b| &b.cubes) {
+        let origin = Vec3::from(cube.origin);
+        let size = Vec3::from(cube.size);
+        let pivot = Vec3::from(cube.pivot.unwrap_or([0.0; 3]));
+
+        // Bedrock rotations are clockwise; invert them to map cells into the cube frame
+        let rotation = cube.rotation.map(|[x, y, z]| {
+            Quat::from_euler(EulerRot::ZYX, -z.to_radians(), -y.to_radians(), -x.to_radians())
+        });
+        let (min, max) = match rotation {
+            Some(q) => {
+                let corners = (0..8).map(|i| {
+                    let corner = origin + size * Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32);
+                    pivot + q * (corner - pivot)
+                });
+                corners.fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), c| (min.min(c), max.max(c)))
+            }
+            None => (origin, origin + size),
+        };
+
+        let inverse = rotation.map(Quat::inverse);
+        let lo = min.floor().as_ivec3();
+        let hi = max.ceil().as_ivec3();
+        for x in lo.x..hi.x {
+            for y in lo.y..hi.y {
+                for z in lo.z..hi.z {
+                    let cell = IVec3::new(x, y, z);
+                    let mut center = cell.as_vec3() + 0.5;
+                    if let Some(q) = inverse {
+                        center = pivot + q * (center - pivot);
+                    }
+                    if center.cmpge(origin).all() && center.cmplt(origin + size).all() {
+                        cells.insert(cell);
+                    }
+                }
+            }
+        }
+    }
+
+    cells
+}
+
+fn orient_blocks(blocks: &mut HashMap<IVec3, BlockShape, RandomState>, kind: Orientable) {
+    let solid = |p: IVec3| blocks.contains_key(&p);
+    let run = |p: IVec3, step: IVec3| (1..=MAX_RUN).take_while(|&i| solid(p + step * i)).count();
+
+    let oriented: Vec<(IVec3, BlockShape)> = blocks.iter()
+        .filter(|(_, &shape)| shape == BlockShape::Full)
+        .map(|(&p, _)| {
+            let shape = match kind {
+                Orientable::Pillar => {
+                    let length = |axis: IVec3| run(p, axis) + run(p, -axis);
+                    // Ties go to Y, the way logs are placed by default
+                    let axis = [(Axis::Y, IVec3::Y), (Axis::X, IVec3::X), (Axis::Z, IVec3::Z)]
+                        .into_iter()
+                        .rev()
+                        .max_by_key(|&(_, axis)| length(axis))
+                        .map_or(Axis::Y, |(axis, _)| axis);
+                    BlockShape::Pillar(axis)
+                }
+                Orientable::Rotated => {
+                    let exposed = DIRECTIONS.iter()
+                        .filter(|(_, d)| !solid(p + *d))
+                        .fold(IVec3::ZERO, |sum, (_, d)| sum + *d);
+                    let facing = DIRECTIONS.iter()
+                        .max_by_key(|(_, d)| exposed.dot(*d))
+                        .map_or(Facing::South, |&(facing, _)| facing);
+                    BlockShape::Rotated(facing)
+                }
+            };
+            (p, shape)
+        })
+        .collect();
+    blocks.extend(oriented);
+}
+
+/// Uniform value in 0-1 for `cell` and `seed`, the same on every run.
+pub(crate) fn cell_noise(cell: IVec3, seed: u32) -> f32 {
+    let mut h = (cell.x as u32).wrapping_mul(0x9e37_79b1)
+        ^ (cell.y as u32).wrapping_mul(0x85eb_ca77)
+        ^ (cell.z as u32).wrapping_mul(0xc2b2_ae3d)
+        ^ seed.wrapping_mul(0x27d4_eb2f);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^= h >> 15;
+    h as f32 / u32::MAX as f32
+}
+
+fn vary_blocks(blocks: &mut HashMap<IVec3, BlockShape, RandomState>, options: &ConvertOptions) {
+    let count = options.block_variation.len();
+    // 0 mixes `block` and every variation block evenly
+    let amount = match options.variation_amount {
+        a if a > 0.0 => a.min(1.0),
+        _ => count as f32 / (count + 1) as f32,
+    };
+    let size = IVec3::splat(options.variation_size.max(1) as i32);
+
+    for (p, shape) in blocks.iter_mut() {
+        if *shape != BlockShape::Full {
+            continue;
+        }
+        let patch = p.div_euclid(size);
+        if cell_noise(patch, 0) < amount {
+            let pick = (cell_noise(patch, 1) * count as f32) as usize;
+            *shape = BlockShape::Variation(pick.min(count - 1));
+        }
+    }
+}
+
+fn decorate(blocks: &mut HashMap<IVec3, BlockShape, RandomState>, options: &ConvertOptions) {
+    let count = options.decorations.len();
+    let density = match options.decoration_density {
+        d if d > 0.0 => d.min(1.0),
+        _ => DEFAULT_DECORATION_DENSITY,
+    };
+
+    let mut tops: HashMap<(i32, i32), IVec3, RandomState> = HashMap::default();
+    for &p in blocks.keys() {
+        let top = tops.entry((p.x, p.z)).or_insert(p);
+        if p.y > top.y {
+            *top = p;
+        }
+    }
+
+    for top in tops.into_values() {
+        if !matches!(blocks[&top], BlockShape::Full | BlockShape::Variation(_)) {
+            continue;
+        }
+        let above = top + IVec3::Y;
+        if cell_noise(above, 2) < density {
+            let pick = (cell_noise(above, 3) * count as f32) as usize;
+            blocks.insert(above, BlockShape::Decoration(pick.min(count - 1)));
+        }
+    }
+}
+
+fn add_supports(blocks: &mut HashMap<IVec3, BlockShape, RandomState>, cells: &HashSet<IVec3, RandomState>, spacing: i32) {
+    let Some(min) = cells.iter().copied().reduce(IVec3::min) else { return };
+    for cell in fill::interior_cells(cells) {
+        let l = cell - min;
+        let floor = l.y % spacing == 0;
+        let pillar = l.x % spacing == 0 && l.z % spacing == 0;
+        if floor || pillar {
+            blocks.entry(cell).or_insert(BlockShape::Support);
+        }
+    }
+}
+
+fn shape_slopes(blocks: &mut HashMap<IVec3, BlockShape, RandomState>) {
+    let mut heights: HashMap<(i32, i32), i32, RandomState> = HashMap::default();
+    for p in blocks.keys() {
+        let top = heights.entry((p.x, p.z)).or_insert(p.y);
+        *top = (*top).max(p.y);
+    }
+    let height = |column: IVec3| heights.get(&(column.x, column.z)).copied();
+
+    let mut stairs = Vec::new();
+    let mut slabs = Vec::new();
+    for (&(x, z), &h) in &heights {
+        let column = IVec3::new(x, 0, z);
+        let rising = |climb_back: i32| {
+            let mut matches = DIRECTIONS.iter().filter(|(_, d)| {
+                height(column + *d) == Some(h + 1) && height(column - *d) == Some(h - climb_back)
+            });
+            // Corners and saddles climb in several directions; leave them as full blocks
+            match (matches.next(), matches.next()) {
+                (Some(&(facing, _)), None) => Some(facing),
+                _ => None,
+            }
+        };
+
+        if let Some(facing) = rising(1) {
+            stairs.push((IVec3::new(x, h, z), facing));
+        } else if rising(0).is_some() {
+            slabs.push(IVec3::new(x, h + 1, z));
+        }
+    }
+
+    for (p, facing) in stairs {
+        blocks.insert(p, BlockShape::Stairs(facing));
+    }
+    for p in slabs {
+        blocks.insert(p, BlockShape::Slab);
+    }
+}
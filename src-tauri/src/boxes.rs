@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use ahash::RandomState;
+use glam::Vec3;
+
+use crate::McCube;
+
+// ================= BOX DETECTION =================
+//
+// Splits a mesh into connected parts (vertices welded by position) and keeps
+// every part that is exactly an axis-aligned cuboid: only corner vertices of
+// its bounding box, only axis-aligned faces, and a total area equal to the
+// box surface. Those become cubes at full float precision; all other
+// triangles are returned for regular voxelization.
+
+const RELATIVE_EPSILON: f32 = 1e-4;
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root { root = parent[root]; }
+    let mut i = i;
+    while parent[i] != root {
+        let next = parent[i];
+        parent[i] = root;
+        i = next;
+    }
+    root
+}
+
+fn weld_key(v: Vec3) -> [i64; 3] {
+    // Positions closer than ~1e-5 model units are treated as the same vertex
+    (v * 1e5).round().as_i64vec3().to_array()
+}
+
+fn as_box(vertices: &[Vec3], triangles: &[[u32; 3]]) -> Option<(Vec3, Vec3)> {
+    let points = || triangles.iter().flatten().map(|&i| vertices[i as usize]);
+    let min = points().fold(Vec3::splat(f32::INFINITY), Vec3::min);
+    let max = points().fold(Vec3::splat(f32::NEG_INFINITY), Vec3::max);
+
+    let extent = max - min;
+    let eps = extent.max_element() * RELATIVE_EPSILON;
+    if extent.min_element() <= eps { return None; }
+
+    let on_bound = |c: f32, lo: f32, hi: f32| (c - lo).abs() <= eps || (c - hi).abs() <= eps;
+    if !points().all(|p| on_bound(p.x, min.x, max.x) && on_bound(p.y, min.y, max.y) && on_bound(p.z, min.z, max.z)) {
+        return None;
+    }
+
+    let mut area = 0.0;
+    for t in triangles {
+        let [a, b, c] = t.map(|i| vertices[i as usize]);
+        let n = (b - a).cross(c - a);
+        let axis_aligned = [n.x, n.y, n.z].iter().filter(|c| c.abs() > n.length() * RELATIVE_EPSILON).count() == 1;
+        if !axis_aligned { return None; }
+        area += n.length() / 2.0;
+    }
+
+    let box_area = 2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x);
+    ((area - box_area).abs() <= box_area * 1e-3).then_some((min, max))
+}
+
+/// Returns cubes for every box-shaped part and the indices of the remaining triangles.
+pub fn extract_boxes(vertices: &[Vec3], indices: &[u32], scale: f32) -> (Vec<McCube>, Vec<u32>) {
+    let mut welded: HashMap<[i64; 3], usize, RandomState> = HashMap::default();
+    let ids: Vec<usize> = vertices.iter()
+        .map(|&v| {
+            let next = welded.len();
+            *welded.entry(weld_key(v)).or_insert(next)
+        })
+        .collect();
+
+    let mut parent: Vec<usize> = (0..welded.len()).collect();
+    for t in indices.chunks(3) {
+        let a = find(&mut parent, ids[t[0] as usize]);
+        for &k in &t[1..] {
+            let b = find(&mut parent, ids[k as usize]);
+            parent[b] = a;
+        }
+    }
+
+    let mut parts: HashMap<usize, Vec<[u32; 3]>, RandomState> = HashMap::default();
+    for t in indices.chunks(3) {
+        let root = find(&mut parent, ids[t[0] as usize]);
+        parts.entry(root).or_default().push([t[0], t[1], t[2]]);
+    }
+
+    // By key, so cubes and leftover triangles keep the same order between runs
+    let mut parts: Vec<_> = parts.into_iter().collect();
+    parts.sort_unstable_by_key(|&(root, _)| root);
+
+    let mut cubes = Vec::new();
+    let mut remaining = Vec::new();
+
+    for (_, triangles) in &parts {
+        match as_box(vertices, triangles) {
+            Some((min, max)) => {
+                let min = min * scale;
+                let max = max * scale;
+                cubes.push(McCube::new(min.to_array(), (max - min).to_array()));
+            }
+            None => remaining.extend(triangles.iter().flatten()),
+        }
+    }
+
+    (cubes, remaining)
+}
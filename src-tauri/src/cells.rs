@@ -0,0 +1,51 @@
+use glam::{IVec3, Vec3};
+
+use crate::McBone;
+
+// ================= ANISOTROPIC CELLS =================
+//
+// `cell_size` makes voxel cells non-cubic, e.g. `[1, 0.5, 1]` for half-height
+// voxels with slab-resolution vertical detail. The mesh is stretched by the
+// inverse cell size and voxelized on the usual cubic grid, then the finished
+// cubes are scaled back, so greedy meshing, fills and scripts all work in
+// whole cells. Rotated cubes from `fit_slopes` keep their angle. World exports
+// resample the scaled cubes onto whole blocks.
+
+/// Copies of `models` with positions multiplied by `factor` per axis.
+pub fn stretch_models(models: &[tobj::Model], factor: Vec3) -> Vec<tobj::Model> {
+    models.iter()
+        .map(|model| {
+            let mut mesh = model.mesh.clone();
+            for p in mesh.positions.chunks_mut(3) {
+                p.copy_from_slice(&(Vec3::new(p[0], p[1], p[2]) * factor).to_array());
+            }
+            // Normals transform by the inverse
+            for n in mesh.normals.chunks_mut(3) {
+                n.copy_from_slice(&(Vec3::new(n[0], n[1], n[2]) / factor).normalize_or_zero().to_array());
+            }
+            tobj::Model { mesh, name: model.name.clone() }
+        })
+        .collect()
+}
+
+/// Scales voxelized `bones` from whole cells to geometry units.
+pub fn scale_bones(bones: &mut [McBone], cell_size: Vec3) {
+    let scaled = |p: [f32; 3]| (Vec3::from(p) * cell_size).to_array();
+    for bone in bones {
+        bone.pivot = (IVec3::from(bone.pivot).as_vec3() * cell_size).round().as_ivec3().to_array();
+        for cube in &mut bone.cubes {
+            cube.origin = scaled(cube.origin);
+            cube.size = scaled(cube.size);
+            cube.pivot = cube.pivot.map(scaled);
+            if let Some(surface) = &mut cube.surface {
+                surface.rescale(cell_size);
+            }
+        }
+        if let Some(mesh) = &mut bone.poly_mesh {
+            mesh.scale(cell_size);
+        }
+        for position in bone.locators.values_mut() {
+            *position = scaled(*position);
+        }
+    }
+}
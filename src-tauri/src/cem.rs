@@ -0,0 +1,167 @@
+use std::fs;
+use std::path::Path;
+
+use glam::Vec3;
+use serde_json::{json, Map, Value};
+
+use crate::messages::{failed, Message};
+use crate::texture::Texture;
+use crate::{McBone, McCube, McFaceUv, McUv};
+
+// ================= OPTIFINE CEM (.JEM / .JPM) =================
+//
+// Custom entity models for OptiFine and compatible Java mods. Every bone
+// becomes a model part, named in snake_case (`rightArm` → `right_arm`) to
+// match the Java entity parts. OptiFine addresses parts by name only, so bone
+// parents are dropped; vanilla animations move each part on its own. Rotated
+// cubes are nested as submodels, since JEM boxes can't rotate on their own.
+//
+// JEM uses Java model space with `invertAxis: "xy"`, which is Bedrock space
+// mirrored in X. A part or submodel is placed by `translate` (minus its pivot
+// relative to the parent's) and its boxes are given relative to the parent's
+// pivot.
+//
+// The file has to be renamed after the entity it replaces (e.g. `zombie.jem`)
+// and placed in `assets/minecraft/optifine/cem/` of a resource pack.
+
+/// Bedrock point in JEM space.
+fn jem_point(p: Vec3) -> Vec3 {
+    Vec3::new(-p.x, p.y, p.z)
+}
+
+/// Whole numbers without a fraction, others rounded to stay readable as f64.
+pub(crate) fn number(v: f32) -> Value {
+    if v.fract() == 0.0 && v.abs() < 1e9 {
+        json!(v as i64)
+    } else {
+        json!((v as f64 * 1e4).round() / 1e4)
+    }
+}
+
+fn vec3(v: Vec3) -> Value {
+    json!([number(v.x), number(v.y), number(v.z)])
+}
+
+fn part_name(bone_name: &str) -> String {
+    let mut name = String::new();
+    for c in bone_name.chars() {
+        if c.is_ascii_uppercase() && !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+fn face_uv(face: &Option<McFaceUv>) -> Option<Value> {
+    face.map(|f| {
+        json!([number(f.uv[0]), number(f.uv[1]), number(f.uv[0] + f.uv_size[0]), number(f.uv[1] + f.uv_size[1])])
+    })
+}
+
+fn jem_box(cube: &McCube, parent_pivot: Vec3) -> Value {
+    let origin = Vec3::from(cube.origin);
+    let size = Vec3::from(cube.size);
+    let from = jem_point(origin + Vec3::new(size.x, 0.0, 0.0)) - parent_pivot;
+
+    let mut jem_box = Map::new();
+    jem_box.insert(
+        "coordinates".to_string(),
+        json!([number(from.x), number(from.y), number(from.z), number(size.x), number(size.y), number(size.z)]),
+    );
+    if let Some(inflate) = cube.inflate {
+        jem_box.insert("sizeAdd".to_string(), number(inflate));
+    }
+    match &cube.uv {
+        McUv::Box(offset) => {
+            jem_box.insert("textureOffset".to_string(), json!(offset));
+        }
+        McUv::PerFace(faces) => {
+            let named = [
+                ("uvNorth", &faces.north),
+                ("uvEast", &faces.east),
+                ("uvSouth", &faces.south),
+                ("uvWest", &faces.west),
+                ("uvUp", &faces.up),
+                ("uvDown", &faces.down),
+            ];
+            for (key, face) in named {
+                if let Some(uv) = face_uv(face) {
+                    jem_box.insert(key.to_string(), uv);
+                }
+            }
+        }
+    }
+    Value::Object(jem_box)
+}
+
+/// Boxes of `bone`, with rotated cubes as submodels.
+fn part_contents(bone: &McBone, pivot: Vec3) -> Map<String, Value> {
+    let mut boxes = Vec::new();
+    let mut submodels = Vec::new();
+    for (i, cube) in bone.cubes.iter().enumerate() {
+        match (cube.rotation, cube.pivot) {
+            (Some(rotation), cube_pivot) => {
+                let cube_pivot = jem_point(Vec3::from(cube_pivot.unwrap_or([0.0; 3])));
+                submodels.push(json!({
+                    "id": format!("{}_{}", bone.name, i),
+                    "translate": vec3(-(cube_pivot - pivot)),
+                    "rotate": vec3(Vec3::from(rotation)),
+                    "boxes": [jem_box(cube, pivot)],
+                }));
+            }
+            (None, _) => boxes.push(jem_box(cube, Vec3::ZERO)),
+        }
+    }
+
+    let mut contents = Map::new();
+    if !boxes.is_empty() {
+        contents.insert("boxes".to_string(), json!(boxes));
+    }
+    if !submodels.is_empty() {
+        contents.insert("submodels".to_string(), json!(submodels));
+    }
+    contents
+}
+
+/// Writes `bones` as a `.jem` at `path`. With `part_files`, each part goes to
+/// its own `.jpm` next to it and the `.jem` only references them.
+pub fn write_jem(bones: &[McBone], texture: &Texture, path: &Path, part_files: bool) -> Result<(), Message> {
+    if bones.iter().any(|b| b.poly_mesh.is_some()) {
+        return Err(Message::new("error.cem_needs_cubes"));
+    }
+
+    let texture_size = json!([texture.width, texture.height]);
+    let mut models = Vec::new();
+    for bone in bones {
+        let part = part_name(&bone.name);
+        let pivot = jem_point(Vec3::from(bone.pivot.map(|c| c as f32)));
+
+        let mut model = Map::new();
+        model.insert("part".to_string(), json!(part));
+        model.insert("id".to_string(), json!(bone.name));
+        model.insert("invertAxis".to_string(), json!("xy"));
+        model.insert("translate".to_string(), vec3(-pivot));
+
+        let contents = part_contents(bone, pivot);
+        if part_files {
+            let mut jpm = Map::new();
+            jpm.insert("textureSize".to_string(), texture_size.clone());
+            jpm.extend(contents);
+            let jpm_path = path.with_file_name(format!("{}.jpm", part));
+            fs::write(&jpm_path, serde_json::to_string_pretty(&jpm).unwrap())
+                .map_err(failed("error.write_part_model"))?;
+            model.insert("model".to_string(), json!(format!("{}.jpm", part)));
+        } else {
+            model.extend(contents);
+        }
+        models.push(Value::Object(model));
+    }
+
+    let jem = json!({
+        "textureSize": texture_size,
+        "models": models,
+    });
+    fs::write(path, serde_json::to_string_pretty(&jem).unwrap())
+        .map_err(failed("error.write_file"))
+}
@@ -0,0 +1,30 @@
+use crate::projection::ColorSources;
+use crate::texture::{self, Texture};
+use crate::{ConvertOptions, McBone};
+
+// ================= CLAY SHADING =================
+//
+// `texture_layout = "clay"` textures a model with no input at all, like a
+// clay render: every cube is one neutral color and each face is shaded by
+// the way it points, tops lightest, bottoms darkest and sides in between
+// (east and west a little darker than north and south, as the game shades
+// blocks), so untextured exports read at a glance. Rotated cubes shade by
+// their turned normals. Ambient occlusion and a baked light still apply on
+// top.
+
+const CLAY: [u8; 4] = [214, 206, 196, 255];
+
+/// Brightness of faces pointing along each axis: X sides, top, bottom, Z sides.
+pub const SIDE_X: f32 = 0.7;
+pub const TOP: f32 = 1.0;
+pub const BOTTOM: f32 = 0.5;
+pub const SIDE_Z: f32 = 0.85;
+
+/// Paints every cube of `bones` clay; returns the shaded atlas.
+pub fn apply_clay(bones: &mut [McBone], options: &ConvertOptions) -> Texture {
+    for cube in bones.iter_mut().flat_map(|b| &mut b.cubes) {
+        cube.color = Some(CLAY);
+        cube.surface = None;
+    }
+    texture::bake_atlas(bones, options, &ColorSources::default())
+}
@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::messages::{failed, Message};
+use crate::McBone;
+
+// ================= CUBE COLORS =================
+//
+// Optional `<model>.colors.json` sidecar recording the material color each
+// cube was painted with, by bone name and cube index in the geometry file, so
+// external tools can remap colors later without re-running the sampler.
+// Colors are taken before the texture layout (the preview palette replaces
+// them); cubes without a material color are left out.
+
+#[derive(Debug, Serialize)]
+pub struct CubeColors {
+    /// Bone name → cube index → RGB.
+    bones: BTreeMap<String, BTreeMap<usize, [u8; 3]>>,
+}
+
+pub fn cube_colors(bones: &[McBone]) -> CubeColors {
+    let bones = bones.iter()
+        .map(|bone| {
+            let colors: BTreeMap<usize, [u8; 3]> = bone.cubes.iter().enumerate()
+                .filter_map(|(i, cube)| cube.color.map(|[r, g, b, _]| (i, [r, g, b])))
+                .collect();
+            (bone.name.clone(), colors)
+        })
+        .filter(|(_, colors)| !colors.is_empty())
+        .collect();
+    CubeColors { bones }
+}
+
+pub fn write_cube_colors(colors: &CubeColors, path: &Path) -> Result<(), Message> {
+    let text = serde_json::to_string(colors).unwrap();
+    fs::write(path, text).map_err(failed("error.write_cube_colors"))
+}
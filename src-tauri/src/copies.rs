@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap};
+
+use glam::{EulerRot, Mat3, Quat, Vec3};
+
+use crate::materials::FaceMaterials;
+use crate::{locators, McBone};
+
+// ================= RIGID COPIES =================
+//
+// Models often repeat a part as a moved or rotated duplicate: the wheels of a
+// car, the legs of a table. An object whose vertices are those of an earlier
+// object under one rotation and translation (same vertex order and
+// triangles, as duplicating in an editor leaves them) and painted the same
+// (materials, UVs and vertex colors) is not voxelized
+// again, which would give a differently stair-stepped copy. Its bone gets
+// the earlier object's cubes turned about the copy's center instead;
+// copies that are only moved land on the nearest whole voxel.
+
+/// Largest vertex mismatch, relative to the object size, still counted as a copy.
+const TOLERANCE: f32 = 1e-4;
+/// Rotations closer to none than this (in radians) only move the copy.
+const MIN_ANGLE: f32 = 1e-3;
+
+pub struct RigidCopy {
+    /// Index of the object this one copies.
+    pub source: usize,
+    rotation: Quat,
+    /// Centers of the source and the copy, in model units.
+    from: Vec3,
+    to: Vec3,
+}
+
+fn points(model: &tobj::Model) -> Vec<Vec3> {
+    model.mesh.positions.chunks(3).map(|p| Vec3::new(p[0], p[1], p[2])).collect()
+}
+
+fn centroid(points: &[Vec3]) -> Vec3 {
+    points.iter().sum::<Vec3>() / points.len() as f32
+}
+
+/// Indices of two vertices that span a frame with the center, if the object isn't flat along a line.
+fn frame_vertices(points: &[Vec3], center: Vec3) -> Option<(usize, usize)> {
+    let far = (0..points.len()).max_by(|&a, &b| {
+        points[a].distance_squared(center).total_cmp(&points[b].distance_squared(center))
+    })?;
+    let arm = points[far] - center;
+    let side = (0..points.len()).max_by(|&a, &b| {
+        arm.cross(points[a] - center).length_squared().total_cmp(&arm.cross(points[b] - center).length_squared())
+    })?;
+    (arm.cross(points[side] - center).length_squared() > 0.0).then_some((far, side))
+}
+
+fn frame(points: &[Vec3], center: Vec3, (a, b): (usize, usize)) -> Mat3 {
+    let x = (points[a] - center).normalize();
+    let z = x.cross(points[b] - center).normalize();
+    Mat3::from_cols(x, z.cross(x), z)
+}
+
+/// The rigid motion taking `source` onto `copy`, if there is one.
+fn rigid_motion(source: &[Vec3], copy: &[Vec3], source_index: usize) -> Option<RigidCopy> {
+    let (from, to) = (centroid(source), centroid(copy));
+    let corners = frame_vertices(source, from)?;
+    let rotation = Quat::from_mat3(&(frame(copy, to, corners) * frame(source, from, corners).transpose()));
+
+    let size = source.iter().fold(0.0f32, |r, p| r.max(p.distance(from)));
+    let tolerance = TOLERANCE * size.max(f32::EPSILON);
+    source.iter().zip(copy)
+        .all(|(&s, &c)| (rotation * (s - from) + to).distance(c) <= tolerance)
+        .then_some(RigidCopy { source: source_index, rotation, from, to })
+}
+
+/// Whether `a` and `b` take their colors from the same places.
+fn same_paint(models: &[tobj::Model], faces: &FaceMaterials, a: usize, b: usize) -> bool {
+    let (ma, mb) = (&models[a].mesh, &models[b].mesh);
+    ma.material_id == mb.material_id
+        && ma.texcoords == mb.texcoords
+        && ma.vertex_color == mb.vertex_color
+        && faces.get(&a) == faces.get(&b)
+}
+
+/// Objects of `models` that copy an earlier one, by index. `faces` holds the
+/// materials of objects with several.
+pub fn find_copies(models: &[tobj::Model], faces: &FaceMaterials) -> HashMap<usize, RigidCopy> {
+    let mut copies = HashMap::new();
+    // Objects that aren't copies, by vertex count
+    let mut sources: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+
+    for (index, model) in models.iter().enumerate() {
+        if model.mesh.indices.is_empty() || locators::is_marker(&model.name) {
+            continue;
+        }
+        let vertices = points(model);
+        let candidates = sources.entry(vertices.len()).or_default();
+        let found = candidates.iter()
+            .filter(|&&s| models[s].mesh.indices == model.mesh.indices && same_paint(models, faces, s, index))
+            .find_map(|&s| rigid_motion(&points(&models[s]), &vertices, s));
+        match found {
+            Some(copy) => { copies.insert(index, copy); }
+            None => candidates.push(index),
+        }
+    }
+    copies
+}
+
+/// Bedrock rotation of `rotation`; Bedrock turns clockwise, Z then Y then X.
+fn euler_degrees(rotation: Quat) -> [f32; 3] {
+    let (z, y, x) = rotation.to_euler(EulerRot::ZYX);
+    let [x, y, z] = [-x, -y, -z];
+    // Thousandths of a degree, so float noise doesn't turn 30 into 29.999998
+    // (adding 0 turns -0 into 0)
+    [x, y, z].map(|a| (a.to_degrees() * 1000.0).round() / 1000.0 + 0.0)
+}
+
+/// A bone named `name` holding the cubes of `source` as placed by `copy`, at voxel `scale`.
+pub fn place_copy(source: &McBone, name: &str, copy: &RigidCopy, scale: f32) -> McBone {
+    // Cell `p` covers `p..p + 1`, so the cubes' center sits half a voxel up from the model's
+    let (from, to) = (copy.from * scale + 0.5, copy.to * scale + 0.5);
+    let moves_only = copy.rotation.angle_between(Quat::IDENTITY) < MIN_ANGLE;
+
+    let cubes = source.cubes.iter()
+        .map(|cube| {
+            let mut cube = cube.clone();
+            let shift = if moves_only {
+                (to - from).round()
+            } else {
+                // Already rotated cubes keep turning about their own pivot, carried along
+                let own = cube.rotation.map_or(Quat::IDENTITY, |[x, y, z]| {
+                    Quat::from_euler(EulerRot::ZYX, -z.to_radians(), -y.to_radians(), -x.to_radians())
+                });
+                let pivot = cube.pivot.map_or(from, Vec3::from);
+                let moved = copy.rotation * (pivot - from) + to;
+                cube.rotation = Some(euler_degrees(copy.rotation * own));
+                cube.pivot = Some(moved.to_array());
+                moved - pivot
+            };
+            cube.origin = (Vec3::from(cube.origin) + shift).to_array();
+            if moves_only {
+                cube.pivot = cube.pivot.map(|p| (Vec3::from(p) + shift).to_array());
+            }
+            if let Some(surface) = &mut cube.surface {
+                surface.translate(shift);
+            }
+            cube
+        })
+        .collect();
+
+    McBone {
+        name: name.to_string(),
+        parent: None,
+        pivot: (copy.to * scale).round().as_ivec3().to_array(),
+        binding: None,
+        cubes,
+        poly_mesh: None,
+        locators: BTreeMap::new(),
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashSet};
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::rasterize;
+use crate::messages::Message;
+use crate::{run_greedy_meshing, McBone, McCube};
+
+// ================= CUTTING PLANES =================
+//
+// Splits parts off the voxelized model along planes so wheels, doors and
+// turrets can be animated. Everything in front of a plane (the side its
+// normal points to) becomes a bone of the plane's name, pivoting on the
+// plane at the middle of the part. Cubes on one side move whole; cubes
+// crossing the plane are cut along whole cells, rotated ones go by their
+// center. Planes apply in order, so a later plane can cut a part off an
+// earlier one; the new bone's parent is the bone most of it came from.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CutPlane {
+    /// Name of the bone the cut-off part becomes.
+    pub name: String,
+    /// A point on the plane, in geometry units (1/16 block).
+    pub point: [f32; 3],
+    /// Points towards the part that is cut off.
+    pub normal: [f32; 3],
+    /// Only cut this bone; unset cuts every bone.
+    #[serde(default)]
+    pub bone: Option<String>,
+}
+
+/// Plane in voxel cells, for grids with stretched cells.
+struct Plane {
+    point: Vec3,
+    normal: Vec3,
+}
+
+impl Plane {
+    fn new(cut: &CutPlane, cell_size: Vec3) -> Self {
+        // Normals transform by the inverse
+        Self { point: Vec3::from(cut.point) / cell_size, normal: (Vec3::from(cut.normal) * cell_size).normalize_or_zero() }
+    }
+
+    fn in_front(&self, p: Vec3) -> bool {
+        (p - self.point).dot(self.normal) > 0.0
+    }
+
+    fn project(&self, p: Vec3) -> Vec3 {
+        p - self.normal * (p - self.point).dot(self.normal)
+    }
+}
+
+/// `cube` split into the parts behind and in front of `plane`.
+fn cut(cube: McCube, plane: &Plane) -> (Vec<McCube>, Vec<McCube>) {
+    let min = Vec3::from(cube.origin);
+    let max = min + Vec3::from(cube.size);
+    if cube.rotation.is_some() {
+        return match plane.in_front((min + max) / 2.0) {
+            true => (vec![], vec![cube]),
+            false => (vec![cube], vec![]),
+        };
+    }
+
+    let corners = (0..8).map(|i| min + (max - min) * Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32));
+    let sides: Vec<bool> = corners.map(|c| plane.in_front(c)).collect();
+    if sides.iter().all(|&s| s) {
+        return (vec![], vec![cube]);
+    }
+    if !sides.iter().any(|&s| s) {
+        return (vec![cube], vec![]);
+    }
+
+    let single = McBone {
+        name: String::new(),
+        parent: None,
+        pivot: [0, 0, 0],
+        binding: None,
+        cubes: vec![cube.clone()],
+        poly_mesh: None,
+        locators: BTreeMap::new(),
+    };
+    let (front, back): (HashSet<IVec3, RandomState>, _) = rasterize(&[single])
+        .into_iter()
+        .partition(|&cell| plane.in_front(cell.as_vec3() + 0.5));
+    let restyle = |cells: &HashSet<IVec3, RandomState>| {
+        run_greedy_meshing(cells).into_iter()
+            .map(|c| McCube { origin: c.origin, size: c.size, ..cube.clone() })
+            .collect::<Vec<_>>()
+    };
+    (restyle(&back), restyle(&front))
+}
+
+fn volume(cubes: &[McCube]) -> f32 {
+    cubes.iter().map(|c| Vec3::from(c.size).element_product()).sum()
+}
+
+/// Applies `cuts` to the voxelized `bones`, whose cells are `cell_size` geometry units.
+pub fn apply_cuts(bones: &mut Vec<McBone>, cuts: &[CutPlane], cell_size: Vec3) -> Result<(), Message> {
+    for cut_plane in cuts {
+        if bones.iter().any(|b| b.name == cut_plane.name) {
+            return Err(Message::new("error.cut_bone_exists").with("bone", &cut_plane.name));
+        }
+        if let Some(name) = &cut_plane.bone {
+            if !bones.iter().any(|b| &b.name == name) {
+                return Err(Message::new("error.unknown_cut_bone").with("plane", &cut_plane.name).with("bone", name));
+            }
+        }
+
+        let plane = Plane::new(cut_plane, cell_size);
+        let mut part = Vec::new();
+        // Cut-off volume per source bone, for picking the parent
+        let mut sources: BTreeMap<&str, f32> = BTreeMap::new();
+        for bone in bones.iter_mut() {
+            if cut_plane.bone.as_ref().is_some_and(|name| name != &bone.name) {
+                continue;
+            }
+            let mut kept = Vec::with_capacity(bone.cubes.len());
+            let mut front = Vec::new();
+            for cube in std::mem::take(&mut bone.cubes) {
+                let (behind, ahead) = cut(cube, &plane);
+                kept.extend(behind);
+                front.extend(ahead);
+            }
+            bone.cubes = kept;
+            if !front.is_empty() {
+                sources.insert(&bone.name, volume(&front));
+                part.extend(front);
+            }
+        }
+        let Some((&parent, _)) = sources.iter().max_by(|a, b| a.1.total_cmp(b.1)) else { continue };
+        let parent = parent.to_string();
+
+        let (lo, hi) = part.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(lo, hi), c| {
+            let min = Vec3::from(c.origin);
+            (lo.min(min), hi.max(min + Vec3::from(c.size)))
+        });
+        let pivot = plane.project((lo + hi) / 2.0).round().as_ivec3();
+        bones.push(McBone {
+            name: cut_plane.name.clone(),
+            parent: Some(parent),
+            pivot: pivot.to_array(),
+            binding: None,
+            cubes: part,
+            poly_mesh: None,
+            locators: BTreeMap::new(),
+        });
+    }
+    Ok(())
+}
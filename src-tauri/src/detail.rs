@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Message;
+use crate::{run_greedy_meshing, voxelize_mesh, ConvertOptions, GeometryMode, McBone, McCube};
+
+// ================= DETAIL REGIONS =================
+//
+// Spends the cube budget where it shows, on faces and logos: the part of the
+// model inside a box is voxelized again at a higher scale, and the coarse
+// voxels there are dropped. The fine cubes are scaled down into the
+// geometry, in the object's own bone or, for a named region, a bone of the
+// region's name the frontend can reconvert without touching the rest. Only
+// the surface is refined; an interior fill stops at the region. Where regions
+// overlap, the one listed first refines the shared part.
+//
+// Boxes in the options are in the voxel grid's coordinates, before
+// `voxel_origin` moves the geometry. A box picked on a finished conversion is
+// in output coordinates instead; it is moved back by the conversion's offset
+// and only that box is voxelized again and merged into the kept geometry.
+//
+// Adaptive, spilled and poly mesh conversions don't refine; they warn that
+// the regions were left out.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailRegion {
+    /// Bone the fine cubes go to; unset keeps them in each object's bone.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Corners of the box, in geometry units (1/16 block) on the voxel grid
+    /// before `voxel_origin` moves the model.
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    /// Voxels per model unit inside the box, like the conversion scale.
+    pub scale: f32,
+}
+
+/// Low and high corners of `region`.
+fn corners(region: &DetailRegion) -> (Vec3, Vec3) {
+    let (a, b) = (Vec3::from(region.min), Vec3::from(region.max));
+    (a.min(b), a.max(b))
+}
+
+/// `region` in voxel cells of a grid with `cell_size` cells.
+fn cell_box(region: &DetailRegion, cell_size: Vec3) -> (Vec3, Vec3) {
+    let (min, max) = corners(region);
+    (min / cell_size, max / cell_size)
+}
+
+fn in_box(cell: IVec3, (min, max): (Vec3, Vec3)) -> bool {
+    let center = cell.as_vec3() + 0.5;
+    center.cmpge(min).all() && center.cmplt(max).all()
+}
+
+/// Whether the center of voxel `cell` lies inside any of `regions`.
+pub fn covers(regions: &[DetailRegion], cell_size: Vec3, cell: IVec3) -> bool {
+    regions.iter().any(|r| in_box(cell, cell_box(r, cell_size)))
+}
+
+/// Warns when the conversion `options` ask for leaves the detail regions out.
+pub fn skipped_warning(options: &ConvertOptions) -> Option<Message> {
+    if options.detail_regions.is_empty() {
+        return None;
+    }
+    let option = if options.geometry_mode == GeometryMode::PolyMesh {
+        "geometry_mode"
+    } else if options.adaptive_levels > 0 {
+        "adaptive_levels"
+    } else if options.spill_slab > 0 {
+        "spill_slab"
+    } else {
+        return None;
+    };
+    Some(Message::new("warning.detail_regions_skipped").with("option", option))
+}
+
+/// `region` picked on geometry moved by `offset`, moved back onto the voxel grid.
+pub fn unshift(region: &DetailRegion, offset: IVec3) -> DetailRegion {
+    let back = |p: [f32; 3]| (Vec3::from(p) - offset.as_vec3()).to_array();
+    DetailRegion { min: back(region.min), max: back(region.max), ..region.clone() }
+}
+
+/// The part of `a` inside `b`, with the name and scale of `a`.
+pub fn overlap(a: &DetailRegion, b: &DetailRegion) -> Option<DetailRegion> {
+    let ((a_min, a_max), (b_min, b_max)) = (corners(a), corners(b));
+    let (min, max) = (a_min.max(b_min), a_max.min(b_max));
+    min.cmplt(max).all().then(|| DetailRegion { min: min.to_array(), max: max.to_array(), ..a.clone() })
+}
+
+/// Cuts the voxels `region` covers out of `bones`, finished geometry with
+/// `cell_size` cells moved by `offset`, splitting cubes across the edges of
+/// the box; returns the number of voxels removed. Rotated cubes stay whole.
+pub fn carve(bones: &mut [McBone], region: &DetailRegion, cell_size: Vec3, offset: IVec3) -> usize {
+    // The cells whose centers lie in the box, as `covers` picks them
+    let (lo, hi) = cell_box(region, cell_size);
+    let placed = |cells: Vec3| cells * cell_size + offset.as_vec3();
+    let (min, max) = (placed((lo - 0.5).ceil()), placed((hi - 0.5).ceil()));
+
+    let volume = |cube: &McCube| Vec3::from(cube.size).element_product();
+    let mut removed = 0.0;
+    for bone in bones {
+        for cube in std::mem::take(&mut bone.cubes) {
+            let pieces = cut_out(&cube, min, max);
+            removed += volume(&cube) - pieces.iter().map(volume).sum::<f32>();
+            bone.cubes.extend(pieces);
+        }
+    }
+    (removed / cell_size.element_product()).round() as usize
+}
+
+/// The parts of `cube` outside the box `min`..`max`.
+fn cut_out(cube: &McCube, min: Vec3, max: Vec3) -> Vec<McCube> {
+    let (mut lo, mut hi) = (Vec3::from(cube.origin), Vec3::from(cube.origin) + Vec3::from(cube.size));
+    if cube.rotation.is_some() || lo.cmpge(max).any() || hi.cmple(min).any() {
+        return vec![cube.clone()];
+    }
+    let mut pieces = Vec::new();
+    let mut piece = |lo: Vec3, hi: Vec3| {
+        pieces.push(McCube { origin: lo.to_array(), size: (hi - lo).to_array(), ..cube.clone() });
+    };
+    for axis in 0..3 {
+        if lo[axis] < min[axis] {
+            let mut end = hi;
+            end[axis] = min[axis];
+            piece(lo, end);
+            lo[axis] = min[axis];
+        }
+        if hi[axis] > max[axis] {
+            let mut start = lo;
+            start[axis] = max[axis];
+            piece(start, hi);
+            hi[axis] = max[axis];
+        }
+    }
+    pieces
+}
+
+/// Voxels of `region`'s scale in `cubes` refined for it at `scale`.
+pub fn voxel_count(cubes: &[McCube], region: &DetailRegion, scale: f32) -> usize {
+    let voxel = scale / region.scale;
+    let volume: f32 = cubes.iter().map(|c| Vec3::from(c.size).element_product()).sum();
+    (volume / voxel.powi(3)).round() as usize
+}
+
+/// Cubes of the mesh inside `region` at its scale, in voxels of `scale`,
+/// leaving out the voxels of the `earlier` regions.
+pub fn refine(
+    vertices: &[Vec3],
+    indices: &[u32],
+    scale: f32,
+    epsilon: f32,
+    region: &DetailRegion,
+    earlier: &[DetailRegion],
+    cell_size: Vec3,
+) -> Vec<McCube> {
+    if region.scale <= 0.0 {
+        return vec![];
+    }
+    let cells_box = cell_box(region, cell_size);
+    // Vertices are in model units, the box in coarse voxels
+    let (lo, hi) = (cells_box.0 / scale, cells_box.1 / scale);
+    let inside: Vec<u32> = indices.chunks(3)
+        .filter(|t| {
+            let corners = [0, 1, 2].map(|k| vertices[t[k] as usize]);
+            let t_min = corners.iter().fold(Vec3::INFINITY, |m, &c| m.min(c));
+            let t_max = corners.iter().fold(Vec3::NEG_INFINITY, |m, &c| m.max(c));
+            t_min.cmple(hi).all() && t_max.cmpge(lo).all()
+        })
+        .flatten()
+        .copied()
+        .collect();
+    if inside.is_empty() {
+        return vec![];
+    }
+
+    // Size of a fine voxel in coarse ones; fine voxels fill exactly the coarse ones dropped
+    let ratio = scale / region.scale;
+    let cells: HashSet<IVec3, RandomState> = voxelize_mesh(vertices, &inside, region.scale, epsilon)
+        .into_iter()
+        .filter(|c| {
+            let coarse = ((c.as_vec3() + 0.5) * ratio).floor().as_ivec3();
+            in_box(coarse, cells_box) && !covers(earlier, cell_size, coarse)
+        })
+        .collect();
+
+    run_greedy_meshing(&cells).into_iter()
+        .map(|mut cube| {
+            cube.origin = (Vec3::from(cube.origin) * ratio).to_array();
+            cube.size = (Vec3::from(cube.size) * ratio).to_array();
+            cube
+        })
+        .collect()
+}
@@ -0,0 +1,108 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::messages::{failed, Message};
+use crate::packs::{Pack, Packs};
+
+// ================= DEVELOPMENT PACK FOLDERS =================
+//
+// Bedrock loads packs from `development_resource_packs` and
+// `development_behavior_packs` in its `com.mojang` folder without importing
+// them, and picks up changes when a world is reopened. `format = "dev_packs"`
+// writes both packs straight there, so a conversion can be tried in game
+// without copying anything. The folder is `game_folder` if set, else the first
+// installation found:
+//
+//   Windows  %APPDATA%\Minecraft Bedrock\Users\Shared\games\com.mojang
+//            %LOCALAPPDATA%\Packages\Microsoft.MinecraftUWP_8wekyb3d8bbwe\LocalState\games\com.mojang
+//            (and the same for Preview)
+//   Linux    ~/.local/share/mcpelauncher/games/com.mojang
+//   macOS    ~/Library/Application Support/mcpelauncher/games/com.mojang
+//
+// Files of an earlier export are overwritten; files it had that this one
+// doesn't are left alone.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameFolder {
+    /// Which installation, e.g. "Minecraft Preview".
+    pub name: String,
+    /// The `com.mojang` folder.
+    pub path: String,
+    pub resource_packs: String,
+    pub behavior_packs: String,
+}
+
+fn candidates() -> Vec<(&'static str, PathBuf)> {
+    let var = |name: &str| env::var_os(name).map(PathBuf::from);
+    let mut candidates = Vec::new();
+
+    if cfg!(windows) {
+        if let Some(roaming) = var("APPDATA") {
+            for (name, folder) in [("Minecraft", "Minecraft Bedrock"), ("Minecraft Preview", "Minecraft Bedrock Preview")] {
+                candidates.push((name, roaming.join(folder).join("Users/Shared/games/com.mojang")));
+            }
+        }
+        if let Some(local) = var("LOCALAPPDATA") {
+            for (name, package) in [
+                ("Minecraft", "Microsoft.MinecraftUWP_8wekyb3d8bbwe"),
+                ("Minecraft Preview", "Microsoft.MinecraftWindowsBeta_8wekyb3d8bbwe"),
+            ] {
+                candidates.push((name, local.join("Packages").join(package).join("LocalState/games/com.mojang")));
+            }
+        }
+    } else if cfg!(target_os = "macos") {
+        if let Some(home) = var("HOME") {
+            candidates.push(("mcpelauncher", home.join("Library/Application Support/mcpelauncher/games/com.mojang")));
+        }
+    } else {
+        let data = var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local/share")));
+        if let Some(data) = data {
+            candidates.push(("mcpelauncher", data.join("mcpelauncher/games/com.mojang")));
+        }
+    }
+    candidates
+}
+
+fn game_folder(name: &str, path: &Path) -> GameFolder {
+    let folder = |sub: &str| path.join(sub).to_string_lossy().to_string();
+    GameFolder {
+        name: name.to_string(),
+        path: path.to_string_lossy().to_string(),
+        resource_packs: folder("development_resource_packs"),
+        behavior_packs: folder("development_behavior_packs"),
+    }
+}
+
+/// Bedrock installations found on this machine.
+pub fn find_game_folders() -> Vec<GameFolder> {
+    candidates().into_iter()
+        .filter(|(_, path)| path.is_dir())
+        .map(|(name, path)| game_folder(name, &path))
+        .collect()
+}
+
+fn write_pack(pack: &Pack, folder: &Path) -> Result<(), Message> {
+    for (name, data) in &pack.files {
+        let path = folder.join(&pack.name).join(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(failed("error.create_folder"))?;
+        }
+        fs::write(&path, data).map_err(failed("error.write_file"))?;
+    }
+    Ok(())
+}
+
+/// Writes `packs` into the development folders; returns the resource pack folder.
+pub fn write_dev_packs(packs: &Packs, game_folder: Option<&str>) -> Result<PathBuf, Message> {
+    let folder = match game_folder {
+        Some(path) => self::game_folder("", Path::new(path)),
+        None => find_game_folders().into_iter().next().ok_or_else(|| Message::new("error.no_game_folder"))?,
+    };
+
+    write_pack(&packs.resource, Path::new(&folder.resource_packs))?;
+    write_pack(&packs.behavior, Path::new(&folder.behavior_packs))?;
+    Ok(Path::new(&folder.resource_packs).join(&packs.resource.name))
+}
@@ -0,0 +1,149 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::messages::{failed, Message};
+
+// ================= URL DOWNLOADS =================
+
+/// Downloads larger than this are rejected.
+pub const MAX_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+const PROGRESS_STEP: u64 = 256 * 1024;
+
+/// Emitted as `download-progress` while a model is fetched from a URL.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub url: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+pub fn is_url(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Last path segment of the URL, stripped of query/fragment and unsafe characters.
+fn file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name: String = path.rsplit('/')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+
+    if name.trim_matches('.').is_empty() { "model.obj".to_string() } else { name }
+}
+
+/// Cache validators the server sent with a download, as `(header, value)`.
+const VALIDATORS: [(&str, &str); 2] = [("etag", "if-none-match"), ("last-modified", "if-modified-since")];
+
+/// Validators saved next to a cached download, one `header: value` per line.
+fn read_validators(path: &Path) -> Vec<(String, String)> {
+    fs::read_to_string(path).unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .map(|(header, value)| (header.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Downloads `url` into the temp folder and returns the local file path.
+/// Files keep their original name, so output names match the remote model.
+/// A URL that was already downloaded is reused while the server answers the
+/// saved ETag or Last-Modified with 304 Not Modified, or can't be reached;
+/// without either, it is downloaded again.
+pub fn download(url: &str, on_progress: &dyn Fn(DownloadProgress)) -> Result<String, Message> {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+
+    let dir: PathBuf = std::env::temp_dir()
+        .join("obj2mc-downloads")
+        .join(format!("{:016x}", hasher.finish()));
+    let target = dir.join(file_name(url));
+    let validators_file = dir.join("validators");
+    let cached = target.is_file();
+
+    let download_failed = |e: &dyn std::fmt::Display| Message::new("error.download").with("url", url).with("error", e);
+    let too_large = || Message::new("error.download_too_large").with("limit", MAX_DOWNLOAD_BYTES / 1024 / 1024);
+
+    let mut request = ureq::get(url);
+    if cached {
+        for (header, value) in read_validators(&validators_file) {
+            if let Some((_, condition)) = VALIDATORS.iter().find(|(name, _)| *name == header) {
+                request = request.header(*condition, value);
+            }
+        }
+    }
+    let response = match request.call() {
+        Ok(response) if cached && response.status() == 304 => return Ok(target.to_string_lossy().to_string()),
+        Ok(response) => response,
+        // Offline: the copy from last time is better than nothing
+        Err(e) if cached && !matches!(e, ureq::Error::StatusCode(_)) => return Ok(target.to_string_lossy().to_string()),
+        Err(e) => return Err(download_failed(&e)),
+    };
+
+    let validators: String = VALIDATORS.iter()
+        .filter_map(|(header, _)| {
+            let value = response.headers().get(*header)?.to_str().ok()?;
+            Some(format!("{}: {}\n", header, value))
+        })
+        .collect();
+
+    let total = response.headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    if total.is_some_and(|total| total > MAX_DOWNLOAD_BYTES) {
+        return Err(too_large());
+    }
+
+    fs::create_dir_all(&dir).map_err(failed("error.create_download_folder"))?;
+
+    // Download next to the target and rename once complete, so an interrupted
+    // download is never picked up as a cached file.
+    let partial = dir.join(format!("{}.partial", file_name(url)));
+    let result = (|| {
+        let mut reader = response.into_body().into_reader();
+        let mut writer = BufWriter::new(File::create(&partial).map_err(|e| download_failed(&e))?);
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut downloaded = 0u64;
+        let mut next_report = 0u64;
+
+        loop {
+            let read = reader.read(&mut buffer).map_err(|e| download_failed(&e))?;
+            if read == 0 { break; }
+
+            downloaded += read as u64;
+            if downloaded > MAX_DOWNLOAD_BYTES {
+                return Err(too_large());
+            }
+            writer.write_all(&buffer[..read]).map_err(|e| download_failed(&e))?;
+
+            if downloaded >= next_report {
+                on_progress(DownloadProgress { url: url.to_string(), downloaded, total });
+                next_report = downloaded + PROGRESS_STEP;
+            }
+        }
+
+        writer.flush().map_err(|e| download_failed(&e))?;
+        on_progress(DownloadProgress { url: url.to_string(), downloaded, total: Some(downloaded) });
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+
+    fs::rename(&partial, &target).map_err(failed("error.save_download"))?;
+    // Without validators the next conversion downloads the model again
+    let _ = fs::write(&validators_file, validators);
+    Ok(target.to_string_lossy().to_string())
+}
@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+use ahash::RandomState;
+use glam::IVec3;
+
+// ================= INTERIOR FILL =================
+//
+// Cells enclosed by the voxelized shell are found by flood-filling the outside
+// of the shell's bounding box (padded by one cell so the outside is connected).
+// Whatever the flood can't reach and isn't shell is interior.
+
+struct BitGrid {
+    bits: Vec<u64>,
+}
+
+impl BitGrid {
+    fn new(len: usize) -> Self {
+        Self { bits: vec![0; len.div_ceil(64)] }
+    }
+
+    fn get(&self, i: usize) -> bool {
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn set(&mut self, i: usize) {
+        self.bits[i / 64] |= 1 << (i % 64);
+    }
+}
+
+/// Returns every empty cell fully enclosed by `shell` (6-connected flood).
+pub fn interior_cells(shell: &HashSet<IVec3, RandomState>) -> Vec<IVec3> {
+    let mut iter = shell.iter();
+    let Some(&first) = iter.next() else { return vec![] };
+    let (min, max) = iter.fold((first, first), |(min, max), &v| (min.min(v), max.max(v)));
+
+    let min = min - IVec3::ONE;
+    let size = (max + IVec3::ONE - min + IVec3::ONE).as_uvec3();
+    let (sx, sy, sz) = (size.x as usize, size.y as usize, size.z as usize);
+    let len = sx * sy * sz;
+
+    let index = |p: IVec3| {
+        let l = p - min;
+        l.x as usize + sx * (l.y as usize + sy * l.z as usize)
+    };
+
+    let mut solid = BitGrid::new(len);
+    for &v in shell {
+        solid.set(index(v));
+    }
+
+    let mut outside = BitGrid::new(len);
+    let mut stack = vec![0usize];
+    outside.set(0);
+
+    while let Some(i) = stack.pop() {
+        let (x, y, z) = (i % sx, (i / sx) % sy, i / (sx * sy));
+        let neighbors = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < sx).then(|| i + 1),
+            (y > 0).then(|| i - sx),
+            (y + 1 < sy).then(|| i + sx),
+            (z > 0).then(|| i - sx * sy),
+            (z + 1 < sz).then(|| i + sx * sy),
+        ];
+
+        for n in neighbors.into_iter().flatten() {
+            if !outside.get(n) && !solid.get(n) {
+                outside.set(n);
+                stack.push(n);
+            }
+        }
+    }
+
+    let mut interior = Vec::new();
+    for z in 0..sz {
+        for y in 0..sy {
+            for x in 0..sx {
+                let i = x + sx * (y + sy * z);
+                if !solid.get(i) && !outside.get(i) {
+                    interior.push(min + IVec3::new(x as i32, y as i32, z as i32));
+                }
+            }
+        }
+    }
+
+    interior
+}
@@ -0,0 +1,538 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use glam::{Mat3, Mat4, Quat, Vec3};
+use serde::Deserialize;
+
+use crate::base64;
+use crate::messages::{failed, Message};
+use crate::McBone;
+
+// ================= GLTF IMPORT =================
+//
+// glTF 2.0 files (.gltf with its buffers, or a single .glb) load into the
+// same objects and materials as an OBJ, so the rest of the pipeline doesn't
+// know the difference. Every mesh node becomes an object named after the
+// node, its vertices moved by the node's world transform; the primitives of
+// a mesh (one per material) are joined into that object, keeping the
+// material of each triangle. The node tree is
+// kept as the bones' parents; empty nodes with bones below them become bones
+// without cubes.
+//
+// Materials take the base color factor and texture. Textures embedded in
+// the file are written to the temp folder so they load like any other,
+// named by a hash of the file's full path so two models of the same name
+// don't paint with each other's images;
+// only PNG textures are painted, as with OBJ. Sparse accessors, morph
+// targets and skins are ignored, as are primitives that aren't triangles.
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const MODE_TRIANGLES: u32 = 4;
+const MODE_TRIANGLE_STRIP: u32 = 5;
+const MODE_TRIANGLE_FAN: u32 = 6;
+
+#[derive(Deserialize)]
+struct Document {
+    #[serde(default)]
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneDef>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    meshes: Vec<MeshDef>,
+    #[serde(default)]
+    accessors: Vec<Accessor>,
+    #[serde(default, rename = "bufferViews")]
+    buffer_views: Vec<BufferView>,
+    #[serde(default)]
+    buffers: Vec<Buffer>,
+    #[serde(default)]
+    materials: Vec<MaterialDef>,
+    #[serde(default)]
+    textures: Vec<TextureDef>,
+    #[serde(default)]
+    images: Vec<Image>,
+}
+
+#[derive(Deserialize)]
+struct SceneDef {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    name: Option<String>,
+    mesh: Option<usize>,
+    #[serde(default)]
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+struct MeshDef {
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Deserialize)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "triangles")]
+    mode: u32,
+}
+
+fn triangles() -> u32 {
+    MODE_TRIANGLES
+}
+
+#[derive(Deserialize)]
+struct Accessor {
+    #[serde(rename = "bufferView")]
+    buffer_view: Option<usize>,
+    #[serde(default, rename = "byteOffset")]
+    byte_offset: usize,
+    #[serde(rename = "componentType")]
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct BufferView {
+    buffer: usize,
+    #[serde(default, rename = "byteOffset")]
+    byte_offset: usize,
+    #[serde(rename = "byteLength")]
+    byte_length: usize,
+    #[serde(rename = "byteStride")]
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Buffer {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MaterialDef {
+    name: Option<String>,
+    #[serde(rename = "pbrMetallicRoughness")]
+    pbr: Option<Pbr>,
+}
+
+#[derive(Deserialize)]
+struct Pbr {
+    #[serde(rename = "baseColorFactor")]
+    base_color_factor: Option<[f32; 4]>,
+    #[serde(rename = "baseColorTexture")]
+    base_color_texture: Option<TextureRef>,
+}
+
+#[derive(Deserialize)]
+struct TextureRef {
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct TextureDef {
+    source: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Image {
+    uri: Option<String>,
+    #[serde(rename = "bufferView")]
+    buffer_view: Option<usize>,
+    #[serde(rename = "mimeType")]
+    mime_type: Option<String>,
+}
+
+/// Each object's or empty node's bone name with the bone name of its parent node.
+pub type Hierarchy = Vec<(String, Option<String>)>;
+
+pub struct GltfScene {
+    pub models: Vec<tobj::Model>,
+    pub materials: Vec<tobj::Material>,
+    pub hierarchy: Hierarchy,
+}
+
+pub fn is_gltf(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gltf") || ext.eq_ignore_ascii_case("glb"))
+}
+
+pub fn load_gltf(path: &str) -> Result<GltfScene, Message> {
+    let bytes = fs::read(path).map_err(failed("error.load_gltf"))?;
+    let (json, bin) = if bytes.starts_with(GLB_MAGIC) { split_glb(&bytes)? } else { (&bytes[..], None) };
+    let document: Document = serde_json::from_slice(json).map_err(failed("error.load_gltf"))?;
+
+    let folder = Path::new(path).parent().unwrap_or(Path::new(""));
+    let buffers = document.buffers.iter()
+        .map(|buffer| match &buffer.uri {
+            Some(uri) => read_uri(uri, folder),
+            None => bin.map(<[u8]>::to_vec).ok_or_else(|| invalid("a buffer has no data")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let reader = Reader { document: &document, buffers: &buffers };
+    let mut hasher = DefaultHasher::new();
+    fs::canonicalize(path).unwrap_or_else(|_| path.into()).hash(&mut hasher);
+    let stem = Path::new(path).file_stem().map_or("model".into(), |s| s.to_string_lossy());
+    let prefix = format!("{}-{:016x}", stem, hasher.finish());
+    let materials = document.materials.iter()
+        .map(|m| reader.material(m, folder, &prefix))
+        .collect();
+
+    let roots = match document.scenes.get(document.scene.unwrap_or(0)) {
+        Some(scene) => scene.nodes.clone(),
+        // Without scenes, every node that isn't a child is a root
+        None => {
+            let children: HashSet<usize> = document.nodes.iter().flat_map(|n| n.children.iter().copied()).collect();
+            (0..document.nodes.len()).filter(|i| !children.contains(i)).collect()
+        }
+    };
+
+    let mut walk = Walk { reader: &reader, models: vec![], hierarchy: vec![], names: HashSet::new(), visited: HashSet::new() };
+    for root in roots {
+        walk.node(root, Mat4::IDENTITY, None)?;
+    }
+    Ok(GltfScene { models: walk.models, materials, hierarchy: walk.hierarchy })
+}
+
+fn invalid(problem: &str) -> Message {
+    Message::new("error.load_gltf").with("error", problem)
+}
+
+/// The JSON and binary chunks of a .glb file.
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), Message> {
+    let word = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    if word(4) != Some(2) {
+        return Err(invalid("only glTF 2.0 is supported"));
+    }
+    let (mut json, mut bin) = (None, None);
+    let mut at = 12;
+    while let (Some(length), Some(kind)) = (word(at), word(at + 4)) {
+        let chunk = bytes.get(at + 8..at + 8 + length as usize).ok_or_else(|| invalid("truncated chunk"))?;
+        match kind {
+            CHUNK_JSON => json = Some(chunk),
+            CHUNK_BIN => bin = Some(chunk),
+            _ => {}
+        }
+        at += 8 + length as usize;
+    }
+    Ok((json.ok_or_else(|| invalid("no JSON chunk"))?, bin))
+}
+
+/// Contents of a `data:` URI or a file relative to `folder`.
+fn read_uri(uri: &str, folder: &Path) -> Result<Vec<u8>, Message> {
+    match uri.strip_prefix("data:") {
+        Some(data) => {
+            let (_, encoded) = data.split_once(";base64,").ok_or_else(|| invalid("data URI is not base64"))?;
+            base64::decode(encoded).ok_or_else(|| invalid("invalid base64 data"))
+        }
+        None => fs::read(folder.join(percent_decode(uri))).map_err(failed("error.load_gltf")),
+    }
+}
+
+/// Undoes `%20`-style escapes in file URIs.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+struct Reader<'a> {
+    document: &'a Document,
+    buffers: &'a [Vec<u8>],
+}
+
+impl Reader<'_> {
+    fn view(&self, index: usize) -> Result<(&[u8], Option<usize>), Message> {
+        let view = self.document.buffer_views.get(index).ok_or_else(|| invalid("missing buffer view"))?;
+        let bytes = self.buffers.get(view.buffer)
+            .and_then(|b| b.get(view.byte_offset..view.byte_offset + view.byte_length))
+            .ok_or_else(|| invalid("buffer view outside its buffer"))?;
+        Ok((bytes, view.byte_stride))
+    }
+
+    /// Components of each element of accessor `index`, normalized integers as 0-1 (or -1-1).
+    fn read(&self, index: usize) -> Result<Vec<Vec<f32>>, Message> {
+        let accessor = self.document.accessors.get(index).ok_or_else(|| invalid("missing accessor"))?;
+        let components = match accessor.kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            _ => return Err(invalid("unsupported accessor type")),
+        };
+        let size = match accessor.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(invalid("unsupported component type")),
+        };
+        // An accessor without a buffer view is all zeros
+        let Some(view) = accessor.buffer_view else {
+            return Ok(vec![vec![0.0; components]; accessor.count]);
+        };
+        let (bytes, stride) = self.view(view)?;
+        let stride = stride.unwrap_or(components * size);
+
+        let component = |at: usize| -> Option<f32> {
+            let b = bytes.get(at..at + size)?;
+            let n = accessor.normalized;
+            Some(match accessor.component_type {
+                5120 => if n { (b[0] as i8 as f32 / 127.0).max(-1.0) } else { b[0] as i8 as f32 },
+                5121 => if n { b[0] as f32 / 255.0 } else { b[0] as f32 },
+                5122 => {
+                    let v = i16::from_le_bytes([b[0], b[1]]) as f32;
+                    if n { (v / 32767.0).max(-1.0) } else { v }
+                }
+                5123 => {
+                    let v = u16::from_le_bytes([b[0], b[1]]) as f32;
+                    if n { v / 65535.0 } else { v }
+                }
+                5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            })
+        };
+        (0..accessor.count)
+            .map(|i| {
+                let at = accessor.byte_offset + i * stride;
+                (0..components).map(|c| component(at + c * size)).collect::<Option<Vec<f32>>>()
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("accessor outside its buffer view"))
+    }
+
+    fn indices(&self, index: usize) -> Result<Vec<u32>, Message> {
+        let accessor = self.document.accessors.get(index).ok_or_else(|| invalid("missing accessor"))?;
+        // Read as integers: u32 indices don't all fit in an f32
+        if accessor.component_type == 5125 {
+            let (bytes, stride) = match accessor.buffer_view {
+                Some(view) => self.view(view)?,
+                None => return Ok(vec![0; accessor.count]),
+            };
+            let stride = stride.unwrap_or(4);
+            return (0..accessor.count)
+                .map(|i| {
+                    let at = accessor.byte_offset + i * stride;
+                    bytes.get(at..at + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+                })
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid("accessor outside its buffer view"));
+        }
+        Ok(self.read(index)?.into_iter().map(|v| v[0] as u32).collect())
+    }
+
+    fn material(&self, material: &MaterialDef, folder: &Path, prefix: &str) -> tobj::Material {
+        let pbr = material.pbr.as_ref();
+        let [r, g, b, a] = pbr.and_then(|p| p.base_color_factor).unwrap_or([1.0; 4]);
+        let texture = pbr
+            .and_then(|p| p.base_color_texture.as_ref())
+            .and_then(|t| self.document.textures.get(t.index)?.source)
+            .and_then(|image| self.image_path(image, folder, prefix));
+        tobj::Material {
+            name: material.name.clone().unwrap_or_default(),
+            diffuse: Some([r, g, b]),
+            dissolve: Some(a),
+            diffuse_texture: texture,
+            ..Default::default()
+        }
+    }
+
+    /// Path of image `index`, embedded images written to the temp folder
+    /// first, named after `prefix`.
+    fn image_path(&self, index: usize, folder: &Path, prefix: &str) -> Option<String> {
+        let image = self.document.images.get(index)?;
+        if let Some(uri) = image.uri.as_deref().filter(|u| !u.starts_with("data:")) {
+            return Some(folder.join(percent_decode(uri)).to_string_lossy().to_string());
+        }
+        let bytes = match (&image.uri, image.buffer_view) {
+            (Some(uri), _) => read_uri(uri, folder).ok()?,
+            (None, Some(view)) => self.view(view).ok()?.0.to_vec(),
+            (None, None) => return None,
+        };
+        let extension = match image.mime_type.as_deref() {
+            Some("image/jpeg") => "jpg",
+            _ => "png",
+        };
+        let path = std::env::temp_dir().join(format!("obj2mc-{}-{}.{}", prefix, index, extension));
+        fs::write(&path, bytes).ok()?;
+        Some(path.to_string_lossy().to_string())
+    }
+}
+
+struct Walk<'a> {
+    reader: &'a Reader<'a>,
+    models: Vec<tobj::Model>,
+    hierarchy: Hierarchy,
+    names: HashSet<String>,
+    /// Nodes already loaded, so a malformed tree with cycles ends.
+    visited: HashSet<usize>,
+}
+
+impl Walk<'_> {
+    /// `name`, or with a number appended if an earlier object or node has it.
+    fn unique(&mut self, name: String) -> String {
+        let mut unique = name.clone();
+        let mut n = 1;
+        while !self.names.insert(unique.clone()) {
+            unique = format!("{}_{}", name, n);
+            n += 1;
+        }
+        unique
+    }
+
+    fn node(&mut self, index: usize, parent_transform: Mat4, parent: Option<String>) -> Result<(), Message> {
+        if !self.visited.insert(index) {
+            return Ok(());
+        }
+        let document = self.reader.document;
+        let node = document.nodes.get(index).ok_or_else(|| invalid("missing node"))?;
+        let local = match node.matrix {
+            Some(m) => Mat4::from_cols_array(&m),
+            None => Mat4::from_scale_rotation_translation(
+                node.scale.map_or(Vec3::ONE, Vec3::from),
+                node.rotation.map_or(Quat::IDENTITY, Quat::from_array),
+                node.translation.map_or(Vec3::ZERO, Vec3::from),
+            ),
+        };
+        let transform = parent_transform * local;
+        let name = self.unique(node.name.clone().filter(|n| !n.is_empty()).unwrap_or_else(|| format!("node_{}", index)));
+        self.hierarchy.push((name.clone(), parent));
+
+        if let Some(mesh) = node.mesh {
+            let mesh = document.meshes.get(mesh).ok_or_else(|| invalid("missing mesh"))?;
+            for primitive in &mesh.primitives {
+                let Some(mesh) = self.primitive(primitive, transform)? else { continue };
+                self.models.push(tobj::Model::new(mesh, name.clone()));
+            }
+        }
+        for &child in &node.children {
+            self.node(child, transform, Some(name.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// A primitive as a mesh in scene space, if it has triangles.
+    fn primitive(&self, primitive: &Primitive, transform: Mat4) -> Result<Option<tobj::Mesh>, Message> {
+        let Some(&positions) = primitive.attributes.get("POSITION") else { return Ok(None) };
+        let positions = self.reader.read(positions)?;
+        let count = positions.len() as u32;
+        let indices = match primitive.indices {
+            Some(accessor) => self.reader.indices(accessor)?,
+            None => (0..count).collect(),
+        };
+        let mut indices = match primitive.mode {
+            MODE_TRIANGLES => indices,
+            MODE_TRIANGLE_STRIP => (2..indices.len())
+                .flat_map(|i| if i % 2 == 0 { [indices[i - 2], indices[i - 1], indices[i]] } else { [indices[i - 1], indices[i - 2], indices[i]] })
+                .collect(),
+            MODE_TRIANGLE_FAN => (2..indices.len()).flat_map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+            _ => return Ok(None),
+        };
+        indices.truncate(indices.len() / 3 * 3);
+        if indices.iter().any(|&i| i >= count) {
+            return Err(invalid("index outside the vertices"));
+        }
+        if indices.is_empty() {
+            return Ok(None);
+        }
+        // Mirroring transforms turn the triangles inside out
+        if transform.determinant() < 0.0 {
+            for t in indices.chunks_mut(3) {
+                t.swap(1, 2);
+            }
+        }
+
+        let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+        let normals = match primitive.attributes.get("NORMAL") {
+            Some(&normals) => self.reader.read(normals)?.iter()
+                .flat_map(|n| (normal_matrix * Vec3::new(n[0], n[1], n[2])).normalize_or_zero().to_array())
+                .collect(),
+            None => vec![],
+        };
+        // glTF UVs start at the top of the image, OBJ ones at the bottom
+        let texcoords = match primitive.attributes.get("TEXCOORD_0") {
+            Some(&uvs) => self.reader.read(uvs)?.iter().flat_map(|uv| [uv[0], 1.0 - uv[1]]).collect(),
+            None => vec![],
+        };
+
+        Ok(Some(tobj::Mesh {
+            positions: positions.iter().flat_map(|p| transform.transform_point3(Vec3::new(p[0], p[1], p[2])).to_array()).collect(),
+            normals: if normals.len() == positions.len() * 3 { normals } else { vec![] },
+            texcoords: if texcoords.len() == positions.len() * 2 { texcoords } else { vec![] },
+            indices,
+            material_id: primitive.material,
+            ..Default::default()
+        }))
+    }
+}
+
+/// Parents `bones` as the node tree in `hierarchy` does, adding a bone
+/// without cubes for each empty node with bones below it.
+pub fn attach_hierarchy(bones: &mut Vec<McBone>, hierarchy: &Hierarchy) {
+    if hierarchy.is_empty() {
+        return;
+    }
+    let existing: HashSet<String> = bones.iter().map(|b| b.name.clone()).collect();
+    let mut kept = existing.clone();
+    // Children come after their parents, so walking backwards sees them first
+    for (name, parent) in hierarchy.iter().rev() {
+        if let Some(parent) = parent.as_ref().filter(|_| kept.contains(name)) {
+            kept.insert(parent.clone());
+        }
+    }
+
+    let empty = hierarchy.iter()
+        .filter(|(name, _)| kept.contains(name) && !existing.contains(name))
+        .map(|(name, _)| McBone {
+            name: name.clone(),
+            parent: None,
+            pivot: [0, 0, 0],
+            binding: None,
+            cubes: vec![],
+            poly_mesh: None,
+            locators: BTreeMap::new(),
+        })
+        .collect::<Vec<_>>();
+    bones.splice(0..0, empty);
+
+    let parents: HashMap<&str, &str> = hierarchy.iter()
+        .filter_map(|(name, parent)| Some((name.as_str(), parent.as_deref()?)))
+        .collect();
+    for bone in bones.iter_mut() {
+        if let Some(&parent) = parents.get(bone.name.as_str()) {
+            bone.parent = Some(parent.to_string());
+        }
+    }
+}
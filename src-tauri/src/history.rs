@@ -0,0 +1,64 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{failed, Message};
+use crate::ConvertOptions;
+
+// ================= CONVERSION HISTORY =================
+
+const MAX_ENTRIES: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub path: String,
+    pub output_dir: String,
+    pub scale: f32,
+    pub options: ConvertOptions,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl HistoryEntry {
+    pub fn new(path: &str, output_dir: &str, scale: f32, options: &ConvertOptions) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            path: path.to_string(),
+            output_dir: output_dir.to_string(),
+            scale,
+            options: options.clone(),
+            timestamp,
+        }
+    }
+}
+
+/// Reads the history file, newest entry last. A missing or unreadable file is an empty history.
+pub fn load(file: &Path) -> Vec<HistoryEntry> {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn push(file: &Path, entry: HistoryEntry) -> Result<(), Message> {
+    let mut entries = load(file);
+    entries.push(entry);
+    if entries.len() > MAX_ENTRIES {
+        entries.drain(..entries.len() - MAX_ENTRIES);
+    }
+
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(failed("error.create_folder"))?;
+    }
+
+    let file = File::create(file).map_err(failed("error.write_history"))?;
+    serde_json::to_writer(BufWriter::new(file), &entries)
+        .map_err(failed("error.write_history"))
+}
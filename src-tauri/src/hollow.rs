@@ -0,0 +1,61 @@
+use glam::{IVec3, Vec2};
+use serde::{Deserialize, Serialize};
+
+// ================= HOLLOW PATTERNS =================
+//
+// Filled interiors of huge builds cost blocks nobody sees. A hollow pattern
+// keeps only part of the interior cells, as a self-supporting infill like a
+// 3D printer's: the shell from voxelization is never touched, so the outside
+// looks the same. Patterns are laid out in absolute voxel coordinates, so
+// neighbouring bones line up.
+
+const DEFAULT_SPACING: u32 = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HollowPattern {
+    /// Keep the whole interior.
+    #[default]
+    Solid,
+    /// Beams along X, Y and Z every `spacing` cells.
+    Lattice,
+    /// Hexagonal cells `spacing` wide, with walls standing along Y.
+    Honeycomb,
+}
+
+/// On a line of a cubic grid: at least two coordinates on a grid plane.
+fn on_lattice(cell: IVec3, spacing: i32) -> bool {
+    let on = |v: i32| (v.rem_euclid(spacing) == 0) as u32;
+    on(cell.x) + on(cell.y) + on(cell.z) >= 2
+}
+
+/// On a wall between hexagons: about as close to the second-nearest hexagon
+/// center as to the nearest one.
+fn on_honeycomb(cell: IVec3, spacing: i32) -> bool {
+    let spacing = spacing as f32;
+    let row_height = spacing * 3f32.sqrt() / 2.0;
+    let point = Vec2::new(cell.x as f32 + 0.5, cell.z as f32 + 0.5);
+
+    let row = (point.y / row_height).floor() as i32;
+    let mut distances = Vec::with_capacity(9);
+    for r in row - 1..=row + 1 {
+        let shift = if r.rem_euclid(2) == 1 { spacing / 2.0 } else { 0.0 };
+        let column = ((point.x - shift) / spacing).floor() as i32;
+        for c in column - 1..=column + 1 {
+            let center = Vec2::new(c as f32 * spacing + shift, r as f32 * row_height);
+            distances.push(point.distance(center));
+        }
+    }
+    distances.sort_by(f32::total_cmp);
+    distances[1] - distances[0] < 1.0
+}
+
+/// The cells of `interior` the pattern keeps; `spacing` 0 uses the default of 4.
+pub fn carve(interior: Vec<IVec3>, pattern: HollowPattern, spacing: u32) -> Vec<IVec3> {
+    let spacing = if spacing == 0 { DEFAULT_SPACING } else { spacing } as i32;
+    match pattern {
+        HollowPattern::Solid => interior,
+        HollowPattern::Lattice => interior.into_iter().filter(|&c| on_lattice(c, spacing)).collect(),
+        HollowPattern::Honeycomb => interior.into_iter().filter(|&c| on_honeycomb(c, spacing)).collect(),
+    }
+}
@@ -0,0 +1,31 @@
+use std::process::Command;
+
+use crate::messages::Message;
+
+// ================= POST-CONVERSION HOOK =================
+//
+// `post_convert` names a program to run after every successful conversion,
+// e.g. to copy the output into a development pack or trigger a reload script.
+// The first entry is the program, the rest its arguments; the output paths
+// are appended. It runs without a shell, in the current folder, and the
+// conversion waits for it. A failing hook doesn't fail the conversion; it
+// becomes a warning on the result.
+
+/// Runs `command` with `outputs` appended; a warning if it couldn't run or failed.
+pub fn run_hook(command: &[String], outputs: &[String]) -> Option<Message> {
+    let (program, args) = command.split_first()?;
+    let warning = |error: String| Message::new("warning.hook_failed").with("command", program).with("error", error);
+
+    match Command::new(program).args(args).args(outputs).output() {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let error = match stderr.trim() {
+                "" => output.status.to_string(),
+                stderr => stderr.to_string(),
+            };
+            Some(warning(error))
+        }
+        Err(e) => Some(warning(e.to_string())),
+    }
+}
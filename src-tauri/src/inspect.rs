@@ -0,0 +1,309 @@
+use std::fs;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::messages::{failed, Message};
+use crate::perf::{self, PerformanceEstimate};
+use crate::validate;
+
+// ================= GEOMETRY INSPECTION =================
+//
+// Loads an existing geo.json (current `minecraft:geometry` files as well as the
+// legacy 1.8/1.10 layout keyed by `geometry.*`, checked as the current layout
+// it maps to) and collects per-geometry stats. What the game rejects is the
+// error `validate` finds, the same check conversions pass before writing;
+// suspicious but loadable content is a warning.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeometryIssue {
+    pub severity: Severity,
+    /// Where the issue is, e.g. `geometry.chair > bones[2] 'leg'`.
+    pub location: String,
+    pub message: Message,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeometryStats {
+    pub identifier: String,
+    pub texture_width: u32,
+    pub texture_height: u32,
+    pub bone_count: usize,
+    pub cube_count: usize,
+    pub poly_count: usize,
+    pub locator_count: usize,
+    /// Min and max corner of all cubes, in model units.
+    pub bounds: Option<[[f32; 3]; 2]>,
+    pub performance: PerformanceEstimate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeometryReport {
+    pub path: String,
+    pub format_version: Option<String>,
+    /// False when any issue is an error.
+    pub valid: bool,
+    pub geometries: Vec<GeometryStats>,
+    pub issues: Vec<GeometryIssue>,
+}
+
+struct Checker {
+    issues: Vec<GeometryIssue>,
+}
+
+impl Checker {
+    fn warning(&mut self, location: &str, message: Message) {
+        self.issues.push(GeometryIssue { severity: Severity::Warning, location: location.to_string(), message });
+    }
+
+    /// Reads an optional `[x, y, z]` field; malformed ones are `validate`'s to report.
+    fn vec3(object: &Map<String, Value>, key: &str) -> Option<[f32; 3]> {
+        let a = object.get(key)?.as_array().filter(|a| a.len() == 3)?;
+        let v: Vec<f32> = a.iter().filter_map(|c| c.as_f64().map(|c| c as f32)).collect();
+        (v.len() == 3).then(|| [v[0], v[1], v[2]])
+    }
+
+    fn check_uv(&mut self, cube: &Map<String, Value>, size: [f32; 3], texture: (u32, u32), location: &str) {
+        let Some(uv) = cube.get("uv") else { return };
+        let (tw, th) = (texture.0 as f32, texture.1 as f32);
+
+        match uv {
+            Value::Array(a) => {
+                let Some([u, v]) = a.iter().map(Value::as_f64).collect::<Option<Vec<_>>>()
+                    .and_then(|v| <[f64; 2]>::try_from(v).ok())
+                else {
+                    return;
+                };
+                // Box UV unwraps the cube into a (2·(x+z)) × (z+y) strip
+                let [sx, sy, sz] = size.map(|c| c.abs().ceil());
+                let (u, v) = (u as f32, v as f32);
+                if u < 0.0 || v < 0.0 || u + 2.0 * (sx + sz) > tw || v + sz + sy > th {
+                    self.warning(location, Message::new("issue.box_uv_outside"));
+                }
+            }
+            Value::Object(faces) => {
+                for (face, mapping) in faces {
+                    if !["north", "south", "east", "west", "up", "down"].contains(&face.as_str()) {
+                        self.warning(location, Message::new("issue.unknown_uv_face").with("face", face));
+                        continue;
+                    }
+                    let numbers = |key: &str| mapping.get(key)
+                        .and_then(Value::as_array)
+                        .and_then(|a| a.iter().map(Value::as_f64).collect::<Option<Vec<_>>>())
+                        .filter(|v| v.len() == 2);
+                    let Some(uv) = numbers("uv") else { continue };
+                    let size = numbers("uv_size").unwrap_or(vec![0.0, 0.0]);
+                    let u = [uv[0], uv[0] + size[0]];
+                    let v = [uv[1], uv[1] + size[1]];
+                    let outside = |range: [f64; 2], limit: f32| range.iter().any(|&c| c < 0.0 || c > limit as f64);
+                    if outside(u, tw) || outside(v, th) {
+                        self.warning(location, Message::new("issue.face_uv_outside").with("face", face));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_geometry(&mut self, identifier: &str, geometry: &Map<String, Value>, legacy: bool) -> GeometryStats {
+        let size_key = |modern: &'static str, old: &'static str| if legacy { old } else { modern };
+        let (width_key, height_key) = (size_key("texture_width", "texturewidth"), size_key("texture_height", "textureheight"));
+        let description = match legacy {
+            true => Some(geometry),
+            false => geometry.get("description").and_then(Value::as_object),
+        };
+
+        let mut texture_size = |key: &str| match description.and_then(|d| d.get(key)) {
+            None => {
+                self.warning(identifier, Message::new("issue.missing_texture_size").with("key", key));
+                16
+            }
+            Some(v) => v.as_u64().filter(|&n| n > 0).map_or(16, |n| n as u32),
+        };
+        let texture = (texture_size(width_key), texture_size(height_key));
+
+        let mut stats = GeometryStats {
+            identifier: identifier.to_string(),
+            texture_width: texture.0,
+            texture_height: texture.1,
+            bone_count: 0,
+            cube_count: 0,
+            poly_count: 0,
+            locator_count: 0,
+            bounds: None,
+            performance: perf::estimate(0, 0, texture.0, texture.1),
+        };
+
+        let Some(bones) = geometry.get("bones").and_then(Value::as_array).filter(|b| !b.is_empty()) else {
+            self.warning(identifier, Message::new("issue.no_bones"));
+            return stats;
+        };
+
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+
+        for (i, bone) in bones.iter().enumerate() {
+            let Some(bone) = bone.as_object() else { continue };
+            let name = bone.get("name").and_then(Value::as_str);
+            let location = format!("{} > bones[{}] '{}'", identifier, i, name.unwrap_or("?"));
+            stats.bone_count += 1;
+
+            if let Some(locators) = bone.get("locators").and_then(Value::as_object) {
+                stats.locator_count += locators.len();
+            }
+            if let Some(polys) = bone.get("poly_mesh").and_then(|m| m.get("polys")).and_then(Value::as_array) {
+                stats.poly_count += polys.len();
+            }
+
+            let Some(cubes) = bone.get("cubes").and_then(Value::as_array) else { continue };
+            for (j, cube) in cubes.iter().enumerate() {
+                let location = format!("{} > cubes[{}]", location, j);
+                let Some(cube) = cube.as_object() else { continue };
+                stats.cube_count += 1;
+
+                let (Some(origin), Some(size)) = (Self::vec3(cube, "origin"), Self::vec3(cube, "size")) else { continue };
+
+                if size.iter().any(|&c| c < 0.0) {
+                    self.warning(&location, Message::new("issue.negative_size"));
+                } else if size.iter().all(|&c| c == 0.0) {
+                    self.warning(&location, Message::new("issue.empty_cube"));
+                }
+                for k in 0..3 {
+                    let (a, b) = (origin[k], origin[k] + size[k]);
+                    min[k] = min[k].min(a.min(b));
+                    max[k] = max[k].max(a.max(b));
+                }
+                self.check_uv(cube, size, texture, &location);
+            }
+        }
+
+        if stats.cube_count > 0 {
+            stats.bounds = Some([min, max]);
+        }
+        stats.performance = perf::estimate(stats.cube_count, stats.bone_count, texture.0, texture.1);
+        stats
+    }
+}
+
+/// Description fields of the legacy layout, kept at the top of each
+/// geometry, and their names in the current one.
+const LEGACY_DESCRIPTION: [(&str, &str); 5] = [
+    ("texturewidth", "texture_width"),
+    ("textureheight", "texture_height"),
+    ("visible_bounds_width", "visible_bounds_width"),
+    ("visible_bounds_height", "visible_bounds_height"),
+    ("visible_bounds_offset", "visible_bounds_offset"),
+];
+
+/// Bone fields only the legacy layout has.
+const LEGACY_BONE_FIELDS: [&str; 3] = ["neverRender", "reset", "META_BoneType"];
+
+/// The legacy layout `root` as the current one, for `validate`: each
+/// `geometry.name[:parent]` key becomes a geometry with a `description`.
+fn current_layout(root: &Map<String, Value>) -> Value {
+    let geometries = root.iter()
+        .filter(|(key, _)| key.starts_with("geometry."))
+        .map(|(key, geometry)| {
+            let Some(fields) = geometry.as_object() else { return geometry.clone() };
+            let identifier = key.split(':').next().unwrap_or(key);
+            let mut description = Map::from_iter([("identifier".to_string(), json!(identifier))]);
+            let mut converted = Map::new();
+            for (name, value) in fields {
+                match LEGACY_DESCRIPTION.iter().find(|(old, _)| old == name) {
+                    Some((_, new)) => description.insert(new.to_string(), value.clone()),
+                    None => converted.insert(name.clone(), value.clone()),
+                };
+            }
+            for bone in converted.get_mut("bones").and_then(Value::as_array_mut).into_iter().flatten() {
+                if let Some(bone) = bone.as_object_mut() {
+                    bone.retain(|name, _| !LEGACY_BONE_FIELDS.contains(&name.as_str()));
+                }
+            }
+            converted.insert("description".to_string(), Value::Object(description));
+            Value::Object(converted)
+        })
+        .collect();
+
+    let mut current = Map::new();
+    if let Some(version) = root.get("format_version") {
+        current.insert("format_version".to_string(), version.clone());
+    }
+    current.insert("minecraft:geometry".to_string(), Value::Array(geometries));
+    Value::Object(current)
+}
+
+/// Validates the geo.json at `path`. Schema problems are reported as issues;
+/// `Err` means the file couldn't be read or isn't JSON at all.
+pub fn inspect_geometry(path: &str) -> Result<GeometryReport, Message> {
+    let text = fs::read_to_string(path).map_err(failed("error.read_file"))?;
+    let value: Value = serde_json::from_str(&text).map_err(failed("error.parse_json"))?;
+    let root = value.as_object().ok_or_else(|| Message::new("error.geometry_not_object"))?;
+    let legacy = !root.contains_key("minecraft:geometry") && root.keys().any(|k| k.starts_with("geometry."));
+
+    let mut checker = Checker { issues: Vec::new() };
+    let checked = match legacy {
+        true => validate::check_json(&current_layout(root)),
+        false => validate::check_json(&value),
+    };
+    if let Err((mut pointer, problem)) = checked {
+        if legacy {
+            // Point at the geometry's own key rather than its converted index
+            let rest = pointer.strip_prefix("/minecraft:geometry/").unwrap_or_default();
+            let (index, field) = rest.split_once('/').unwrap_or((rest, ""));
+            let key = index.parse().ok().and_then(|i: usize| root.keys().filter(|k| k.starts_with("geometry.")).nth(i));
+            if let Some(key) = key {
+                pointer = format!("/{}/{}", key, field).trim_end_matches('/').to_string();
+            }
+        }
+        checker.issues.push(GeometryIssue {
+            severity: Severity::Error,
+            location: pointer,
+            message: Message::new("issue.invalid").with("problem", problem),
+        });
+    }
+    let format_version = root.get("format_version").and_then(Value::as_str).map(str::to_string);
+
+    let mut geometries = Vec::new();
+    if legacy {
+        // One object per `geometry.name[:parent]` key
+        for (key, geometry) in root.iter().filter(|(k, _)| k.starts_with("geometry.")) {
+            let identifier = key.split(':').next().unwrap_or(key);
+            if let Some(geometry) = geometry.as_object() {
+                geometries.push(checker.check_geometry(identifier, geometry, true));
+            }
+        }
+    } else {
+        let list = root.get("minecraft:geometry").and_then(Value::as_array);
+        for (i, geometry) in list.into_iter().flatten().enumerate() {
+            let Some(geometry) = geometry.as_object() else { continue };
+            let identifier = geometry.get("description")
+                .and_then(|d| d.get("identifier"))
+                .and_then(Value::as_str);
+            let identifier = match identifier {
+                Some(id) => {
+                    if !id.starts_with("geometry.") {
+                        checker.warning(id, Message::new("issue.identifier_prefix"));
+                    }
+                    id.to_string()
+                }
+                None => format!("minecraft:geometry[{}]", i),
+            };
+            geometries.push(checker.check_geometry(&identifier, geometry, false));
+        }
+    }
+
+    Ok(GeometryReport {
+        path: path.to_string(),
+        format_version,
+        valid: checker.issues.iter().all(|i| i.severity != Severity::Error),
+        geometries,
+        issues: checker.issues,
+    })
+}
@@ -0,0 +1,119 @@
+use std::collections::{HashMap, HashSet};
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+
+// ================= REPEATED PARTS =================
+//
+// Architectural models repeat the same part many times inside one object:
+// columns, windows, railings. The triangles are split into connected parts
+// (by shared positions, so UV seams don't split them) and parts that are the
+// same triangles moved elsewhere are grouped. One part of each group is
+// voxelized and its voxels are stamped at the others, moved by whole voxels,
+// so every copy comes out identical; the other triangles are voxelized as
+// usual.
+
+/// Position precision when comparing parts, relative to the object size.
+const PRECISION: f32 = 1e-4;
+
+/// Triangle corners of a part relative to its lowest corner, in precision steps.
+type ShapeKey = Vec<[[i64; 3]; 3]>;
+
+type Voxels = HashSet<IVec3, RandomState>;
+
+/// Triangles of each connected part of the mesh.
+fn parts(vertices: &[Vec3], indices: &[u32]) -> Vec<Vec<usize>> {
+    // Vertices at the same position are one, whatever else differs
+    let mut welded: HashMap<[u32; 3], usize> = HashMap::new();
+    let ids: Vec<usize> = vertices.iter()
+        .map(|v| {
+            let next = welded.len();
+            *welded.entry(v.to_array().map(f32::to_bits)).or_insert(next)
+        })
+        .collect();
+
+    let mut parent: Vec<usize> = (0..welded.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for t in indices.chunks(3) {
+        let a = root(&mut parent, ids[t[0] as usize]);
+        for &v in &t[1..] {
+            let b = root(&mut parent, ids[v as usize]);
+            parent[b] = a;
+        }
+    }
+
+    let mut parts: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (triangle, t) in indices.chunks(3).enumerate() {
+        parts.entry(root(&mut parent, ids[t[0] as usize])).or_default().push(triangle);
+    }
+    let mut parts: Vec<Vec<usize>> = parts.into_values().collect();
+    parts.sort_unstable_by_key(|p| p[0]);
+    parts
+}
+
+/// Shape of a part independent of where it is, and its lowest corner.
+fn shape_key(vertices: &[Vec3], indices: &[u32], triangles: &[usize], step: f32) -> (ShapeKey, Vec3) {
+    let corner = |t: usize, k: usize| vertices[indices[t * 3 + k] as usize];
+    let min = triangles.iter()
+        .flat_map(|&t| (0..3).map(move |k| (t, k)))
+        .fold(Vec3::INFINITY, |min, (t, k)| min.min(corner(t, k)));
+
+    let mut key: ShapeKey = triangles.iter()
+        .map(|&t| {
+            let mut points = [0, 1, 2].map(|k| ((corner(t, k) - min) / step).round().to_array().map(|c| c as i64));
+            // Start at the smallest corner, keeping the winding
+            let first = (0..3).min_by_key(|&k| points[k]).unwrap_or(0);
+            points.rotate_left(first);
+            points
+        })
+        .collect();
+    key.sort_unstable();
+    (key, min)
+}
+
+/// Voxelizes the mesh with `voxelize`, voxelizing repeated parts once.
+pub fn voxelize_instanced<F>(vertices: &[Vec3], indices: &[u32], scale: f32, voxelize: F) -> Voxels
+where
+    F: Fn(&[Vec3], &[u32], f32) -> Voxels,
+{
+    let (lo, hi) = vertices.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let step = (PRECISION * (hi - lo).max_element()).max(f32::EPSILON);
+
+    // Parts with the same shape, with their lowest corners
+    let mut groups: HashMap<ShapeKey, Vec<(Vec<usize>, Vec3)>> = HashMap::new();
+    let mut order = Vec::new();
+    for part in parts(vertices, indices) {
+        let (key, corner) = shape_key(vertices, indices, &part, step);
+        let group = groups.entry(key.clone()).or_default();
+        if group.is_empty() {
+            order.push(key);
+        }
+        group.push((part, corner));
+    }
+
+    let mut voxels = HashSet::default();
+    let mut single = Vec::new();
+    for key in order {
+        let group = &groups[&key];
+        let triangles = |part: &[usize]| part.iter().flat_map(|&t| indices[t * 3..t * 3 + 3].iter().copied()).collect::<Vec<u32>>();
+        if group.len() == 1 {
+            single.extend(triangles(&group[0].0));
+            continue;
+        }
+
+        let (first, origin) = &group[0];
+        let stamp = voxelize(vertices, &triangles(first), scale);
+        for (_, corner) in group {
+            let offset = ((*corner - *origin) * scale).round().as_ivec3();
+            voxels.extend(stamp.iter().map(|&v| v + offset));
+        }
+    }
+    voxels.extend(voxelize(vertices, &single, scale));
+    voxels
+}
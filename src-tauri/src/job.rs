@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::messages::{failed, Message};
+use crate::{convert, convert_shared, download, ConvertOptions, ConvertResult, SharedInput};
+
+// ================= JOB FILES =================
+//
+// A job file (TOML or JSON) lists the inputs of a batch conversion:
+//
+//   output_dir = "out"
+//   scale = 16
+//
+//   [options]
+//   script = "scripts/carve.rhai"
+//
+//   [[inputs]]
+//   path = "models/chair.obj"
+//
+//   [[inputs]]
+//   path = "models/tower.obj"
+//   scale = 32
+//   output_dir = "out/buildings"
+//   options = { script = "scripts/ruin.rhai" }
+//
+// Top-level `output_dir`, `scale` and `options` are defaults for every input;
+// per-input `options` are merged over the defaults key by key. Relative paths
+// are resolved against the directory containing the job file.
+//
+// Inputs whose options name the same `shared_atlas` are converted together
+// when the first of them is reached, with one atlas `<name>.png` for all.
+//
+// While a job runs, the results so far are saved to `<job>.progress.json`
+// after every input. If the run is interrupted, running the same job again
+// skips the inputs already done; the file is removed once the job finishes.
+
+const DEFAULT_SCALE: f32 = 16.0;
+
+#[derive(Debug, Deserialize)]
+struct JobFile {
+    output_dir: Option<String>,
+    scale: Option<f32>,
+    #[serde(default)]
+    options: Map<String, Value>,
+    inputs: Vec<JobInput>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobInput {
+    path: String,
+    output_dir: Option<String>,
+    scale: Option<f32>,
+    #[serde(default)]
+    options: Map<String, Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobEntry {
+    pub path: String,
+    pub output_dir: String,
+    pub scale: f32,
+    pub options: ConvertOptions,
+}
+
+fn resolve(base: &Path, path: &str) -> String {
+    if download::is_url(path) {
+        return path.to_string();
+    }
+    base.join(path).to_string_lossy().to_string()
+}
+
+pub fn load_job(path: &Path) -> Result<Vec<JobEntry>, Message> {
+    let text = fs::read_to_string(path)
+        .map_err(failed("error.read_job_file"))?;
+
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let job: JobFile = if is_json {
+        serde_json::from_str(&text).map_err(failed("error.invalid_job_file"))?
+    } else {
+        toml::from_str(&text).map_err(failed("error.invalid_job_file"))?
+    };
+
+    let base = path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."));
+
+    job.inputs.iter().map(|input| {
+        let output_dir = input.output_dir.as_ref()
+            .or(job.output_dir.as_ref())
+            .ok_or_else(|| Message::new("error.job_no_output_dir").with("path", &input.path))?;
+
+        let mut merged = job.options.clone();
+        merged.extend(input.options.clone());
+
+        let mut options: ConvertOptions = serde_json::from_value(Value::Object(merged))
+            .map_err(|e| Message::new("error.invalid_job_options").with("path", &input.path).with("error", e))?;
+        options.resolve_paths(&base);
+
+        Ok(JobEntry {
+            path: resolve(&base, &input.path),
+            output_dir: resolve(&base, output_dir),
+            scale: input.scale.or(job.scale).unwrap_or(DEFAULT_SCALE),
+            options,
+        })
+    }).collect()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JobResult {
+    pub result: ConvertResult,
+    /// Time taken by the input, download included.
+    pub elapsed: Duration,
+}
+
+#[derive(Deserialize)]
+struct Progress {
+    /// The job's inputs, so progress of an edited job isn't reused.
+    entries: Value,
+    results: Vec<JobResult>,
+}
+
+pub fn progress_file(job_path: &Path) -> PathBuf {
+    job_path.with_extension("progress.json")
+}
+
+/// Results saved by an interrupted run of the same `entries`, if any.
+pub fn load_progress(file: &Path, entries: &[JobEntry]) -> Vec<JobResult> {
+    let progress: Option<Progress> = fs::read_to_string(file)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok());
+
+    match progress {
+        Some(progress) if progress.entries == serde_json::to_value(entries).unwrap()
+            && progress.results.len() <= entries.len() => progress.results,
+        _ => Vec::new(),
+    }
+}
+
+/// Written to a temporary file first so a crash mid-write keeps the previous progress.
+fn save_progress(file: &Path, entries: &[JobEntry], results: &[JobResult]) -> Result<(), Message> {
+    let progress = json!({ "entries": entries, "results": results });
+    let temp = file.with_extension("tmp");
+    fs::write(&temp, progress.to_string()).map_err(failed("error.write_progress"))?;
+    fs::rename(&temp, file).map_err(failed("error.write_progress"))
+}
+
+/// Runs the inputs after the `done` ones, saving progress to `progress` (if
+/// given) after each. Returns the results of every input, `done` included.
+pub fn run_job(entries: &[JobEntry], done: Vec<JobResult>, progress: Option<&Path>) -> Result<Vec<JobResult>, Message> {
+    let mut results = done;
+    // Results of shared-atlas inputs converted along with an earlier one
+    let mut converted: HashMap<usize, JobResult> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate().skip(results.len()) {
+        let result = match (converted.remove(&index), &entry.options.shared_atlas) {
+            (Some(result), _) => result,
+            (None, Some(atlas)) => {
+                // The whole group is converted again, members done before a resume included,
+                // so the atlas keeps their patches; only the results not yet recorded are kept
+                let start = Instant::now();
+                let group: Vec<usize> = (0..entries.len())
+                    .filter(|&i| entries[i].options.shared_atlas.as_ref() == Some(atlas))
+                    .collect();
+                let members: Vec<&JobEntry> = group.iter().map(|&i| &entries[i]).collect();
+                let mut current = None;
+                for (&i, result) in group.iter().zip(run_shared(&members, atlas)) {
+                    match i {
+                        i if i == index => current = Some(JobResult { result, elapsed: start.elapsed() }),
+                        i if i > index => { converted.insert(i, JobResult { result, elapsed: Duration::ZERO }); }
+                        _ => {}
+                    }
+                }
+                current.unwrap()
+            }
+            (None, None) => {
+                let start = Instant::now();
+                let result = run_entry(entry);
+                JobResult { result, elapsed: start.elapsed() }
+            }
+        };
+        results.push(result);
+        if let Some(file) = progress {
+            save_progress(file, entries, &results)?;
+        }
+    }
+
+    if let Some(file) = progress.filter(|file| file.exists()) {
+        fs::remove_file(file).map_err(failed("error.write_progress"))?;
+    }
+    Ok(results)
+}
+
+/// The entry's input as a local file, its output folder created.
+fn local_input(entry: &JobEntry) -> Result<String, Message> {
+    if !entry.options.dry_run {
+        fs::create_dir_all(&entry.output_dir).map_err(failed("error.create_output_dir"))?;
+    }
+    if download::is_url(&entry.path) {
+        download::download(&entry.path, &|_| {})
+    } else {
+        Ok(entry.path.clone())
+    }
+}
+
+fn run_entry(entry: &JobEntry) -> ConvertResult {
+    match local_input(entry) {
+        Ok(path) => convert(&path, &entry.output_dir, entry.scale, &entry.options, &|_| {}),
+        Err(e) => ConvertResult::failed(e),
+    }
+}
+
+fn run_shared(entries: &[&JobEntry], atlas: &str) -> Vec<ConvertResult> {
+    let mut inputs = Vec::new();
+    for entry in entries {
+        match local_input(entry) {
+            Ok(path) => inputs.push(SharedInput {
+                path,
+                output_dir: &entry.output_dir,
+                scale: entry.scale,
+                options: &entry.options,
+            }),
+            Err(e) => return entries.iter().map(|_| ConvertResult::failed(e.clone())).collect(),
+        }
+    }
+    convert_shared(&inputs, atlas)
+}
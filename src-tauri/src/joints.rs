@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use glam::{IVec3, Vec3};
+
+use crate::blocks::rasterize;
+use crate::McBone;
+
+// ================= JOINT PIVOTS =================
+//
+// Bones split off a parent (by cutting planes or the auto-rig) should turn
+// where they meet it, like a door on its hinge side or a turret on its ring.
+// Each child bone's pivot is put at the middle of its contact with the
+// parent: the faces between a child cell and a parent cell next to it, and
+// cells both share. Children that don't touch their parent keep their pivot.
+// Bedrock pivots are absolute; formats with relative parts (CEM) offset each
+// part from its parent's pivot when writing.
+
+const NEIGHBORS: [IVec3; 6] = [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z];
+
+/// Moves the pivot of every parented bone to where it touches its parent.
+pub fn place_joint_pivots(bones: &mut [McBone]) {
+    let cells: HashMap<&str, _> = bones.iter()
+        .map(|b| (b.name.as_str(), rasterize(std::slice::from_ref(b))))
+        .collect();
+
+    let pivots: Vec<Option<IVec3>> = bones.iter()
+        .map(|bone| {
+            let parent = cells.get(bone.parent.as_deref()?)?;
+            let (sum, count) = cells[bone.name.as_str()].iter()
+                .flat_map(|&c| {
+                    let center = c.as_vec3() + 0.5;
+                    let shared = parent.contains(&c).then_some(center);
+                    let faces = NEIGHBORS.iter()
+                        .filter(move |&&n| parent.contains(&(c + n)))
+                        .map(move |&n| center + n.as_vec3() * 0.5);
+                    shared.into_iter().chain(faces)
+                })
+                .fold((Vec3::ZERO, 0), |(sum, count), p| (sum + p, count + 1));
+            (count > 0).then(|| (sum / count as f32).round().as_ivec3())
+        })
+        .collect();
+
+    for (bone, pivot) in bones.iter_mut().zip(pivots) {
+        if let Some(pivot) = pivot {
+            bone.pivot = pivot.to_array();
+        }
+    }
+}
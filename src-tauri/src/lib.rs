@@ -1,29 +1,204 @@
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::BufWriter;
-use std::path::Path;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 use glam::{IVec3, Vec3};
 use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Serialize, Serializer};
 use ahash::RandomState;
-use tauri::Manager;
+use tauri::{AppHandle, Emitter, Manager};
+
+mod adaptive;
+mod base64;
+mod bbmodel;
+mod block_model;
+pub mod bench;
+mod blocks;
+mod boxes;
+mod cells;
+mod cem;
+mod clay;
+mod colors;
+mod copies;
+mod cuts;
+mod detail;
+mod devpacks;
+mod download;
+mod fill;
+mod gltf;
+mod history;
+mod hollow;
+mod hook;
+mod inspect;
+mod instances;
+mod joints;
+mod locators;
+mod materials;
+pub mod job;
+mod mcfunction;
+mod merge;
+mod mcstructure;
+pub mod messages;
+mod mtl;
+mod mcworld;
+mod normals;
+mod orient;
+mod origin;
+pub mod nbt;
+pub mod report;
+mod packs;
+mod palette;
+mod palette_lock;
+mod pieces;
+mod perf;
+mod ply;
+mod poly_mesh;
+mod profiles;
+mod regions;
+mod preview;
+mod projection;
+mod rebake;
+mod rig;
+mod schema;
+mod schematic;
+mod script;
+mod session;
+mod skin;
+mod slopes;
+mod stl;
+mod spill;
+mod sponge;
+mod sorting;
+mod stream;
+mod structure_nbt;
+mod surface;
+mod template;
+mod texture;
+mod tighten;
+mod topology;
+mod validate;
+mod variants;
+mod vox;
+mod weld;
+mod workers;
+
+use blocks::BlockGrid;
+use history::HistoryEntry;
+use inspect::GeometryReport;
+use mcfunction::ProgressiveBuild;
+use messages::{failed, Message};
+use perf::{BoneStats, FaceStats, OutputSize, PerformanceEstimate};
+use poly_mesh::McPolyMesh;
+use script::VoxelScript;
+use session::Session;
+use texture::Texture;
+use topology::ObjectStats;
 
 // ================= СТРУКТУРЫ MINECRAFT =================
 
-#[derive(Serialize, Debug)]
+const TEXTURE_SIZE: u32 = 64;
+
+/// Writes whole numbers without a fractional part, so voxel-aligned cubes stay compact.
+fn serialize_coords<S: Serializer>(v: &[f32; 3], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut tuple = serializer.serialize_tuple(3)?;
+    for &c in v {
+        if c.fract() == 0.0 && c.abs() < 1e9 {
+            tuple.serialize_element(&(c as i64))?;
+        } else {
+            tuple.serialize_element(&c)?;
+        }
+    }
+    tuple.end()
+}
+
+fn serialize_opt_coords<S: Serializer>(v: &Option<[f32; 3]>, serializer: S) -> Result<S::Ok, S::Error> {
+    match v {
+        Some(v) => serialize_coords(v, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+struct McFaceUv {
+    uv: [f32; 2],
+    uv_size: [f32; 2],
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+struct McFaceUvs {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    north: Option<McFaceUv>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    east: Option<McFaceUv>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    south: Option<McFaceUv>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    west: Option<McFaceUv>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    up: Option<McFaceUv>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    down: Option<McFaceUv>,
+}
+
+/// Box UV (one corner, faces unwrapped around it) or explicit per-face UVs.
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+enum McUv {
+    Box([i32; 2]),
+    PerFace(McFaceUvs),
+}
+
+#[derive(Serialize, Debug, Clone)]
 struct McCube {
-    origin: [i32; 3],
-    size: [i32; 3],
-    uv: [i32; 2],
+    #[serde(serialize_with = "serialize_coords")]
+    origin: [f32; 3],
+    #[serde(serialize_with = "serialize_coords")]
+    size: [f32; 3],
+    uv: McUv,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_opt_coords")]
+    pivot: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_opt_coords")]
+    rotation: Option<[f32; 3]>,
+    /// Grows (or with a negative value shrinks) the cube on every side without moving its UVs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inflate: Option<f32>,
+    /// Material color (RGBA) the texture atlas paints the cube with.
+    #[serde(skip)]
+    color: Option<[u8; 4]>,
+    /// Material whose texture the atlas projects onto the cube, for objects without UVs.
+    #[serde(skip)]
+    texture: Option<usize>,
+    /// Textured or vertex-colored surface the atlas samples.
+    #[serde(skip)]
+    surface: Option<surface::SurfaceRef>,
 }
 
-#[derive(Serialize, Debug)]
+impl McCube {
+    fn new(origin: [f32; 3], size: [f32; 3]) -> Self {
+        Self { origin, size, uv: McUv::Box([0, 0]), pivot: None, rotation: None, inflate: None, color: None, texture: None, surface: None }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
 struct McBone {
     name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
     pivot: [i32; 3],
+    /// Molang expression naming the bone this one follows when attached to another entity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    binding: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     cubes: Vec<McCube>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    poly_mesh: Option<McPolyMesh>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    locators: BTreeMap<String, [f32; 3]>,
 }
 
 #[derive(Serialize, Debug)]
@@ -59,18 +234,392 @@ pub struct FileInfo {
     pub faces: usize,
     pub voxel_count: usize,
     pub cube_count: usize,
+    /// Voxels on the surface only.
+    pub shell_voxel_count: usize,
+    /// Voxels with the enclosed interior filled.
+    pub solid_voxel_count: usize,
+    pub bone_count: usize,
+    pub performance: PerformanceEstimate,
+    /// Rotation (degrees around X, Y, Z) that stands the model on its largest
+    /// flat face; all zero when it already does.
+    pub suggested_rotation: [f32; 3],
+    pub objects: Vec<ObjectStats>,
+    /// Problems with the options chosen, e.g. filling objects that have holes.
+    pub warnings: Vec<Message>,
+    /// Size of the geometry file and texture the conversion would write.
+    pub estimated_size: OutputSize,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConvertResult {
     pub success: bool,
-    pub message: String,
+    pub message: Message,
     pub output_path: Option<String>,
     pub voxel_count: usize,
     pub cube_count: usize,
+    /// In-game performance limits the converted model goes beyond, and
+    /// objects with holes when filling the interior, and a failed
+    /// `post_convert` command.
+    pub warnings: Vec<Message>,
+    /// Per-bone breakdown of the counts, largest first.
+    #[serde(default)]
+    pub bones: Vec<BoneStats>,
+    /// Files written besides `output_path`: `extra_formats` and the
+    /// `cube_colors` and `export_palette` sidecars.
+    #[serde(default)]
+    pub other_outputs: Vec<String>,
+    /// Width and height of the baked texture, if one was baked.
+    #[serde(default)]
+    pub texture_size: Option<[u32; 2]>,
+    /// Size the geometry file and texture would have, reported by dry runs.
+    #[serde(default)]
+    pub estimated_size: Option<OutputSize>,
+    /// Exposed faces and visible against total cube surface, for judging
+    /// overdraw; unset without cubes.
+    #[serde(default)]
+    pub faces: Option<FaceStats>,
+    /// Geometry units a model far from the origin was moved by to bring it
+    /// back; subtract it to place the model where it was.
+    #[serde(default)]
+    pub relocation: Option<[i32; 3]>,
+    /// Handle of the kept geometry for `rebake`, on success.
+    #[serde(default)]
+    pub result_id: Option<u64>,
+}
+
+impl ConvertResult {
+    pub(crate) fn failed(message: Message) -> Self {
+        Self {
+            success: false,
+            message,
+            output_path: None,
+            voxel_count: 0,
+            cube_count: 0,
+            warnings: vec![],
+            bones: vec![],
+            other_outputs: vec![],
+            texture_size: None,
+            estimated_size: None,
+            faces: None,
+            relocation: None,
+            result_id: None,
+        }
+    }
+}
+
+/// Emitted as `model-progress` each time an object of the file finishes meshing.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelProgress {
+    pub name: String,
+    pub completed: usize,
+    pub total: usize,
+    pub voxel_count: usize,
+    pub cube_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeometryMode {
+    /// Voxelize into cubes.
+    #[default]
+    Cubes,
+    /// Keep the source triangles as a `poly_mesh`.
+    PolyMesh,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Bedrock `.geo.json` model.
+    #[default]
+    Geometry,
+    /// Bedrock structure file with one block per voxel.
+    #[serde(rename = "mcstructure")]
+    McStructure,
+    /// Java datapack with a function that places the blocks.
+    Datapack,
+    /// Legacy MCEdit `.schematic` with numeric block ids.
+    Schematic,
+    /// Java structure block `.nbt`, cut into pieces of at most 48 blocks.
+    StructureNbt,
+    /// Sponge `.schem` for WorldEdit and similar tools.
+    Schem,
+    /// Bedrock world with the model as an entity and (for cubes) a
+    /// structure, both packs embedded.
+    #[serde(rename = "mcworld")]
+    McWorld,
+    /// The resource pack alone, zipped as `.mcpack`.
+    #[serde(rename = "mcpack")]
+    McPack,
+    /// Resource and behavior packs zipped together as `.mcaddon`.
+    #[serde(rename = "mcaddon")]
+    McAddon,
+    /// OptiFine custom entity model for Java.
+    Jem,
+    /// Resource and behavior packs written into the game's development pack folders.
+    DevPacks,
+    /// Blockbench project, for tweaking the model by hand.
+    Bbmodel,
+    /// Java block model (`models/block/*.json`).
+    BlockModel,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureLayout {
+    /// Every cube maps to the texture origin.
+    #[default]
+    Default,
+    /// Humanoid bones map onto the standard 64×64 skin layout.
+    Skin,
+    /// Bake the material colors into a texture atlas with a patch per face.
+    Atlas,
+    /// Debug view: every cube shrunk slightly and painted its own color, to
+    /// inspect the cube decomposition.
+    Preview,
+    /// One neutral color shaded by face orientation, like a clay render.
+    Clay,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConvertOptions {
+    pub format: OutputFormat,
+    /// Formats written besides `format` from the same voxelized model.
+    pub extra_formats: Vec<OutputFormat>,
+    /// Folder for the outputs, with `{output_dir}`, `{model}`, `{scale}`,
+    /// `{format}` and `{date}` filled in, e.g. `{output_dir}/{model}/{format}`.
+    pub output_template: Option<String>,
+    pub geometry_mode: GeometryMode,
+    /// Rotate the mesh before converting, in degrees around X, Y and Z.
+    pub rotation: Option<[f32; 3]>,
+    /// Apply the suggested rotation (largest flat face down) instead of `rotation`.
+    pub auto_orient: bool,
+    /// In `poly_mesh` mode, merge vertices that share a voxel cell.
+    pub decimate: bool,
+    /// How voxel coordinates map to cube origins: as in the source, centered
+    /// on the model, or moved into positive space.
+    pub voxel_origin: origin::VoxelOrigin,
+    /// Size of a voxel cell along X, Y and Z relative to a cubic one, e.g.
+    /// `[1, 0.5, 1]` for half-height voxels. Unset means cubic cells.
+    pub cell_size: Option<[f32; 3]>,
+    /// Octree depth for adaptive voxelization: flat regions become cubes up to
+    /// 2^n voxels wide while curved regions keep full resolution. 0 uses the
+    /// uniform grid. Filling, welding, scripts, normal offsets, instancing and
+    /// slopes only apply to the uniform grid and warn when set; solid voxel
+    /// counts are the shell alone.
+    pub adaptive_levels: u32,
+    /// Out-of-core voxelization for scales beyond memory: surface voxels are
+    /// spilled to temporary files in slabs of this many layers along Y and
+    /// meshed one slab at a time. 0 keeps the whole grid in memory; steps
+    /// that need the whole grid are skipped.
+    pub spill_slab: u32,
+    /// Emit parts that are exact axis-aligned boxes as cubes at full precision,
+    /// voxelizing only the rest.
+    pub detect_boxes: bool,
+    /// Voxelize objects that are moved or rotated copies of an earlier
+    /// object once, turning the earlier object's cubes into place.
+    pub rigid_copies: bool,
+    /// Voxelize parts repeated inside an object (columns, windows) once and
+    /// stamp the voxels at every copy.
+    pub instance_parts: bool,
+    /// Fill empty cells between two filled ones, closing the one-cell cracks
+    /// of thin double-walled meshes without growing the surface.
+    pub weld_gaps: bool,
+    /// Share (0-1) of the outward inflation of surface voxels trimmed by
+    /// sampling cells against the triangle planes; 0 keeps every touched cell.
+    pub normal_offset: f32,
+    /// Widens each cell by this share of a voxel in the triangle overlap
+    /// test, keeping thin features that only graze cells; negative narrows.
+    pub sat_epsilon: f32,
+    /// Fill the enclosed interior of each bone instead of keeping only its surface.
+    pub fill_interior: bool,
+    /// Keep only a pattern of the filled interior (lattice or honeycomb) to
+    /// save blocks, leaving the surface intact.
+    pub hollow_pattern: hollow::HollowPattern,
+    /// Cells between the beams or walls of `hollow_pattern` (0 uses the default of 4).
+    pub hollow_spacing: u32,
+    /// Wind triangles consistently and outwards before anything that depends
+    /// on normals, for meshes with flipped faces.
+    pub fix_normals: bool,
+    /// Treat faces as visible from both sides: poly meshes get back faces and
+    /// adaptive flatness ignores which way faces point.
+    pub double_sided: bool,
+    /// Replace stair-stepped slopes with rotated cubes (22.5° steps).
+    pub fit_slopes: bool,
+    /// Path to a rhai script run on each bone's voxels before meshing.
+    pub script: Option<String>,
+    pub texture_layout: TextureLayout,
+    /// In a job, inputs naming the same atlas are baked into one texture,
+    /// written as `<name>.png`, instead of one per model.
+    pub shared_atlas: Option<String>,
+    /// In the atlas, let faces that look the same share one texture region.
+    pub uv_sharing: bool,
+    /// Largest per-channel difference (0-255) at which `uv_sharing` treats
+    /// colors as the same.
+    pub uv_share_tolerance: u8,
+    /// In the atlas, paint faces from the material texture through the mesh
+    /// UVs instead of one color, keeping painted details.
+    pub sample_textures: bool,
+    /// Atlas texels per geometry unit along each face side (0 means 1, at
+    /// most 16), for sampled or projected detail finer than a voxel.
+    pub texel_density: u32,
+    /// Fixed atlas side in texels; faces then get texels in proportion to
+    /// their size to fill it, instead of `texel_density` each.
+    pub atlas_budget: Option<u32>,
+    /// Strength (0-1) of ambient occlusion baked into the atlas; 0 turns it off.
+    pub ambient_occlusion: f32,
+    /// Bake lambert shading from a fixed light into the atlas; the direction
+    /// points towards the light, e.g. `[0, 1, 0]` for a sun straight above.
+    pub light_direction: Option<[f32; 3]>,
+    /// Image projected onto the model as the atlas colors, for meshes
+    /// without UVs.
+    pub projection: Option<projection::Projection>,
+    /// Also write `<model>.colors.json` with the material color of each cube,
+    /// for recoloring without converting again.
+    pub cube_colors: bool,
+    /// Also write `<model>.palette.json` with the colors of the converted
+    /// model, for `palette_lock` in later conversions.
+    pub export_palette: bool,
+    /// Palette file whose colors every cube color and texel is snapped to,
+    /// so models converted apart keep identical colors.
+    pub palette_lock: Option<String>,
+    /// Experimental: re-split the model into humanoid bones (head, body,
+    /// arms, legs) so it can be animated.
+    pub auto_rig: bool,
+    /// Bone regions for `auto_rig` in fractions of the model bounds; empty
+    /// uses player proportions.
+    pub rig_regions: Vec<rig::RigRegion>,
+    /// Planes splitting parts off into their own bones, pivoting on the
+    /// plane, so wheels, doors and turrets can be animated.
+    pub cut_planes: Vec<cuts::CutPlane>,
+    /// Pivot bones split off a parent (cut planes, auto-rig) at the middle of
+    /// where they touch it, so they turn at the joint.
+    pub joint_pivots: bool,
+    /// Boxes voxelized again at a higher scale, to spend cubes on faces and
+    /// logos while the rest stays coarse.
+    pub detail_regions: Vec<detail::DetailRegion>,
+    /// Split bones into child bones per region this many blocks wide, so
+    /// parts of large props can be hidden or animated on their own. 0 keeps
+    /// bones whole.
+    pub region_size: u32,
+    /// Order bones parents first and by name, and cubes by region with the
+    /// largest first, for render-friendly output that is the same every run.
+    pub sort_cubes: bool,
+    /// Extra geometries written into the same geo.json under
+    /// `geometry.<model>.<name>`, e.g. an eroded damaged version.
+    pub variants: Vec<variants::Variant>,
+    /// Also write an X-mirrored copy as the variant `geometry.<model>.mirrored`.
+    pub mirrored_copy: bool,
+    /// Block placed for every voxel in world exports (structure, datapack,
+    /// schematic).
+    pub block: Option<String>,
+    /// Orient `block` along the model when it has a direction: logs and
+    /// pillars along the solid, glazed terracotta towards the surface.
+    pub orient_blocks: bool,
+    /// Similar blocks mixed in among `block` in world exports, so large flat
+    /// areas don't look like one monotone wall.
+    pub block_variation: Vec<String>,
+    /// Share (0-1) of full blocks replaced by `block_variation`; 0 mixes all
+    /// of them evenly.
+    pub variation_amount: f32,
+    /// Width in blocks of the patches that vary together; 0 varies single
+    /// blocks.
+    pub variation_size: u32,
+    /// In world exports, smooth 45° slopes with stairs and gentler slopes
+    /// with slabs.
+    pub partial_blocks: bool,
+    pub stair_block: Option<String>,
+    pub slab_block: Option<String>,
+    /// In world exports, add floors and pillars every this many blocks inside
+    /// the model so it can be built by hand; 0 adds none.
+    pub scaffolding: u32,
+    /// Block the `scaffolding` is made of.
+    pub support_block: Option<String>,
+    /// Blocks (grass, flowers) scattered on top of the surface in world
+    /// exports, for terrain.
+    pub decorations: Vec<String>,
+    /// Share (0-1) of top blocks that get a decoration; 0 uses 0.3.
+    pub decoration_density: f32,
+    /// What empty cells of world exports do to the world they're placed in:
+    /// replace it with `air`, `keep` it, or `dry_interior` (keep it outside,
+    /// air in the enclosed inside).
+    pub empty_cells: blocks::EmptyCells,
+    /// Restrictions the blocks set above must meet, e.g. `no_falling_blocks`.
+    pub palette_filters: Vec<palette::PaletteFilter>,
+    /// Point of the model placed at the target position in world exports.
+    pub anchor: blocks::Anchor,
+    /// Blocks added to the anchored position in world exports.
+    pub offset: [i32; 3],
+    /// In datapack exports, place the model step by step over time.
+    pub progressive_build: ProgressiveBuild,
+    /// Ticks between progressive build steps (0 uses the default of 10).
+    pub build_interval: u32,
+    /// Split world exports into chunk-aligned pieces this many chunks wide,
+    /// listed in an index file. 0 only splits structures beyond 64 blocks.
+    pub split_chunks: u32,
+    /// `com.mojang` folder `dev_packs` exports go to; unset uses the first
+    /// installation found.
+    pub game_folder: Option<String>,
+    /// In pack exports, also add a wearable item for this equipment slot
+    /// (e.g. `slot.armor.head`), rendered with the geometry as an attachable.
+    pub wearable_slot: Option<String>,
+    /// Player bone each model bone follows when worn, e.g. `hat` → `head`.
+    /// Unlisted bones named like a player bone follow that one.
+    pub bone_bindings: HashMap<String, String>,
+    /// In JEM exports, write each part to its own `.jpm` file.
+    pub cem_part_files: bool,
+    /// How Java block models beyond the -16 to 32 element range are made to
+    /// fit: shrunk, or split into a model per block.
+    pub block_model_fit: block_model::BlockModelFit,
+    /// Sponge schematic version of `.schem` exports: `v3`, or `v2` for
+    /// older tools.
+    pub sponge_version: sponge::SpongeVersion,
+    /// Locators added besides those marked by `locator_<name>` OBJ objects.
+    pub locators: Vec<locators::Locator>,
+    /// Program and arguments run after each successful conversion, with the
+    /// output paths appended.
+    pub post_convert: Vec<String>,
+    /// Run the whole conversion, texture baking included, and report the
+    /// results without writing anything.
+    pub dry_run: bool,
+    /// Worker threads for voxelizing; 0 uses one per core.
+    pub threads: usize,
+    /// Run the workers at background priority, so other programs stay responsive.
+    pub low_priority: bool,
+}
+
+impl ConvertOptions {
+    /// Resolves relative file paths in the options against `base`.
+    pub fn resolve_paths(&mut self, base: &Path) {
+        if let Some(script) = &mut self.script {
+            *script = base.join(&*script).to_string_lossy().to_string();
+        }
+        if let Some(projection) = &mut self.projection {
+            projection.image = base.join(&projection.image).to_string_lossy().to_string();
+        }
+        if let Some(palette) = &mut self.palette_lock {
+            *palette = base.join(&*palette).to_string_lossy().to_string();
+        }
+        if let Some(folder) = &mut self.game_folder {
+            *folder = base.join(&*folder).to_string_lossy().to_string();
+        }
+        // Bare program names are looked up on PATH
+        if let Some(program) = self.post_convert.first_mut().filter(|p| p.contains(['/', '\\'])) {
+            *program = base.join(&*program).to_string_lossy().to_string();
+        }
+    }
 }
 
 // ================= SAT INTERSECTION =================
+//
+// Cells are half-open: a cell owns its low faces but not its high ones, so a
+// triangle lying exactly on a cell boundary belongs to the cell above it
+// only, and axis-aligned walls voxelize one cell thick.
+//
+// The test runs in voxel units relative to a cell at the triangle, where the
+// cell centers are exact, so models far from the origin don't lose thin
+// features to float cancellation. `sat_epsilon` widens or narrows the cells.
 
 fn triangle_aabb_intersect(v0: Vec3, v1: Vec3, v2: Vec3, center: Vec3, half_size: f32) -> bool {
     let v0 = v0 - center;
@@ -83,9 +632,9 @@ fn triangle_aabb_intersect(v0: Vec3, v1: Vec3, v2: Vec3, center: Vec3, half_size
 
     let hs = half_size;
 
-    if v0.x.min(v1.x).min(v2.x) > hs || v0.x.max(v1.x).max(v2.x) < -hs { return false; }
-    if v0.y.min(v1.y).min(v2.y) > hs || v0.y.max(v1.y).max(v2.y) < -hs { return false; }
-    if v0.z.min(v1.z).min(v2.z) > hs || v0.z.max(v1.z).max(v2.z) < -hs { return false; }
+    if v0.x.min(v1.x).min(v2.x) >= hs || v0.x.max(v1.x).max(v2.x) < -hs { return false; }
+    if v0.y.min(v1.y).min(v2.y) >= hs || v0.y.max(v1.y).max(v2.y) < -hs { return false; }
+    if v0.z.min(v1.z).min(v2.z) >= hs || v0.z.max(v1.z).max(v2.z) < -hs { return false; }
 
     let normal = f0.cross(f1);
     let d = normal.dot(v0);
@@ -168,11 +717,10 @@ fn run_greedy_meshing(voxels: &HashSet<IVec3, RandomState>) -> Vec<McCube> {
             }
         }
 
-        cubes.push(McCube {
-            origin: [x, y, z],
-            size: [width, height, depth],
-            uv: [0, 0],
-        });
+        cubes.push(McCube::new(
+            [x as f32, y as f32, z as f32],
+            [width as f32, height as f32, depth as f32],
+        ));
     }
 
     cubes
@@ -181,200 +729,1723 @@ fn run_greedy_meshing(voxels: &HashSet<IVec3, RandomState>) -> Vec<McCube> {
 
 // ================= VOXELIZATION =================
 
-fn voxelize_model(models: &[tobj::Model], scale: f32) -> (Vec<McBone>, usize, usize) {
-    let voxel_size = 1.0 / scale;
-    let half_size = voxel_size / 2.0;
+#[derive(Debug, Default, Clone, Copy)]
+struct VoxelStats {
+    voxel_count: usize,
+    cube_count: usize,
+    poly_count: usize,
+    shell_voxel_count: usize,
+    solid_voxel_count: usize,
+    /// Offset a model far from the origin was moved by.
+    relocation: Option<IVec3>,
+    /// Offset `voxel_origin` moved the geometry by, relocations included.
+    origin_offset: IVec3,
+}
+
+/// Calls `visit` with each cell the triangle `v` (model units) overlaps at
+/// `scale`, and the triangle and cell center in voxels from a cell nearby.
+fn triangle_cells(v: [Vec3; 3], scale: f32, epsilon: f32, mut visit: impl FnMut(IVec3, [Vec3; 3], Vec3)) {
+    // Relative to a whole cell at the triangle, so models far from the origin
+    // compare small numbers instead of cancelling large ones
+    let scaled = v.map(|p| p.as_dvec3() * scale as f64);
+    let base = scaled[0].floor();
+    let local = scaled.map(|p| (p - base).as_vec3());
+    let base = base.as_ivec3();
+
+    let epsilon = epsilon.max(-0.5);
+    // Up to the cell holding the high corner, which owns it when on a boundary
+    let i_min = (local[0].min(local[1]).min(local[2]) - epsilon.max(0.0)).floor().as_ivec3();
+    let i_max = (local[0].max(local[1]).max(local[2]) + epsilon.max(0.0)).floor().as_ivec3();
+    let half_size = 0.5 + epsilon;
+
+    for x in i_min.x..=i_max.x {
+        for y in i_min.y..=i_max.y {
+            for z in i_min.z..=i_max.z {
+                let cell = IVec3::new(x, y, z);
+                let center = cell.as_vec3() + 0.5;
+                if triangle_aabb_intersect(local[0], local[1], local[2], center, half_size) {
+                    visit(base + cell, local, center);
+                }
+            }
+        }
+    }
+}
+
+fn voxelize_mesh(vertices: &[Vec3], indices: &[u32], scale: f32, epsilon: f32) -> HashSet<IVec3, RandomState> {
+    indices.par_chunks(3)
+        .map(|chunk| {
+            let mut local_voxels = Vec::new();
+            let v = [0, 1, 2].map(|k| vertices[chunk[k] as usize]);
+            triangle_cells(v, scale, epsilon, |cell, _, _| local_voxels.push(cell));
+            local_voxels
+        })
+        .flatten()
+        .collect()
+}
+
+/// Called as each model is done, with its bone unless it came out empty.
+type OnModel<'a> = dyn Fn(ModelProgress, Option<&McBone>) + Sync + 'a;
+
+/// `voxelize_scene` on the worker threads `options` ask for.
+fn voxelize_model(
+    scene: &Scene,
+    scale: f32,
+    options: &ConvertOptions,
+    on_model: &OnModel,
+) -> Result<(Vec<McBone>, VoxelStats), Message> {
+    workers::with_workers(options, || voxelize_scene(scene, scale, options, on_model))?
+}
+
+fn voxelize_scene(
+    scene: &Scene,
+    scale: f32,
+    options: &ConvertOptions,
+    on_model: &OnModel,
+) -> Result<(Vec<McBone>, VoxelStats), Message> {
+    let script = options.script.as_deref().map(VoxelScript::load).transpose()?;
+
+    let models = oriented_models(&scene.models, options);
+    let cell_size = cell_size(options)?;
+    let voxel_models = stretched_models(&models, cell_size);
+
+    let copies = match options.rigid_copies && options.geometry_mode == GeometryMode::Cubes {
+        true => copies::find_copies(&voxel_models, &scene.face_materials),
+        false => HashMap::new(),
+    };
+    // Stats of the objects copied, counted again for each copy
+    let source_stats = Mutex::new(HashMap::new());
+    // Fine cubes of the detail regions, by bone
+    let detail_cubes: Mutex<BTreeMap<String, Vec<McCube>>> = Mutex::new(BTreeMap::new());
 
     let bones = Arc::new(Mutex::new(Vec::new()));
-    let total_voxels = Arc::new(Mutex::new(0usize));
-    let total_cubes = Arc::new(Mutex::new(0usize));
+    let totals = Arc::new(Mutex::new(VoxelStats::default()));
+    let is_voxelized = |m: &tobj::Model| !m.mesh.indices.is_empty() && !locators::is_marker(&m.name);
+    let total = voxel_models.iter().enumerate().filter(|(i, m)| is_voxelized(m) && !copies.contains_key(i)).count()
+        + scene.voxel_shapes.len();
+    let completed = AtomicUsize::new(0);
+
+    voxel_models.par_iter().enumerate().try_for_each(|(index, model)| {
+        if !is_voxelized(model) || copies.contains_key(&index) { return Ok(()); }
 
-    models.par_iter().for_each(|model| {
-        let mesh = &model.mesh;
-        if mesh.indices.is_empty() { return; }
+        let repaired;
+        let mesh = match options.fix_normals.then(|| normals::orient_triangles(&model.mesh.positions, &model.mesh.indices)).flatten() {
+            Some(indices) => {
+                // Vertex normals no longer match the flipped triangles; shade flat instead
+                repaired = tobj::Mesh { indices, normals: vec![], ..model.mesh.clone() };
+                &repaired
+            }
+            None => &model.mesh,
+        };
+
+        if options.geometry_mode == GeometryMode::PolyMesh {
+            let poly_mesh = poly_mesh::build_poly_mesh(mesh, scale, options.decimate, options.double_sided);
+            let poly_count = poly_mesh.poly_count();
+            totals.lock().unwrap().poly_count += poly_count;
+
+            let bone = McBone {
+                name: model.name.clone(),
+                parent: None,
+                pivot: [0, 0, 0],
+                binding: None,
+                cubes: vec![],
+                poly_mesh: Some(poly_mesh),
+                locators: BTreeMap::new(),
+            };
+            on_model(ModelProgress {
+                name: model.name.clone(),
+                completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+                voxel_count: 0,
+                cube_count: 0,
+            }, Some(&bone));
+            bones.lock().unwrap().push(bone);
+            return Ok(());
+        }
 
         let vertex_vecs: Vec<Vec3> = mesh.positions.chunks(3)
             .map(|v| Vec3::new(v[0], v[1], v[2]))
             .collect();
 
-        let voxels: HashSet<IVec3, RandomState> = mesh.indices.par_chunks(3)
-            .map(|chunk| {
-                let mut local_voxels = Vec::new();
-                let v0 = vertex_vecs[chunk[0] as usize];
-                let v1 = vertex_vecs[chunk[1] as usize];
-                let v2 = vertex_vecs[chunk[2] as usize];
-
-                let t_min = v0.min(v1).min(v2) * scale;
-                let t_max = v0.max(v1).max(v2) * scale;
-                
-                let i_min = t_min.floor().as_ivec3();
-                let i_max = t_max.ceil().as_ivec3();
-
-                for x in i_min.x..=i_max.x {
-                    for y in i_min.y..=i_max.y {
-                        for z in i_min.z..=i_max.z {
-                            let center = Vec3::new(
-                                (x as f32 + 0.5) * voxel_size,
-                                (y as f32 + 0.5) * voxel_size,
-                                (z as f32 + 0.5) * voxel_size
-                            );
-
-                            if triangle_aabb_intersect(v0, v1, v2, center, half_size) {
-                                local_voxels.push(IVec3::new(x, y, z));
-                            }
-                        }
-                    }
+        let (box_cubes, remaining) = if options.detect_boxes {
+            boxes::extract_boxes(&vertex_vecs, &mesh.indices, scale)
+        } else {
+            (vec![], vec![])
+        };
+        let indices = if options.detect_boxes { &remaining } else { &mesh.indices };
+
+        let mut details = Vec::new();
+        let (mut cubes, mut stats) = if options.adaptive_levels > 0 {
+            let (cubes, cell_count) = adaptive::voxelize_adaptive(
+                &vertex_vecs, indices, scale, options.adaptive_levels, options.double_sided, options.sat_epsilon,
+            );
+            let stats = VoxelStats {
+                voxel_count: cell_count,
+                shell_voxel_count: cell_count,
+                solid_voxel_count: cell_count,
+                ..Default::default()
+            };
+            (cubes, stats)
+        } else if options.spill_slab > 0 {
+            let (cubes, cell_count) = spill::voxelize_spilled(
+                &vertex_vecs, indices, scale, options.sat_epsilon, options.spill_slab,
+            )?;
+            let stats = VoxelStats {
+                voxel_count: cell_count,
+                shell_voxel_count: cell_count,
+                solid_voxel_count: cell_count,
+                ..Default::default()
+            };
+            (cubes, stats)
+        } else {
+            let epsilon = options.sat_epsilon;
+            let voxelize = |vertices: &[Vec3], indices: &[u32], scale: f32| match options.normal_offset > 0.0 {
+                true => tighten::voxelize_offset(vertices, indices, scale, epsilon, options.normal_offset, options.double_sided),
+                false => voxelize_mesh(vertices, indices, scale, epsilon),
+            };
+            let mut voxels = match options.instance_parts {
+                true => instances::voxelize_instanced(&vertex_vecs, indices, scale, voxelize),
+                false => voxelize(&vertex_vecs, indices, scale),
+            };
+            if options.weld_gaps {
+                weld::weld_gaps(&mut voxels);
+            }
+
+            let shell_voxel_count = voxels.len();
+            // Counted even when not filling, so analysis shows what filling would cost
+            let interior = fill::interior_cells(&voxels);
+            let solid_voxel_count = shell_voxel_count + interior.len();
+            if options.fill_interior {
+                voxels.extend(hollow::carve(interior, options.hollow_pattern, options.hollow_spacing));
+            }
+
+            let mut voxels = match &script {
+                Some(script) => script.run(&model.name, scale, voxels)?,
+                None => voxels,
+            };
+
+            if !options.detail_regions.is_empty() {
+                let cell = cell_size.unwrap_or(Vec3::ONE);
+                voxels.retain(|&c| !detail::covers(&options.detail_regions, cell, c));
+                for (i, region) in options.detail_regions.iter().enumerate() {
+                    let earlier = &options.detail_regions[..i];
+                    details.push((region.name.clone(), detail::refine(&vertex_vecs, indices, scale, epsilon, region, earlier, cell)));
                 }
-                local_voxels
-            })
-            .flatten()
-            .collect();
+            }
 
-        if !voxels.is_empty() {
-            let voxel_count = voxels.len();
-            let optimized_cubes = run_greedy_meshing(&voxels);
-            let cube_count = optimized_cubes.len();
-            
-            *total_voxels.lock().unwrap() += voxel_count;
-            *total_cubes.lock().unwrap() += cube_count;
-            
-            bones.lock().unwrap().push(McBone {
-                name: model.name.clone(),
+            let stats = VoxelStats {
+                voxel_count: voxels.len(),
+                shell_voxel_count,
+                solid_voxel_count,
+                ..Default::default()
+            };
+
+            let slabs = if options.fit_slopes { slopes::fit_slopes(&mut voxels) } else { vec![] };
+            let mut cubes = run_greedy_meshing(&voxels);
+            cubes.extend(slabs);
+            (cubes, stats)
+        };
+        cubes.extend(box_cubes);
+        stats.cube_count = cubes.len() + details.iter().map(|(_, d)| d.len()).sum::<usize>();
+
+        let paint = model_painter(scene, index, model, scale, options);
+        paint(&mut cubes);
+        for (name, mut fine) in details {
+            paint(&mut fine);
+            match name {
+                Some(name) => detail_cubes.lock().unwrap().entry(name).or_default().extend(fine),
+                None => cubes.extend(fine),
+            }
+        }
+
+        if copies.values().any(|c| c.source == index) {
+            source_stats.lock().unwrap().insert(index, stats);
+        }
+
+        {
+            let mut totals = totals.lock().unwrap();
+            totals.voxel_count += stats.voxel_count;
+            totals.cube_count += stats.cube_count;
+            totals.shell_voxel_count += stats.shell_voxel_count;
+            totals.solid_voxel_count += stats.solid_voxel_count;
+        }
+
+        let bone = (!cubes.is_empty()).then(|| McBone {
+            name: model.name.clone(),
+            parent: None,
+            pivot: [0, 0, 0],
+            binding: None,
+            cubes,
+            poly_mesh: None,
+            locators: BTreeMap::new(),
+        });
+        on_model(ModelProgress {
+            name: model.name.clone(),
+            completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+            total,
+            voxel_count: stats.voxel_count,
+            cube_count: stats.cube_count,
+        }, bone.as_ref());
+        if let Some(bone) = bone {
+            bones.lock().unwrap().push(bone);
+        }
+
+        Ok::<(), Message>(())
+    })?;
+
+    let mut final_bones = Arc::try_unwrap(bones).unwrap().into_inner().unwrap();
+    let mut final_totals = *totals.lock().unwrap();
+
+    for shape in &scene.voxel_shapes {
+        let cubes = vox::mesh_shape(shape);
+        let voxel_count = shape.cells.len();
+        final_totals.voxel_count += voxel_count;
+        final_totals.cube_count += cubes.len();
+        final_totals.shell_voxel_count += voxel_count;
+        final_totals.solid_voxel_count += voxel_count;
+        let cube_count = cubes.len();
+        let bone = (!cubes.is_empty()).then(|| McBone {
+            name: shape.name.clone(),
+            parent: None,
+            pivot: [0, 0, 0],
+            binding: None,
+            cubes,
+            poly_mesh: None,
+            locators: BTreeMap::new(),
+        });
+        on_model(ModelProgress {
+            name: shape.name.clone(),
+            completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+            total,
+            voxel_count,
+            cube_count,
+        }, bone.as_ref());
+        final_bones.extend(bone);
+    }
+
+    let source_stats = source_stats.into_inner().unwrap();
+    let mut copy_indices: Vec<usize> = copies.keys().copied().collect();
+    copy_indices.sort_unstable();
+    for index in copy_indices {
+        let copy = &copies[&index];
+        let source_name = &voxel_models[copy.source].name;
+        let Some(source) = final_bones.iter().find(|b| &b.name == source_name) else { continue };
+        // Paint comes with the source's cubes; copies only match sources painted the same
+        let bone = copies::place_copy(source, &voxel_models[index].name, copy, scale);
+
+        let stats = source_stats[&copy.source];
+        final_totals.voxel_count += stats.voxel_count;
+        final_totals.cube_count += stats.cube_count;
+        final_totals.shell_voxel_count += stats.shell_voxel_count;
+        final_totals.solid_voxel_count += stats.solid_voxel_count;
+        final_bones.push(bone);
+    }
+
+    for (name, cubes) in detail_cubes.into_inner().unwrap() {
+        if final_bones.iter().any(|b| b.name == name) {
+            return Err(Message::new("error.detail_bone_exists").with("bone", &name));
+        }
+        if !cubes.is_empty() {
+            final_bones.push(McBone {
+                name,
+                parent: None,
                 pivot: [0, 0, 0],
-                cubes: optimized_cubes,
+                binding: None,
+                cubes,
+                poly_mesh: None,
+                locators: BTreeMap::new(),
             });
         }
-    });
+    }
 
-    let final_bones = Arc::try_unwrap(bones).unwrap().into_inner().unwrap();
-    let final_voxels = *total_voxels.lock().unwrap();
-    let final_cubes = *total_cubes.lock().unwrap();
-    
-    (final_bones, final_voxels, final_cubes)
+    gltf::attach_hierarchy(&mut final_bones, &scene.hierarchy);
+
+    if options.auto_rig && options.geometry_mode == GeometryMode::Cubes {
+        final_bones = rig::rig_humanoid(&final_bones, &options.rig_regions)?;
+        final_totals.cube_count = final_bones.iter().map(|b| b.cubes.len()).sum();
+    }
+    if !options.cut_planes.is_empty() && options.geometry_mode == GeometryMode::Cubes {
+        cuts::apply_cuts(&mut final_bones, &options.cut_planes, cell_size.unwrap_or(Vec3::ONE))?;
+        final_totals.cube_count = final_bones.iter().map(|b| b.cubes.len()).sum();
+    }
+    if let Some(cell_size) = cell_size {
+        cells::scale_bones(&mut final_bones, cell_size);
+    }
+    if options.joint_pivots {
+        joints::place_joint_pivots(&mut final_bones);
+    }
+    if options.region_size > 0 {
+        final_bones = regions::split_regions(final_bones, options.region_size);
+        final_totals.cube_count = final_bones.iter().map(|b| b.cubes.len()).sum();
+    }
+
+    let markers = locators::marker_locators(&models, scale);
+    if !markers.is_empty() || !options.locators.is_empty() {
+        locators::attach_locators(&mut final_bones, markers.into_iter().chain(options.locators.iter().cloned()))?;
+    }
+    let (offset, relocated) = origin::apply_origin(&mut final_bones, options.voxel_origin);
+    final_totals.origin_offset = offset;
+    final_totals.relocation = relocated.then_some(offset);
+    if options.sort_cubes {
+        sorting::sort_cubes(&mut final_bones);
+    }
+
+    Ok((final_bones, final_totals))
 }
 
-fn load_obj(path: &str) -> Result<(Vec<tobj::Model>, usize, usize), String> {
-    let load_opts = tobj::LoadOptions {
+fn obj_load_options() -> tobj::LoadOptions {
+    tobj::LoadOptions {
         single_index: true,
         triangulate: true,
         ..Default::default()
-    };
-    
-    let (models, _) = tobj::load_obj(path, &load_opts)
-        .map_err(|e| format!("Failed to load OBJ: {}", e))?;
-
-    let mut total_verts = 0;
-    let mut total_faces = 0;
-    
-    for model in &models {
-        total_verts += model.mesh.positions.len() / 3;
-        total_faces += model.mesh.indices.len() / 3;
     }
+}
 
-    Ok((models, total_verts, total_faces))
+/// Loaded objects with their materials; a missing `.mtl` leaves no materials.
+struct Scene {
+    models: Vec<tobj::Model>,
+    materials: Vec<tobj::Material>,
+    /// Parents of the bones, from a glTF node tree.
+    hierarchy: gltf::Hierarchy,
+    /// Voxels meshed as they are, from a `.vox` file.
+    voxel_shapes: Vec<vox::VoxShape>,
+    /// Material libraries and textures the OBJ names that weren't found.
+    missing_files: Vec<String>,
+    /// Triangle materials of the objects with several.
+    face_materials: materials::FaceMaterials,
+    vertex_count: usize,
+    face_count: usize,
 }
 
+impl Scene {
+    fn new(models: Vec<tobj::Model>, materials: Vec<tobj::Material>) -> Self {
+        let (models, face_materials) = materials::join_parts(models);
+        let mut vertex_count = 0;
+        let mut face_count = 0;
 
-// ================= TAURI COMMANDS =================
+        for model in &models {
+            vertex_count += model.mesh.positions.len() / 3;
+            face_count += model.mesh.indices.len() / 3;
+        }
 
-#[tauri::command]
-fn analyze_file(path: String, scale: f32) -> Result<FileInfo, String> {
-    let (models, vertices, faces) = load_obj(&path)?;
-    
-    let name = Path::new(&path)
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| "unknown".to_string());
+        Self {
+            models,
+            materials,
+            hierarchy: vec![],
+            voxel_shapes: vec![],
+            missing_files: vec![],
+            face_materials,
+            vertex_count,
+            face_count,
+        }
+    }
+}
 
-    let (_, voxel_count, cube_count) = voxelize_model(&models, scale);
+/// Loads an OBJ or, by extension, a glTF, STL, PLY or MagicaVoxel file.
+fn load_model(path: &str) -> Result<Scene, Message> {
+    if gltf::is_gltf(path) {
+        let loaded = gltf::load_gltf(path)?;
+        return Ok(Scene { hierarchy: loaded.hierarchy, ..Scene::new(loaded.models, loaded.materials) });
+    }
+    if stl::is_stl(path) {
+        return Ok(Scene::new(vec![stl::load_stl(path)?], vec![]));
+    }
+    if ply::is_ply(path) {
+        return Ok(Scene::new(vec![ply::load_ply(path)?], vec![]));
+    }
+    if vox::is_vox(path) {
+        return Ok(Scene { voxel_shapes: vox::load_vox(path)?, ..Scene::new(vec![], vec![]) });
+    }
+    load_obj(path)
+}
 
-    Ok(FileInfo {
-        path,
-        name,
-        vertices,
-        faces,
-        voxel_count,
-        cube_count,
+fn load_obj(path: &str) -> Result<Scene, Message> {
+    let loaded = mtl::load_obj(path, &obj_load_options())?;
+    Ok(Scene { missing_files: loaded.missing, ..Scene::new(loaded.models, loaded.materials) })
+}
+
+/// Parses OBJ source held in memory. `mtllib` references are ignored.
+fn load_obj_text(text: &str) -> Result<Scene, Message> {
+    let mut reader = BufReader::new(text.as_bytes());
+    let (models, _) = tobj::load_obj_buf(&mut reader, &obj_load_options(), |_| {
+        Err(tobj::LoadError::OpenFileFailed)
     })
+    .map_err(failed("error.parse_obj"))?;
+
+    Ok(Scene::new(models, vec![]))
 }
 
-#[tauri::command]
-fn convert_file(path: String, output_dir: String, scale: f32) -> ConvertResult {
-    let (models, _, _) = match load_obj(&path) {
-        Ok(v) => v,
-        Err(e) => return ConvertResult {
-            success: false,
-            message: e,
-            output_path: None,
-            voxel_count: 0,
-            cube_count: 0,
-        },
+/// `models` turned as `options` ask.
+fn oriented_models<'a>(models: &'a [tobj::Model], options: &ConvertOptions) -> Cow<'a, [tobj::Model]> {
+    match orient::requested_rotation(models, options) {
+        Some(rotation) => Cow::Owned(orient::rotate_models(models, rotation)),
+        None => Cow::Borrowed(models),
+    }
+}
+
+/// Size of a voxel cell relative to a cubic one; unset for cubic cells.
+fn cell_size(options: &ConvertOptions) -> Result<Option<Vec3>, Message> {
+    let cell_size = options.cell_size.map(Vec3::from).filter(|&c| c != Vec3::ONE);
+    if cell_size.is_some_and(|c| c.min_element() <= 0.0) {
+        return Err(Message::new("error.invalid_cell_size"));
+    }
+    Ok(cell_size)
+}
+
+/// `models` stretched so cells of `cell_size` voxelize as cubes.
+fn stretched_models<'a>(models: &'a [tobj::Model], cell_size: Option<Vec3>) -> Cow<'a, [tobj::Model]> {
+    match cell_size {
+        Some(cell_size) => Cow::Owned(cells::stretch_models(models, 1.0 / cell_size)),
+        None => Cow::Borrowed(models),
+    }
+}
+
+/// Paints cubes of the object `index` of `scene`, as voxelized in `model`,
+/// with its material color, projected texture, sampled surface or vertex colors.
+fn model_painter<'a>(
+    scene: &'a Scene,
+    index: usize,
+    model: &'a tobj::Model,
+    scale: f32,
+    options: &ConvertOptions,
+) -> impl Fn(&mut [McCube]) + 'a {
+    let materials = &scene.materials[..];
+    let color = material_color(model, materials);
+    let texture = untextured_material(model, materials);
+    let layered = scene.face_materials.get(&index)
+        .map(|faces| (layered_surface(model, faces, materials, scale, options), faces));
+    let surface = match &layered {
+        Some(((surface, textured), _)) => textured.then(|| surface.clone()),
+        None => sampled_surface(model, materials, scale, options).map(Arc::new),
     };
+    move |cubes: &mut [McCube]| {
+        for cube in cubes.iter_mut() {
+            cube.color = color;
+            cube.texture = texture;
+            cube.surface = surface.clone().map(surface::SurfaceRef::new);
+        }
+        match (&layered, &surface) {
+            (Some(((layered, _), faces)), _) => paint_face_materials(cubes, model, layered, faces, materials),
+            (None, Some(surface)) => paint_vertex_colors(cubes, surface),
+            (None, None) => {}
+        }
+    }
+}
 
-    let (bones, voxel_count, cube_count) = voxelize_model(&models, scale);
-    
-    if bones.is_empty() {
-        return ConvertResult {
-            success: false,
-            message: "No geometry generated".to_string(),
-            output_path: None,
-            voxel_count: 0,
-            cube_count: 0,
-        };
+/// Diffuse color of the object's material as RGBA.
+fn material_color(model: &tobj::Model, materials: &[tobj::Material]) -> Option<[u8; 4]> {
+    diffuse_rgba(materials.get(model.mesh.material_id?)?)
+}
+
+fn diffuse_rgba(material: &tobj::Material) -> Option<[u8; 4]> {
+    let [r, g, b] = material.diffuse?;
+    let alpha = material.dissolve.unwrap_or(1.0);
+    Some([r, g, b, alpha].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
+
+/// Material of an object without UVs whose texture has to be projected instead.
+fn untextured_material(model: &tobj::Model, materials: &[tobj::Material]) -> Option<usize> {
+    untextured(model, materials, model.mesh.material_id?)
+}
+
+fn untextured(model: &tobj::Model, materials: &[tobj::Material], id: usize) -> Option<usize> {
+    let has_texture = materials.get(id)?.diffuse_texture.is_some();
+    (has_texture && model.mesh.texcoords.is_empty()).then_some(id)
+}
+
+/// Textured surface of an object with UVs for `sample_textures`, else its
+/// vertex colors if it has any; an unreadable texture leaves none.
+fn sampled_surface(
+    model: &tobj::Model,
+    materials: &[tobj::Material],
+    scale: f32,
+    options: &ConvertOptions,
+) -> Option<surface::Surface> {
+    let textured = || {
+        if !options.sample_textures || options.texture_layout != TextureLayout::Atlas { return None; }
+        let path = materials.get(model.mesh.material_id?)?.diffuse_texture.as_deref()?;
+        surface::Surface::new(&model.mesh, scale, Texture::load_png(path).ok()?)
+    };
+    textured().or_else(|| surface::Surface::from_vertex_colors(&model.mesh, scale))
+}
+
+/// Surface of an object with several materials, by the triangles' materials,
+/// and whether it carries textures for `sample_textures`. Without them it only
+/// tells which material each cube lies on.
+fn layered_surface(
+    model: &tobj::Model,
+    faces: &[Option<usize>],
+    materials: &[tobj::Material],
+    scale: f32,
+    options: &ConvertOptions,
+) -> (Arc<surface::Surface>, bool) {
+    let textured = options.sample_textures && options.texture_layout == TextureLayout::Atlas && !model.mesh.texcoords.is_empty();
+    let mut ids: Vec<usize> = faces.iter().flatten().copied().collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let layers = ids.iter()
+        .map(|&id| {
+            let material = materials.get(id);
+            let image = material.and_then(|m| m.diffuse_texture.as_deref()).filter(|_| textured).and_then(|p| Texture::load_png(p).ok());
+            surface::Layer { image, color: material.and_then(diffuse_rgba) }
+        })
+        .collect();
+    let layer_of = faces.iter().map(|f| f.and_then(|id| ids.binary_search(&id).ok()).map(|l| l as u32)).collect();
+    (Arc::new(surface::Surface::with_layers(&model.mesh, scale, layers, layer_of)), textured)
+}
+
+/// Gives the cubes of an object with several materials the color (or
+/// projected texture) of the material of the triangle nearest their center;
+/// cubes too deep inside keep the object's first material.
+fn paint_face_materials(
+    cubes: &mut [McCube],
+    model: &tobj::Model,
+    surface: &surface::Surface,
+    faces: &[Option<usize>],
+    materials: &[tobj::Material],
+) {
+    for cube in cubes {
+        let center = Vec3::from(cube.origin) + Vec3::from(cube.size) / 2.0;
+        let Some(id) = surface.nearest_triangle(center).and_then(|t| faces[t]) else { continue };
+        cube.color = materials.get(id).and_then(diffuse_rgba);
+        cube.texture = untextured(model, materials, id);
+    }
+}
+
+/// Colors the cubes of a vertex-colored object by the surface at their
+/// center, with the object's mean color for those too deep inside.
+fn paint_vertex_colors(cubes: &mut [McCube], surface: &Arc<surface::Surface>) {
+    let Some(mean) = surface.mean_color() else { return };
+    let surface = surface::SurfaceRef::new(surface.clone());
+    for cube in cubes {
+        let center = Vec3::from(cube.origin) + Vec3::from(cube.size) / 2.0;
+        cube.color = Some(surface.sample(center).unwrap_or(mean));
     }
+}
+
+// ================= CONVERSION =================
+
+pub fn convert(
+    path: &str,
+    output_dir: &str,
+    scale: f32,
+    options: &ConvertOptions,
+    on_progress: &(dyn Fn(ModelProgress) + Sync),
+) -> ConvertResult {
+    let scene = match load_model(path) {
+        Ok(v) => v,
+        Err(e) => return ConvertResult::failed(e),
+    };
+
+    convert_models(scene, &model_name(path), output_dir, scale, options, on_progress)
+}
 
-    let model_name = Path::new(&path)
+/// Name of the outputs of the file at `path`.
+fn model_name(path: &str) -> String {
+    Path::new(path)
         .file_stem()
         .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| "model".to_string());
-
-    let output = OutputRoot {
-        format_version: "1.12.0".to_string(),
-        geometry: vec![McGeometry {
-            description: McDescription {
-                identifier: format!("geometry.{}", model_name),
-                texture_width: 64,
-                texture_height: 64,
-                visible_bounds_width: 4,
-                visible_bounds_height: 4,
-                visible_bounds_offset: [0, 1, 0],
-            },
-            bones,
-        }],
+        .unwrap_or_else(|| "model".to_string())
+}
+
+/// Converts OBJ source text (e.g. pasted from the clipboard) without a file on disk.
+pub fn convert_text(
+    text: &str,
+    model_name: &str,
+    output_dir: &str,
+    scale: f32,
+    options: &ConvertOptions,
+    on_progress: &(dyn Fn(ModelProgress) + Sync),
+) -> ConvertResult {
+    if let Err(e) = check_model_name(model_name) {
+        return ConvertResult::failed(e);
+    }
+    let scene = match load_obj_text(text) {
+        Ok(v) => v,
+        Err(e) => return ConvertResult::failed(e),
     };
 
-    let output_path = Path::new(&output_dir).join(format!("{}.geo.json", model_name));
-    let output_str = output_path.to_string_lossy().to_string();
+    convert_models(scene, model_name, output_dir, scale, options, on_progress)
+}
 
-    let file = match File::create(&output_path) {
-        Ok(f) => f,
-        Err(e) => return ConvertResult {
-            success: false,
-            message: format!("Failed to create file: {}", e),
-            output_path: None,
-            voxel_count: 0,
-            cube_count: 0,
+/// Fails for caller-given model names that would put the outputs outside
+/// the output folder.
+pub(crate) fn check_model_name(model_name: &str) -> Result<(), Message> {
+    if model_name.is_empty() || model_name == ".." || model_name.contains(['/', '\\', ':']) {
+        return Err(Message::new("error.invalid_model_name").with("name", model_name));
+    }
+    Ok(())
+}
+
+/// Lowercase name usable in resource locations and identifiers.
+pub(crate) fn resource_name(model_name: &str) -> String {
+    let name: String = model_name.chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            c @ ('a'..='z' | '0'..='9' | '_' | '-' | '.') => c,
+            _ => '_',
+        })
+        .collect();
+    if name.is_empty() { "model".to_string() } else { name }
+}
+
+/// Bones of the geometry variants, by variant name.
+type VariantBones = Vec<(String, Vec<McBone>)>;
+
+fn geometry_file(bones: Vec<McBone>, variants: VariantBones, model_name: &str, texture: &Texture) -> OutputRoot {
+    let geometry = |identifier: String, bones: Vec<McBone>| McGeometry {
+        description: McDescription {
+            identifier,
+            texture_width: texture.width as i32,
+            texture_height: texture.height as i32,
+            visible_bounds_width: 4,
+            visible_bounds_height: 4,
+            visible_bounds_offset: [0, 1, 0],
         },
+        bones,
     };
+    let mut geometries = vec![geometry(format!("geometry.{}", model_name), bones)];
+    for (name, bones) in variants {
+        geometries.push(geometry(format!("geometry.{}.{}", model_name, name), bones));
+    }
+    OutputRoot { format_version: "1.12.0".to_string(), geometry: geometries }
+}
 
+fn write_geometry(bones: Vec<McBone>, variants: VariantBones, model_name: &str, texture: &Texture, path: &Path) -> Result<(), Message> {
+    let output = geometry_file(bones, variants, model_name, texture);
+    validate::check_geometry(&output)?;
+    let file = File::create(path).map_err(failed("error.create_file"))?;
     let writer = BufWriter::new(file);
-    if let Err(e) = serde_json::to_writer_pretty(writer, &output) {
-        return ConvertResult {
-            success: false,
-            message: format!("Failed to write JSON: {}", e),
-            output_path: None,
-            voxel_count: 0,
-            cube_count: 0,
+    serde_json::to_writer_pretty(writer, &output).map_err(failed("error.write_json"))
+}
+
+fn estimate_size(bones: Vec<McBone>, variants: VariantBones, model_name: &str, atlas: Option<&Texture>) -> OutputSize {
+    let blank = Texture::blank();
+    perf::output_size(&geometry_file(bones, variants, model_name, atlas.unwrap_or(&blank)), atlas)
+}
+
+fn geometry_summary(options: &ConvertOptions, stats: &VoxelStats) -> Message {
+    match options.geometry_mode {
+        GeometryMode::Cubes => Message::new("result.voxels_to_cubes")
+            .with("voxels", stats.voxel_count)
+            .with("cubes", stats.cube_count),
+        GeometryMode::PolyMesh => Message::new("result.polygons").with("polygons", stats.poly_count),
+    }
+}
+
+fn relocation_warning(offset: IVec3) -> Message {
+    Message::new("warning.model_relocated").with("x", offset.x).with("y", offset.y).with("z", offset.z)
+}
+
+fn blocks_summary(stats: &VoxelStats, grid: &BlockGrid, pieces: usize) -> Message {
+    let message = match pieces {
+        1 => Message::new("result.voxels_to_blocks"),
+        _ => Message::new("result.voxels_to_blocks_in_pieces").with("pieces", pieces),
+    };
+    message.with("voxels", stats.voxel_count).with("blocks", grid.block_count())
+}
+
+/// Summary of what `export` would write, failing where it needs cube geometry.
+fn dry_run_summary(bones: &[McBone], options: &ConvertOptions, stats: &VoxelStats) -> Result<Message, Message> {
+    let needs_cubes = match options.format {
+        OutputFormat::McStructure => "error.structure_needs_cubes",
+        OutputFormat::Datapack => "error.datapack_needs_cubes",
+        OutputFormat::Schematic => "error.schematic_needs_cubes",
+        OutputFormat::StructureNbt => "error.structure_needs_cubes",
+        OutputFormat::Schem => "error.schematic_needs_cubes",
+        OutputFormat::Jem if bones.iter().any(|b| b.poly_mesh.is_some()) => return Err(Message::new("error.cem_needs_cubes")),
+        OutputFormat::Bbmodel if bones.iter().any(|b| b.poly_mesh.is_some()) => return Err(Message::new("error.bbmodel_needs_cubes")),
+        OutputFormat::BlockModel if bones.iter().any(|b| b.poly_mesh.is_some()) => {
+            return Err(Message::new("error.block_model_needs_cubes"));
+        }
+        OutputFormat::McWorld | OutputFormat::McAddon | OutputFormat::DevPacks => {
+            return Ok(match BlockGrid::from_bones(bones, options) {
+                Some(grid) => Message::new("result.cubes_and_blocks")
+                    .with("cubes", stats.cube_count)
+                    .with("blocks", grid.block_count()),
+                None => geometry_summary(options, stats),
+            });
+        }
+        OutputFormat::Geometry | OutputFormat::McPack | OutputFormat::Jem | OutputFormat::Bbmodel | OutputFormat::BlockModel => {
+            return Ok(geometry_summary(options, stats));
+        }
+    };
+    let grid = BlockGrid::from_bones(bones, options).ok_or_else(|| Message::new(needs_cubes))?;
+    Ok(blocks_summary(stats, &grid, 1))
+}
+
+/// Writes the converted bones in the requested format; returns the file and a summary.
+fn export(
+    bones: Vec<McBone>,
+    variants: VariantBones,
+    atlas: Option<Texture>,
+    model_name: &str,
+    output_dir: &str,
+    options: &ConvertOptions,
+    stats: &VoxelStats,
+) -> Result<(PathBuf, Message), Message> {
+    let output_dir = Path::new(output_dir);
+    // Only a baked atlas is written next to the files; packs always need a texture
+    let baked = atlas.is_some();
+    let texture = atlas.unwrap_or_else(Texture::blank);
+    let write_atlas = |path: PathBuf| {
+        if !baked { return Ok(()); }
+        std::fs::write(path, texture.to_png()).map_err(failed("error.write_texture"))
+    };
+
+    match options.format {
+        OutputFormat::Geometry => {
+            let path = output_dir.join(format!("{}.geo.json", model_name));
+            write_geometry(bones, variants, model_name, &texture, &path)?;
+            let texture_name = options.shared_atlas.as_deref().unwrap_or(model_name);
+            write_atlas(output_dir.join(format!("{}.png", texture_name)))?;
+            Ok((path, geometry_summary(options, stats)))
+        }
+        OutputFormat::McStructure => {
+            let grid = BlockGrid::from_bones(&bones, options)
+                .ok_or_else(|| Message::new("error.structure_needs_cubes"))?;
+            let Some(size) = pieces::piece_size(&grid, options, Some(mcstructure::MAX_SIZE)) else {
+                let path = output_dir.join(format!("{}.mcstructure", model_name));
+                mcstructure::write_mcstructure(&grid, options, &path)?;
+                return Ok((path, blocks_summary(stats, &grid, 1)));
+            };
+
+            let dir = pieces::pieces_dir(output_dir, model_name, "mcstructure")?;
+            let name = resource_name(model_name);
+            let pieces = pieces::split(&grid, size);
+            let mut index = Vec::new();
+            for piece in &pieces {
+                let stem = format!("{}_{}", name, piece.name);
+                mcstructure::write_mcstructure(&piece.grid, options, &dir.join(format!("{}.mcstructure", stem)))?;
+                let p = piece.grid.origin;
+                let command = format!("structure load mystructure:{} ~{} ~{} ~{}", stem, p.x, p.y, p.z);
+                index.push(pieces::IndexEntry::new(piece, format!("{}.mcstructure", stem), Some(command)));
+            }
+            pieces::write_index(&dir, &index)?;
+            Ok((dir, blocks_summary(stats, &grid, pieces.len())))
+        }
+        OutputFormat::Datapack => {
+            let grid = BlockGrid::from_bones(&bones, options)
+                .ok_or_else(|| Message::new("error.datapack_needs_cubes"))?;
+            let path = output_dir.join(format!("{}_datapack", model_name));
+            let parts = mcfunction::write_datapack(&grid, options, model_name, &path)?;
+            // Without a progressive build the parts are pieces, split or not
+            let message = if parts == 1 || options.progressive_build == ProgressiveBuild::Off {
+                blocks_summary(stats, &grid, parts)
+            } else {
+                Message::new("result.voxels_to_blocks_in_steps")
+                    .with("voxels", stats.voxel_count)
+                    .with("blocks", grid.block_count())
+                    .with("steps", parts)
+            };
+            Ok((path, message))
+        }
+        OutputFormat::Schematic => {
+            let grid = BlockGrid::from_bones(&bones, options)
+                .ok_or_else(|| Message::new("error.schematic_needs_cubes"))?;
+            let Some(size) = pieces::piece_size(&grid, options, None) else {
+                let path = output_dir.join(format!("{}.schematic", model_name));
+                schematic::write_schematic(&grid, options, &path)?;
+                return Ok((path, blocks_summary(stats, &grid, 1)));
+            };
+
+            // Each piece's paste offset already places it; no command needed
+            let dir = pieces::pieces_dir(output_dir, model_name, "schematic")?;
+            let name = resource_name(model_name);
+            let pieces = pieces::split(&grid, size);
+            let mut index = Vec::new();
+            for piece in &pieces {
+                let file = format!("{}_{}.schematic", name, piece.name);
+                schematic::write_schematic(&piece.grid, options, &dir.join(&file))?;
+                index.push(pieces::IndexEntry::new(piece, file, None));
+            }
+            pieces::write_index(&dir, &index)?;
+            Ok((dir, blocks_summary(stats, &grid, pieces.len())))
+        }
+        OutputFormat::Schem => {
+            let grid = BlockGrid::from_bones(&bones, options)
+                .ok_or_else(|| Message::new("error.schematic_needs_cubes"))?;
+            let Some(size) = pieces::piece_size(&grid, options, None) else {
+                let path = output_dir.join(format!("{}.schem", model_name));
+                sponge::write_schem(&grid, options, &path)?;
+                return Ok((path, blocks_summary(stats, &grid, 1)));
+            };
+
+            let dir = pieces::pieces_dir(output_dir, model_name, "schem")?;
+            let name = resource_name(model_name);
+            let pieces = pieces::split(&grid, size);
+            let mut index = Vec::new();
+            for piece in &pieces {
+                let file = format!("{}_{}.schem", name, piece.name);
+                sponge::write_schem(&piece.grid, options, &dir.join(&file))?;
+                index.push(pieces::IndexEntry::new(piece, file, None));
+            }
+            pieces::write_index(&dir, &index)?;
+            Ok((dir, blocks_summary(stats, &grid, pieces.len())))
+        }
+        OutputFormat::StructureNbt => {
+            let grid = BlockGrid::from_bones(&bones, options)
+                .ok_or_else(|| Message::new("error.structure_needs_cubes"))?;
+            let size = pieces::piece_size(&grid, options, Some(structure_nbt::MAX_SIZE));
+            let tall = grid.size.y > structure_nbt::MAX_SIZE;
+            if size.is_none() && !tall {
+                let path = output_dir.join(format!("{}.nbt", model_name));
+                structure_nbt::write_structure(&grid, options, &path)?;
+                return Ok((path, blocks_summary(stats, &grid, 1)));
+            }
+
+            let dir = pieces::pieces_dir(output_dir, model_name, "nbt")?;
+            let name = resource_name(model_name);
+            let block_count = grid.block_count();
+            let mut pieces = match size {
+                Some(size) => pieces::split(&grid, size),
+                None => vec![pieces::Piece { name: "x0_z0".to_string(), grid }],
+            };
+            if tall {
+                pieces = pieces.into_iter().flat_map(|p| pieces::split_layers(p, structure_nbt::MAX_SIZE)).collect();
+            }
+            let mut index = Vec::new();
+            for piece in &pieces {
+                let stem = format!("{}_{}", name, piece.name);
+                structure_nbt::write_structure(&piece.grid, options, &dir.join(format!("{}.nbt", stem)))?;
+                let p = piece.grid.origin;
+                let command = format!("place template minecraft:{} ~{} ~{} ~{}", stem, p.x, p.y, p.z);
+                index.push(pieces::IndexEntry::new(piece, format!("{}.nbt", stem), Some(command)));
+            }
+            pieces::write_index(&dir, &index)?;
+            let message = Message::new("result.voxels_to_blocks_in_pieces")
+                .with("pieces", pieces.len())
+                .with("voxels", stats.voxel_count)
+                .with("blocks", block_count);
+            Ok((dir, message))
+        }
+        OutputFormat::McWorld | OutputFormat::McPack | OutputFormat::McAddon | OutputFormat::DevPacks => {
+            // The structure goes into the behavior pack, which `.mcpack` leaves out
+            let grid = match options.format {
+                OutputFormat::McPack => None,
+                _ => BlockGrid::from_bones(&bones, options),
+            };
+            let message = match &grid {
+                Some(grid) => Message::new("result.cubes_and_blocks")
+                    .with("cubes", stats.cube_count)
+                    .with("blocks", grid.block_count()),
+                None => geometry_summary(options, stats),
+            };
+            let packs = packs::build_packs(bones, variants, grid.as_ref(), &texture, model_name, options)?;
+
+            let path = match options.format {
+                OutputFormat::McPack => {
+                    let path = output_dir.join(format!("{}.mcpack", packs.resource.name));
+                    packs::write_mcpack(&packs.resource, &path)?;
+                    path
+                }
+                OutputFormat::McAddon => {
+                    let path = output_dir.join(format!("{}.mcaddon", model_name));
+                    packs::write_mcaddon(&packs, &path)?;
+                    path
+                }
+                OutputFormat::DevPacks => devpacks::write_dev_packs(&packs, options.game_folder.as_deref())?,
+                _ => {
+                    let path = output_dir.join(format!("{}.mcworld", model_name));
+                    mcworld::write_mcworld(&packs, model_name, &path)?;
+                    path
+                }
+            };
+            Ok((path, message))
+        }
+        OutputFormat::Jem => {
+            let path = if options.cem_part_files {
+                let dir = output_dir.join(format!("{}_cem", model_name));
+                std::fs::create_dir_all(&dir).map_err(failed("error.create_folder"))?;
+                dir.join(format!("{}.jem", model_name))
+            } else {
+                output_dir.join(format!("{}.jem", model_name))
+            };
+            cem::write_jem(&bones, &texture, &path, options.cem_part_files)?;
+            write_atlas(path.with_extension("png"))?;
+            Ok((path, geometry_summary(options, stats)))
+        }
+        OutputFormat::Bbmodel => {
+            // The texture is embedded in the project
+            let path = output_dir.join(format!("{}.bbmodel", model_name));
+            bbmodel::write_bbmodel(&bones, &texture, model_name, &path)?;
+            Ok((path, geometry_summary(options, stats)))
+        }
+        OutputFormat::BlockModel => {
+            // Java needs a texture file even when nothing was baked
+            let written = block_model::write_block_models(&bones, &texture, model_name, output_dir, options.block_model_fit)?;
+            let message = match (written.pieces, written.scale) {
+                (1, scale) if scale < 1.0 => Message::new("result.voxels_to_elements_scaled")
+                    .with("percent", (scale * 100.0).round() as u32),
+                (1, _) => Message::new("result.voxels_to_elements"),
+                (pieces, _) => Message::new("result.voxels_to_elements_in_pieces").with("pieces", pieces),
+            };
+            Ok((written.path, message.with("voxels", stats.voxel_count).with("elements", written.elements)))
+        }
+    }
+}
+
+/// Lays the cube faces out as `options.texture_layout` asks; returns the baked texture, if any.
+fn apply_texture_layout(bones: &mut [McBone], materials: &[tobj::Material], options: &ConvertOptions) -> Result<Option<Texture>, Message> {
+    Ok(match options.texture_layout {
+        TextureLayout::Default => None,
+        TextureLayout::Skin => {
+            skin::apply_skin_layout(bones)?;
+            None
+        }
+        TextureLayout::Atlas => {
+            let sources = projection::ColorSources::load(options.projection.as_ref(), bones, materials)?;
+            Some(texture::bake_atlas(bones, options, &sources))
+        }
+        TextureLayout::Preview => Some(preview::apply_preview(bones)),
+        TextureLayout::Clay => Some(clay::apply_clay(bones, options)),
+    })
+}
+
+/// The bones of `groups` in one list, and how many came from each.
+fn gather<'a>(groups: impl Iterator<Item = &'a mut Vec<McBone>>) -> (Vec<McBone>, Vec<usize>) {
+    let mut all = Vec::new();
+    let counts = groups
+        .map(|group| {
+            let count = group.len();
+            all.append(group);
+            count
+        })
+        .collect();
+    (all, counts)
+}
+
+/// Hands the bones `gather` took back to `groups`.
+fn scatter<'a>(all: Vec<McBone>, groups: impl Iterator<Item = &'a mut Vec<McBone>>, counts: &[usize]) {
+    let mut all = all.into_iter();
+    for (group, &count) in groups.zip(counts) {
+        group.extend(all.by_ref().take(count));
+    }
+}
+
+/// A model's bones, then each variant's.
+fn bone_groups<'a>(bones: &'a mut Vec<McBone>, variants: &'a mut VariantBones) -> impl Iterator<Item = &'a mut Vec<McBone>> {
+    std::iter::once(bones).chain(variants.iter_mut().map(|(_, b)| b))
+}
+
+/// `apply_texture_layout` over `bones` and their variants together, so they share one texture.
+fn apply_texture_layouts(
+    bones: &mut Vec<McBone>,
+    variants: &mut VariantBones,
+    materials: &[tobj::Material],
+    options: &ConvertOptions,
+) -> Result<Option<Texture>, Message> {
+    let (mut all, counts) = gather(bone_groups(bones, variants));
+    let atlas = apply_texture_layout(&mut all, materials, options);
+    scatter(all, bone_groups(bones, variants), &counts);
+    atlas
+}
+
+/// A voxelized model waiting for its texture.
+struct Prepared {
+    bones: Vec<McBone>,
+    variants: VariantBones,
+    colors: Option<colors::CubeColors>,
+    stats: VoxelStats,
+    /// The `palette_lock` colors, already applied to the cubes.
+    palette: Option<palette_lock::Palette>,
+}
+
+/// Converted geometry before palettes and textures touch it.
+#[derive(Clone)]
+struct Voxelized {
+    bones: Vec<McBone>,
+    variants: VariantBones,
+    stats: VoxelStats,
+}
+
+fn voxelize_models(
+    scene: &Scene,
+    scale: f32,
+    options: &ConvertOptions,
+    on_progress: &(dyn Fn(ModelProgress) + Sync),
+) -> Result<Voxelized, Message> {
+    let (bones, stats) = voxelize_model(scene, scale, options, &|progress, _| on_progress(progress))?;
+    if bones.is_empty() {
+        return Err(Message::new("error.no_geometry"));
+    }
+    let variants = variants::build_variants(&bones, options);
+    Ok(Voxelized { bones, variants, stats })
+}
+
+/// Locks the cube colors of `voxelized` to `palette` and gathers them for `cube_colors`.
+fn paint_models(voxelized: Voxelized, palette: Option<palette_lock::Palette>, options: &ConvertOptions) -> Prepared {
+    let Voxelized { mut bones, mut variants, stats } = voxelized;
+    if let Some(palette) = &palette {
+        for group in bone_groups(&mut bones, &mut variants) {
+            palette_lock::lock_cubes(group, palette);
+        }
+    }
+    let colors = options.cube_colors.then(|| colors::cube_colors(&bones));
+    Prepared { bones, variants, colors, stats, palette }
+}
+
+fn load_palette(options: &ConvertOptions) -> Result<Option<palette_lock::Palette>, Message> {
+    options.palette_lock.as_deref().map(palette_lock::Palette::load).transpose()
+}
+
+fn prepare_models(
+    scene: &Scene,
+    scale: f32,
+    options: &ConvertOptions,
+    on_progress: &(dyn Fn(ModelProgress) + Sync),
+) -> Result<Prepared, Message> {
+    palette::check_palette(options)?;
+    let palette = load_palette(options)?;
+    Ok(paint_models(voxelize_models(scene, scale, options, on_progress)?, palette, options))
+}
+
+/// Converts `scene` and keeps its geometry for `rebake`.
+fn convert_models(
+    scene: Scene,
+    model_name: &str,
+    output_dir: &str,
+    scale: f32,
+    options: &ConvertOptions,
+    on_progress: &(dyn Fn(ModelProgress) + Sync),
+) -> ConvertResult {
+    let voxelized = palette::check_palette(options)
+        .and_then(|_| load_palette(options))
+        .and_then(|_| voxelize_models(&scene, scale, options, on_progress));
+    let voxelized = match voxelized {
+        Ok(v) => v,
+        Err(e) => return ConvertResult::failed(e),
+    };
+    let cached = rebake::CachedResult::new(scene, voxelized, model_name, output_dir, scale, options);
+    let mut result = texture_models(&cached, cached.voxelized(), options);
+    if result.success {
+        result.result_id = Some(rebake::store(cached));
+    }
+    result
+}
+
+/// Paints, textures and writes `voxelized`, the geometry of `cached` or one
+/// made from it, as `options` ask.
+fn texture_models(cached: &rebake::CachedResult, voxelized: Voxelized, options: &ConvertOptions) -> ConvertResult {
+    let palette = match load_palette(options) {
+        Ok(v) => v,
+        Err(e) => return ConvertResult::failed(e),
+    };
+    let mut prepared = paint_models(voxelized, palette, options);
+    let materials = &cached.scene.materials;
+    let mut atlas = match apply_texture_layouts(&mut prepared.bones, &mut prepared.variants, materials, options) {
+        Ok(atlas) => atlas,
+        Err(e) => return ConvertResult::failed(e),
+    };
+    if let (Some(texture), Some(palette)) = (&mut atlas, &prepared.palette) {
+        palette_lock::lock_texture(texture, palette);
+    }
+    // Only a batch shares its atlas; a model converted on its own names its texture after itself
+    let options = ConvertOptions { shared_atlas: None, ..options.clone() };
+    finish_models(prepared, atlas, &cached.scene, &cached.model_name, &cached.output_dir, cached.scale, &options)
+}
+
+/// Bakes the texture of an earlier conversion again with `texture_options`,
+/// reusing its voxelized geometry, and writes the outputs over the old ones.
+pub fn rebake(result_id: u64, texture_options: &rebake::TextureOptions) -> ConvertResult {
+    let Some(cached) = rebake::get(result_id) else {
+        return ConvertResult::failed(Message::new("error.result_not_cached"));
+    };
+    let options = match texture_options.apply(&cached.options()) {
+        Ok(options) => options,
+        Err(e) => return ConvertResult::failed(e),
+    };
+    let mut result = texture_models(&cached, cached.voxelized(), &options);
+    if result.success {
+        cached.set_options(options);
+        result.result_id = Some(result_id);
+    }
+    result
+}
+
+/// Voxelizes `region`, a box in the output coordinates of the conversion
+/// `result_id`, again at its own scale and merges it into the kept geometry
+/// without voxelizing the rest. It replaces an earlier region of the same
+/// name, whose box goes back to the conversion scale; unnamed regions are added.
+pub fn refine_region(result_id: u64, region: &detail::DetailRegion) -> ConvertResult {
+    let Some(cached) = rebake::get(result_id) else {
+        return ConvertResult::failed(Message::new("error.result_not_cached"));
+    };
+    let mut voxelized = cached.voxelized();
+    let offset = voxelized.stats.origin_offset;
+    let region = detail::unshift(region, offset);
+
+    let mut options = cached.options();
+    let regions = &mut options.detail_regions;
+    let (index, replaced) = match regions.iter().position(|r| region.name.is_some() && r.name == region.name) {
+        Some(i) => (i, Some(std::mem::replace(&mut regions[i], region.clone()))),
+        None => {
+            regions.push(region.clone());
+            (regions.len() - 1, None)
+        }
+    };
+    if let Some(warning) = detail::skipped_warning(&options) {
+        return ConvertResult::failed(warning);
+    }
+    let cell_size = match cell_size(&options) {
+        Ok(c) => c,
+        Err(e) => return ConvertResult::failed(e),
+    };
+    let cell = cell_size.unwrap_or(Vec3::ONE);
+    let models = oriented_models(&cached.scene.models, &options);
+    let models = stretched_models(&models, cell_size);
+    let scale = cached.scale;
+    let bones = &mut voxelized.bones;
+    let mut added = 0;
+
+    // Cubes of each object inside `target` at its scale, leaving out the
+    // `earlier` regions, in the target's bone or the object's own
+    let mut refill = |target: &detail::DetailRegion, earlier: &[detail::DetailRegion], bones: &mut Vec<McBone>| {
+        for (i, model) in models.iter().enumerate() {
+            if model.mesh.indices.is_empty() || locators::is_marker(&model.name) { continue; }
+            let vertices: Vec<Vec3> = model.mesh.positions.chunks(3).map(|v| Vec3::new(v[0], v[1], v[2])).collect();
+            let mut cubes = detail::refine(&vertices, &model.mesh.indices, scale, options.sat_epsilon, target, earlier, cell);
+            if cubes.is_empty() { continue; }
+            added += detail::voxel_count(&cubes, target, scale);
+            model_painter(&cached.scene, i, model, scale, &options)(&mut cubes);
+
+            let mut bone = McBone {
+                name: target.name.clone().unwrap_or_else(|| model.name.clone()),
+                parent: None,
+                pivot: [0, 0, 0],
+                binding: None,
+                cubes,
+                poly_mesh: None,
+                locators: BTreeMap::new(),
+            };
+            if let Some(cell_size) = cell_size {
+                cells::scale_bones(std::slice::from_mut(&mut bone), cell_size);
+            }
+            origin::shift_cubes(&mut bone.cubes, offset);
+            bone.pivot = offset.to_array();
+            match bones.iter_mut().find(|b| b.name == bone.name) {
+                Some(existing) => existing.cubes.extend(bone.cubes),
+                None => bones.push(bone),
+            }
+        }
+    };
+
+    if let Some(name) = &region.name {
+        match replaced {
+            Some(_) => bones.retain(|b| &b.name != name),
+            None if bones.iter().any(|b| &b.name == name) => {
+                return ConvertResult::failed(Message::new("error.detail_bone_exists").with("bone", name));
+            }
+            None => {}
+        }
+    }
+    let regions = &options.detail_regions;
+    let removed = detail::carve(bones, &region, cell, offset);
+    refill(&region, &regions[..index], bones);
+    // Regions listed first keep the parts they share with the new box
+    for (i, earlier) in regions[..index].iter().enumerate() {
+        if let Some(shared) = detail::overlap(earlier, &region) {
+            refill(&shared, &regions[..i], bones);
+        }
+    }
+    // The part of a replaced box the new one leaves goes to later regions or back to the conversion scale
+    if let Some(old) = &replaced {
+        for (i, later) in regions.iter().enumerate().skip(index + 1) {
+            if let Some(shared) = detail::overlap(later, old) {
+                refill(&shared, &regions[..i], bones);
+            }
+        }
+        let coarse = detail::DetailRegion { name: None, scale, ..old.clone() };
+        refill(&coarse, regions, bones);
+    }
+
+    if options.sort_cubes {
+        sorting::sort_cubes(bones);
+    }
+    let stats = &mut voxelized.stats;
+    stats.voxel_count = (stats.voxel_count + added).saturating_sub(removed);
+    stats.cube_count = voxelized.bones.iter().map(|b| b.cubes.len()).sum();
+    voxelized.variants = variants::build_variants(&voxelized.bones, &options);
+
+    let mut result = texture_models(&cached, voxelized.clone(), &options);
+    if result.success {
+        cached.set_voxelized(voxelized);
+        cached.set_options(options);
+        result.result_id = Some(result_id);
+    }
+    result
+}
+
+/// One file of `convert_shared`.
+pub struct SharedInput<'a> {
+    pub path: String,
+    pub output_dir: &'a str,
+    pub scale: f32,
+    pub options: &'a ConvertOptions,
+}
+
+/// Converts `inputs` with one atlas for all of them, laid out as the first
+/// input's `texture_layout` asks and locked to its `palette_lock`, so a kit of props uses one texture and one
+/// palette. Every geometry refers to `<shared_atlas>.png`. Results are in
+/// input order; if any input fails, they all do.
+pub fn convert_shared(inputs: &[SharedInput], shared_atlas: &str) -> Vec<ConvertResult> {
+    let fail_all = |e: Message| inputs.iter().map(|_| ConvertResult::failed(e.clone())).collect();
+    if inputs.is_empty() {
+        return vec![];
+    }
+
+    let mut models = Vec::new();
+    for input in inputs {
+        let prepared = load_model(&input.path)
+            .and_then(|scene| prepare_models(&scene, input.scale, input.options, &|_| {}).map(|p| (scene, p)));
+        match prepared {
+            Ok(model) => models.push(model),
+            Err(e) => return fail_all(e),
+        }
+    }
+
+    // Material indices of later models move past the earlier models' materials
+    let mut materials = Vec::new();
+    for (scene, prepared) in &mut models {
+        let base = materials.len();
+        materials.extend(scene.materials.iter().cloned());
+        for bone in bone_groups(&mut prepared.bones, &mut prepared.variants).flatten() {
+            for cube in &mut bone.cubes {
+                cube.texture = cube.texture.map(|t| t + base);
+            }
+        }
+    }
+
+    fn groups(models: &mut [(Scene, Prepared)]) -> impl Iterator<Item = &mut Vec<McBone>> {
+        models.iter_mut().flat_map(|(_, p)| bone_groups(&mut p.bones, &mut p.variants))
+    }
+    let (mut all, counts) = gather(groups(&mut models));
+    let mut atlas = match apply_texture_layout(&mut all, &materials, inputs[0].options) {
+        Ok(atlas) => atlas,
+        Err(e) => return fail_all(e),
+    };
+    if let (Some(texture), Some(palette)) = (&mut atlas, &models[0].1.palette) {
+        palette_lock::lock_texture(texture, palette);
+    }
+    scatter(all, groups(&mut models), &counts);
+
+    models.into_iter().zip(inputs)
+        .map(|((scene, prepared), input)| {
+            let options = ConvertOptions { shared_atlas: Some(shared_atlas.to_string()), ..input.options.clone() };
+            let name = model_name(&input.path);
+            finish_models(prepared, atlas.clone(), &scene, &name, input.output_dir, input.scale, &options)
+        })
+        .collect()
+}
+
+/// Bindings, warnings and the written files of a textured model.
+fn finish_models(
+    prepared: Prepared,
+    atlas: Option<Texture>,
+    scene: &Scene,
+    model_name: &str,
+    output_dir: &str,
+    scale: f32,
+    options: &ConvertOptions,
+) -> ConvertResult {
+    let Prepared { mut bones, mut variants, colors, stats, .. } = prepared;
+    let VoxelStats { voxel_count, cube_count, .. } = stats;
+
+    for bone in bones.iter_mut().chain(variants.iter_mut().flat_map(|(_, b)| b)) {
+        let target = options.bone_bindings.get(&bone.name).map(String::as_str)
+            .or_else(|| options.wearable_slot.as_ref().and_then(|_| packs::player_bone(&bone.name)));
+        if let Some(target) = target {
+            bone.binding = Some(format!("'{}'", target));
+        }
+    }
+
+    let (texture_width, texture_height) = atlas.as_ref().map_or((TEXTURE_SIZE, TEXTURE_SIZE), |t| (t.width, t.height));
+    let mut warnings = perf::estimate(cube_count, bones.len(), texture_width, texture_height).reasons;
+    if options.fill_interior {
+        warnings.extend(topology::fill_warnings(&topology::object_stats(&scene.models)));
+    }
+    warnings.extend(atlas.as_ref().and_then(|t| texture::budget_warning(t, options)));
+    warnings.extend(detail::skipped_warning(options));
+    warnings.extend(adaptive::skipped_warning(options));
+    warnings.extend(stats.relocation.map(relocation_warning));
+    warnings.extend(mtl::missing_warning(&scene.missing_files));
+    let relocation = stats.relocation.map(|r| r.to_array());
+    let bone_stats = perf::bone_stats(&bones);
+    let faces = perf::face_stats(&bones);
+    let texture_size = atlas.as_ref().map(|t| [t.width, t.height]);
+
+    if options.dry_run {
+        let estimated_size = estimate_size(bones.clone(), variants, model_name, atlas.as_ref());
+        return match dry_run_summary(&bones, options, &stats) {
+            Ok(message) => ConvertResult {
+                success: true,
+                message,
+                output_path: None,
+                voxel_count,
+                cube_count,
+                warnings,
+                bones: bone_stats,
+                other_outputs: vec![],
+                texture_size,
+                estimated_size: Some(estimated_size),
+                faces,
+                relocation,
+                result_id: None,
+            },
+            Err(e) => ConvertResult::failed(e),
         };
     }
 
+    let mut extra_formats: Vec<OutputFormat> = Vec::new();
+    for &format in &options.extra_formats {
+        if format != options.format && !extra_formats.contains(&format) {
+            extra_formats.push(format);
+        }
+    }
+    let folder = |format: OutputFormat| -> Result<String, Message> {
+        let Some(template) = &options.output_template else { return Ok(output_dir.to_string()) };
+        let folder = template::output_folder(template, output_dir, model_name, scale, format)?;
+        std::fs::create_dir_all(&folder).map_err(failed("error.create_output_dir"))?;
+        Ok(folder)
+    };
+
+    let mut other_outputs = Vec::new();
+    for format in extra_formats {
+        let target = ConvertOptions { format, ..options.clone() };
+        let written = folder(format)
+            .and_then(|dir| export(bones.clone(), variants.clone(), atlas.clone(), model_name, &dir, &target, &stats));
+        match written {
+            Ok((path, _)) => other_outputs.push(path.to_string_lossy().to_string()),
+            Err(e) => return ConvertResult::failed(e),
+        }
+    }
+
+    let written = folder(options.format).and_then(|dir| {
+        if let Some(colors) = &colors {
+            let path = Path::new(&dir).join(format!("{}.colors.json", model_name));
+            colors::write_cube_colors(colors, &path)?;
+            other_outputs.push(path.to_string_lossy().to_string());
+        }
+        if options.export_palette {
+            let path = Path::new(&dir).join(format!("{}.palette.json", model_name));
+            palette_lock::Palette::of(&bones, atlas.as_ref()).write(&path)?;
+            other_outputs.push(path.to_string_lossy().to_string());
+        }
+        export(bones, variants, atlas, model_name, &dir, options, &stats)
+    });
+    let (output_path, message) = match written {
+        Ok(v) => v,
+        Err(e) => return ConvertResult::failed(e),
+    };
+    let output_path = output_path.to_string_lossy().to_string();
+
+    let outputs: Vec<String> = std::iter::once(output_path.clone()).chain(other_outputs.iter().cloned()).collect();
+    warnings.extend(hook::run_hook(&options.post_convert, &outputs));
+
     ConvertResult {
         success: true,
-        message: format!("{} voxels → {} cubes", voxel_count, cube_count),
-        output_path: Some(output_str),
+        message,
+        output_path: Some(output_path),
         voxel_count,
         cube_count,
+        warnings,
+        bones: bone_stats,
+        other_outputs,
+        texture_size,
+        estimated_size: None,
+        faces,
+        relocation,
+        result_id: None,
+    }
+}
+
+// ================= TAURI COMMANDS =================
+
+fn history_file(app: &AppHandle) -> Result<PathBuf, Message> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join("history.json"))
+        .map_err(failed("error.app_data_folder"))
+}
+
+fn session_file(app: &AppHandle) -> Result<PathBuf, Message> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join("session.json"))
+        .map_err(failed("error.app_data_folder"))
+}
+
+fn profiles_file(app: &AppHandle) -> Result<PathBuf, Message> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join("profiles.json"))
+        .map_err(failed("error.app_data_folder"))
+}
+
+/// Downloads http(s) inputs to a temp file, emitting `download-progress`; local paths pass through.
+fn fetch_input(app: &AppHandle, path: &str) -> Result<String, Message> {
+    if !download::is_url(path) {
+        return Ok(path.to_string());
+    }
+    download::download(path, &|progress| {
+        let _ = app.emit("download-progress", progress);
+    })
+}
+
+fn convert_with_events(app: &AppHandle, entry: &HistoryEntry) -> ConvertResult {
+    if let Some(file) = history_file(app).ok().filter(|_| !entry.options.dry_run) {
+        let _ = history::push(&file, entry.clone());
     }
+
+    let path = match fetch_input(app, &entry.path) {
+        Ok(path) => path,
+        Err(e) => return ConvertResult::failed(e),
+    };
+
+    let on_progress = |progress: ModelProgress| {
+        let _ = app.emit("model-progress", progress);
+    };
+    convert(&path, &entry.output_dir, entry.scale, &entry.options, &on_progress)
+}
+
+#[tauri::command(async)]
+fn analyze_file(
+    app: AppHandle,
+    path: String,
+    scale: f32,
+    options: Option<ConvertOptions>,
+) -> Result<FileInfo, Message> {
+    let options = options.unwrap_or_default();
+    let local_path = fetch_input(&app, &path)?;
+    let scene = load_model(&local_path)?;
+    
+    let name = Path::new(&local_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let (mut bones, stats) = voxelize_model(&scene, scale, &options, &|_, _| {})?;
+    let bone_count = bones.len();
+    // A layout that doesn't fit (skin on a non-humanoid) is reported by the conversion itself
+    let mut variants = variants::build_variants(&bones, &options);
+    let atlas = apply_texture_layouts(&mut bones, &mut variants, &scene.materials, &options).unwrap_or(None);
+    let model_name = Path::new(&local_path).file_stem().map_or("model".into(), |n| n.to_string_lossy());
+    let (texture_width, texture_height) = atlas.as_ref().map_or((TEXTURE_SIZE, TEXTURE_SIZE), |t| (t.width, t.height));
+    let estimated_size = estimate_size(bones, variants, &model_name, atlas.as_ref());
+    let objects = topology::object_stats(&scene.models);
+    let mut warnings = if options.fill_interior { topology::fill_warnings(&objects) } else { vec![] };
+    warnings.extend(detail::skipped_warning(&options));
+    warnings.extend(adaptive::skipped_warning(&options));
+    warnings.extend(stats.relocation.map(relocation_warning));
+    warnings.extend(mtl::missing_warning(&scene.missing_files));
+
+    Ok(FileInfo {
+        path,
+        name,
+        vertices: scene.vertex_count,
+        faces: scene.face_count,
+        voxel_count: stats.voxel_count,
+        cube_count: stats.cube_count,
+        shell_voxel_count: stats.shell_voxel_count,
+        solid_voxel_count: stats.solid_voxel_count,
+        bone_count,
+        performance: perf::estimate(stats.cube_count, bone_count, texture_width, texture_height),
+        suggested_rotation: orient::suggest_rotation(&scene.models),
+        objects,
+        warnings,
+        estimated_size,
+    })
+}
+
+#[tauri::command(async)]
+fn inspect_geometry(path: String) -> Result<GeometryReport, Message> {
+    inspect::inspect_geometry(&path)
+}
+
+/// Merges separately converted geo.json files into one geometry.
+#[tauri::command(async)]
+fn merge_geometry(parts: Vec<merge::MergePart>, output_dir: String, model_name: String) -> Result<merge::MergeReport, Message> {
+    merge::merge_geometry(&parts, &output_dir, &model_name)
+}
+
+#[tauri::command]
+fn estimate_performance(
+    cube_count: usize,
+    bone_count: usize,
+    texture_width: u32,
+    texture_height: u32,
+) -> PerformanceEstimate {
+    perf::estimate(cube_count, bone_count, texture_width, texture_height)
+}
+
+#[tauri::command]
+fn save_session(app: AppHandle, session: Session) -> Result<(), Message> {
+    session::save(&session_file(&app)?, &session)
+}
+
+/// The state saved before the app was last closed, if any.
+#[tauri::command]
+fn get_last_session(app: AppHandle) -> Option<Session> {
+    session::load(&session_file(&app).ok()?)
+}
+
+/// The options to switch to when `format` is picked: its saved profile, else the built-in one.
+#[tauri::command]
+fn get_profile(app: AppHandle, format: OutputFormat) -> Result<ConvertOptions, Message> {
+    Ok(profiles::get(&profiles_file(&app)?, format))
+}
+
+/// Saves `options` as the profile of `options.format`.
+#[tauri::command]
+fn save_profile(app: AppHandle, options: ConvertOptions) -> Result<(), Message> {
+    profiles::save(&profiles_file(&app)?, options.format, Some(options))
+}
+
+/// Drops the saved profile of `format` and returns its built-in options.
+#[tauri::command]
+fn reset_profile(app: AppHandle, format: OutputFormat) -> Result<ConvertOptions, Message> {
+    profiles::save(&profiles_file(&app)?, format, None)?;
+    Ok(profiles::builtin(format))
+}
+
+/// Bedrock installations whose development pack folders `dev_packs` can write to.
+#[tauri::command]
+fn find_game_folders() -> Vec<devpacks::GameFolder> {
+    devpacks::find_game_folders()
+}
+
+/// Converts a built-in reference mesh at a few scales and reports throughput.
+#[tauri::command(async)]
+fn run_benchmark(options: Option<ConvertOptions>) -> Result<bench::BenchmarkReport, Message> {
+    bench::run_benchmark(&options.unwrap_or_default())
+}
+
+/// Keys and English templates of every backend message, for translators.
+#[tauri::command]
+fn get_message_catalog() -> BTreeMap<&'static str, &'static str> {
+    messages::catalog()
+}
+
+/// JSON Schema of the conversion options, for generated forms and validation.
+#[tauri::command]
+fn get_option_schema() -> serde_json::Value {
+    schema::option_schema()
+}
+
+#[tauri::command(async)]
+fn convert_file(
+    app: AppHandle,
+    path: String,
+    output_dir: String,
+    scale: f32,
+    options: Option<ConvertOptions>,
+) -> ConvertResult {
+    let entry = HistoryEntry::new(&path, &output_dir, scale, &options.unwrap_or_default());
+    convert_with_events(&app, &entry)
+}
+
+#[tauri::command(async)]
+fn convert_obj_text(
+    app: AppHandle,
+    text: String,
+    name: Option<String>,
+    output_dir: String,
+    scale: f32,
+    options: Option<ConvertOptions>,
+) -> ConvertResult {
+    let on_progress = |progress: ModelProgress| {
+        let _ = app.emit("model-progress", progress);
+    };
+    let name = name.unwrap_or_else(|| "clipboard".to_string());
+    convert_text(&text, &name, &output_dir, scale, &options.unwrap_or_default(), &on_progress)
+}
+
+/// Re-runs the most recent conversion with the same file, output folder and options.
+#[tauri::command(async)]
+fn reconvert_last(app: AppHandle) -> Result<ConvertResult, Message> {
+    let last = history::load(&history_file(&app)?)
+        .pop()
+        .ok_or_else(|| Message::new("error.no_previous_conversion"))?;
+
+    let entry = HistoryEntry::new(&last.path, &last.output_dir, last.scale, &last.options);
+    Ok(convert_with_events(&app, &entry))
+}
+
+/// Bakes the texture of the conversion `result_id` names again with
+/// `texture_options`, without voxelizing again.
+#[tauri::command(async)]
+fn rebake_texture(result_id: u64, texture_options: rebake::TextureOptions) -> ConvertResult {
+    rebake(result_id, &texture_options)
+}
+
+/// Voxelizes `region`, a box picked in the output of the conversion
+/// `result_id`, again at its own scale, leaving the rest of the geometry.
+#[tauri::command(async)]
+fn reconvert_region(result_id: u64, region: detail::DetailRegion) -> ConvertResult {
+    refine_region(result_id, &region)
+}
+
+/// Meshes the file as `convert_file` would and sends its cubes to the
+/// preview as `preview-cubes` batches, after `model-progress` per object.
+#[tauri::command(async)]
+fn stream_preview(
+    app: AppHandle,
+    path: String,
+    scale: f32,
+    options: Option<ConvertOptions>,
+) -> Result<stream::PreviewSummary, Message> {
+    // Rigging, cuts, regions and joint pivots only regroup the cubes, after every model is done
+    let options = ConvertOptions {
+        auto_rig: false,
+        cut_planes: vec![],
+        region_size: 0,
+        joint_pivots: false,
+        ..options.unwrap_or_default()
+    };
+    let scene = load_model(&fetch_input(&app, &path)?)?;
+    let cell_size = options.cell_size.map(Vec3::from).filter(|&c| c != Vec3::ONE);
+    let emit = |batch: stream::CubeBatch| {
+        let _ = app.emit("preview-cubes", batch);
+    };
+
+    let cubes = Mutex::new(stream::CubeStream::default());
+    let on_model = |progress: ModelProgress, bone: Option<&McBone>| {
+        let _ = app.emit("model-progress", progress);
+        let Some(bone) = bone else { return };
+        let mut bone = bone.clone();
+        bone.parent = scene.hierarchy.iter().find(|(name, _)| *name == bone.name).and_then(|(_, p)| p.clone());
+        if let Some(cell_size) = cell_size {
+            cells::scale_bones(std::slice::from_mut(&mut bone), cell_size);
+        }
+        cubes.lock().unwrap().send(&bone, &emit);
+    };
+    let (bones, stats) = voxelize_model(&scene, scale, &options, &on_model)?;
+
+    // Bones made once every model is done: copies and detail regions, moved
+    // back to where the bones sent so far are
+    let mut cubes = cubes.into_inner().unwrap();
+    for bone in &bones {
+        if !cubes.has_sent(&bone.name) {
+            let mut bone = bone.clone();
+            origin::shift_cubes(&mut bone.cubes, -stats.origin_offset);
+            bone.pivot = (IVec3::from(bone.pivot) - stats.origin_offset).to_array();
+            cubes.send(&bone, &emit);
+        }
+    }
+    Ok(cubes.summary(stats.origin_offset))
+}
+
+/// Meshes the file as `convert_file` would and returns all its cubes as one
+/// binary buffer of typed arrays, for previews too large for JSON.
+#[tauri::command(async)]
+fn preview_buffer(
+    app: AppHandle,
+    path: String,
+    scale: f32,
+    options: Option<ConvertOptions>,
+) -> Result<tauri::ipc::Response, Message> {
+    let options = options.unwrap_or_default();
+    let scene = load_model(&fetch_input(&app, &path)?)?;
+    let on_progress = |progress: ModelProgress| {
+        let _ = app.emit("model-progress", progress);
+    };
+    let (bones, _) = voxelize_model(&scene, scale, &options, &|progress, _| on_progress(progress))?;
+    Ok(tauri::ipc::Response::new(stream::encode_cubes(&bones)))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -382,7 +2453,28 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![analyze_file, convert_file])
+        .invoke_handler(tauri::generate_handler![
+            analyze_file,
+            convert_file,
+            convert_obj_text,
+            reconvert_last,
+            reconvert_region,
+            rebake_texture,
+            stream_preview,
+            preview_buffer,
+            estimate_performance,
+            inspect_geometry,
+            merge_geometry,
+            get_option_schema,
+            get_message_catalog,
+            run_benchmark,
+            find_game_folders,
+            save_session,
+            get_last_session,
+            get_profile,
+            save_profile,
+            reset_profile,
+        ])
         .setup(|app| {
             let window = app.get_webview_window("main").unwrap();
             window.show().unwrap();
@@ -0,0 +1,70 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Message;
+use crate::McBone;
+
+// ================= LOCATORS =================
+//
+// Named attachment points for particles and held items. They come from OBJ
+// objects named `locator_<name>`, which are not voxelized (their bounding box
+// center becomes the point), and from explicit positions in the options.
+// Each locator goes on the bone it names, or else on the bone with the
+// nearest cube.
+
+const MARKER_PREFIX: &str = "locator_";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Locator {
+    pub name: String,
+    /// Position in geometry units (1/16 block).
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub bone: Option<String>,
+}
+
+/// Whether the OBJ object only marks a locator.
+pub fn is_marker(object_name: &str) -> bool {
+    object_name.to_ascii_lowercase().starts_with(MARKER_PREFIX)
+}
+
+/// Locators at the centers of the marker objects in `models`.
+pub fn marker_locators(models: &[tobj::Model], scale: f32) -> Vec<Locator> {
+    models.iter()
+        .filter(|m| is_marker(&m.name) && !m.mesh.positions.is_empty())
+        .map(|m| {
+            let (min, max) = m.mesh.positions.chunks(3)
+                .map(|v| Vec3::new(v[0], v[1], v[2]))
+                .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), v| (min.min(v), max.max(v)));
+            Locator {
+                name: m.name[MARKER_PREFIX.len()..].to_string(),
+                position: ((min + max) / 2.0 * scale).to_array(),
+                bone: None,
+            }
+        })
+        .collect()
+}
+
+fn distance_to_bone(bone: &McBone, p: Vec3) -> f32 {
+    bone.cubes.iter()
+        .map(|c| {
+            let min = Vec3::from(c.origin);
+            p.clamp(min, min + Vec3::from(c.size)).distance_squared(p)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+pub fn attach_locators(bones: &mut [McBone], locators: impl IntoIterator<Item = Locator>) -> Result<(), Message> {
+    for locator in locators {
+        let p = Vec3::from(locator.position);
+        let index = match &locator.bone {
+            Some(name) => bones.iter().position(|b| &b.name == name)
+                .ok_or_else(|| Message::new("error.unknown_locator_bone").with("locator", &locator.name).with("bone", name))?,
+            None => (0..bones.len())
+                .min_by(|&a, &b| distance_to_bone(&bones[a], p).total_cmp(&distance_to_bone(&bones[b], p)))
+                .ok_or_else(|| Message::new("error.locators_need_bones"))?,
+        };
+        bones[index].locators.insert(locator.name, locator.position);
+    }
+    Ok(())
+}
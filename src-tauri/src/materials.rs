@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+// ================= PER-TRIANGLE MATERIALS =================
+//
+// An object that switches material part way (`usemtl` inside one OBJ
+// object, or a glTF mesh with a primitive per material) loads as several
+// parts with the same name. The parts are joined back into one object, and
+// when their materials differ the material of every triangle is kept, so
+// cubes take the color of the material under them and atlas texels read the
+// texture of the triangle they sample rather than the object's first one.
+
+/// Material of each triangle, by object index, for objects with several.
+pub type FaceMaterials = HashMap<usize, Vec<Option<usize>>>;
+
+/// `models` with same-named parts joined, and the materials of the triangles
+/// of the joined objects whose parts differ in material.
+pub fn join_parts(models: Vec<tobj::Model>) -> (Vec<tobj::Model>, FaceMaterials) {
+    let mut groups: Vec<Vec<tobj::Model>> = Vec::new();
+    let mut index_of: HashMap<String, usize> = HashMap::new();
+    for model in models {
+        match index_of.get(&model.name) {
+            Some(&i) => groups[i].push(model),
+            None => {
+                index_of.insert(model.name.clone(), groups.len());
+                groups.push(vec![model]);
+            }
+        }
+    }
+
+    let mut faces = FaceMaterials::new();
+    let mut joined = Vec::with_capacity(groups.len());
+    for (index, parts) in groups.into_iter().enumerate() {
+        if parts.len() == 1 {
+            joined.extend(parts);
+            continue;
+        }
+        let ids: Vec<Option<usize>> = parts.iter().map(|p| p.mesh.material_id).collect();
+        if ids.iter().any(|&id| id != ids[0]) {
+            let per_face = parts.iter().flat_map(|p| vec![p.mesh.material_id; p.mesh.indices.len() / 3]).collect();
+            faces.insert(index, per_face);
+        }
+        joined.push(join(parts));
+    }
+    (joined, faces)
+}
+
+/// One object of `parts`, with the first part's name and material. UVs are
+/// zero on the parts without them; normals and vertex colors are kept when
+/// every part has them.
+fn join(parts: Vec<tobj::Model>) -> tobj::Model {
+    let any_uvs = parts.iter().any(|p| !p.mesh.texcoords.is_empty());
+    let all_normals = parts.iter().all(|p| !p.mesh.normals.is_empty());
+    let all_colors = parts.iter().all(|p| !p.mesh.vertex_color.is_empty());
+
+    let mut mesh = tobj::Mesh { material_id: parts[0].mesh.material_id, ..Default::default() };
+    for part in &parts {
+        let offset = (mesh.positions.len() / 3) as u32;
+        let vertices = part.mesh.positions.len() / 3;
+        mesh.positions.extend(&part.mesh.positions);
+        mesh.indices.extend(part.mesh.indices.iter().map(|i| i + offset));
+        if any_uvs {
+            match part.mesh.texcoords.is_empty() {
+                true => mesh.texcoords.extend(vec![0.0; vertices * 2]),
+                false => mesh.texcoords.extend(&part.mesh.texcoords),
+            }
+        }
+        if all_normals {
+            mesh.normals.extend(&part.mesh.normals);
+        }
+        if all_colors {
+            mesh.vertex_color.extend(&part.mesh.vertex_color);
+        }
+    }
+    let name = parts.into_iter().next().unwrap().name;
+    tobj::Model::new(mesh, name)
+}
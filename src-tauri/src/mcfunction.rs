@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use ahash::RandomState;
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::{Axis, BlockGrid, BlockShape, Facing};
+use crate::messages::{failed, Message};
+use crate::pieces::{self, IndexEntry};
+use crate::{resource_name, run_greedy_meshing, ConvertOptions};
+
+// ================= JAVA DATAPACK (MCFUNCTION) =================
+//
+// Writes a datapack whose `obj2mc:<model>` function places the model at the
+// execution position, by its anchor. Full blocks are merged into
+// `fill` boxes, partial blocks use `setblock`.
+//
+// A progressive build splits placement into steps (one layer or one chunk
+// column each). Scheduled functions run without a position, so the entry
+// function summons a marker entity as the anchor; every step places its blocks
+// at the marker and schedules the next one, and the last step removes it.
+//
+// Split into pieces, each piece gets its own function (listed in an index
+// file) that can be run on its own; the entry function runs all of them. A
+// model placed at once whose function would pass MAX_COMMANDS, where Java
+// silently stops, is split on its own into pieces that each stay below it.
+
+const NAMESPACE: &str = "obj2mc";
+/// Datapack format for Java 1.21.
+const PACK_FORMAT: i32 = 48;
+const DEFAULT_BLOCK: &str = "minecraft:stone";
+const DEFAULT_STAIR_BLOCK: &str = "minecraft:stone_stairs";
+const DEFAULT_SLAB_BLOCK: &str = "minecraft:stone_slab";
+const DEFAULT_SUPPORT_BLOCK: &str = "minecraft:oak_planks";
+/// `fill` refuses regions above this many blocks.
+const MAX_FILL_VOLUME: i32 = 32768;
+/// Java stops running a function after this many commands (`maxCommandChainLength`).
+const MAX_COMMANDS: usize = 65536;
+const DEFAULT_BUILD_INTERVAL: u32 = 10;
+const CHUNK_SIZE: i32 = 16;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressiveBuild {
+    /// Place everything at once.
+    #[default]
+    Off,
+    /// One horizontal layer per step, bottom to top.
+    Layers,
+    /// One 16×16 column of the model per step.
+    Chunks,
+}
+
+/// Java block state of `shape`, as written in commands (`name[key=value]`).
+pub(crate) fn block_state(options: &ConvertOptions, shape: BlockShape) -> String {
+    let name = |custom: &Option<String>, default: &str| custom.clone().unwrap_or_else(|| default.to_string());
+
+    let facing_name = |facing: Facing| match facing {
+        Facing::East => "east",
+        Facing::West => "west",
+        Facing::South => "south",
+        Facing::North => "north",
+    };
+
+    match shape {
+        BlockShape::Full => name(&options.block, DEFAULT_BLOCK),
+        BlockShape::Stairs(facing) => {
+            format!("{}[facing={},half=bottom]", name(&options.stair_block, DEFAULT_STAIR_BLOCK), facing_name(facing))
+        }
+        BlockShape::Slab => format!("{}[type=bottom]", name(&options.slab_block, DEFAULT_SLAB_BLOCK)),
+        BlockShape::Support => name(&options.support_block, DEFAULT_SUPPORT_BLOCK),
+        BlockShape::Pillar(axis) => {
+            let axis = match axis {
+                Axis::X => "x",
+                Axis::Y => "y",
+                Axis::Z => "z",
+            };
+            format!("{}[axis={}]", name(&options.block, DEFAULT_BLOCK), axis)
+        }
+        BlockShape::Rotated(facing) => format!("{}[facing={}]", name(&options.block, DEFAULT_BLOCK), facing_name(facing)),
+        BlockShape::Air => "minecraft:air".to_string(),
+        BlockShape::Variation(i) => options.block_variation[i].clone(),
+        BlockShape::Decoration(i) => options.decorations[i].clone(),
+    }
+}
+
+/// `fill`/`setblock` commands for the given blocks, relative to the execution position.
+fn place_commands(grid: &BlockGrid, positions: &[IVec3], options: &ConvertOptions) -> Vec<String> {
+    // Full blocks of each kind are merged into fill boxes, in the order first seen
+    let mut boxed: Vec<(BlockShape, HashSet<IVec3, RandomState>)> = Vec::new();
+    let mut commands = Vec::new();
+
+    for &p in positions {
+        match grid.get(p) {
+            Some(shape @ (BlockShape::Stairs(_) | BlockShape::Slab)) => {
+                let p = grid.origin + p;
+                commands.push(format!("setblock ~{} ~{} ~{} {}", p.x, p.y, p.z, block_state(options, shape)));
+            }
+            Some(shape) => match boxed.iter_mut().find(|(s, _)| *s == shape) {
+                Some((_, cells)) => { cells.insert(p); }
+                None => boxed.push((shape, HashSet::from_iter([p]))),
+            },
+            None => {}
+        }
+    }
+
+    for (shape, cells) in &boxed {
+        commands.extend(fill_commands(grid, cells, &block_state(options, *shape)));
+    }
+    commands
+}
+
+/// `fill` commands placing `block` at `positions`, merged into boxes.
+fn fill_commands(grid: &BlockGrid, positions: &HashSet<IVec3, RandomState>, block: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    for cube in run_greedy_meshing(positions) {
+        let corner = grid.origin + IVec3::from_array(cube.origin.map(|c| c as i32));
+        let size = IVec3::from_array(cube.size.map(|c| c as i32));
+
+        // Split oversized boxes into blocks of at most MAX_FILL_VOLUME, whole
+        // Z rows first, then Y layers, then X slices
+        let depth = size.z.min(MAX_FILL_VOLUME);
+        let height = size.y.min(MAX_FILL_VOLUME / depth);
+        let width = size.x.min(MAX_FILL_VOLUME / (depth * height));
+        let step = IVec3::new(width, height, depth);
+        for x in (0..size.x).step_by(width as usize) {
+            for y in (0..size.y).step_by(height as usize) {
+                for z in (0..size.z).step_by(depth as usize) {
+                    let start = IVec3::new(x, y, z);
+                    let from = corner + start;
+                    let to = corner + (start + step).min(size) - IVec3::ONE;
+                    commands.push(format!(
+                        "fill ~{} ~{} ~{} ~{} ~{} ~{} {}",
+                        from.x, from.y, from.z, to.x, to.y, to.z, block
+                    ));
+                }
+            }
+        }
+    }
+
+    commands
+}
+
+/// Side of the pieces a grid too large for one function is cut into: the
+/// largest whole number of chunks whose pieces each stay within MAX_COMMANDS.
+fn command_piece_size(grid: &BlockGrid, options: &ConvertOptions) -> Option<i32> {
+    let count = |grid: &BlockGrid| {
+        let positions: Vec<IVec3> = grid.blocks.keys().copied().collect();
+        place_commands(grid, &positions, options).len()
+    };
+    if count(grid) <= MAX_COMMANDS {
+        return None;
+    }
+    let mut chunks = (grid.size.x.max(grid.size.z) + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    while chunks > 1 {
+        chunks /= 2;
+        if pieces::split(grid, chunks * CHUNK_SIZE).iter().all(|piece| count(&piece.grid) <= MAX_COMMANDS) {
+            break;
+        }
+    }
+    Some(chunks * CHUNK_SIZE)
+}
+
+fn write_function(dir: &Path, name: &str, commands: &[String]) -> Result<(), Message> {
+    let path = dir.join(format!("{}.mcfunction", name));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(failed("error.create_folder"))?;
+    }
+    fs::write(&path, commands.join("\n") + "\n").map_err(failed("error.write_function"))
+}
+
+/// Writes a datapack folder at `path`; returns the number of build steps, or
+/// of pieces when split.
+pub fn write_datapack(grid: &BlockGrid, options: &ConvertOptions, model_name: &str, path: &Path) -> Result<usize, Message> {
+    let name = resource_name(model_name);
+    let functions = path.join("data").join(NAMESPACE).join("function");
+    fs::create_dir_all(&functions).map_err(failed("error.create_folder"))?;
+
+    let mcmeta = serde_json::json!({
+        "pack": {
+            "pack_format": PACK_FORMAT,
+            "description": format!("{} (generated by OBJ2MC)", model_name),
+        }
+    });
+    fs::write(path.join("pack.mcmeta"), serde_json::to_string_pretty(&mcmeta).unwrap())
+        .map_err(failed("error.write_pack_mcmeta"))?;
+
+    // Drop steps or pieces left over from an earlier, larger build
+    let parts_dir = functions.join(&name);
+    if parts_dir.exists() {
+        fs::remove_dir_all(&parts_dir).map_err(failed("error.clear_old_steps"))?;
+    }
+
+    let size = pieces::piece_size(grid, options, None).or_else(|| match options.progressive_build {
+        ProgressiveBuild::Off => command_piece_size(grid, options),
+        _ => None,
+    });
+    if let Some(size) = size {
+        if options.progressive_build != ProgressiveBuild::Off {
+            return Err(Message::new("error.progressive_pieces"));
+        }
+        let pieces = pieces::split(grid, size);
+        let mut entry = Vec::new();
+        let mut index = Vec::new();
+        for piece in &pieces {
+            let mut positions: Vec<IVec3> = piece.grid.blocks.keys().copied().collect();
+            positions.sort_unstable_by_key(|p| (p.y, p.z, p.x));
+            let function = format!("{}/{}", name, piece.name);
+            write_function(&functions, &function, &place_commands(&piece.grid, &positions, options))?;
+
+            let command = format!("function {}:{}", NAMESPACE, function);
+            index.push(IndexEntry::new(piece, format!("data/{}/function/{}.mcfunction", NAMESPACE, function), Some(command.clone())));
+            entry.push(command);
+        }
+        write_function(&functions, &name, &entry)?;
+        pieces::write_index(path, &index)?;
+        return Ok(pieces.len());
+    }
+
+    let mut positions: Vec<IVec3> = grid.blocks.keys().copied().collect();
+    let key = |p: &IVec3| match options.progressive_build {
+        ProgressiveBuild::Off => 0,
+        ProgressiveBuild::Layers => p.y,
+        ProgressiveBuild::Chunks => (p.x / CHUNK_SIZE) * (grid.size.z / CHUNK_SIZE + 1) + p.z / CHUNK_SIZE,
+    };
+    positions.sort_unstable_by_key(|p| (key(p), p.y, p.z, p.x));
+
+    if options.progressive_build == ProgressiveBuild::Off {
+        write_function(&functions, &name, &place_commands(grid, &positions, options))?;
+        return Ok(1);
+    }
+
+    let steps: Vec<&[IVec3]> = positions.chunk_by(|a, b| key(a) == key(b)).collect();
+
+    let tag = format!("{}.{}", NAMESPACE, name);
+    let anchor = format!("@e[type=minecraft:marker,tag={},limit=1]", tag);
+    let interval = match options.build_interval {
+        0 => DEFAULT_BUILD_INTERVAL,
+        ticks => ticks,
+    };
+
+    write_function(&functions, &name, &[
+        format!("kill @e[type=minecraft:marker,tag={}]", tag),
+        format!("summon minecraft:marker ~ ~ ~ {{Tags:[\"{}\"]}}", tag),
+        format!("function {}:{}/step_0", NAMESPACE, name),
+    ])?;
+
+    for (i, step) in steps.iter().enumerate() {
+        let mut commands: Vec<String> = place_commands(grid, step, options).into_iter()
+            .map(|c| format!("execute at {} run {}", anchor, c))
+            .collect();
+        if i + 1 < steps.len() {
+            commands.push(format!("schedule function {}:{}/step_{} {}t", NAMESPACE, name, i + 1, interval));
+        } else {
+            commands.push(format!("kill {}", anchor));
+        }
+        write_function(&functions, &format!("{}/step_{}", name, i), &commands)?;
+    }
+
+    Ok(steps.len())
+}
@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::Path;
+
+use glam::IVec3;
+
+use crate::blocks::{Axis, BlockGrid, BlockShape, EmptyCells, Facing};
+use crate::messages::{failed, Message};
+use crate::nbt::{self, compound, Compression, Endian, Tag};
+use crate::ConvertOptions;
+
+// ================= BEDROCK .MCSTRUCTURE =================
+//
+// Uncompressed little-endian NBT, as written by structure blocks. Blocks are
+// stored as palette indices in ZYX order (z fastest, x slowest); the second
+// layer holds waterlogging and stays empty (-1). Index -1 in the first layer
+// is a structure void, which leaves the block in the world as it is.
+//
+// `/structure load` always places the minimum corner, so the anchor is only
+// recorded as `structure_world_origin` for editors that honor it.
+
+const DEFAULT_BLOCK: &str = "minecraft:stone";
+const DEFAULT_STAIR_BLOCK: &str = "minecraft:normal_stone_stairs";
+const DEFAULT_SLAB_BLOCK: &str = "minecraft:normal_stone_slab";
+const DEFAULT_SUPPORT_BLOCK: &str = "minecraft:oak_planks";
+/// Largest structure a structure block handles along X and Z.
+pub const MAX_SIZE: i32 = 64;
+/// Block data version 1.21.0.3; the game upgrades older states on load.
+const BLOCK_VERSION: i32 = 18153475;
+
+fn palette_entry(options: &ConvertOptions, shape: BlockShape) -> Tag {
+    let name = |custom: &Option<String>, default: &str| Tag::String(custom.clone().unwrap_or_else(|| default.to_string()));
+
+    let (name, states) = match shape {
+        BlockShape::Air => return air(),
+        BlockShape::Full => (name(&options.block, DEFAULT_BLOCK), compound([])),
+        BlockShape::Stairs(facing) => {
+            let direction = match facing {
+                Facing::East => 0,
+                Facing::West => 1,
+                Facing::South => 2,
+                Facing::North => 3,
+            };
+            (
+                name(&options.stair_block, DEFAULT_STAIR_BLOCK),
+                compound([("upside_down_bit", Tag::Byte(0)), ("weirdo_direction", Tag::Int(direction))]),
+            )
+        }
+        BlockShape::Slab => (
+            name(&options.slab_block, DEFAULT_SLAB_BLOCK),
+            compound([("minecraft:vertical_half", Tag::String("bottom".to_string()))]),
+        ),
+        BlockShape::Support => (name(&options.support_block, DEFAULT_SUPPORT_BLOCK), compound([])),
+        BlockShape::Variation(i) => (Tag::String(options.block_variation[i].clone()), compound([])),
+        BlockShape::Decoration(i) => (Tag::String(options.decorations[i].clone()), compound([])),
+        BlockShape::Pillar(axis) => {
+            let axis = match axis {
+                Axis::X => "x",
+                Axis::Y => "y",
+                Axis::Z => "z",
+            };
+            (name(&options.block, DEFAULT_BLOCK), compound([("pillar_axis", Tag::String(axis.to_string()))]))
+        }
+        BlockShape::Rotated(facing) => {
+            let direction = match facing {
+                Facing::North => 2,
+                Facing::South => 3,
+                Facing::West => 4,
+                Facing::East => 5,
+            };
+            (name(&options.block, DEFAULT_BLOCK), compound([("facing_direction", Tag::Int(direction))]))
+        }
+    };
+
+    compound([
+        ("name", name),
+        ("states", states),
+        ("version", Tag::Int(BLOCK_VERSION)),
+    ])
+}
+
+fn air() -> Tag {
+    compound([
+        ("name", Tag::String("minecraft:air".to_string())),
+        ("states", compound([])),
+        ("version", Tag::Int(BLOCK_VERSION)),
+    ])
+}
+
+/// Encodes `grid` as the contents of a Bedrock structure file.
+pub fn encode_mcstructure(grid: &BlockGrid, options: &ConvertOptions) -> Vec<u8> {
+    let mut shapes: Vec<BlockShape> = Vec::new();
+    let mut palette = vec![air()];
+    let mut indices = Vec::with_capacity(grid.volume());
+    let empty = if options.empty_cells == EmptyCells::Air { 0 } else { -1 };
+
+    for x in 0..grid.size.x {
+        for y in 0..grid.size.y {
+            for z in 0..grid.size.z {
+                let index = match grid.get(IVec3::new(x, y, z)) {
+                    None => empty,
+                    Some(shape) => match shapes.iter().position(|&s| s == shape) {
+                        Some(i) => i as i32 + 1,
+                        None => {
+                            shapes.push(shape);
+                            palette.push(palette_entry(options, shape));
+                            shapes.len() as i32
+                        }
+                    },
+                };
+                indices.push(index);
+            }
+        }
+    }
+
+    let waterlog = vec![-1; indices.len()];
+    let root = compound([
+        ("format_version", Tag::Int(1)),
+        ("size", Tag::IntList(grid.size.to_array().to_vec())),
+        ("structure", compound([
+            ("block_indices", Tag::List(vec![Tag::IntList(indices), Tag::IntList(waterlog)])),
+            ("entities", Tag::List(vec![])),
+            ("palette", compound([
+                ("default", compound([
+                    ("block_palette", Tag::List(palette)),
+                    ("block_position_data", compound([])),
+                ])),
+            ])),
+        ])),
+        ("structure_world_origin", Tag::IntList(grid.origin.to_array().to_vec())),
+    ]);
+
+    nbt::to_bytes("", &root, Endian::Little, Compression::None)
+}
+
+/// Writes `grid` as a Bedrock structure file.
+pub fn write_mcstructure(grid: &BlockGrid, options: &ConvertOptions, path: &Path) -> Result<(), Message> {
+    fs::write(path, encode_mcstructure(grid, options)).map_err(failed("error.write_structure"))
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::messages::Message;
+use crate::nbt::{self, compound, Compression, Endian, Tag};
+use crate::packs::{write_zip, Pack, Packs, MIN_ENGINE_VERSION, PACK_VERSION};
+
+// ================= BEDROCK .MCWORLD =================
+//
+// A zipped world folder: a creative flat world with cheats on, no chunk data
+// (the game generates terrain on first load) and both packs embedded and
+// enabled, so opening the file imports everything at once.
+
+const STORAGE_VERSION: i32 = 10;
+/// Protocol version of 1.21.0.
+const NETWORK_VERSION: i32 = 685;
+const GAME_TYPE_CREATIVE: i32 = 1;
+const GENERATOR_FLAT: i32 = 2;
+/// Spawn height that makes the game search for a safe spot.
+const SPAWN_Y_SEARCH: i32 = 32767;
+
+fn level_dat(world_name: &str) -> Vec<u8> {
+    let [major, minor, patch] = MIN_ENGINE_VERSION.map(|v| v as i32);
+    let version = || Tag::IntList(vec![major, minor, patch, 0, 0]);
+    let last_played = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+
+    let root = compound([
+        ("LevelName", Tag::String(world_name.to_string())),
+        ("GameType", Tag::Int(GAME_TYPE_CREATIVE)),
+        ("Generator", Tag::Int(GENERATOR_FLAT)),
+        ("RandomSeed", Tag::Long(0)),
+        ("SpawnX", Tag::Int(0)),
+        ("SpawnY", Tag::Int(SPAWN_Y_SEARCH)),
+        ("SpawnZ", Tag::Int(0)),
+        ("StorageVersion", Tag::Int(STORAGE_VERSION)),
+        ("NetworkVersion", Tag::Int(NETWORK_VERSION)),
+        ("lastOpenedWithVersion", version()),
+        ("MinimumCompatibleClientVersion", version()),
+        ("LastPlayed", Tag::Long(last_played)),
+        ("commandsEnabled", Tag::Byte(1)),
+        ("hasBeenLoadedInCreative", Tag::Byte(1)),
+    ]);
+
+    let nbt = nbt::to_bytes("", &root, Endian::Little, Compression::None);
+
+    let mut bytes = Vec::with_capacity(nbt.len() + 8);
+    bytes.extend_from_slice(&STORAGE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(nbt.len() as i32).to_le_bytes());
+    bytes.extend(nbt);
+    bytes
+}
+
+fn pack_reference(pack: &Pack) -> serde_json::Value {
+    json!([{ "pack_id": pack.uuid, "version": PACK_VERSION }])
+}
+
+/// Writes a world containing `packs` to `path`.
+pub fn write_mcworld(packs: &Packs, world_name: &str, path: &Path) -> Result<(), Message> {
+    let world_files = [
+        ("level.dat", level_dat(world_name)),
+        ("levelname.txt", world_name.as_bytes().to_vec()),
+        ("world_resource_packs.json", serde_json::to_vec_pretty(&pack_reference(&packs.resource)).unwrap()),
+        ("world_behavior_packs.json", serde_json::to_vec_pretty(&pack_reference(&packs.behavior)).unwrap()),
+    ];
+
+    let pack_files = [("resource_packs", &packs.resource), ("behavior_packs", &packs.behavior)]
+        .into_iter()
+        .flat_map(|(folder, pack)| {
+            pack.files.iter().map(move |(name, data)| (format!("{}/{}/{}", folder, pack.name, name), data.as_slice()))
+        });
+
+    write_zip(path, world_files.iter().map(|(name, data)| (name.to_string(), data.as_slice())).chain(pack_files))
+}
@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::cem::number;
+use crate::messages::{failed, Message};
+use crate::texture::Texture;
+use crate::{check_model_name, validate};
+
+// ================= GEOMETRY MERGING =================
+//
+// Joins the main geometry of several geo.json files into one, for models
+// converted part by part. Each part is moved by its offset: bone pivots, cube
+// corners and pivots, locators and poly mesh positions alike. Bones keep
+// their names unless one is taken by an earlier part, in which case the part's
+// file name goes in front; parents follow the renames. The parts' textures
+// (the PNG next to each file, as written by a conversion) are stacked top to
+// bottom into one, and every UV moves down to its part's slice.
+
+/// One geo.json to merge and where its origin goes in the merged model.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergePart {
+    pub path: String,
+    #[serde(default)]
+    pub offset: [f32; 3],
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenamedBone {
+    pub file: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeReport {
+    pub path: String,
+    /// The stacked texture, when any part had one.
+    pub texture_path: Option<String>,
+    pub bone_count: usize,
+    pub cube_count: usize,
+    pub texture_width: u32,
+    pub texture_height: u32,
+    pub renamed: Vec<RenamedBone>,
+    pub warnings: Vec<Message>,
+}
+
+struct Loaded {
+    /// File name without `.geo.json`.
+    stem: String,
+    geometry: Map<String, Value>,
+    width: u32,
+    height: u32,
+    texture: Option<Texture>,
+}
+
+fn load_part(part: &MergePart, warnings: &mut Vec<Message>) -> Result<Loaded, Message> {
+    let path = Path::new(&part.path);
+    let file = path.file_name().map_or(part.path.clone(), |n| n.to_string_lossy().to_string());
+    let stem = file.strip_suffix(".geo.json").or_else(|| file.strip_suffix(".json")).unwrap_or(&file).to_string();
+
+    let text = fs::read_to_string(path).map_err(failed("error.read_file"))?;
+    let root: Value = serde_json::from_str(&text).map_err(failed("error.parse_json"))?;
+    let geometry = root.get("minecraft:geometry")
+        .and_then(|g| g.get(0))
+        .and_then(Value::as_object)
+        .cloned()
+        .ok_or_else(|| Message::new("error.merge_not_geometry").with("file", &file))?;
+
+    let size = |key: &str| geometry.get("description")
+        .and_then(|d| d.get(key))
+        .and_then(Value::as_u64)
+        .filter(|&n| n > 0)
+        .map_or(16, |n| n as u32);
+    let (width, height) = (size("texture_width"), size("texture_height"));
+
+    let png = path.with_file_name(format!("{}.png", stem));
+    let texture = match png.exists() {
+        true => Some(Texture::load_png(&png.to_string_lossy())?),
+        false => {
+            warnings.push(Message::new("warning.merge_texture_missing").with("file", &file));
+            None
+        }
+    };
+    Ok(Loaded { stem, geometry, width, height, texture })
+}
+
+/// Adds `offset` to a `[x, y, z]` value.
+fn shift(value: &mut Value, offset: [f32; 3]) {
+    if let Some(v) = value.as_array_mut().filter(|v| v.len() == 3) {
+        for (c, d) in v.iter_mut().zip(offset) {
+            if let Some(n) = c.as_f64() {
+                *c = number(n as f32 + d);
+            }
+        }
+    }
+}
+
+/// Moves a `[u, v]` value down by `top` texels.
+fn shift_uv(value: &mut Value, top: u32) {
+    if let Some(v) = value.as_array_mut().filter(|v| v.len() == 2) {
+        if let Some(n) = v[1].as_f64() {
+            v[1] = number(n as f32 + top as f32);
+        }
+    }
+}
+
+/// Moves `bone` by `offset` and its UVs into the slice at `top` of the
+/// stacked `size` texture.
+fn place_bone(bone: &mut Map<String, Value>, offset: [f32; 3], part: &Loaded, top: u32, size: (u32, u32)) {
+    if let Some(pivot) = bone.get_mut("pivot") {
+        shift(pivot, offset);
+    }
+
+    for cube in bone.get_mut("cubes").and_then(Value::as_array_mut).into_iter().flatten() {
+        for key in ["origin", "pivot"] {
+            if let Some(p) = cube.get_mut(key) {
+                shift(p, offset);
+            }
+        }
+        match cube.get_mut("uv") {
+            Some(Value::Object(faces)) => faces.values_mut().filter_map(|f| f.get_mut("uv")).for_each(|uv| shift_uv(uv, top)),
+            Some(uv) => shift_uv(uv, top),
+            None => {}
+        }
+    }
+
+    for locator in bone.get_mut("locators").and_then(Value::as_object_mut).into_iter().flat_map(|l| l.values_mut()) {
+        match locator.get_mut("offset") {
+            Some(p) => shift(p, offset),
+            None => shift(locator, offset),
+        }
+    }
+
+    if let Some(mesh) = bone.get_mut("poly_mesh").and_then(Value::as_object_mut) {
+        let normalized = mesh.get("normalized_uvs").and_then(Value::as_bool).unwrap_or(false);
+        mesh.get_mut("positions").and_then(Value::as_array_mut).into_iter().flatten().for_each(|p| shift(p, offset));
+        for uv in mesh.get_mut("uvs").and_then(Value::as_array_mut).into_iter().flatten() {
+            if !normalized {
+                shift_uv(uv, top);
+                continue;
+            }
+            let Some([u, v]) = uv.as_array().and_then(|a| Some([a.first()?.as_f64()?, a.get(1)?.as_f64()?])) else { continue };
+            let u = u * part.width as f64 / size.0 as f64;
+            let v = (v * part.height as f64 + top as f64) / size.1 as f64;
+            *uv = json!([u, v]);
+        }
+    }
+}
+
+/// `texture` nearest-sampled to `width` × `height`, the UV size of its part.
+fn resized(texture: &Texture, width: u32, height: u32) -> Texture {
+    let pixels = (0..width * height)
+        .map(|i| {
+            let x = (i % width) * texture.width / width;
+            let y = (i / width) * texture.height / height;
+            texture.pixels[(y * texture.width + x) as usize]
+        })
+        .collect();
+    Texture { width, height, pixels }
+}
+
+/// Merges `parts` into `<model_name>.geo.json` (and `<model_name>.png`) in
+/// `output_dir`.
+pub fn merge_geometry(parts: &[MergePart], output_dir: &str, model_name: &str) -> Result<MergeReport, Message> {
+    check_model_name(model_name)?;
+    if parts.is_empty() {
+        return Err(Message::new("error.merge_no_parts"));
+    }
+    let mut warnings = Vec::new();
+    let loaded = parts.iter().map(|p| load_part(p, &mut warnings)).collect::<Result<Vec<_>, _>>()?;
+
+    // Parts stacked top to bottom, in the order given
+    let width = loaded.iter().map(|p| p.width).max().unwrap_or(16);
+    let height = loaded.iter().map(|p| p.height).sum::<u32>();
+    let mut texture = Texture { width, height, pixels: vec![[0; 4]; (width * height) as usize] };
+
+    let mut names: HashSet<String> = HashSet::new();
+    let mut renamed = Vec::new();
+    let mut bones = Vec::new();
+    let mut cube_count = 0;
+    let mut top = 0;
+    for (part, loaded) in parts.iter().zip(&loaded) {
+        let part_bones: Vec<Map<String, Value>> = loaded.geometry.get("bones")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|b| b.as_object().cloned())
+            .collect();
+
+        // New names first, so parents can follow bones listed after them
+        let mut renames: Vec<(String, String)> = Vec::new();
+        for bone in &part_bones {
+            let Some(name) = bone.get("name").and_then(Value::as_str) else { continue };
+            let mut new_name = name.to_string();
+            let mut n = 1;
+            while names.contains(&new_name.to_lowercase()) {
+                new_name = match n {
+                    1 => format!("{}_{}", loaded.stem, name),
+                    _ => format!("{}_{}_{}", loaded.stem, name, n),
+                };
+                n += 1;
+            }
+            names.insert(new_name.to_lowercase());
+            if new_name != name {
+                renamed.push(RenamedBone { file: loaded.stem.clone(), from: name.to_string(), to: new_name.clone() });
+                renames.push((name.to_string(), new_name));
+            }
+        }
+        let rename = |value: &mut Value| {
+            let new_name = value.as_str().and_then(|name| renames.iter().find(|(from, _)| from == name)).map(|(_, to)| to.clone());
+            if let Some(new_name) = new_name {
+                *value = json!(new_name);
+            }
+        };
+
+        for mut bone in part_bones {
+            for key in ["name", "parent"] {
+                if let Some(value) = bone.get_mut(key) {
+                    rename(value);
+                }
+            }
+            place_bone(&mut bone, part.offset, loaded, top, (width, height));
+            cube_count += bone.get("cubes").and_then(Value::as_array).map_or(0, Vec::len);
+            bones.push(Value::Object(bone));
+        }
+
+        if let Some(image) = &loaded.texture {
+            let image = resized(image, loaded.width, loaded.height);
+            for y in 0..loaded.height {
+                let row = (y * loaded.width) as usize..((y + 1) * loaded.width) as usize;
+                let start = ((top + y) * width) as usize;
+                texture.pixels[start..start + loaded.width as usize].copy_from_slice(&image.pixels[row]);
+            }
+        }
+        top += loaded.height;
+    }
+
+    let mut description = loaded[0].geometry.get("description").and_then(Value::as_object).cloned().unwrap_or_default();
+    description.insert("identifier".to_string(), json!(format!("geometry.{}", model_name)));
+    description.insert("texture_width".to_string(), json!(width));
+    description.insert("texture_height".to_string(), json!(height));
+    let bone_count = bones.len();
+    let root = json!({
+        "format_version": "1.12.0",
+        "minecraft:geometry": [{ "description": description, "bones": bones }],
+    });
+    validate::check_geometry(&root)?;
+
+    let output_dir = Path::new(output_dir);
+    fs::create_dir_all(output_dir).map_err(failed("error.create_output_dir"))?;
+    let path = output_dir.join(format!("{}.geo.json", model_name));
+    fs::write(&path, serde_json::to_string_pretty(&root).unwrap()).map_err(failed("error.write_json"))?;
+    let texture_path = match loaded.iter().any(|p| p.texture.is_some()) {
+        true => {
+            let png = output_dir.join(format!("{}.png", model_name));
+            fs::write(&png, texture.to_png()).map_err(failed("error.write_texture"))?;
+            Some(png.to_string_lossy().to_string())
+        }
+        false => None,
+    };
+
+    Ok(MergeReport {
+        path: path.to_string_lossy().to_string(),
+        texture_path,
+        bone_count,
+        cube_count,
+        texture_width: width,
+        texture_height: height,
+        renamed,
+        warnings,
+    })
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+// ================= MESSAGES =================
+//
+// Every user-facing string from the backend (errors, warnings, result
+// summaries, geometry issues) is a `Message`: a key and named parameters. It
+// serializes as `{ "key", "params", "text" }`, where `text` is the English
+// rendering, so the frontend can look the key up in its own translations and
+// fall back to the text. Templates name their parameters in braces.
+
+const ENGLISH: &[(&str, &str)] = &[
+    // Conversion results
+    ("result.voxels_to_cubes", "{voxels} voxels → {cubes} cubes"),
+    ("result.polygons", "{polygons} polygons"),
+    ("result.voxels_to_blocks", "{voxels} voxels → {blocks} blocks"),
+    ("result.voxels_to_blocks_in_pieces", "{voxels} voxels → {blocks} blocks in {pieces} pieces"),
+    ("result.voxels_to_blocks_in_steps", "{voxels} voxels → {blocks} blocks in {steps} steps"),
+    ("result.cubes_and_blocks", "{cubes} cubes, {blocks} blocks"),
+    ("result.voxels_to_elements", "{voxels} voxels → {elements} elements"),
+    ("result.voxels_to_elements_scaled", "{voxels} voxels → {elements} elements, scaled to {percent}% to fit the block model range"),
+    ("result.voxels_to_elements_in_pieces", "{voxels} voxels → {elements} elements in {pieces} block models"),
+    // Performance limits
+    ("perf.cubes", "{count} cubes"),
+    ("perf.bones", "{count} bones"),
+    ("perf.texture", "{width}×{height} texture"),
+    // Warnings
+    ("warning.fill_leaks", "Interior fill may leak out of '{object}': {holes} hole(s), the largest near ({x}, {y}, {z})"),
+    ("warning.model_relocated", "Model was far from the origin and moved by ({x}, {y}, {z}) to bring it back"),
+    ("warning.hook_failed", "Post-conversion command '{command}' failed: {error}"),
+    ("warning.merge_texture_missing", "No texture next to {file}; its part of the merged texture is left empty"),
+    ("warning.atlas_over_budget", "Atlas is {width}×{height}, over the {budget} budget even at one texel per face"),
+    ("warning.adaptive_skipped", "Adaptive voxelization leaves out {options}; set adaptive_levels to 0 to use them"),
+    ("warning.detail_regions_skipped", "Detail regions aren't refined with {option} set; they were left out"),
+    ("warning.missing_files", "Not found, painted with material or placeholder colors instead: {files}"),
+    // Errors
+    ("error.load_obj", "Failed to load OBJ: {error}"),
+    ("error.load_gltf", "Failed to load glTF: {error}"),
+    ("error.load_stl", "Failed to load STL: {error}"),
+    ("error.load_ply", "Failed to load PLY: {error}"),
+    ("error.load_vox", "Failed to load MagicaVoxel file: {error}"),
+    ("error.worker_threads", "Failed to start worker threads: {error}"),
+    ("error.spill", "Failed to spill voxels to disk: {error}"),
+    ("error.parse_obj", "Failed to parse OBJ: {error}"),
+    ("error.no_geometry", "No geometry generated"),
+    ("error.rebake_sampling_changed", "Switching texture sampling on or off needs the model voxelized again; convert it again"),
+    ("error.result_not_cached", "That conversion is no longer kept for rebaking; convert the model again"),
+    ("error.invalid_cell_size", "Cell sizes must be greater than 0"),
+    ("error.structure_needs_cubes", "Structure export needs cube geometry"),
+    ("error.datapack_needs_cubes", "Datapack export needs cube geometry"),
+    ("error.schematic_needs_cubes", "Schematic export needs cube geometry"),
+    ("error.cem_needs_cubes", "CEM export needs cube geometry"),
+    ("error.bbmodel_needs_cubes", "Blockbench projects need cube geometry; switch off poly_mesh mode"),
+    ("error.block_model_needs_cubes", "Java block models need cube geometry; switch off poly_mesh mode"),
+    ("error.rig_needs_cubes", "Auto-rig needs cube geometry"),
+    ("error.skin_needs_humanoid", "Skin layout needs bones named like a humanoid (head, body, rightArm, leftArm, rightLeg, leftLeg)"),
+    ("error.unknown_locator_bone", "Locator '{locator}' refers to unknown bone '{bone}'"),
+    ("error.locators_need_bones", "Locators need at least one bone"),
+    ("error.cut_bone_exists", "Cut plane bone '{bone}' already exists"),
+    ("error.unknown_cut_bone", "Cut plane '{plane}' refers to unknown bone '{bone}'"),
+    ("error.detail_bone_exists", "Detail region bone '{bone}' already exists"),
+    ("error.progressive_pieces", "Progressive builds can't be split into pieces"),
+    ("error.schematic_too_large", "Model is too large for a .schematic (max {max} blocks per side)"),
+    ("error.block_filtered", "'{block}' is ruled out by the {filter} palette filter"),
+    ("error.unknown_legacy_block", "No legacy block id for '{block}'; use 'id' or 'id:data'"),
+    ("error.template_unclosed", "Unclosed '{' in output template '{template}'"),
+    ("error.unknown_template_variable", "Unknown output template variable '{name}'"),
+    ("error.read_image", "Failed to read image: {error}"),
+    ("error.read_script", "Failed to read script: {error}"),
+    ("error.script", "Script error: {error}"),
+    ("error.script_in_bone", "Script error in bone '{bone}': {error}"),
+    ("error.script_too_long", "Script in bone '{bone}' ran past {limit} operations; it may loop forever"),
+    ("error.script_replaced_grid", "Script replaced `grid` in bone '{bone}'"),
+    ("error.no_game_folder", "No Minecraft installation found; set game_folder to its com.mojang folder"),
+    ("error.download", "Failed to download {url}: {error}"),
+    ("error.download_too_large", "Download exceeds the {limit} MB limit"),
+    ("error.create_download_folder", "Failed to create download folder: {error}"),
+    ("error.save_download", "Failed to save download: {error}"),
+    ("error.read_job_file", "Failed to read job file: {error}"),
+    ("error.invalid_job_file", "Invalid job file: {error}"),
+    ("error.read_palette", "Failed to read palette file: {error}"),
+    ("error.invalid_palette", "Invalid palette file: {error}"),
+    ("error.empty_palette", "The palette file has no colors"),
+    ("error.job_no_output_dir", "No output_dir for {path}"),
+    ("error.invalid_job_options", "Invalid options for {path}: {error}"),
+    ("error.no_previous_conversion", "No previous conversion"),
+    ("error.app_data_folder", "Failed to locate app data folder: {error}"),
+    ("error.read_file", "Failed to read file: {error}"),
+    ("error.parse_json", "Failed to parse JSON: {error}"),
+    ("error.geometry_not_object", "Geometry file must be a JSON object"),
+    ("error.invalid_model_name", "The model name '{name}' can't contain path separators or be '..'"),
+    ("error.merge_no_parts", "Pick at least one geo.json to merge"),
+    ("error.merge_not_geometry", "{file} has no minecraft:geometry to merge"),
+    ("error.create_folder", "Failed to create folder: {error}"),
+    ("error.create_output_dir", "Failed to create output directory: {error}"),
+    ("error.create_file", "Failed to create file: {error}"),
+    ("error.write_file", "Failed to write file: {error}"),
+    ("error.write_json", "Failed to write JSON: {error}"),
+    ("error.invalid_geometry", "Generated geometry is invalid at {pointer}: {problem}"),
+    ("error.write_texture", "Failed to write texture: {error}"),
+    ("error.write_structure", "Failed to write structure: {error}"),
+    ("error.write_schematic", "Failed to write schematic: {error}"),
+    ("error.write_function", "Failed to write function: {error}"),
+    ("error.write_pack_mcmeta", "Failed to write pack.mcmeta: {error}"),
+    ("error.write_archive", "Failed to write archive: {error}"),
+    ("error.write_part_model", "Failed to write part model: {error}"),
+    ("error.write_index", "Failed to write index: {error}"),
+    ("error.write_report", "Failed to write report: {error}"),
+    ("error.write_history", "Failed to write history: {error}"),
+    ("error.write_session", "Failed to write session: {error}"),
+    ("error.write_profiles", "Failed to write profiles: {error}"),
+    ("error.write_cube_colors", "Failed to write cube colors: {error}"),
+    ("error.write_palette", "Failed to write palette: {error}"),
+    ("error.write_progress", "Failed to save job progress: {error}"),
+    ("error.clear_old_steps", "Failed to clear old steps: {error}"),
+    ("error.clear_old_pieces", "Failed to clear old pieces: {error}"),
+    // Geometry issues found by `inspect_geometry`
+    ("issue.invalid", "{problem}"),
+    ("issue.box_uv_outside", "box UV extends outside the texture"),
+    ("issue.unknown_uv_face", "unknown UV face '{face}'"),
+    ("issue.face_uv_outside", "'{face}' UV extends outside the texture"),
+    ("issue.missing_texture_size", "missing '{key}', the game assumes 16"),
+    ("issue.no_bones", "no bones"),
+    ("issue.negative_size", "negative size"),
+    ("issue.empty_cube", "empty cube"),
+    ("issue.identifier_prefix", "identifier should start with 'geometry.'"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Message {
+    pub key: String,
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+}
+
+impl Message {
+    pub fn new(key: &str) -> Self {
+        Self { key: key.to_string(), params: BTreeMap::new() }
+    }
+
+    pub fn with(mut self, name: &str, value: impl fmt::Display) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// English rendering; unknown keys render as the key itself.
+    pub fn text(&self) -> String {
+        let template = ENGLISH.iter().find(|(key, _)| *key == self.key).map_or(self.key.as_str(), |(_, t)| t);
+        let mut text = template.to_string();
+        for (name, value) in &self.params {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Rendered<'a> {
+            key: &'a str,
+            params: &'a BTreeMap<String, String>,
+            text: String,
+        }
+        Rendered { key: &self.key, params: &self.params, text: self.text() }.serialize(serializer)
+    }
+}
+
+/// `map_err` helper: the message `key` with the error as its `error` parameter.
+pub fn failed<E: fmt::Display>(key: &'static str) -> impl Fn(E) -> Message {
+    move |e| Message::new(key).with("error", e)
+}
+
+/// Keys and English templates of every message, for translators.
+pub fn catalog() -> BTreeMap<&'static str, &'static str> {
+    ENGLISH.iter().cloned().collect()
+}
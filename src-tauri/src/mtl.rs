@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Component, Path, PathBuf};
+
+use crate::messages::{failed, Message};
+
+// ================= MATERIAL FILES =================
+//
+// OBJ files often come from another machine: `mtllib` and `map_Kd` point at
+// absolute paths that don't exist here, differ in case from the files
+// (fine on Windows, not elsewhere), or name a texture that sits in a
+// `textures` folder beside the OBJ. References are tried as written, relative
+// to the OBJ, ignoring case, then by file name alone in the OBJ's folder and
+// its common subfolders. A material library that still can't be found falls
+// back to the `.mtl` named like the OBJ. Textures that can't be found are
+// dropped so the material's diffuse color (or a placeholder) paints the
+// model, and are reported by name.
+
+/// Folders textures are often kept in, next to the OBJ or the MTL.
+const SUBFOLDERS: &[&str] = &["textures", "texture", "tex", "maps", "images", "materials", "mtl"];
+
+/// Gray for textured materials whose texture is missing and that set no color.
+const PLACEHOLDER: [f32; 3] = [0.5, 0.5, 0.5];
+
+/// The entry of `folder` named `name`, ignoring case when not found as written.
+fn entry_ignoring_case(folder: &Path, name: &str) -> Option<PathBuf> {
+    let exact = folder.join(name);
+    if exact.exists() {
+        return Some(exact);
+    }
+    fs::read_dir(if folder.as_os_str().is_empty() { Path::new(".") } else { folder })
+        .ok()?
+        .flatten()
+        .find(|e| e.file_name().to_string_lossy().eq_ignore_ascii_case(name))
+        .map(|e| folder.join(e.file_name()))
+}
+
+/// `relative` under `base`, matching each part ignoring case.
+fn join_ignoring_case(base: &Path, relative: &Path) -> Option<PathBuf> {
+    let mut path = base.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => path = entry_ignoring_case(&path, &part.to_string_lossy())?,
+            Component::ParentDir => path.push(".."),
+            _ => {}
+        }
+    }
+    path.is_file().then_some(path)
+}
+
+/// Where the file `reference` names is, looking from `folder`.
+pub fn find_file(folder: &Path, reference: &str) -> Option<PathBuf> {
+    // Windows separators in files written on Windows
+    let reference = reference.trim().replace('\\', "/");
+    let path = Path::new(&reference);
+    if path.is_absolute() && path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    if path.is_relative() {
+        if let Some(found) = join_ignoring_case(folder, path) {
+            return Some(found);
+        }
+    }
+    let name = path.file_name()?.to_string_lossy().to_string();
+    std::iter::once(folder.to_path_buf())
+        .chain(SUBFOLDERS.iter().filter_map(|sub| entry_ignoring_case(folder, sub)))
+        .find_map(|dir| entry_ignoring_case(&dir, &name).filter(|p| p.is_file()))
+}
+
+pub struct LoadedObj {
+    pub models: Vec<tobj::Model>,
+    pub materials: Vec<tobj::Material>,
+    /// References that couldn't be found.
+    pub missing: Vec<String>,
+}
+
+pub fn load_obj(path: &str, options: &tobj::LoadOptions) -> Result<LoadedObj, Message> {
+    let obj = Path::new(path);
+    let folder = obj.parent().unwrap_or(Path::new(""));
+    let missing = RefCell::new(Vec::new());
+    // Folder of each library loaded, for its textures
+    let libraries = RefCell::new(Vec::new());
+
+    let file = File::open(path).map_err(failed("error.load_obj"))?;
+    let (models, materials) = tobj::load_obj_buf(&mut BufReader::new(file), options, |reference| {
+        let reference = reference.to_string_lossy();
+        let found = find_file(folder, &reference)
+            .or_else(|| find_file(folder, &format!("{}.mtl", obj.file_stem()?.to_string_lossy())));
+        let Some(found) = found else {
+            missing.borrow_mut().push(reference.to_string());
+            return Err(tobj::LoadError::OpenFileFailed);
+        };
+        let loaded = tobj::load_mtl(&found)?;
+        let library = found.parent().map(Path::to_path_buf).unwrap_or_default();
+        libraries.borrow_mut().extend(vec![library; loaded.0.len()]);
+        Ok(loaded)
+    })
+    .map_err(failed("error.load_obj"))?;
+
+    let mut materials = materials.unwrap_or_default();
+    let libraries = libraries.into_inner();
+    let mut missing = missing.into_inner();
+    for (i, material) in materials.iter_mut().enumerate() {
+        let Some(texture) = material.diffuse_texture.take() else { continue };
+        let library = libraries.get(i).map_or(folder, PathBuf::as_path);
+        match find_file(library, &texture).or_else(|| find_file(folder, &texture)) {
+            Some(found) => material.diffuse_texture = Some(found.to_string_lossy().to_string()),
+            None => {
+                material.diffuse.get_or_insert(PLACEHOLDER);
+                if !missing.contains(&texture) {
+                    missing.push(texture);
+                }
+            }
+        }
+    }
+    Ok(LoadedObj { models, materials, missing })
+}
+
+/// Warns about the files of `missing`, if any.
+pub fn missing_warning(missing: &[String]) -> Option<Message> {
+    (!missing.is_empty()).then(|| Message::new("warning.missing_files").with("files", missing.join(", ")))
+}
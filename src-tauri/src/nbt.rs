@@ -0,0 +1,181 @@
+use std::io::Write;
+
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+// ================= NBT =================
+//
+// Writer for Minecraft's Named Binary Tag format, shared by the world-format
+// exporters. Java files are big-endian and usually gzipped; Bedrock files are
+// little-endian and uncompressed.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    /// Java Edition.
+    Big,
+    /// Bedrock Edition.
+    Little,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zlib,
+}
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_SHORT: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_LONG: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+const TAG_INT_ARRAY: u8 = 11;
+const TAG_LONG_ARRAY: u8 = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    /// List of ints, stored without a `Tag` per element (block index layers).
+    IntList(Vec<i32>),
+    /// Any other list; all elements must have the same type.
+    List(Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+/// Builds a compound from `(name, tag)` pairs.
+pub fn compound<const N: usize>(entries: [(&str, Tag); N]) -> Tag {
+    Tag::Compound(entries.into_iter().map(|(name, tag)| (name.to_string(), tag)).collect())
+}
+
+impl Tag {
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => TAG_BYTE,
+            Tag::Short(_) => TAG_SHORT,
+            Tag::Int(_) => TAG_INT,
+            Tag::Long(_) => TAG_LONG,
+            Tag::Float(_) => TAG_FLOAT,
+            Tag::Double(_) => TAG_DOUBLE,
+            Tag::ByteArray(_) => TAG_BYTE_ARRAY,
+            Tag::String(_) => TAG_STRING,
+            Tag::IntList(_) | Tag::List(_) => TAG_LIST,
+            Tag::Compound(_) => TAG_COMPOUND,
+            Tag::IntArray(_) => TAG_INT_ARRAY,
+            Tag::LongArray(_) => TAG_LONG_ARRAY,
+        }
+    }
+}
+
+struct Writer<W: Write> {
+    out: W,
+    endian: Endian,
+}
+
+macro_rules! write_number {
+    ($self:ident, $v:expr) => {
+        match $self.endian {
+            Endian::Big => $self.out.write_all(&$v.to_be_bytes()),
+            Endian::Little => $self.out.write_all(&$v.to_le_bytes()),
+        }
+    };
+}
+
+impl<W: Write> Writer<W> {
+    fn length(&mut self, len: usize) -> std::io::Result<()> {
+        write_number!(self, len as i32)
+    }
+
+    fn string(&mut self, s: &str) -> std::io::Result<()> {
+        write_number!(self, s.len() as u16)?;
+        self.out.write_all(s.as_bytes())
+    }
+
+    fn payload(&mut self, tag: &Tag) -> std::io::Result<()> {
+        match tag {
+            Tag::Byte(v) => write_number!(self, v),
+            Tag::Short(v) => write_number!(self, v),
+            Tag::Int(v) => write_number!(self, v),
+            Tag::Long(v) => write_number!(self, v),
+            Tag::Float(v) => write_number!(self, v),
+            Tag::Double(v) => write_number!(self, v),
+            Tag::ByteArray(bytes) => {
+                self.length(bytes.len())?;
+                self.out.write_all(bytes)
+            }
+            Tag::String(s) => self.string(s),
+            Tag::IntList(values) => {
+                self.out.write_all(&[TAG_INT])?;
+                self.length(values.len())?;
+                values.iter().try_for_each(|v| write_number!(self, v))
+            }
+            Tag::List(items) => {
+                // Empty lists are typed as compounds, which every reader accepts
+                self.out.write_all(&[items.first().map_or(TAG_COMPOUND, Tag::id)])?;
+                self.length(items.len())?;
+                items.iter().try_for_each(|t| self.payload(t))
+            }
+            Tag::Compound(entries) => {
+                for (name, tag) in entries {
+                    self.out.write_all(&[tag.id()])?;
+                    self.string(name)?;
+                    self.payload(tag)?;
+                }
+                self.out.write_all(&[TAG_END])
+            }
+            Tag::IntArray(values) => {
+                self.length(values.len())?;
+                values.iter().try_for_each(|v| write_number!(self, v))
+            }
+            Tag::LongArray(values) => {
+                self.length(values.len())?;
+                values.iter().try_for_each(|v| write_number!(self, v))
+            }
+        }
+    }
+
+    fn root(&mut self, name: &str, root: &Tag) -> std::io::Result<()> {
+        self.out.write_all(&[root.id()])?;
+        self.string(name)?;
+        self.payload(root)
+    }
+}
+
+/// Writes `root` as a named root tag (`""` for most files) to `out`.
+pub fn write(out: impl Write, name: &str, root: &Tag, endian: Endian, compression: Compression) -> std::io::Result<()> {
+    match compression {
+        Compression::None => Writer { out, endian }.root(name, root),
+        Compression::Gzip => {
+            let mut writer = Writer { out: GzEncoder::new(out, flate2::Compression::default()), endian };
+            writer.root(name, root)?;
+            writer.out.finish()?;
+            Ok(())
+        }
+        Compression::Zlib => {
+            let mut writer = Writer { out: ZlibEncoder::new(out, flate2::Compression::default()), endian };
+            writer.root(name, root)?;
+            writer.out.finish()?;
+            Ok(())
+        }
+    }
+}
+
+/// Encodes `root` in memory.
+pub fn to_bytes(name: &str, root: &Tag, endian: Endian, compression: Compression) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write(&mut bytes, name, root, endian, compression).expect("writing to memory cannot fail");
+    bytes
+}
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, VecDeque};
+
+use ahash::RandomState;
+use glam::Vec3;
+
+use crate::topology;
+
+// ================= NORMAL REPAIR =================
+//
+// Game meshes often have single triangles or whole parts wound the wrong way.
+// Triangles are welded by position and walked across shared edges: each
+// neighbor is flipped when it runs the shared edge in the same direction,
+// which makes every connected surface consistent. A closed surface enclosing
+// negative volume is then inside out and gets flipped as a whole; an open one
+// has no inside, and its volume sign only says where it lies from the origin,
+// so it keeps the winding of the triangle it was walked from.
+
+/// Triangle indices wound consistently and outwards, or `None` if nothing changed.
+pub fn orient_triangles(positions: &[f32], indices: &[u32]) -> Option<Vec<u32>> {
+    // Vertices split for UVs or normals still belong to the same surface
+    let canonical = topology::weld(positions);
+    let position = |i: u32| {
+        let i = i as usize * 3;
+        Vec3::new(positions[i], positions[i + 1], positions[i + 2])
+    };
+
+    let triangles: Vec<[u32; 3]> = indices.chunks(3).map(|t| [t[0], t[1], t[2]]).collect();
+    let corners = |t: usize, flipped: bool| {
+        let [a, b, c] = triangles[t].map(|i| canonical[i as usize]);
+        if flipped { [a, c, b] } else { [a, b, c] }
+    };
+
+    // Collapsed triangles (e.g. at the poles of a sphere) join nothing
+    let degenerate = |t: usize| {
+        let [a, b, c] = corners(t, false);
+        a == b || b == c || a == c
+    };
+
+    let mut edges: HashMap<(u32, u32), Vec<usize>, RandomState> = HashMap::default();
+    for t in (0..triangles.len()).filter(|&t| !degenerate(t)) {
+        let [a, b, c] = corners(t, false);
+        for (x, y) in [(a, b), (b, c), (c, a)] {
+            edges.entry((x.min(y), x.max(y))).or_default().push(t);
+        }
+    }
+
+    let mut flipped = vec![false; triangles.len()];
+    let mut visited = vec![false; triangles.len()];
+    for start in 0..triangles.len() {
+        if visited[start] || degenerate(start) { continue; }
+        visited[start] = true;
+        let mut component = vec![start];
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(t) = queue.pop_front() {
+            let [a, b, c] = corners(t, flipped[t]);
+            for (x, y) in [(a, b), (b, c), (c, a)] {
+                for &n in &edges[&(x.min(y), x.max(y))] {
+                    if visited[n] { continue; }
+                    visited[n] = true;
+                    // A consistent neighbor runs the shared edge the other way
+                    let [p, q, r] = corners(n, false);
+                    let same_direction = [(p, q), (q, r), (r, p)].contains(&(x, y));
+                    flipped[n] = same_direction;
+                    component.push(n);
+                    queue.push_back(n);
+                }
+            }
+        }
+
+        let closed = topology::is_closed(&component.iter().map(|&t| corners(t, false)).collect::<Vec<_>>());
+        if !closed { continue; }
+        let volume: f32 = component.iter()
+            .map(|&t| {
+                let [a, b, c] = triangles[t].map(position);
+                let volume = a.dot(b.cross(c));
+                if flipped[t] { -volume } else { volume }
+            })
+            .sum();
+        if volume < 0.0 {
+            for &t in &component {
+                flipped[t] = !flipped[t];
+            }
+        }
+    }
+
+    if !flipped.contains(&true) {
+        return None;
+    }
+    Some(triangles.iter().zip(&flipped)
+        .flat_map(|(&[a, b, c], &flip)| if flip { [a, c, b] } else { [a, b, c] })
+        .collect())
+}
@@ -0,0 +1,131 @@
+use glam::{EulerRot, Quat, Vec3};
+
+use crate::ConvertOptions;
+
+// ================= AUTO-ORIENTATION =================
+//
+// Models exported from other tools often arrive lying on their side. Each
+// axis direction is scored by the flat area the model could stand on there:
+// faces square to that axis lying in the outermost plane of the bounds, so a
+// ground plane counts whichever way it faces. The suggested rotation turns the
+// best direction down. Among directions within a tenth of the best, one that
+// leaves the longest side of the model horizontal wins, then the one needing
+// the least rotation. Many models have flat sides as well as a base, so the
+// current orientation is kept unless another side offers more than twice the
+// support of its base.
+//
+// Rotations are in degrees around X, Y and Z (applied in that order, counter-
+// clockwise looking down each axis) and turn the scene around the center of
+// its bounds, keeping its lowest point where it was.
+
+/// Faces tilted less than about 10° from an axis count as facing it.
+const FLAT_COS: f32 = 0.985;
+/// Faces this close to the bounds (as a fraction of their size) lie in the outermost plane.
+const SUPPORT_TOLERANCE: f32 = 0.01;
+/// Candidates within this fraction of the largest support count as ties.
+const TIE_FRACTION: f32 = 0.9;
+/// How much more support another side needs before turning the model.
+const CHANGE_FACTOR: f32 = 2.0;
+
+/// Axis directions in order of preference, with the rotation turning each one down.
+const DOWN_ROTATIONS: [(Vec3, [f32; 3]); 6] = [
+    (Vec3::NEG_Y, [0.0, 0.0, 0.0]),
+    (Vec3::X, [0.0, 0.0, -90.0]),
+    (Vec3::NEG_X, [0.0, 0.0, 90.0]),
+    (Vec3::Z, [90.0, 0.0, 0.0]),
+    (Vec3::NEG_Z, [-90.0, 0.0, 0.0]),
+    (Vec3::Y, [180.0, 0.0, 0.0]),
+];
+
+fn points(models: &[tobj::Model]) -> impl Iterator<Item = Vec3> + '_ {
+    models.iter().flat_map(|m| m.mesh.positions.chunks(3).map(|v| Vec3::new(v[0], v[1], v[2])))
+}
+
+fn bounds(models: &[tobj::Model]) -> Option<(Vec3, Vec3)> {
+    points(models).fold(None, |b, p| match b {
+        None => Some((p, p)),
+        Some((min, max)) => Some((min.min(p), max.max(p))),
+    })
+}
+
+/// Rotation standing the scene on its largest flat face, `[0, 0, 0]` if it already does.
+pub fn suggest_rotation(models: &[tobj::Model]) -> [f32; 3] {
+    let Some((min, max)) = bounds(models) else { return [0.0; 3] };
+
+    let extent = max - min;
+    let tolerance = extent.max_element() * SUPPORT_TOLERANCE;
+    let mut areas = [0.0f32; 6];
+    for mesh in models.iter().map(|m| &m.mesh) {
+        let vertex = |i: u32| {
+            let i = i as usize * 3;
+            Vec3::new(mesh.positions[i], mesh.positions[i + 1], mesh.positions[i + 2])
+        };
+        for t in mesh.indices.chunks(3) {
+            let cross = (vertex(t[1]) - vertex(t[0])).cross(vertex(t[2]) - vertex(t[0]));
+            let area = cross.length() / 2.0;
+            if area == 0.0 { continue; }
+            let normal = cross / (area * 2.0);
+            for (i, (axis, _)) in DOWN_ROTATIONS.iter().enumerate() {
+                let outermost = [min, max].map(|b| b.dot(*axis)).into_iter().fold(f32::NEG_INFINITY, f32::max);
+                if normal.dot(*axis).abs() > FLAT_COS && (vertex(t[0]).dot(*axis) - outermost).abs() <= tolerance {
+                    areas[i] += area;
+                }
+            }
+        }
+    }
+
+    let best = areas.iter().cloned().fold(0.0, f32::max);
+    if best <= areas[0] * CHANGE_FACTOR {
+        return [0.0; 3];
+    }
+
+    // Longest side of the bounds; turning a direction along it down stands the model on end
+    let longest = if extent.x >= extent.y && extent.x >= extent.z {
+        Vec3::X
+    } else if extent.y >= extent.z {
+        Vec3::Y
+    } else {
+        Vec3::Z
+    };
+    let keeps_longest_horizontal = |axis: Vec3| axis.dot(longest) == 0.0;
+
+    let candidates = (0..DOWN_ROTATIONS.len()).filter(|&i| areas[i] >= best * TIE_FRACTION);
+    let index = candidates.clone().find(|&i| keeps_longest_horizontal(DOWN_ROTATIONS[i].0))
+        .or_else(|| candidates.clone().next())
+        .unwrap_or(0);
+    DOWN_ROTATIONS[index].1
+}
+
+fn quat(rotation: [f32; 3]) -> Quat {
+    let [x, y, z] = rotation.map(f32::to_radians);
+    Quat::from_euler(EulerRot::ZYX, z, y, x)
+}
+
+/// Rotation the options ask for: the suggested one with `auto_orient`, else `rotation`.
+pub fn requested_rotation(models: &[tobj::Model], options: &ConvertOptions) -> Option<[f32; 3]> {
+    let rotation = if options.auto_orient { suggest_rotation(models) } else { options.rotation? };
+    (rotation != [0.0; 3]).then_some(rotation)
+}
+
+/// Copies of `models` turned by `rotation`.
+pub fn rotate_models(models: &[tobj::Model], rotation: [f32; 3]) -> Vec<tobj::Model> {
+    let Some((min, max)) = bounds(models) else { return models.to_vec() };
+    let q = quat(rotation);
+    let center = (min + max) / 2.0;
+    let rotated_bottom = points(models).map(|p| (q * (p - center)).y).fold(f32::INFINITY, f32::min);
+    let shift = center + Vec3::new(0.0, min.y - center.y - rotated_bottom, 0.0);
+
+    models.iter()
+        .map(|model| {
+            let mut mesh = model.mesh.clone();
+            for p in mesh.positions.chunks_mut(3) {
+                let r = q * (Vec3::new(p[0], p[1], p[2]) - center) + shift;
+                p.copy_from_slice(&r.to_array());
+            }
+            for n in mesh.normals.chunks_mut(3) {
+                n.copy_from_slice(&(q * Vec3::new(n[0], n[1], n[2])).to_array());
+            }
+            tobj::Model { mesh, name: model.name.clone() }
+        })
+        .collect()
+}
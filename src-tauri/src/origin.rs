@@ -0,0 +1,90 @@
+use glam::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::{McBone, McCube};
+
+// ================= VOXEL ORIGIN =================
+//
+// By default voxel (x, y, z) becomes a cube at origin (x, y, z), so the model
+// keeps its position from the OBJ. The other conventions move the finished
+// geometry by a whole number of voxels, keeping cubes on the grid: cubes,
+// cube and bone pivots, poly mesh positions and locators all move together.
+//
+// A model far from the origin would keep huge coordinates and render far
+// from the entity it belongs to, so with the source placement it is moved
+// back as if centered once no part of it is within MAX_DISTANCE; the offset
+// is reported so it can be placed back.
+
+/// Geometry units from the origin past which source coordinates are moved back.
+const MAX_DISTANCE: f32 = 1024.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoxelOrigin {
+    /// Coordinates as in the source mesh.
+    #[default]
+    Source,
+    /// Centered on the origin in X and Z and resting on y = 0 (negative X
+    /// and Z allowed), the usual placement for entities.
+    Centered,
+    /// Moved into positive space, with the lowest corner at the origin.
+    Positive,
+}
+
+pub(crate) fn bounds(bones: &[McBone]) -> Option<(Vec3, Vec3)> {
+    let cube_corners = bones.iter()
+        .flat_map(|b| &b.cubes)
+        .flat_map(|c| [Vec3::from(c.origin), Vec3::from(c.origin) + Vec3::from(c.size)]);
+    let poly_points = bones.iter()
+        .filter_map(|b| b.poly_mesh.as_ref())
+        .flat_map(|m| m.positions().iter().map(|&p| Vec3::from(p)));
+
+    cube_corners.chain(poly_points)
+        .fold(None, |bounds, p| match bounds {
+            None => Some((p, p)),
+            Some((min, max)) => Some((min.min(p), max.max(p))),
+        })
+}
+
+/// Moves `cubes` by `offset`, with their pivots and sampled surfaces.
+pub fn shift_cubes(cubes: &mut [McCube], offset: IVec3) {
+    let shift = offset.as_vec3();
+    let moved = |p: [f32; 3]| (Vec3::from(p) + shift).to_array();
+    for cube in cubes {
+        cube.origin = moved(cube.origin);
+        cube.pivot = cube.pivot.map(moved);
+        if let Some(surface) = &mut cube.surface {
+            surface.translate(shift);
+        }
+    }
+}
+
+/// Moves `bones` to match `origin`; returns the offset they were moved by
+/// and whether that brought a model far from the origin back to it.
+pub fn apply_origin(bones: &mut [McBone], origin: VoxelOrigin) -> (IVec3, bool) {
+    let Some((min, max)) = bounds(bones) else { return (IVec3::ZERO, false) };
+    let far = origin == VoxelOrigin::Source && Vec3::ZERO.clamp(min, max).length() > MAX_DISTANCE;
+    let offset = match origin {
+        VoxelOrigin::Source if !far => return (IVec3::ZERO, false),
+        VoxelOrigin::Source | VoxelOrigin::Centered => {
+            let center = (min + max) / 2.0;
+            IVec3::new(-center.x.round() as i32, -min.y.floor() as i32, -center.z.round() as i32)
+        }
+        VoxelOrigin::Positive => -min.floor().as_ivec3(),
+    };
+    if offset == IVec3::ZERO { return (IVec3::ZERO, false); }
+
+    let shift = offset.as_vec3();
+    let moved = |p: [f32; 3]| (Vec3::from(p) + shift).to_array();
+    for bone in bones {
+        bone.pivot = (IVec3::from(bone.pivot) + offset).to_array();
+        shift_cubes(&mut bone.cubes, offset);
+        if let Some(mesh) = &mut bone.poly_mesh {
+            mesh.translate(shift);
+        }
+        for position in bone.locators.values_mut() {
+            *position = moved(*position);
+        }
+    }
+    (offset, far)
+}
@@ -0,0 +1,250 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::blocks::BlockGrid;
+use crate::messages::{failed, Message};
+use crate::texture::Texture;
+use crate::{geometry_file, mcstructure, origin, resource_name, validate, ConvertOptions, McBone, VariantBones};
+
+// ================= BEDROCK PACKS =================
+//
+// A resource pack with the geometry as a client entity (plus its texture,
+// plain white unless baked) and a behavior pack with the matching server entity and, for cube
+// geometry, the structure. Pack UUIDs are derived from the model name, so
+// converting the same model again produces the same packs.
+//
+// In game: `/summon obj2mc:<model>` or `/structure load obj2mc:<model> ~ ~ ~`.
+// The entity's collision box covers the model around its position, from
+// its feet up, within the sizes Bedrock handles.
+//
+// With a wearable slot, the packs also get an item of the same name that
+// can be worn (`/give @s obj2mc:<model>`) and an attachable drawing the
+// geometry on the player. Bones follow the player bone named by their
+// `binding`, or the one they share a name with.
+//
+// Packs are installed by opening a zip: `.mcpack` holds one pack at its root,
+// `.mcaddon` holds both packs in their own folders.
+
+const NAMESPACE: &str = "obj2mc";
+pub const MIN_ENGINE_VERSION: [u32; 3] = [1, 21, 0];
+pub const PACK_VERSION: [u32; 3] = [1, 0, 0];
+/// Collision box sides in blocks: Bedrock's smallest and largest.
+const MIN_HITBOX: f32 = 0.1;
+const MAX_HITBOX: f32 = 16.0;
+const UNITS_PER_BLOCK: f32 = 16.0;
+/// Bones of the player geometry an attachable can follow.
+const PLAYER_BONES: [&str; 17] = [
+    "root", "waist", "body", "jacket", "cape", "head", "hat",
+    "leftArm", "leftSleeve", "leftItem", "rightArm", "rightSleeve", "rightItem",
+    "leftLeg", "leftPants", "rightLeg", "rightPants",
+];
+
+pub struct Pack {
+    /// Folder name inside worlds and archives, e.g. `chair_rp`.
+    pub name: String,
+    pub uuid: Uuid,
+    /// Path inside the pack and file contents.
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+pub struct Packs {
+    pub resource: Pack,
+    pub behavior: Pack,
+}
+
+/// The player bone named like `bone`, ignoring case.
+pub fn player_bone(bone: &str) -> Option<&'static str> {
+    PLAYER_BONES.into_iter().find(|p| p.eq_ignore_ascii_case(bone))
+}
+
+fn pack_uuid(name: &str, part: &str) -> Uuid {
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, format!("{}:{}:{}", NAMESPACE, name, part).as_bytes())
+}
+
+fn json_file(path: &str, value: &Value) -> (String, Vec<u8>) {
+    (path.to_string(), serde_json::to_vec_pretty(value).unwrap())
+}
+
+fn manifest(model_name: &str, uuid: Uuid, module_uuid: Uuid, module_type: &str, dependency: Option<Uuid>) -> Value {
+    let mut manifest = json!({
+        "format_version": 2,
+        "header": {
+            "name": model_name,
+            "description": "Generated by OBJ2MC",
+            "uuid": uuid,
+            "version": PACK_VERSION,
+            "min_engine_version": MIN_ENGINE_VERSION,
+        },
+        "modules": [{
+            "type": module_type,
+            "uuid": module_uuid,
+            "version": PACK_VERSION,
+        }],
+    });
+    if let Some(dependency) = dependency {
+        manifest["dependencies"] = json!([{ "uuid": dependency, "version": PACK_VERSION }]);
+    }
+    manifest
+}
+
+/// Suggested collision box width and height in blocks for `bones`.
+fn hitbox(bones: &[McBone]) -> (f64, f64) {
+    let Some((min, max)) = origin::bounds(bones) else { return (1.0, 1.0) };
+    // The box is centered on the entity, so it must reach the farthest side
+    let reach = [min.x, max.x, min.z, max.z].into_iter().fold(0.0f32, |r, v| r.max(v.abs()));
+    // Rounded to hundredths in f64 so the JSON stays short
+    let size = |units: f32| ((units / UNITS_PER_BLOCK).clamp(MIN_HITBOX, MAX_HITBOX) as f64 * 100.0).round() / 100.0;
+    (size(2.0 * reach), size(max.y))
+}
+
+pub fn build_packs(
+    bones: Vec<McBone>,
+    variants: VariantBones,
+    grid: Option<&BlockGrid>,
+    texture: &Texture,
+    model_name: &str,
+    options: &ConvertOptions,
+) -> Result<Packs, Message> {
+    let name = resource_name(model_name);
+    let identifier = format!("{}:{}", NAMESPACE, name);
+    let geometry = format!("geometry.{}", name);
+    let texture_path = format!("textures/entity/{}", name);
+
+    let rp_uuid = pack_uuid(&name, "resources");
+    let bp_uuid = pack_uuid(&name, "behavior");
+
+    // Variants are listed for render controllers to pick; the default controller shows the model
+    let (hitbox_width, hitbox_height) = hitbox(&bones);
+    let mut geometries = json!({ "default": geometry });
+    for (variant, _) in &variants {
+        geometries[variant] = json!(format!("{}.{}", geometry, variant));
+    }
+    let geometry_root = geometry_file(bones, variants, &name, texture);
+    validate::check_geometry(&geometry_root)?;
+    let geometry_json = serde_json::to_vec_pretty(&geometry_root).unwrap();
+    let client_entity = json!({
+        "format_version": "1.10.0",
+        "minecraft:client_entity": {
+            "description": {
+                "identifier": identifier,
+                "materials": { "default": "entity_alphatest" },
+                "textures": { "default": texture_path },
+                "geometry": geometries,
+                "render_controllers": ["controller.render.default"],
+                "spawn_egg": { "base_color": "#8a8a8a", "overlay_color": "#ffffff" },
+            }
+        }
+    });
+
+    let mut resource = Pack {
+        name: format!("{}_rp", name),
+        uuid: rp_uuid,
+        files: vec![
+            json_file("manifest.json", &manifest(model_name, rp_uuid, pack_uuid(&name, "resources.module"), "resources", None)),
+            (format!("models/entity/{}.geo.json", name), geometry_json),
+            json_file(&format!("entity/{}.entity.json", name), &client_entity),
+            (format!("{}.png", texture_path), texture.to_png()),
+        ],
+    };
+
+    let entity = json!({
+        "format_version": "1.20.80",
+        "minecraft:entity": {
+            "description": {
+                "identifier": identifier,
+                "is_spawnable": true,
+                "is_summonable": true,
+            },
+            "components": {
+                "minecraft:physics": { "has_gravity": false },
+                "minecraft:collision_box": { "width": hitbox_width, "height": hitbox_height },
+                "minecraft:pushable": { "is_pushable": false, "is_pushable_by_piston": false },
+                "minecraft:damage_sensor": { "triggers": { "cause": "all", "deals_damage": "no" } },
+            }
+        }
+    });
+
+    let mut behavior = Pack {
+        name: format!("{}_bp", name),
+        uuid: bp_uuid,
+        files: vec![
+            json_file("manifest.json", &manifest(model_name, bp_uuid, pack_uuid(&name, "behavior.module"), "data", Some(rp_uuid))),
+            json_file(&format!("entities/{}.json", name), &entity),
+        ],
+    };
+    if let Some(grid) = grid {
+        behavior.files.push((
+            format!("structures/{}/{}.mcstructure", NAMESPACE, name),
+            mcstructure::encode_mcstructure(grid, options),
+        ));
+    }
+
+    if let Some(slot) = &options.wearable_slot {
+        let attachable = json!({
+            "format_version": "1.10.0",
+            "minecraft:attachable": {
+                "description": {
+                    "identifier": identifier,
+                    "materials": { "default": "entity_alphatest", "enchanted": "entity_alphatest_glint" },
+                    "textures": { "default": texture_path, "enchanted": "textures/misc/enchanted_item_glint" },
+                    "geometry": { "default": geometry },
+                    "render_controllers": ["controller.render.item_default"],
+                }
+            }
+        });
+        resource.files.push(json_file(&format!("attachables/{}.json", name), &attachable));
+
+        let item = json!({
+            "format_version": "1.20.80",
+            "minecraft:item": {
+                "description": {
+                    "identifier": identifier,
+                    "menu_category": { "category": "equipment" },
+                },
+                "components": {
+                    "minecraft:max_stack_size": 1,
+                    "minecraft:wearable": { "slot": slot },
+                }
+            }
+        });
+        behavior.files.push(json_file(&format!("items/{}.json", name), &item));
+    }
+
+    Ok(Packs { resource, behavior })
+}
+
+/// Writes `files` (path inside the archive, contents) to a zip at `path`.
+pub fn write_zip<'a>(path: &Path, files: impl IntoIterator<Item = (String, &'a [u8])>) -> Result<(), Message> {
+    let file = File::create(path).map_err(failed("error.create_file"))?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+
+    let result = (|| -> zip::result::ZipResult<()> {
+        for (name, data) in files {
+            zip.start_file(name, SimpleFileOptions::default())?;
+            zip.write_all(data)?;
+        }
+        zip.finish()?.flush()?;
+        Ok(())
+    })();
+    result.map_err(failed("error.write_archive"))
+}
+
+fn pack_files<'a>(pack: &'a Pack, folder: &'a str) -> impl Iterator<Item = (String, &'a [u8])> {
+    pack.files.iter().map(move |(name, data)| (format!("{}{}", folder, name), data.as_slice()))
+}
+
+pub fn write_mcpack(pack: &Pack, path: &Path) -> Result<(), Message> {
+    write_zip(path, pack_files(pack, ""))
+}
+
+pub fn write_mcaddon(packs: &Packs, path: &Path) -> Result<(), Message> {
+    let rp_folder = format!("{}/", packs.resource.name);
+    let bp_folder = format!("{}/", packs.behavior.name);
+    write_zip(path, pack_files(&packs.resource, &rp_folder).chain(pack_files(&packs.behavior, &bp_folder)))
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Message;
+use crate::ConvertOptions;
+
+// ================= PALETTE FILTERS =================
+//
+// Servers and game modes restrict which blocks a build may use. Filters check
+// every block named in the options (the defaults pass all of them) before
+// anything is written, so a build that can't work there fails up front
+// instead of collapsing or being rejected in game. The lists cover the
+// vanilla blocks a voxel build is likely to use, not every block in the game.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaletteFilter {
+    /// Only blocks a survival player can get and place.
+    SurvivalObtainable,
+    /// No blocks that fall without support.
+    NoFallingBlocks,
+    /// No blocks with a block entity (containers, signs, banners, ...).
+    NoTileEntities,
+}
+
+const UNOBTAINABLE: &[&str] = &[
+    "bedrock", "barrier", "light_block", "light", "structure_block", "structure_void", "jigsaw",
+    "command_block", "chain_command_block", "repeating_command_block", "end_portal_frame", "end_portal",
+    "nether_portal", "portal", "end_gateway", "spawner", "mob_spawner", "budding_amethyst",
+    "reinforced_deepslate", "petrified_oak_slab", "frosted_ice", "allow", "deny", "border_block",
+    "invisible_bedrock", "farmland", "dirt_path", "grass_path",
+];
+const UNOBTAINABLE_PREFIXES: &[&str] = &["infested_"];
+
+const FALLING: &[&str] = &[
+    "sand", "red_sand", "gravel", "suspicious_sand", "suspicious_gravel", "anvil", "chipped_anvil",
+    "damaged_anvil", "dragon_egg", "scaffolding", "pointed_dripstone",
+];
+const FALLING_SUFFIXES: &[&str] = &["_concrete_powder"];
+
+const BLOCK_ENTITIES: &[&str] = &[
+    "chest", "trapped_chest", "ender_chest", "barrel", "furnace", "blast_furnace", "smoker", "hopper",
+    "dispenser", "dropper", "beacon", "spawner", "mob_spawner", "enchanting_table", "brewing_stand",
+    "lectern", "bell", "beehive", "bee_nest", "jukebox", "noteblock", "note_block", "command_block",
+    "chain_command_block", "repeating_command_block", "structure_block", "jigsaw", "campfire",
+    "soul_campfire", "conduit", "daylight_detector", "comparator", "chiseled_bookshelf",
+    "decorated_pot", "crafter", "trial_spawner", "vault", "sculk_sensor", "calibrated_sculk_sensor",
+    "sculk_shrieker", "sculk_catalyst", "end_gateway", "end_portal",
+];
+const BLOCK_ENTITY_SUFFIXES: &[&str] = &["_sign", "_banner", "shulker_box", "_bed", "_head", "_skull"];
+
+fn listed(name: &str, names: &[&str], prefixes: &[&str], suffixes: &[&str]) -> bool {
+    names.contains(&name)
+        || prefixes.iter().any(|p| name.starts_with(p))
+        || suffixes.iter().any(|s| name.ends_with(s))
+}
+
+impl PaletteFilter {
+    fn key(self) -> &'static str {
+        match self {
+            PaletteFilter::SurvivalObtainable => "survival_obtainable",
+            PaletteFilter::NoFallingBlocks => "no_falling_blocks",
+            PaletteFilter::NoTileEntities => "no_tile_entities",
+        }
+    }
+
+    fn allows(self, block: &str) -> bool {
+        let name = block.split('[').next().unwrap_or(block);
+        let name = name.strip_prefix("minecraft:").unwrap_or(name);
+        match self {
+            PaletteFilter::SurvivalObtainable => !listed(name, UNOBTAINABLE, UNOBTAINABLE_PREFIXES, &[]),
+            PaletteFilter::NoFallingBlocks => !listed(name, FALLING, &[], FALLING_SUFFIXES),
+            PaletteFilter::NoTileEntities => !listed(name, BLOCK_ENTITIES, &[], BLOCK_ENTITY_SUFFIXES),
+        }
+    }
+}
+
+/// Fails on the first block set in `options` that a palette filter rules out.
+pub fn check_palette(options: &ConvertOptions) -> Result<(), Message> {
+    let blocks = [&options.block, &options.stair_block, &options.slab_block, &options.support_block];
+    for block in blocks.into_iter().flatten().chain(&options.block_variation).chain(&options.decorations) {
+        if let Some(filter) = options.palette_filters.iter().find(|f| !f.allows(block)) {
+            return Err(Message::new("error.block_filtered").with("block", block).with("filter", filter.key()));
+        }
+    }
+    Ok(())
+}
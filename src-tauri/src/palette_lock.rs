@@ -0,0 +1,77 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{failed, Message};
+use crate::texture::Texture;
+use crate::McBone;
+
+// ================= PALETTE LOCK =================
+//
+// A series of props converted weeks apart drifts in color as materials get
+// tweaked. `export_palette` writes the colors a conversion ended up with to
+// `<model>.palette.json`; `palette_lock` points later conversions at such a
+// file, and every cube color and texel of the baked texture is snapped to the
+// nearest color in it, so the whole series keeps the same colors. Alpha is
+// kept; fully transparent texels are left alone and not part of a palette.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Palette {
+    /// RGB colors, sorted.
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    pub fn load(path: &str) -> Result<Self, Message> {
+        let text = fs::read_to_string(path).map_err(failed("error.read_palette"))?;
+        let palette: Palette = serde_json::from_str(&text).map_err(failed("error.invalid_palette"))?;
+        if palette.colors.is_empty() {
+            return Err(Message::new("error.empty_palette"));
+        }
+        Ok(palette)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Message> {
+        let text = serde_json::to_string(self).unwrap();
+        fs::write(path, text).map_err(failed("error.write_palette"))
+    }
+
+    /// The colors of a converted model: its baked texture's if it has one, else its cubes'.
+    pub fn of(bones: &[McBone], atlas: Option<&Texture>) -> Self {
+        let colors: BTreeSet<[u8; 3]> = match atlas {
+            Some(texture) => texture.pixels.iter()
+                .filter(|p| p[3] > 0)
+                .map(|&[r, g, b, _]| [r, g, b])
+                .collect(),
+            None => bones.iter()
+                .flat_map(|b| &b.cubes)
+                .filter_map(|c| c.color.map(|[r, g, b, _]| [r, g, b]))
+                .collect(),
+        };
+        Self { colors: colors.into_iter().collect() }
+    }
+
+    fn nearest(&self, [r, g, b, a]: [u8; 4]) -> [u8; 4] {
+        let distance = |c: &[u8; 3]| [r, g, b].into_iter().zip(c).map(|(x, &y)| (x as i32 - y as i32).pow(2)).sum::<i32>();
+        let [r, g, b] = *self.colors.iter().min_by_key(|c| distance(c)).unwrap();
+        [r, g, b, a]
+    }
+}
+
+/// Snaps the cube colors of `bones` to `palette`.
+pub fn lock_cubes(bones: &mut [McBone], palette: &Palette) {
+    for cube in bones.iter_mut().flat_map(|b| &mut b.cubes) {
+        cube.color = cube.color.map(|c| palette.nearest(c));
+    }
+}
+
+/// Snaps the visible texels of `texture` to `palette`.
+pub fn lock_texture(texture: &mut Texture, palette: &Palette) {
+    // Textures repeat few colors many times
+    let mut snapped: HashMap<[u8; 4], [u8; 4]> = HashMap::new();
+    for pixel in texture.pixels.iter_mut().filter(|p| p[3] > 0) {
+        *pixel = *snapped.entry(*pixel).or_insert_with(|| palette.nearest(*pixel));
+    }
+}
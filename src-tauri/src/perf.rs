@@ -0,0 +1,202 @@
+use std::io;
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::rasterize;
+use crate::messages::Message;
+use crate::texture::Texture;
+use crate::McBone;
+
+// ================= PERFORMANCE ESTIMATE =================
+//
+// Bedrock renders every cube face as its own quad and re-evaluates every bone
+// transform each frame, so cube and bone counts dominate entity render cost.
+// Large textures mostly hurt on mobile GPUs with little texture memory.
+// The thresholds are rough rules of thumb, not measurements of a specific device.
+//
+// Per-bone stats break the totals down by bone, so the part that blows the
+// budget can be excluded or simplified on its own.
+//
+// Face stats compare the surface of all cube faces with the part of it not
+// covered by a neighbouring filled cell, which is what the player can see:
+// a large gap means buried faces are still drawn, the overdraw that interior
+// fill or overlap meshing trades cubes into. Rotated cubes count as fully
+// visible.
+//
+// Output sizes are what the geometry file and baked texture come to on disk,
+// for packs synced to consoles and phones. The JSON is serialized into a byte
+// counter rather than kept in memory.
+
+const CUBES_HEAVY: u64 = 1_500;
+const CUBES_MOBILE_LAG: u64 = 6_000;
+const BONES_HEAVY: u64 = 64;
+const BONES_MOBILE_LAG: u64 = 256;
+const TEXTURE_PIXELS_HEAVY: u64 = 512 * 512;
+const TEXTURE_PIXELS_MOBILE_LAG: u64 = 2048 * 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PerformanceRating {
+    Fine,
+    Heavy,
+    MobileLag,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceEstimate {
+    pub rating: PerformanceRating,
+    /// Which limits pushed the rating above `fine`.
+    pub reasons: Vec<Message>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoneStats {
+    pub name: String,
+    /// Volume of the bone's cubes in geometry units, i.e. voxels for cubic cells.
+    pub voxel_count: usize,
+    pub cube_count: usize,
+    pub poly_count: usize,
+    /// Share of the model's cubes (polygons in `poly_mesh` mode) in this bone, 0-100.
+    pub percent: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct FaceStats {
+    /// Cube faces not entirely covered by neighbouring cells.
+    pub exposed_faces: usize,
+    pub face_count: usize,
+    /// Area of all cube faces, in square geometry units.
+    pub total_area: f32,
+    /// Area of cube faces with no filled cell in front of them.
+    pub visible_area: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct OutputSize {
+    /// Bytes of the pretty-printed .geo.json.
+    pub geometry_bytes: u64,
+    /// Bytes of the baked texture PNG; 0 when none is baked.
+    pub texture_bytes: u64,
+}
+
+#[derive(Default)]
+struct ByteCount(u64);
+
+impl io::Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn rate(value: u64, heavy: u64, mobile_lag: u64) -> PerformanceRating {
+    if value > mobile_lag {
+        PerformanceRating::MobileLag
+    } else if value > heavy {
+        PerformanceRating::Heavy
+    } else {
+        PerformanceRating::Fine
+    }
+}
+
+pub fn estimate(cube_count: usize, bone_count: usize, texture_width: u32, texture_height: u32) -> PerformanceEstimate {
+    let texture_pixels = texture_width as u64 * texture_height as u64;
+
+    let checks = [
+        (rate(cube_count as u64, CUBES_HEAVY, CUBES_MOBILE_LAG), Message::new("perf.cubes").with("count", cube_count)),
+        (rate(bone_count as u64, BONES_HEAVY, BONES_MOBILE_LAG), Message::new("perf.bones").with("count", bone_count)),
+        (
+            rate(texture_pixels, TEXTURE_PIXELS_HEAVY, TEXTURE_PIXELS_MOBILE_LAG),
+            Message::new("perf.texture").with("width", texture_width).with("height", texture_height),
+        ),
+    ];
+
+    let rating = checks.iter().map(|(rating, _)| *rating).max().unwrap_or(PerformanceRating::Fine);
+    let reasons = checks.into_iter()
+        .filter(|(rating, _)| *rating > PerformanceRating::Fine)
+        .map(|(_, reason)| reason)
+        .collect();
+
+    PerformanceEstimate { rating, reasons }
+}
+
+/// Stats of every bone, largest share first.
+pub fn bone_stats(bones: &[McBone]) -> Vec<BoneStats> {
+    let weight = |b: &McBone| b.cubes.len() + b.poly_mesh.as_ref().map_or(0, |m| m.poly_count());
+    let total = bones.iter().map(weight).sum::<usize>().max(1);
+
+    let mut stats: Vec<BoneStats> = bones.iter()
+        .map(|bone| BoneStats {
+            name: bone.name.clone(),
+            voxel_count: bone.cubes.iter().map(|c| c.size.iter().product::<f32>().round() as usize).sum(),
+            cube_count: bone.cubes.len(),
+            poly_count: bone.poly_mesh.as_ref().map_or(0, |m| m.poly_count()),
+            percent: weight(bone) as f32 * 100.0 / total as f32,
+        })
+        .collect();
+    stats.sort_by(|a, b| b.percent.total_cmp(&a.percent).then_with(|| a.name.cmp(&b.name)));
+    stats
+}
+
+/// Exposed faces and visible surface of the cubes of `bones`; `None` without cubes.
+pub fn face_stats(bones: &[McBone]) -> Option<FaceStats> {
+    let cubes: Vec<_> = bones.iter().flat_map(|b| &b.cubes).collect();
+    if cubes.is_empty() {
+        return None;
+    }
+    let cells = rasterize(bones);
+    let mut stats = FaceStats { face_count: cubes.len() * 6, ..Default::default() };
+
+    for cube in cubes {
+        let (min, size) = (Vec3::from(cube.origin), Vec3::from(cube.size));
+        for axis in 0..3 {
+            let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+            let area = size[a] * size[b];
+            for side in [0.0, 1.0] {
+                stats.total_area += area;
+                if cube.rotation.is_some() {
+                    stats.exposed_faces += 1;
+                    stats.visible_area += area;
+                    continue;
+                }
+
+                // One sample per geometry unit across the face
+                let (steps_a, steps_b) = (size[a].ceil().max(1.0) as usize, size[b].ceil().max(1.0) as usize);
+                let mut normal = Vec3::ZERO;
+                normal[axis] = if side == 0.0 { -0.5 } else { 0.5 };
+                let mut visible = 0;
+                for i in 0..steps_a {
+                    for j in 0..steps_b {
+                        let mut point = min;
+                        point[axis] += size[axis] * side;
+                        point[a] += size[a] * (i as f32 + 0.5) / steps_a as f32;
+                        point[b] += size[b] * (j as f32 + 0.5) / steps_b as f32;
+                        if !cells.contains(&(point + normal).floor().as_ivec3()) {
+                            visible += 1;
+                        }
+                    }
+                }
+                if visible > 0 {
+                    stats.exposed_faces += 1;
+                    stats.visible_area += area * visible as f32 / (steps_a * steps_b) as f32;
+                }
+            }
+        }
+    }
+    Some(stats)
+}
+
+/// Sizes `geometry` and `texture` would take when written.
+pub fn output_size(geometry: &impl Serialize, texture: Option<&Texture>) -> OutputSize {
+    let mut count = ByteCount::default();
+    serde_json::to_writer_pretty(&mut count, geometry).unwrap();
+    OutputSize {
+        geometry_bytes: count.0,
+        texture_bytes: texture.map_or(0, |t| t.to_png().len() as u64),
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ahash::RandomState;
+use glam::IVec3;
+use serde::Serialize;
+
+use crate::blocks::{BlockGrid, BlockShape};
+use crate::messages::{failed, Message};
+use crate::ConvertOptions;
+
+// ================= CHUNK-ALIGNED PIECES =================
+//
+// Large world exports are cut into square columns on a grid of whole chunks,
+// counted from the placement position: placed at a chunk corner, every piece
+// covers its own chunks and pieces never overlap. Each piece keeps its place
+// as its origin, and an index file lists where each one goes. Formats with a
+// height limit also cut the columns into layers from the bottom up.
+
+const CHUNK_SIZE: i32 = 16;
+
+pub struct Piece {
+    /// Column of the piece, counted from the first one (`x0_z1`), and its
+    /// layer when cut into layers (`x0_z1_y2`).
+    pub name: String,
+    pub grid: BlockGrid,
+}
+
+#[derive(Serialize)]
+pub struct IndexEntry {
+    file: String,
+    /// Position of the piece's block (0, 0, 0) relative to the placement position.
+    position: [i32; 3],
+    size: [i32; 3],
+    block_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+}
+
+impl IndexEntry {
+    pub fn new(piece: &Piece, file: String, command: Option<String>) -> Self {
+        Self {
+            file,
+            position: piece.grid.origin.to_array(),
+            size: piece.grid.size.to_array(),
+            block_count: piece.grid.block_count(),
+            command,
+        }
+    }
+}
+
+/// Side of a piece in blocks: `split_chunks` from the options, or else the
+/// largest whole number of chunks within `limit` when the grid exceeds it.
+/// Pieces never exceed `limit`, whatever `split_chunks` asks for.
+pub fn piece_size(grid: &BlockGrid, options: &ConvertOptions, limit: Option<i32>) -> Option<i32> {
+    let fitting = limit.map(|limit| (limit / CHUNK_SIZE).max(1) * CHUNK_SIZE);
+    if options.split_chunks > 0 {
+        let size = options.split_chunks as i32 * CHUNK_SIZE;
+        return Some(fitting.map_or(size, |fitting| size.min(fitting)));
+    }
+    let (limit, fitting) = (limit?, fitting?);
+    (grid.size.x > limit || grid.size.z > limit).then_some(fitting)
+}
+
+/// Cuts `grid` into pieces of `size` blocks per side along X and Z.
+pub fn split(grid: &BlockGrid, size: i32) -> Vec<Piece> {
+    let mut columns: BTreeMap<(i32, i32), Vec<(IVec3, BlockShape)>> = BTreeMap::new();
+    for (&p, &shape) in &grid.blocks {
+        let placed = grid.origin + p;
+        let column = (placed.x.div_euclid(size), placed.z.div_euclid(size));
+        columns.entry(column).or_default().push((placed, shape));
+    }
+
+    let first_x = columns.keys().map(|&(x, _)| x).min().unwrap_or(0);
+    let first_z = columns.keys().map(|&(_, z)| z).min().unwrap_or(0);
+    columns.into_iter()
+        .map(|((x, z), blocks)| {
+            let origin = IVec3::new(x * size, grid.origin.y, z * size);
+            let blocks: HashMap<IVec3, BlockShape, RandomState> =
+                blocks.into_iter().map(|(p, shape)| (p - origin, shape)).collect();
+            let size = blocks.keys().fold(IVec3::ZERO, |a, &p| a.max(p + IVec3::ONE));
+            Piece {
+                name: format!("x{}_z{}", x - first_x, z - first_z),
+                grid: BlockGrid { size, blocks, origin },
+            }
+        })
+        .collect()
+}
+
+/// Cuts `piece` into layers of `height` blocks, counted from its bottom.
+pub fn split_layers(piece: Piece, height: i32) -> Vec<Piece> {
+    let mut layers: BTreeMap<i32, Vec<(IVec3, BlockShape)>> = BTreeMap::new();
+    for (p, shape) in piece.grid.blocks {
+        layers.entry(p.y.div_euclid(height)).or_default().push((p, shape));
+    }
+
+    layers.into_iter()
+        .map(|(y, blocks)| {
+            let offset = IVec3::new(0, y * height, 0);
+            let blocks: HashMap<IVec3, BlockShape, RandomState> =
+                blocks.into_iter().map(|(p, shape)| (p - offset, shape)).collect();
+            let size = blocks.keys().fold(IVec3::ZERO, |a, &p| a.max(p + IVec3::ONE));
+            Piece {
+                name: format!("{}_y{}", piece.name, y),
+                grid: BlockGrid { size, blocks, origin: piece.grid.origin + offset },
+            }
+        })
+        .collect()
+}
+
+/// Output folder for the pieces of a `kind` export, emptied of earlier pieces.
+pub fn pieces_dir(output_dir: &Path, model_name: &str, kind: &str) -> Result<PathBuf, Message> {
+    let dir = output_dir.join(format!("{}_{}_pieces", model_name, kind));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(failed("error.clear_old_pieces"))?;
+    }
+    fs::create_dir_all(&dir).map_err(failed("error.create_folder"))?;
+    Ok(dir)
+}
+
+/// Writes `index.json` into `dir`, listing where each piece goes.
+pub fn write_index(dir: &Path, entries: &[IndexEntry]) -> Result<(), Message> {
+    let index = serde_json::json!({ "pieces": entries });
+    fs::write(dir.join("index.json"), serde_json::to_string_pretty(&index).unwrap())
+        .map_err(failed("error.write_index"))
+}
@@ -0,0 +1,248 @@
+use std::fs;
+use std::path::Path;
+
+use crate::messages::{failed, Message};
+
+// ================= PLY IMPORT =================
+//
+// PLY files (ASCII or binary, either byte order), the usual output of 3D
+// scanners and photogrammetry, become one object named after the file. Vertex
+// colors are kept in the mesh's `vertex_color` so the atlas and cube colors
+// can follow the scan's colors; faces with more than three corners are fanned
+// into triangles, and elements other than vertices and faces are skipped.
+
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn bytes(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// Full brightness of a color stored as this type.
+    fn color_max(self) -> f64 {
+        match self {
+            Self::U8 | Self::I8 => 255.0,
+            Self::U16 | Self::I16 => 65535.0,
+            Self::F32 | Self::F64 => 1.0,
+            Self::I32 | Self::U32 => u32::MAX as f64,
+        }
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+fn error(reason: impl Into<String>) -> Message {
+    Message::new("error.load_ply").with("error", reason.into())
+}
+
+pub fn is_ply(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ply"))
+}
+
+pub fn load_ply(path: &str) -> Result<tobj::Model, Message> {
+    let bytes = fs::read(path).map_err(failed("error.load_ply"))?;
+    let (encoding, elements, body) = header(&bytes)?;
+    let mut reader = Reader { encoding, bytes: &bytes[body..], at: 0, words: None };
+
+    let vertex_count = elements.iter().find(|e| e.name == "vertex").map_or(0, |e| e.count);
+    let mut mesh = tobj::Mesh::default();
+    for element in &elements {
+        let names: Vec<&str> = element.properties.iter().map(|p| match p {
+            Property::Scalar(name, _) | Property::List(name, ..) => name.as_str(),
+        }).collect();
+        let find = |options: &[&str]| names.iter().position(|n| options.contains(n));
+        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+        // Colors are scalar channels; a list of the same name is left alone
+        let channel = |options: &[&str]| element.properties.iter().enumerate().find_map(|(k, p)| match p {
+            Property::Scalar(name, scalar) if options.contains(&name.as_str()) => Some((k, *scalar)),
+            _ => None,
+        });
+        let color = [channel(&["red", "r", "diffuse_red"]), channel(&["green", "g", "diffuse_green"]), channel(&["blue", "b", "diffuse_blue"])];
+        let color = color.iter().all(Option::is_some).then(|| color.map(Option::unwrap)).filter(|_| element.name == "vertex");
+        let corners = find(&["vertex_indices", "vertex_index"]);
+
+        for _ in 0..element.count {
+            let mut values = vec![0.0; element.properties.len()];
+            let mut list = Vec::new();
+            for (k, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::Scalar(_, scalar) => values[k] = reader.scalar(*scalar)?,
+                    Property::List(_, count, item) => {
+                        let n = reader.scalar(*count)? as usize;
+                        let items = (0..n).map(|_| reader.scalar(*item)).collect::<Result<Vec<_>, _>>()?;
+                        if Some(k) == corners {
+                            list = items;
+                        }
+                    }
+                }
+            }
+            reader.end_line();
+
+            match element.name.as_str() {
+                "vertex" => {
+                    for axis in position {
+                        mesh.positions.push(axis.map_or(0.0, |k| values[k] as f32));
+                    }
+                    for (k, scalar) in color.into_iter().flatten() {
+                        mesh.vertex_color.push((values[k] / scalar.color_max()) as f32);
+                    }
+                }
+                "face" => {
+                    if list.iter().any(|&i| i < 0.0 || i as usize >= vertex_count) {
+                        return Err(error("face index out of range"));
+                    }
+                    for k in 1..list.len().saturating_sub(1) {
+                        mesh.indices.extend([list[0], list[k], list[k + 1]].map(|i| i as u32));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let name = Path::new(path).file_stem().map_or("model".to_string(), |s| s.to_string_lossy().to_string());
+    Ok(tobj::Model::new(mesh, name))
+}
+
+/// Encoding, elements and where the body starts.
+fn header(bytes: &[u8]) -> Result<(Encoding, Vec<Element>, usize), Message> {
+    const END: &[u8] = b"end_header";
+    let end = bytes.windows(END.len()).position(|w| w == END).ok_or_else(|| error("no end_header"))?;
+    // The body starts after the line break that ends the header
+    let body = bytes[end..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |n| end + n + 1);
+    let text = String::from_utf8_lossy(&bytes[..end]);
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(error("not a PLY file"));
+    }
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["format", format, ..] => {
+                encoding = Some(match format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::LittleEndian,
+                    "binary_big_endian" => Encoding::BigEndian,
+                    _ => return Err(error(format!("unknown format '{}'", format))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| error(format!("bad element count '{}'", count)))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let scalar = |s: &str| Scalar::parse(s).ok_or_else(|| error(format!("unknown type '{}'", s)));
+                let property = Property::List(name.to_string(), scalar(count)?, scalar(item)?);
+                elements.last_mut().ok_or_else(|| error("property before element"))?.properties.push(property);
+            }
+            ["property", kind, name] => {
+                let scalar = Scalar::parse(kind).ok_or_else(|| error(format!("unknown type '{}'", kind)))?;
+                elements.last_mut().ok_or_else(|| error("property before element"))?.properties.push(Property::Scalar(name.to_string(), scalar));
+            }
+            _ => {}
+        }
+    }
+    Ok((encoding.ok_or_else(|| error("no format line"))?, elements, body))
+}
+
+struct Reader<'a> {
+    encoding: Encoding,
+    bytes: &'a [u8],
+    at: usize,
+    /// Words left on the current ASCII line.
+    words: Option<std::vec::IntoIter<&'a str>>,
+}
+
+impl<'a> Reader<'a> {
+    fn scalar(&mut self, scalar: Scalar) -> Result<f64, Message> {
+        if self.encoding == Encoding::Ascii {
+            return self.word()?.parse().map_err(|_| error("bad number"));
+        }
+        let size = scalar.bytes();
+        let raw = self.bytes.get(self.at..self.at + size).ok_or_else(|| error("file ends early"))?;
+        self.at += size;
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(raw);
+        if self.encoding == Encoding::BigEndian {
+            buf[..size].reverse();
+        }
+        Ok(match scalar {
+            Scalar::I8 => buf[0] as i8 as f64,
+            Scalar::U8 => buf[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            Scalar::U32 => u32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            Scalar::F32 => f32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            Scalar::F64 => f64::from_le_bytes(buf),
+        })
+    }
+
+    /// Next word of the ASCII body, moving across lines as needed.
+    fn word(&mut self) -> Result<&'a str, Message> {
+        loop {
+            if let Some(word) = self.words.as_mut().and_then(Iterator::next) {
+                return Ok(word);
+            }
+            let rest = self.bytes.get(self.at..).filter(|r| !r.is_empty()).ok_or_else(|| error("file ends early"))?;
+            let len = rest.iter().position(|&b| b == b'\n').map_or(rest.len(), |n| n + 1);
+            self.at += len;
+            let line = std::str::from_utf8(&rest[..len]).map_err(|_| error("bad text"))?;
+            self.words = Some(line.split_whitespace().collect::<Vec<_>>().into_iter());
+        }
+    }
+
+    /// In ASCII each element is one line; drops what is left of it.
+    fn end_line(&mut self) {
+        self.words = None;
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+use serde::Serialize;
+
+// ================= POLY MESH =================
+//
+// Writes the source triangles as a Bedrock `poly_mesh` instead of cubes.
+// Positions use the same units as cube geometry (model space × scale).
+// Bedrock draws every poly as a quad, so triangles repeat their last corner.
+// With decimation, vertices are snapped to the voxel grid and merged, and
+// triangles that collapse are dropped. Double-sided meshes repeat every
+// triangle facing the other way, since Bedrock culls back faces.
+
+#[derive(Serialize, Debug, Clone)]
+pub struct McPolyMesh {
+    normalized_uvs: bool,
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    polys: Vec<[[u32; 3]; 4]>,
+}
+
+impl McPolyMesh {
+    pub fn poly_count(&self) -> usize {
+        self.polys.len()
+    }
+
+    pub fn positions(&self) -> &[[f32; 3]] {
+        &self.positions
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        for p in &mut self.positions {
+            *p = (Vec3::from(*p) + offset).to_array();
+        }
+    }
+
+    /// Mirrors across the YZ plane, keeping the faces' winding outwards.
+    pub fn mirror_x(&mut self) {
+        for p in self.positions.iter_mut().chain(&mut self.normals) {
+            // Subtracting keeps 0 from turning into -0 in the JSON
+            p[0] = 0.0 - p[0];
+        }
+        for poly in &mut self.polys {
+            *poly = [poly[0], poly[3], poly[2], poly[1]];
+        }
+    }
+
+    /// Scales positions per axis; normals follow the inverse scale.
+    pub fn scale(&mut self, factor: Vec3) {
+        for p in &mut self.positions {
+            *p = (Vec3::from(*p) * factor).to_array();
+        }
+        for n in &mut self.normals {
+            *n = (Vec3::from(*n) / factor).normalize_or_zero().to_array();
+        }
+    }
+}
+
+pub fn build_poly_mesh(mesh: &tobj::Mesh, scale: f32, decimate: bool, double_sided: bool) -> McPolyMesh {
+    let position = |i: u32| {
+        let i = i as usize * 3;
+        Vec3::new(mesh.positions[i], mesh.positions[i + 1], mesh.positions[i + 2]) * scale
+    };
+
+    // Decimation maps every vertex onto the center of its voxel cell
+    let mut cells: HashMap<IVec3, u32, RandomState> = HashMap::default();
+    let mut positions = Vec::new();
+    let mut vertex_index = |i: u32| -> u32 {
+        let p = position(i);
+        if !decimate {
+            return i;
+        }
+        let cell = p.floor().as_ivec3();
+        *cells.entry(cell).or_insert_with(|| {
+            positions.push((cell.as_vec3() + 0.5).to_array());
+            positions.len() as u32 - 1
+        })
+    };
+
+    let has_uvs = !mesh.texcoords.is_empty();
+    let uvs: Vec<[f32; 2]> = if has_uvs {
+        // OBJ UVs start at the bottom-left, Bedrock at the top-left
+        mesh.texcoords.chunks(2).map(|uv| [uv[0], 1.0 - uv[1]]).collect()
+    } else {
+        vec![[0.0, 0.0]]
+    };
+
+    let has_normals = !mesh.normals.is_empty();
+    let mut normals: Vec<[f32; 3]> = if has_normals {
+        mesh.normals.chunks(3).map(|n| [n[0], n[1], n[2]]).collect()
+    } else {
+        Vec::new()
+    };
+    let mut polys = Vec::new();
+
+    for t in mesh.indices.chunks(3) {
+        let corners = [t[0], t[1], t[2]].map(&mut vertex_index);
+        if decimate && (corners[0] == corners[1] || corners[1] == corners[2] || corners[0] == corners[2]) {
+            continue;
+        }
+
+        let normal: [u32; 3] = if has_normals {
+            [t[0], t[1], t[2]]
+        } else {
+            // Flat shading from the face normal
+            let [a, b, c] = [t[0], t[1], t[2]].map(position);
+            normals.push((b - a).cross(c - a).normalize_or_zero().to_array());
+            [normals.len() as u32 - 1; 3]
+        };
+
+        let uv = |k: usize| if has_uvs { t[k] } else { 0 };
+        let poly = [
+            [corners[0], normal[0], uv(0)],
+            [corners[1], normal[1], uv(1)],
+            [corners[2], normal[2], uv(2)],
+            [corners[2], normal[2], uv(2)],
+        ];
+        polys.push(poly);
+
+        if double_sided {
+            let back = normal.map(|n| {
+                let [x, y, z] = normals[n as usize];
+                normals.push([-x, -y, -z]);
+                normals.len() as u32 - 1
+            });
+            polys.push([
+                [corners[2], back[2], uv(2)],
+                [corners[1], back[1], uv(1)],
+                [corners[0], back[0], uv(0)],
+                [corners[0], back[0], uv(0)],
+            ]);
+        }
+    }
+
+    if !decimate {
+        positions = (0..mesh.positions.len() as u32 / 3).map(|i| position(i).to_array()).collect();
+    }
+
+    McPolyMesh {
+        normalized_uvs: true,
+        positions,
+        normals,
+        uvs,
+        polys,
+    }
+}
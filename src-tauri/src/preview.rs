@@ -0,0 +1,43 @@
+use crate::projection::ColorSources;
+use crate::texture::{self, Texture};
+use crate::{ConvertOptions, McBone};
+
+// ================= WIREFRAME PREVIEW =================
+//
+// `texture_layout = "preview"` is a debug view of the cube decomposition:
+// every cube is shrunk slightly (negative inflate) so the seams between cubes
+// show, and painted a color of its own from a small palette, so wasteful
+// regions (many thin cubes where a few would do) stand out in Blockbench.
+// Consecutive cubes get colors far apart on the color wheel; since greedy
+// meshing emits neighbours close together, touching cubes rarely match.
+
+const PALETTE_SIZE: usize = 12;
+/// Inset on every side, in geometry units.
+const INSET: f32 = 0.05;
+
+/// Fully saturated-ish color at `hue` (0-1) around the color wheel.
+fn hue_color(hue: f32) -> [u8; 4] {
+    let channel = |offset: f32| {
+        let k = (hue * 6.0 + offset) % 6.0;
+        let v = 1.0 - 0.65 * (k.min(4.0 - k).clamp(0.0, 1.0));
+        (v * 235.0) as u8
+    };
+    [channel(5.0), channel(3.0), channel(1.0), 255]
+}
+
+/// Shrinks and colors every cube of `bones`; returns the palette texture.
+pub fn apply_preview(bones: &mut [McBone]) -> Texture {
+    let palette: Vec<[u8; 4]> = (0..PALETTE_SIZE)
+        .map(|i| hue_color((i as f32 * 0.618_034).fract()))
+        .collect();
+
+    for (i, cube) in bones.iter_mut().flat_map(|b| &mut b.cubes).enumerate() {
+        // Thin cubes keep at least half their thickness
+        let thinnest = cube.size.iter().cloned().fold(f32::INFINITY, f32::min);
+        cube.inflate = Some(-INSET.min(thinnest / 4.0));
+        cube.color = Some(palette[i % PALETTE_SIZE]);
+    }
+
+    // Single-color faces share one texel per palette color
+    texture::bake_atlas(bones, &ConvertOptions { uv_sharing: true, ..Default::default() }, &ColorSources::default())
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::messages::{failed, Message};
+use crate::palette::PaletteFilter;
+use crate::{ConvertOptions, OutputFormat, TextureLayout};
+
+// ================= CONVERSION PROFILES =================
+//
+// Sensible defaults differ by target: entity geometry is rendered every frame
+// and wants few cubes and a compact atlas, while a structure is built once
+// and wants solid fill and blocks a player can keep. Each output format has
+// its own option set; picking a format in the app switches to its set and
+// changes made there are saved back to it. Formats never saved start from
+// the built-in set of their target.
+
+pub type Profiles = HashMap<OutputFormat, ConvertOptions>;
+
+/// The starting options of `format` before the user changes any.
+pub fn builtin(format: OutputFormat) -> ConvertOptions {
+    let options = ConvertOptions { format, ..Default::default() };
+    match format {
+        // Entities: faces sharing atlas space
+        OutputFormat::Geometry | OutputFormat::McPack | OutputFormat::McAddon | OutputFormat::DevPacks | OutputFormat::McWorld
+        | OutputFormat::Bbmodel => {
+            ConvertOptions { texture_layout: TextureLayout::Atlas, uv_sharing: true, ..options }
+        }
+        // Structures: solid, from blocks that stay put in survival
+        OutputFormat::McStructure | OutputFormat::Datapack | OutputFormat::Schematic | OutputFormat::StructureNbt
+        | OutputFormat::Schem => ConvertOptions {
+            fill_interior: true,
+            palette_filters: vec![PaletteFilter::SurvivalObtainable, PaletteFilter::NoFallingBlocks],
+            ..options
+        },
+        // Java models: a compact atlas
+        OutputFormat::Jem | OutputFormat::BlockModel => ConvertOptions { texture_layout: TextureLayout::Atlas, uv_sharing: true, ..options },
+    }
+}
+
+/// Reads the saved profiles. A missing or unreadable file means there are none.
+pub fn load(file: &Path) -> Profiles {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// The saved options of `format`, else its built-in ones.
+pub fn get(file: &Path, format: OutputFormat) -> ConvertOptions {
+    let options = load(file).remove(&format).unwrap_or_else(|| builtin(format));
+    ConvertOptions { format, ..options }
+}
+
+/// Saves `options` as the profile of their format; `None` drops the saved
+/// profile so the built-in one applies again.
+pub fn save(file: &Path, format: OutputFormat, options: Option<ConvertOptions>) -> Result<(), Message> {
+    let mut profiles = load(file);
+    match options {
+        Some(options) => profiles.insert(format, ConvertOptions { format, ..options }),
+        None => profiles.remove(&format),
+    };
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(failed("error.create_folder"))?;
+    }
+    fs::write(file, serde_json::to_string_pretty(&profiles).unwrap()).map_err(failed("error.write_profiles"))
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use glam::{IVec2, IVec3, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Message;
+use crate::texture::Texture;
+use crate::{McBone, McCube};
+
+// ================= IMAGE PROJECTION =================
+//
+// Colors the atlas from an image instead of the material colors, for meshes
+// without UVs (scans with a separate photo, say). The image is stretched over
+// the model's bounds: `planar` projects it straight along one axis (front to
+// back along Z by default), `triplanar` along whichever axis each face points
+// along, so sides aren't smeared, and `spherical` wraps it around the center
+// like a globe. Each texel averages the part of the image its voxel covers.
+//
+// Objects without UVs whose material has a texture get that texture the same
+// way, triplanar over the cubes of the material, instead of a flat color. An
+// image projected over the whole model wins over material textures.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionMode {
+    #[default]
+    Planar,
+    Triplanar,
+    Spherical,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionAxis {
+    X,
+    Y,
+    #[default]
+    Z,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Projection {
+    /// PNG projected onto the model.
+    pub image: String,
+    #[serde(default)]
+    pub mode: ProjectionMode,
+    /// Axis `planar` projects along.
+    #[serde(default)]
+    pub axis: ProjectionAxis,
+}
+
+pub struct Projector {
+    image: Texture,
+    mode: ProjectionMode,
+    axis: ProjectionAxis,
+    min: Vec3,
+    extent: Vec3,
+}
+
+/// Image coordinates (0-1, v downwards) of `point` seen along `axis`.
+fn planar(point: Vec3, axis: ProjectionAxis) -> Vec2 {
+    match axis {
+        ProjectionAxis::X => Vec2::new(point.z, 1.0 - point.y),
+        ProjectionAxis::Y => Vec2::new(point.x, point.z),
+        ProjectionAxis::Z => Vec2::new(point.x, 1.0 - point.y),
+    }
+}
+
+/// Size of one voxel in image coordinates along `axis`.
+fn footprint(extent: Vec3, axis: ProjectionAxis) -> Vec2 {
+    match axis {
+        ProjectionAxis::X => Vec2::new(1.0 / extent.z, 1.0 / extent.y),
+        ProjectionAxis::Y => Vec2::new(1.0 / extent.x, 1.0 / extent.z),
+        ProjectionAxis::Z => Vec2::new(1.0 / extent.x, 1.0 / extent.y),
+    }
+}
+
+impl Projector {
+    /// Loads the image of `projection` and fits it to the bounds of `bones`.
+    pub fn new(projection: &Projection, bones: &[McBone]) -> Result<Self, Message> {
+        let image = Texture::load_png(&projection.image)?;
+        let cubes = bones.iter().flat_map(|b| &b.cubes);
+        Ok(Self::fit(image, projection.mode, projection.axis, cubes))
+    }
+
+    fn fit<'a>(image: Texture, mode: ProjectionMode, axis: ProjectionAxis, cubes: impl Iterator<Item = &'a McCube>) -> Self {
+        let corners = cubes.flat_map(|c| [Vec3::from(c.origin), Vec3::from(c.origin) + Vec3::from(c.size)]);
+        let (min, max) = corners.fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), c| (min.min(c), max.max(c)));
+        let (min, max) = if min.is_finite() { (min, max) } else { (Vec3::ZERO, Vec3::ONE) };
+        Self { image, mode, axis, min, extent: (max - min).max(Vec3::ONE) }
+    }
+
+    /// Color of the surface of voxel `cell` facing along `normal`.
+    pub fn sample(&self, cell: IVec3, normal: IVec3) -> [u8; 4] {
+        let point = cell.as_vec3() + 0.5 + normal.as_vec3() * 0.5;
+        let local = (point - self.min) / self.extent;
+
+        let (uv, footprint) = match self.mode {
+            ProjectionMode::Planar => (planar(local, self.axis), footprint(self.extent, self.axis)),
+            ProjectionMode::Triplanar => {
+                let axis = match normal.abs() {
+                    IVec3 { x: 1, .. } => ProjectionAxis::X,
+                    IVec3 { y: 1, .. } => ProjectionAxis::Y,
+                    _ => ProjectionAxis::Z,
+                };
+                (planar(local, axis), footprint(self.extent, axis))
+            }
+            ProjectionMode::Spherical => {
+                let direction = (local - 0.5).normalize_or(Vec3::Y);
+                let uv = Vec2::new(
+                    0.5 + direction.x.atan2(direction.z) / (2.0 * PI),
+                    0.5 - direction.y.clamp(-1.0, 1.0).asin() / PI,
+                );
+                (uv, Vec2::splat(1.0 / self.extent.max_element()))
+            }
+        };
+        self.average(uv, footprint)
+    }
+
+    /// Mean color of the image over a `size` area centered on `uv`.
+    fn average(&self, uv: Vec2, size: Vec2) -> [u8; 4] {
+        let dims = Vec2::new(self.image.width as f32, self.image.height as f32);
+        let lo = ((uv - size / 2.0) * dims).floor().as_ivec2().max(IVec2::ZERO);
+        let hi = ((uv + size / 2.0) * dims).ceil().as_ivec2().min(dims.as_ivec2());
+        let hi = hi.max(lo + 1).min(dims.as_ivec2());
+        let lo = lo.min(hi - 1);
+
+        let mut sum = [0u32; 4];
+        let mut count = 0;
+        for y in lo.y..hi.y {
+            for x in lo.x..hi.x {
+                let texel = self.image.pixels[(y as u32 * self.image.width + x as u32) as usize];
+                for (s, c) in sum.iter_mut().zip(texel) {
+                    *s += c as u32;
+                }
+                count += 1;
+            }
+        }
+        sum.map(|s| (s / count.max(1)) as u8)
+    }
+}
+
+/// Images the atlas takes its colors from.
+#[derive(Default)]
+pub struct ColorSources {
+    /// Projected over the whole model.
+    pub projection: Option<Projector>,
+    /// Material textures by material index, for cubes of objects without UVs.
+    pub materials: HashMap<usize, Projector>,
+}
+
+impl ColorSources {
+    /// The user's projection, then the material textures of the cubes of
+    /// `bones`. A material texture that can't be read leaves its flat color.
+    pub fn load(projection: Option<&Projection>, bones: &[McBone], materials: &[tobj::Material]) -> Result<Self, Message> {
+        if let Some(projection) = projection {
+            return Ok(Self { projection: Some(Projector::new(projection, bones)?), ..Default::default() });
+        }
+
+        let cubes = || bones.iter().flat_map(|b| &b.cubes);
+        let mut used: Vec<usize> = cubes().filter_map(|c| c.texture).collect();
+        used.sort_unstable();
+        used.dedup();
+
+        let materials = used.into_iter()
+            .filter_map(|index| {
+                let path = materials.get(index)?.diffuse_texture.as_deref()?;
+                let image = Texture::load_png(path).ok()?;
+                let cubes = cubes().filter(|c| c.texture == Some(index));
+                Some((index, Projector::fit(image, ProjectionMode::Triplanar, ProjectionAxis::Z, cubes)))
+            })
+            .collect();
+        Ok(Self { projection: None, materials })
+    }
+
+    pub fn for_cube(&self, cube: &McCube) -> Option<&Projector> {
+        self.projection.as_ref().or_else(|| self.materials.get(&cube.texture?))
+    }
+}
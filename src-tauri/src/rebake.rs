@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::messages::Message;
+use crate::{projection, ConvertOptions, Scene, TextureLayout, Voxelized};
+
+// ================= TEXTURE REBAKE =================
+//
+// Iterating on palettes, sharing and shading shouldn't cost a voxelization
+// each time. A successful conversion keeps its loaded scene and voxelized
+// geometry in memory under the `result_id` it reports; rebaking takes the
+// geometry as it was before any color was locked or texel baked, runs the
+// palette, atlas and export steps again with new texture options and writes
+// over the old outputs. Reconverting a detail region replaces the kept
+// geometry with the merged one. Only the last few results are kept.
+
+const MAX_RESULTS: usize = 4;
+
+static RESULTS: Mutex<VecDeque<(u64, Arc<CachedResult>)>> = Mutex::new(VecDeque::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The options that only change colors and the texture, as applied to a
+/// kept geometry. Options left out keep their value from the latest bake;
+/// `null` clears the optional ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextureOptions {
+    pub texture_layout: Option<TextureLayout>,
+    pub uv_sharing: Option<bool>,
+    pub uv_share_tolerance: Option<u8>,
+    pub sample_textures: Option<bool>,
+    pub texel_density: Option<u32>,
+    #[serde(deserialize_with = "given")]
+    pub atlas_budget: Option<Option<u32>>,
+    pub ambient_occlusion: Option<f32>,
+    #[serde(deserialize_with = "given")]
+    pub light_direction: Option<Option<[f32; 3]>>,
+    #[serde(deserialize_with = "given")]
+    pub projection: Option<Option<projection::Projection>>,
+    #[serde(deserialize_with = "given")]
+    pub palette_lock: Option<Option<String>>,
+    pub export_palette: Option<bool>,
+    pub cube_colors: Option<bool>,
+}
+
+/// A field that is present, `null` included.
+fn given<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<Option<T>>, D::Error> {
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Whether atlas texels are painted from the material textures, which
+/// decides the surfaces kept with the cubes when voxelizing.
+fn samples_textures(options: &ConvertOptions) -> bool {
+    options.sample_textures && options.texture_layout == TextureLayout::Atlas
+}
+
+impl TextureOptions {
+    /// `options` with the given texture options in place of its own. Fails
+    /// when they switch texture sampling on or off, which the kept geometry
+    /// can't follow.
+    pub fn apply(&self, options: &ConvertOptions) -> Result<ConvertOptions, Message> {
+        let texture = self.clone();
+        let mut applied = options.clone();
+        macro_rules! set {
+            ($($field:ident),*) => {
+                $(if let Some(value) = texture.$field { applied.$field = value; })*
+            };
+        }
+        set!(
+            texture_layout, uv_sharing, uv_share_tolerance, sample_textures, texel_density, atlas_budget,
+            ambient_occlusion, light_direction, projection, palette_lock, export_palette, cube_colors
+        );
+        if samples_textures(&applied) != samples_textures(options) {
+            return Err(Message::new("error.rebake_sampling_changed"));
+        }
+        Ok(applied)
+    }
+}
+
+/// A converted model kept for rebaking.
+pub struct CachedResult {
+    pub scene: Scene,
+    pub model_name: String,
+    pub output_dir: String,
+    pub scale: f32,
+    /// Geometry of the latest bake.
+    voxelized: Mutex<Voxelized>,
+    /// Options of the latest bake.
+    options: Mutex<ConvertOptions>,
+}
+
+impl CachedResult {
+    pub fn new(scene: Scene, voxelized: Voxelized, model_name: &str, output_dir: &str, scale: f32, options: &ConvertOptions) -> Self {
+        Self {
+            scene,
+            voxelized: Mutex::new(voxelized),
+            model_name: model_name.to_string(),
+            output_dir: output_dir.to_string(),
+            scale,
+            options: Mutex::new(options.clone()),
+        }
+    }
+
+    pub fn options(&self) -> ConvertOptions {
+        self.options.lock().unwrap().clone()
+    }
+
+    pub fn set_options(&self, options: ConvertOptions) {
+        *self.options.lock().unwrap() = options;
+    }
+
+    pub fn voxelized(&self) -> Voxelized {
+        self.voxelized.lock().unwrap().clone()
+    }
+
+    pub fn set_voxelized(&self, voxelized: Voxelized) {
+        *self.voxelized.lock().unwrap() = voxelized;
+    }
+}
+
+/// Keeps `result`, dropping the oldest beyond the limit; returns its id.
+pub fn store(result: CachedResult) -> u64 {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut results = RESULTS.lock().unwrap();
+    results.push_back((id, Arc::new(result)));
+    while results.len() > MAX_RESULTS {
+        results.pop_front();
+    }
+    id
+}
+
+pub fn get(id: u64) -> Option<Arc<CachedResult>> {
+    RESULTS.lock().unwrap().iter().find(|(i, _)| *i == id).map(|(_, r)| r.clone())
+}
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use glam::{IVec3, Vec3};
+
+use crate::{McBone, McCube};
+
+// ================= REGION BONES =================
+//
+// Very large props are easier to work with when parts of them can be hidden,
+// shown or animated on their own. With `region_size`, space is divided into
+// regions (cubes of that many blocks, 16 geometry units each); cubes crossing
+// a region border are cut along it, rotated ones go to the region of their
+// center. The cubes of each bone in each region become a child bone named
+// `<bone>_<x>_<y>_<z>` after its region. The original bone stays as the
+// parent, keeping its pivot, binding and locators, so it still moves the
+// whole object. Bones that fit in a single region are left alone.
+
+const UNITS_PER_BLOCK: f32 = 16.0;
+
+/// Pieces of `cube` in each region it overlaps.
+fn cut(cube: McCube, region_units: f32) -> Vec<(IVec3, McCube)> {
+    let min = Vec3::from(cube.origin);
+    let max = min + Vec3::from(cube.size);
+    if cube.rotation.is_some() {
+        return vec![(((min + max) / 2.0 / region_units).floor().as_ivec3(), cube)];
+    }
+
+    let first = (min / region_units).floor().as_ivec3();
+    // A cube ending exactly on a border doesn't reach into the next region
+    let last = (max / region_units).ceil().as_ivec3() - IVec3::ONE;
+    let mut pieces = Vec::new();
+    for x in first.x..=last.x {
+        for y in first.y..=last.y {
+            for z in first.z..=last.z {
+                let region = IVec3::new(x, y, z);
+                let lo = min.max(region.as_vec3() * region_units);
+                let hi = max.min((region + IVec3::ONE).as_vec3() * region_units);
+                if (hi - lo).min_element() > 0.0 {
+                    pieces.push((region, McCube { origin: lo.to_array(), size: (hi - lo).to_array(), ..cube.clone() }));
+                }
+            }
+        }
+    }
+    pieces
+}
+
+/// Splits every bone of `bones` spanning more than one region of `size` blocks.
+pub fn split_regions(bones: Vec<McBone>, size: u32) -> Vec<McBone> {
+    let region_units = size as f32 * UNITS_PER_BLOCK;
+    let mut result = Vec::with_capacity(bones.len());
+
+    for mut bone in bones {
+        let mut regions: BTreeMap<[i32; 3], Vec<_>> = BTreeMap::new();
+        let cubes = std::mem::take(&mut bone.cubes);
+        for (region, cube) in cubes.iter().cloned().flat_map(|c| cut(c, region_units)) {
+            regions.entry(region.to_array()).or_default().push(cube);
+        }
+
+        if regions.len() <= 1 {
+            bone.cubes = cubes;
+            result.push(bone);
+            continue;
+        }
+
+        let children: Vec<McBone> = regions.into_iter()
+            .map(|([x, y, z], cubes)| McBone {
+                name: format!("{}_{}_{}_{}", bone.name, x, y, z),
+                parent: Some(bone.name.clone()),
+                pivot: bone.pivot,
+                binding: None,
+                cubes,
+                poly_mesh: None,
+                locators: BTreeMap::new(),
+            })
+            .collect();
+        result.push(bone);
+        result.extend(children);
+    }
+    result
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::job::{JobEntry, JobResult};
+use crate::messages::{failed, Message};
+use crate::ConvertOptions;
+
+// ================= CONVERSION REPORT =================
+//
+// Machine-readable summary of a batch job for asset pipelines: one record per
+// input with its settings, counts, time taken, warnings and output. The file
+// extension picks the format: `.csv` gets one row per input (options as a
+// JSON cell, messages in English, warnings joined with `; `), anything else a
+// JSON document with message keys.
+
+#[derive(Serialize)]
+struct ReportEntry<'a> {
+    path: &'a str,
+    output_dir: &'a str,
+    scale: f32,
+    options: &'a ConvertOptions,
+    success: bool,
+    message: &'a Message,
+    output_path: Option<&'a str>,
+    voxel_count: usize,
+    cube_count: usize,
+    seconds: f64,
+    warnings: &'a [Message],
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    converted: usize,
+    failed: usize,
+    seconds: f64,
+    files: Vec<ReportEntry<'a>>,
+}
+
+const CSV_HEADER: &str = "path,output_dir,scale,success,message,output_path,voxel_count,cube_count,seconds,warnings,options";
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(entry: &ReportEntry) -> String {
+    let options = serde_json::to_string(entry.options).unwrap();
+    [
+        csv_field(entry.path),
+        csv_field(entry.output_dir),
+        entry.scale.to_string(),
+        entry.success.to_string(),
+        csv_field(&entry.message.text()),
+        csv_field(entry.output_path.unwrap_or("")),
+        entry.voxel_count.to_string(),
+        entry.cube_count.to_string(),
+        format!("{:.3}", entry.seconds),
+        csv_field(&entry.warnings.iter().map(Message::text).collect::<Vec<_>>().join("; ")),
+        csv_field(&options),
+    ]
+    .join(",")
+}
+
+/// Writes the report for a finished job to `path`.
+pub fn write_report(path: &Path, entries: &[JobEntry], results: &[JobResult]) -> Result<(), Message> {
+    let records: Vec<ReportEntry> = entries.iter().zip(results)
+        .map(|(entry, job)| ReportEntry {
+            path: &entry.path,
+            output_dir: &entry.output_dir,
+            scale: entry.scale,
+            options: &entry.options,
+            success: job.result.success,
+            message: &job.result.message,
+            output_path: job.result.output_path.as_deref(),
+            voxel_count: job.result.voxel_count,
+            cube_count: job.result.cube_count,
+            seconds: job.elapsed.as_secs_f64(),
+            warnings: &job.result.warnings,
+        })
+        .collect();
+
+    let is_csv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let text = if is_csv {
+        let mut lines = vec![CSV_HEADER.to_string()];
+        lines.extend(records.iter().map(csv_row));
+        lines.join("\n") + "\n"
+    } else {
+        let failed = records.iter().filter(|r| !r.success).count();
+        let report = Report {
+            converted: records.len() - failed,
+            failed,
+            seconds: results.iter().map(|r| r.elapsed.as_secs_f64()).sum(),
+            files: records,
+        };
+        serde_json::to_string_pretty(&report).unwrap()
+    };
+
+    fs::write(path, text).map_err(failed("error.write_report"))
+}
@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::rasterize;
+use crate::messages::Message;
+use crate::{run_greedy_meshing, McBone};
+
+// ================= HUMANOID AUTO-RIG (EXPERIMENTAL) =================
+//
+// Re-splits the finished geometry into standard Bedrock humanoid bones so the
+// model can be animated like a player. The cubes of all bones are rasterized
+// back into voxels; each voxel goes to the first region containing its center
+// (or the nearest region when none does), and every region is greedy-meshed
+// into its own bone.
+//
+// Regions are boxes in fractions of the model bounds (x and z from the -X/-Z
+// side, y from the feet). The defaults cut a standing figure by player
+// proportions: legs the lower 3/8, head the top 1/4, arms the outer quarters
+// of the band in between. Bedrock's `rightArm`/`rightLeg` are on the -X side.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RigRegion {
+    pub bone: String,
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+const HIP: f32 = 0.375;
+const NECK: f32 = 0.75;
+
+fn default_regions() -> Vec<RigRegion> {
+    let region = |bone: &str, min: [f32; 3], max: [f32; 3]| RigRegion { bone: bone.to_string(), min, max };
+    vec![
+        region("head", [0.0, NECK, 0.0], [1.0, 1.0, 1.0]),
+        region("rightArm", [0.0, HIP, 0.0], [0.25, NECK, 1.0]),
+        region("leftArm", [0.75, HIP, 0.0], [1.0, NECK, 1.0]),
+        region("body", [0.25, HIP, 0.0], [0.75, NECK, 1.0]),
+        region("rightLeg", [0.0, 0.0, 0.0], [0.5, HIP, 1.0]),
+        region("leftLeg", [0.5, 0.0, 0.0], [1.0, HIP, 1.0]),
+    ]
+}
+
+fn distance_to(region: &RigRegion, p: Vec3) -> f32 {
+    let closest = p.clamp(Vec3::from(region.min), Vec3::from(region.max));
+    closest.distance_squared(p)
+}
+
+/// Splits `bones` into humanoid bones; `regions` empty uses player proportions.
+pub fn rig_humanoid(bones: &[McBone], regions: &[RigRegion]) -> Result<Vec<McBone>, Message> {
+    let defaults;
+    let regions = if regions.is_empty() {
+        defaults = default_regions();
+        &defaults
+    } else {
+        regions
+    };
+
+    let cells = rasterize(bones);
+    let mut iter = cells.iter();
+    let Some(&first) = iter.next() else {
+        return Err(Message::new("error.rig_needs_cubes"));
+    };
+    let (min, max) = iter.fold((first, first), |(min, max), &v| (min.min(v), max.max(v)));
+    let extent = (max + IVec3::ONE - min).as_vec3();
+
+    // Several regions may name the same bone
+    let mut parts: HashMap<&str, HashSet<IVec3, RandomState>, RandomState> = HashMap::default();
+    for &cell in &cells {
+        let p = (cell.as_vec3() + 0.5 - min.as_vec3()) / extent;
+        let inside = regions.iter().position(|r| p.cmpge(Vec3::from(r.min)).all() && p.cmplt(Vec3::from(r.max)).all());
+        let region = inside.unwrap_or_else(|| {
+            (0..regions.len())
+                .min_by(|&a, &b| distance_to(&regions[a], p).total_cmp(&distance_to(&regions[b], p)))
+                .unwrap_or(0)
+        });
+        parts.entry(regions[region].bone.as_str()).or_default().insert(cell);
+    }
+
+    let has_body = parts.contains_key("body");
+    let mut rigged = Vec::new();
+    for region in regions {
+        let Some(voxels) = parts.remove(region.bone.as_str()) else { continue };
+
+        // Joints: the head turns at its base, everything else hangs from its top
+        let lo = voxels.iter().fold(IVec3::MAX, |a, &v| a.min(v));
+        let hi = voxels.iter().fold(IVec3::MIN, |a, &v| a.max(v)) + IVec3::ONE;
+        let center = (lo + hi).as_vec3() / 2.0;
+        let joint_y = if region.bone == "head" { lo.y } else { hi.y };
+
+        let parent = match region.bone.as_str() {
+            "head" | "rightArm" | "leftArm" if has_body => Some("body".to_string()),
+            _ => None,
+        };
+
+        rigged.push(McBone {
+            name: region.bone.clone(),
+            parent,
+            pivot: [center.x.round() as i32, joint_y, center.z.round() as i32],
+            binding: None,
+            cubes: run_greedy_meshing(&voxels),
+            poly_mesh: None,
+            locators: BTreeMap::new(),
+        });
+    }
+
+    Ok(rigged)
+}
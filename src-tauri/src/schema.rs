@@ -0,0 +1,325 @@
+use serde_json::{json, Map, Value};
+
+use crate::adaptive::MAX_LEVELS;
+use crate::texture::{MAX_ATLAS_BUDGET, MAX_TEXEL_DENSITY};
+use crate::ConvertOptions;
+
+// ================= OPTION SCHEMA =================
+//
+// Describes `ConvertOptions` as a JSON Schema (draft 2020-12) so the frontend
+// and third-party UIs can build forms and validate input without hardcoding
+// the option list. Defaults are taken from `ConvertOptions::default()`; types,
+// ranges and descriptions are listed here and must follow the struct.
+
+const FORMATS: &[&str] = &["geometry", "mcstructure", "datapack", "schematic", "structure_nbt", "schem", "mcworld", "mcpack", "mcaddon", "jem", "dev_packs", "bbmodel", "block_model"];
+
+fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+fn integer(min: i64, max: Option<i64>) -> Value {
+    let mut schema = json!({ "type": "integer", "minimum": min });
+    if let Some(max) = max {
+        schema["maximum"] = json!(max);
+    }
+    schema
+}
+
+fn number(min: f64, max: f64) -> Value {
+    json!({ "type": "number", "minimum": min, "maximum": max })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn choice(values: &[&str]) -> Value {
+    json!({ "type": "string", "enum": values })
+}
+
+fn vec3(item: &str) -> Value {
+    json!({ "type": "array", "items": { "type": item }, "minItems": 3, "maxItems": 3 })
+}
+
+fn list(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+/// Also accepts `null` for "not set".
+fn optional(mut schema: Value) -> Value {
+    let kind = schema["type"].clone();
+    schema["type"] = json!([kind, "null"]);
+    schema
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Names, types and descriptions of every option, in struct order.
+fn options() -> Vec<(&'static str, Value, &'static str)> {
+    vec![
+        ("format", choice(FORMATS), "Output format."),
+        (
+            "extra_formats",
+            list(choice(FORMATS)),
+            "Formats written besides format from the same voxelized model.",
+        ),
+        (
+            "output_template",
+            optional(string()),
+            "Folder for the outputs with {output_dir}, {model}, {scale}, {format} and {date} filled in.",
+        ),
+        ("geometry_mode", choice(&["cubes", "poly_mesh"]), "Voxelize into cubes or keep the source triangles as a poly_mesh."),
+        ("rotation", optional(vec3("number")), "Rotate the mesh before converting, in degrees around X, Y and Z."),
+        ("auto_orient", boolean(), "Apply the suggested rotation (largest flat face down) instead of rotation."),
+        ("decimate", boolean(), "In poly_mesh mode, merge vertices that share a voxel cell."),
+        (
+            "voxel_origin",
+            choice(&["source", "centered", "positive"]),
+            "How voxel coordinates map to cube origins: as in the source, centered on the model, or in positive space.",
+        ),
+        (
+            "cell_size",
+            optional(vec3("number")),
+            "Size of a voxel cell along X, Y and Z relative to a cubic one, e.g. [1, 0.5, 1] for half-height voxels.",
+        ),
+        (
+            "adaptive_levels",
+            integer(0, Some(MAX_LEVELS as i64)),
+            "Octree depth for adaptive voxelization; 0 uses the uniform grid.",
+        ),
+        (
+            "spill_slab",
+            integer(0, None),
+            "Spill surface voxels to temporary files in slabs of this many Y layers and mesh them one slab at a time, for scales beyond memory; 0 keeps the grid in memory.",
+        ),
+        ("detect_boxes", boolean(), "Emit parts that are exact axis-aligned boxes as cubes at full precision."),
+        (
+            "rigid_copies",
+            boolean(),
+            "Voxelize objects that are moved or rotated copies of an earlier object once, turning its cubes into place.",
+        ),
+        ("instance_parts", boolean(), "Voxelize parts repeated inside an object once and stamp the voxels at every copy."),
+        ("weld_gaps", boolean(), "Fill empty cells between two filled ones, closing one-cell cracks of thin double-walled meshes."),
+        ("normal_offset", number(0.0, 1.0), "Share of the outward inflation of surface voxels trimmed against the triangle planes; 0 keeps every touched cell."),
+        ("sat_epsilon", number(-0.5, 0.5), "Share of a voxel each cell is widened by in the triangle overlap test; negative narrows."),
+        ("fill_interior", boolean(), "Fill the enclosed interior of each bone instead of keeping only its surface."),
+        (
+            "hollow_pattern",
+            choice(&["solid", "lattice", "honeycomb"]),
+            "Keep only a pattern of the filled interior to save blocks, leaving the surface intact.",
+        ),
+        ("hollow_spacing", integer(0, None), "Cells between the beams or walls of hollow_pattern (0 uses the default of 4)."),
+        ("fix_normals", boolean(), "Wind triangles consistently and outwards, for meshes with flipped faces."),
+        ("double_sided", boolean(), "Treat faces as visible from both sides."),
+        ("fit_slopes", boolean(), "Replace stair-stepped slopes with rotated cubes (22.5° steps)."),
+        ("script", optional(string()), "Path to a rhai script run on each bone's voxels before meshing."),
+        (
+            "texture_layout",
+            choice(&["default", "skin", "atlas", "preview", "clay"]),
+            "How cube faces map onto the texture; preview shrinks and colors each cube to inspect the decomposition, clay shades one color by face orientation.",
+        ),
+        (
+            "shared_atlas",
+            optional(string()),
+            "In a job, inputs naming the same atlas are baked into one texture <name>.png instead of one per model.",
+        ),
+        ("uv_sharing", boolean(), "In the atlas, let faces that look the same share one texture region."),
+        (
+            "uv_share_tolerance",
+            integer(0, Some(255)),
+            "Largest per-channel difference at which uv_sharing treats colors as the same.",
+        ),
+        ("sample_textures", boolean(), "In the atlas, paint faces from the material texture through the mesh UVs."),
+        (
+            "texel_density",
+            integer(0, Some(MAX_TEXEL_DENSITY as i64)),
+            "Atlas texels per geometry unit along each face side (0 means 1), for detail finer than a voxel.",
+        ),
+        (
+            "atlas_budget",
+            optional(integer(16, Some(MAX_ATLAS_BUDGET as i64))),
+            "Fixed atlas side in texels; faces get texels in proportion to their size to fill it, instead of texel_density each.",
+        ),
+        ("ambient_occlusion", number(0.0, 1.0), "Strength of ambient occlusion baked into the atlas; 0 turns it off."),
+        ("light_direction", optional(vec3("number")), "Direction towards a fixed light baked into the atlas."),
+        (
+            "projection",
+            optional(object(
+                json!({
+                    "image": string(),
+                    "mode": choice(&["planar", "triplanar", "spherical"]),
+                    "axis": choice(&["x", "y", "z"]),
+                }),
+                &["image"],
+            )),
+            "Image projected onto the model as the atlas colors, for meshes without UVs.",
+        ),
+        ("cube_colors", boolean(), "Also write <model>.colors.json with the material color of each cube."),
+        ("export_palette", boolean(), "Also write <model>.palette.json with the colors of the converted model."),
+        (
+            "palette_lock",
+            optional(string()),
+            "Palette file (as written by export_palette) whose colors every cube color and texel is snapped to.",
+        ),
+        ("auto_rig", boolean(), "Experimental: re-split the model into humanoid bones so it can be animated."),
+        (
+            "rig_regions",
+            list(object(
+                json!({ "bone": string(), "min": vec3("number"), "max": vec3("number") }),
+                &["bone", "min", "max"],
+            )),
+            "Bone regions for auto_rig in fractions of the model bounds; empty uses player proportions.",
+        ),
+        (
+            "cut_planes",
+            list(object(
+                json!({
+                    "name": string(),
+                    "point": vec3("number"),
+                    "normal": vec3("number"),
+                    "bone": optional(string()),
+                }),
+                &["name", "point", "normal"],
+            )),
+            "Planes splitting what lies in front of them off into a bone of their name, pivoting on the plane.",
+        ),
+        ("joint_pivots", boolean(), "Pivot bones split off a parent at the middle of where they touch it."),
+        (
+            "detail_regions",
+            list(object(
+                json!({
+                    "name": optional(string()),
+                    "min": vec3("number"),
+                    "max": vec3("number"),
+                    "scale": number(0.0, 1024.0),
+                }),
+                &["min", "max", "scale"],
+            )),
+            "Boxes (geometry units, before voxel_origin moves the model) voxelized again at their own scale; named ones get a bone of their name.",
+        ),
+        (
+            "region_size",
+            integer(0, None),
+            "Split bones into child bones per region this many blocks wide; 0 keeps bones whole.",
+        ),
+        ("sort_cubes", boolean(), "Order bones by name and cubes by region, largest first, so output is render-friendly and the same every run."),
+        (
+            "variants",
+            list(object(
+                json!({
+                    "name": string(),
+                    "erosion": number(0.0, 1.0),
+                    "seed": integer(0, Some(u32::MAX as i64)),
+                    "mirror": boolean(),
+                }),
+                &["name"],
+            )),
+            "Extra geometries in the same geo.json as geometry.<model>.<name>, with erosion eating away that share of the surface.",
+        ),
+        ("mirrored_copy", boolean(), "Also write an X-mirrored copy as the variant geometry.<model>.mirrored."),
+        ("block", optional(string()), "Block placed for every voxel in world exports."),
+        (
+            "orient_blocks",
+            boolean(),
+            "Orient block along the model when it has a direction: logs and pillars along the solid, glazed terracotta towards the surface.",
+        ),
+        (
+            "block_variation",
+            list(string()),
+            "Similar blocks mixed in among block in world exports, so large flat areas don't look monotone.",
+        ),
+        (
+            "variation_amount",
+            number(0.0, 1.0),
+            "Share of full blocks replaced by block_variation; 0 mixes all of them evenly.",
+        ),
+        ("variation_size", integer(0, None), "Width in blocks of the patches that vary together; 0 varies single blocks."),
+        ("partial_blocks", boolean(), "In world exports, smooth slopes with stairs and slabs."),
+        ("stair_block", optional(string()), "Stair block used by partial_blocks."),
+        ("slab_block", optional(string()), "Slab block used by partial_blocks."),
+        (
+            "scaffolding",
+            integer(0, None),
+            "In world exports, add floors and pillars every this many blocks inside the model so it can be built by hand; 0 adds none.",
+        ),
+        ("support_block", optional(string()), "Block the scaffolding is made of."),
+        ("decorations", list(string()), "Blocks such as grass and flowers scattered on top of the surface in world exports."),
+        ("decoration_density", number(0.0, 1.0), "Share of top blocks that get a decoration; 0 uses 0.3."),
+        (
+            "empty_cells",
+            choice(&["air", "keep", "dry_interior"]),
+            "What empty cells of world exports do to the world: replace it with air, keep it, or keep it outside and place air inside.",
+        ),
+        (
+            "palette_filters",
+            list(choice(&["survival_obtainable", "no_falling_blocks", "no_tile_entities"])),
+            "Restrictions the blocks set in the options must meet.",
+        ),
+        ("anchor", choice(&["corner", "center", "lowest"]), "Point of the model placed at the target position in world exports."),
+        ("offset", vec3("integer"), "Blocks added to the anchored position in world exports."),
+        ("progressive_build", choice(&["off", "layers", "chunks"]), "In datapack exports, place the model step by step over time."),
+        ("build_interval", integer(0, None), "Ticks between progressive build steps (0 uses the default of 10)."),
+        (
+            "split_chunks",
+            integer(0, None),
+            "Split world exports into chunk-aligned pieces this many chunks wide; 0 only splits large structures.",
+        ),
+        (
+            "game_folder",
+            optional(string()),
+            "com.mojang folder dev_packs exports go to; unset uses the first installation found.",
+        ),
+        ("wearable_slot", optional(string()), "In pack exports, also add a wearable item for this equipment slot."),
+        (
+            "bone_bindings",
+            json!({ "type": "object", "additionalProperties": string() }),
+            "Player bone each model bone follows when worn; unlisted bones named like a player bone follow it.",
+        ),
+        ("cem_part_files", boolean(), "In JEM exports, write each part to its own .jpm file."),
+        (
+            "block_model_fit",
+            choice(&["scale", "split"]),
+            "How Java block models beyond the -16 to 32 element range fit: shrunk, or split into a model per block.",
+        ),
+        ("sponge_version", choice(&["v2", "v3"]), "Sponge schematic version of .schem exports; v2 for older tools."),
+        (
+            "locators",
+            list(object(
+                json!({ "name": string(), "position": vec3("number"), "bone": optional(string()) }),
+                &["name", "position"],
+            )),
+            "Locators added besides those marked by locator_<name> OBJ objects.",
+        ),
+        (
+            "post_convert",
+            list(string()),
+            "Program and arguments run after each successful conversion, with the output paths appended.",
+        ),
+        ("dry_run", boolean(), "Run the whole conversion and report the results without writing anything."),
+        ("threads", integer(0, None), "Worker threads for voxelizing; 0 uses one per core."),
+        ("low_priority", boolean(), "Run the workers at background priority, so other programs stay responsive."),
+    ]
+}
+
+pub fn option_schema() -> Value {
+    let defaults = serde_json::to_value(ConvertOptions::default()).unwrap();
+    let options = options();
+    debug_assert_eq!(options.len(), defaults.as_object().map_or(0, Map::len), "option schema out of date");
+
+    let mut properties = Map::new();
+    for (name, mut schema, description) in options {
+        schema["description"] = json!(description);
+        schema["default"] = defaults[name].clone();
+        properties.insert(name.to_string(), schema);
+    }
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "ConvertOptions",
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
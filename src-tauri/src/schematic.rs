@@ -0,0 +1,222 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use glam::IVec3;
+
+use crate::blocks::{Axis, BlockGrid, BlockShape, EmptyCells, Facing};
+use crate::messages::{failed, Message};
+use crate::nbt::{self, compound, Compression, Endian, Tag};
+use crate::ConvertOptions;
+
+// ================= LEGACY .SCHEMATIC (MCEDIT) =================
+//
+// Gzipped big-endian NBT with numeric pre-1.13 block ids. Blocks and Data are
+// byte arrays in YZX order (x fastest). Block names from the options are
+// mapped to ids through a small table of common blocks; `id` or `id:data`
+// can be given directly for anything else. The anchor is stored as the
+// WorldEdit paste offset. Empty cells are air, or structure voids when
+// `empty_cells` keeps them.
+
+const DEFAULT_BLOCK: &str = "minecraft:stone";
+const DEFAULT_STAIR_BLOCK: &str = "minecraft:cobblestone_stairs";
+const DEFAULT_SLAB_BLOCK: &str = "minecraft:stone_slab";
+const DEFAULT_SUPPORT_BLOCK: &str = "minecraft:oak_planks";
+const MAX_SIZE: i32 = i16::MAX as i32;
+/// Legacy id of `minecraft:structure_void`.
+const STRUCTURE_VOID: u8 = 217;
+
+const COLORS: [&str; 16] = [
+    "white", "orange", "magenta", "light_blue", "yellow", "lime", "pink", "gray",
+    "light_gray", "cyan", "purple", "blue", "brown", "green", "red", "black",
+];
+
+const LEGACY_IDS: &[(&str, u8, u8)] = &[
+    ("stone", 1, 0),
+    ("granite", 1, 1),
+    ("diorite", 1, 3),
+    ("andesite", 1, 5),
+    ("grass_block", 2, 0),
+    ("dirt", 3, 0),
+    ("cobblestone", 4, 0),
+    ("oak_planks", 5, 0),
+    ("spruce_planks", 5, 1),
+    ("birch_planks", 5, 2),
+    ("jungle_planks", 5, 3),
+    ("acacia_planks", 5, 4),
+    ("dark_oak_planks", 5, 5),
+    ("sand", 12, 0),
+    ("gravel", 13, 0),
+    ("oak_log", 17, 0),
+    ("spruce_log", 17, 1),
+    ("birch_log", 17, 2),
+    ("jungle_log", 17, 3),
+    ("glass", 20, 0),
+    ("sandstone", 24, 0),
+    ("gold_block", 41, 0),
+    ("iron_block", 42, 0),
+    ("stone_slab", 44, 0),
+    ("sandstone_slab", 44, 1),
+    ("cobblestone_slab", 44, 3),
+    ("brick_slab", 44, 4),
+    ("stone_brick_slab", 44, 5),
+    ("quartz_slab", 44, 7),
+    ("bricks", 45, 0),
+    ("obsidian", 49, 0),
+    ("oak_stairs", 53, 0),
+    ("diamond_block", 57, 0),
+    ("cobblestone_stairs", 67, 0),
+    ("snow_block", 80, 0),
+    ("clay", 82, 0),
+    ("stone_bricks", 98, 0),
+    ("brick_stairs", 108, 0),
+    ("stone_brick_stairs", 109, 0),
+    ("oak_slab", 126, 0),
+    ("sandstone_stairs", 128, 0),
+    ("quartz_block", 155, 0),
+    ("quartz_stairs", 156, 0),
+    ("acacia_log", 162, 0),
+    ("dark_oak_log", 162, 1),
+    ("terracotta", 172, 0),
+];
+
+/// Blocks that come in the 16 dye colors, with the color as data value.
+const COLORED_IDS: &[(&str, u8)] = &[
+    ("wool", 35),
+    ("stained_glass", 95),
+    ("terracotta", 159),
+    ("concrete", 251),
+];
+
+/// First of the glazed terracotta ids, one per color in `COLORS` order; the
+/// data value is the facing.
+const GLAZED_TERRACOTTA: u8 = 235;
+
+/// Legacy (id, data) for a block name like `minecraft:oak_planks`, `35:14` or `1`.
+fn legacy_id(name: &str) -> Result<(u8, u8), Message> {
+    let unknown = || Message::new("error.unknown_legacy_block").with("block", name);
+
+    if name.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+        let (id, data) = name.split_once(':').unwrap_or((name, "0"));
+        return match (id.parse(), data.parse()) {
+            (Ok(id), Ok(data)) if data < 16 => Ok((id, data)),
+            _ => Err(unknown()),
+        };
+    }
+
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    if let Some(&(_, id, data)) = LEGACY_IDS.iter().find(|(n, ..)| *n == name) {
+        return Ok((id, data));
+    }
+    for (color_index, color) in COLORS.iter().enumerate() {
+        let Some(base) = name.strip_prefix(color).and_then(|rest| rest.strip_prefix('_')) else { continue };
+        if let Some(&(_, id)) = COLORED_IDS.iter().find(|(n, _)| *n == base) {
+            return Ok((id, color_index as u8));
+        }
+        if base == "glazed_terracotta" {
+            return Ok((GLAZED_TERRACOTTA + color_index as u8, 0));
+        }
+    }
+    Err(unknown())
+}
+
+fn legacy_block(options: &ConvertOptions, shape: BlockShape) -> Result<(u8, u8), Message> {
+    let name = |custom: &Option<String>, default: &'static str| custom.as_deref().unwrap_or(default).to_string();
+
+    match shape {
+        BlockShape::Full => legacy_id(&name(&options.block, DEFAULT_BLOCK)),
+        BlockShape::Slab => legacy_id(&name(&options.slab_block, DEFAULT_SLAB_BLOCK)),
+        BlockShape::Support => legacy_id(&name(&options.support_block, DEFAULT_SUPPORT_BLOCK)),
+        // Axis bits on top of the wood type
+        BlockShape::Pillar(axis) => {
+            let (id, data) = legacy_id(&name(&options.block, DEFAULT_BLOCK))?;
+            let bits = match axis {
+                Axis::Y => 0,
+                Axis::X => 4,
+                Axis::Z => 8,
+            };
+            Ok((id, data | bits))
+        }
+        BlockShape::Air => Ok((0, 0)),
+        BlockShape::Variation(i) => legacy_id(&options.block_variation[i]),
+        BlockShape::Decoration(i) => legacy_id(&options.decorations[i]),
+        BlockShape::Rotated(facing) => {
+            let (id, _) = legacy_id(&name(&options.block, DEFAULT_BLOCK))?;
+            let data = match facing {
+                Facing::South => 0,
+                Facing::West => 1,
+                Facing::North => 2,
+                Facing::East => 3,
+            };
+            Ok((id, data))
+        }
+        BlockShape::Stairs(facing) => {
+            let (id, _) = legacy_id(&name(&options.stair_block, DEFAULT_STAIR_BLOCK))?;
+            let data = match facing {
+                Facing::East => 0,
+                Facing::West => 1,
+                Facing::South => 2,
+                Facing::North => 3,
+            };
+            Ok((id, data))
+        }
+    }
+}
+
+/// Writes `grid` as an MCEdit schematic.
+pub fn write_schematic(grid: &BlockGrid, options: &ConvertOptions, path: &Path) -> Result<(), Message> {
+    let size = grid.size;
+    if size.max_element() > MAX_SIZE {
+        return Err(Message::new("error.schematic_too_large").with("max", MAX_SIZE));
+    }
+
+    let facings = [Facing::East, Facing::West, Facing::South, Facing::North];
+    let shapes = [BlockShape::Full, BlockShape::Slab, BlockShape::Support, BlockShape::Air]
+        .into_iter()
+        .chain(facings.map(BlockShape::Stairs))
+        .chain([Axis::X, Axis::Y, Axis::Z].map(BlockShape::Pillar))
+        .chain(facings.map(BlockShape::Rotated))
+        .chain((0..options.block_variation.len()).map(BlockShape::Variation))
+        .chain((0..options.decorations.len()).map(BlockShape::Decoration));
+    let mut ids = Vec::new();
+    for shape in shapes {
+        if grid.blocks.values().any(|&s| s == shape) {
+            ids.push((shape, legacy_block(options, shape)?));
+        }
+    }
+
+    let empty = match options.empty_cells {
+        EmptyCells::Air => 0,
+        EmptyCells::Keep | EmptyCells::DryInterior => STRUCTURE_VOID,
+    };
+    let len = grid.volume();
+    let mut blocks = vec![empty; len];
+    let mut data = vec![0u8; len];
+    for (&p, &shape) in &grid.blocks {
+        let IVec3 { x, y, z } = p;
+        let i = (y as usize * size.z as usize + z as usize) * size.x as usize + x as usize;
+        let &(_, (id, value)) = ids.iter().find(|(s, _)| *s == shape).unwrap();
+        blocks[i] = id;
+        data[i] = value;
+    }
+
+    let root = compound([
+        ("Width", Tag::Short(size.x as i16)),
+        ("Height", Tag::Short(size.y as i16)),
+        ("Length", Tag::Short(size.z as i16)),
+        ("Materials", Tag::String("Alpha".to_string())),
+        ("Blocks", Tag::ByteArray(blocks)),
+        ("Data", Tag::ByteArray(data)),
+        ("Entities", Tag::List(vec![])),
+        ("TileEntities", Tag::List(vec![])),
+        ("WEOffsetX", Tag::Int(grid.origin.x)),
+        ("WEOffsetY", Tag::Int(grid.origin.y)),
+        ("WEOffsetZ", Tag::Int(grid.origin.z)),
+    ]);
+
+    let file = File::create(path).map_err(failed("error.create_file"))?;
+    let mut writer = BufWriter::new(file);
+    nbt::write(&mut writer, "Schematic", &root, Endian::Big, Compression::Gzip)
+        .and_then(|_| writer.flush())
+        .map_err(failed("error.write_schematic"))
+}
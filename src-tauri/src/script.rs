@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+use std::fs;
+
+use ahash::RandomState;
+use glam::IVec3;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST, INT};
+
+use crate::messages::{failed, Message};
+
+// ================= VOXEL SCRIPTS =================
+//
+// A script runs once per bone, after voxelization and before greedy meshing.
+// The bone's voxels are exposed as the variable `grid`:
+//
+//   grid.get(x, y, z)         -> bool, whether the voxel is filled
+//   grid.set(x, y, z, filled)    fill (true) or clear (false) a voxel
+//   grid.count()              -> number of filled voxels
+//   grid.bounds()             -> #{ min: [x, y, z], max: [x, y, z] }, or #{} when empty
+//   grid.voxels()             -> array of [x, y, z] for every filled voxel
+//
+// `bone` holds the bone name and `scale` the voxel scale of the conversion.
+// Scripts stop after a fixed number of operations, so an endless loop fails
+// the conversion instead of hanging it.
+
+/// Operations a script may run per bone.
+const MAX_OPERATIONS: u64 = 500_000_000;
+/// Nesting of expressions, and of expressions inside functions.
+const MAX_EXPR_DEPTH: (usize, usize) = (64, 32);
+
+#[derive(Clone)]
+struct ScriptGrid {
+    voxels: HashSet<IVec3, RandomState>,
+}
+
+fn to_ivec3(x: INT, y: INT, z: INT) -> IVec3 {
+    IVec3::new(x as i32, y as i32, z as i32)
+}
+
+fn to_array(v: IVec3) -> Array {
+    vec![
+        Dynamic::from(v.x as INT),
+        Dynamic::from(v.y as INT),
+        Dynamic::from(v.z as INT),
+    ]
+}
+
+fn build_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH.0, MAX_EXPR_DEPTH.1);
+
+    engine
+        .register_type_with_name::<ScriptGrid>("Grid")
+        .register_fn("get", |grid: &mut ScriptGrid, x: INT, y: INT, z: INT| {
+            grid.voxels.contains(&to_ivec3(x, y, z))
+        })
+        .register_fn("set", |grid: &mut ScriptGrid, x: INT, y: INT, z: INT, filled: bool| {
+            let pos = to_ivec3(x, y, z);
+            if filled {
+                grid.voxels.insert(pos);
+            } else {
+                grid.voxels.remove(&pos);
+            }
+        })
+        .register_fn("count", |grid: &mut ScriptGrid| grid.voxels.len() as INT)
+        .register_fn("bounds", |grid: &mut ScriptGrid| {
+            let mut bounds = Map::new();
+            let mut iter = grid.voxels.iter();
+            if let Some(&first) = iter.next() {
+                let (min, max) = iter.fold((first, first), |(min, max), &v| (min.min(v), max.max(v)));
+                bounds.insert("min".into(), to_array(min).into());
+                bounds.insert("max".into(), to_array(max).into());
+            }
+            bounds
+        })
+        .register_fn("voxels", |grid: &mut ScriptGrid| {
+            grid.voxels.iter().map(|&v| Dynamic::from(to_array(v))).collect::<Array>()
+        });
+
+    engine
+}
+
+pub struct VoxelScript {
+    engine: Engine,
+    ast: AST,
+}
+
+impl VoxelScript {
+    pub fn load(path: &str) -> Result<Self, Message> {
+        let source = fs::read_to_string(path)
+            .map_err(failed("error.read_script"))?;
+
+        let engine = build_engine();
+        let ast = engine.compile(&source)
+            .map_err(failed("error.script"))?;
+
+        Ok(Self { engine, ast })
+    }
+
+    pub fn run(
+        &self,
+        bone: &str,
+        scale: f32,
+        voxels: HashSet<IVec3, RandomState>,
+    ) -> Result<HashSet<IVec3, RandomState>, Message> {
+        let mut scope = Scope::new();
+        scope.push("grid", ScriptGrid { voxels });
+        scope.push_constant("bone", bone.to_string());
+        scope.push_constant("scale", scale as rhai::FLOAT);
+
+        self.engine.run_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| match *e {
+                EvalAltResult::ErrorTooManyOperations(_) => {
+                    Message::new("error.script_too_long").with("bone", bone).with("limit", MAX_OPERATIONS)
+                }
+                e => Message::new("error.script_in_bone").with("bone", bone).with("error", e),
+            })?;
+
+        scope.get_value::<ScriptGrid>("grid")
+            .map(|grid| grid.voxels)
+            .ok_or_else(|| Message::new("error.script_replaced_grid").with("bone", bone))
+    }
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{failed, Message};
+use crate::{ConvertOptions, ConvertResult};
+
+// ================= SESSION RESTORE =================
+//
+// The frontend saves its state whenever the queue or settings change, so a
+// configured batch survives the app being closed. Results are kept for the
+// inputs converted so far, saved after each one, so a restored queue
+// continues with the rest even if the app crashed part way through a batch.
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    /// Input paths in queue order.
+    pub files: Vec<String>,
+    pub output_dir: String,
+    pub scale: f32,
+    pub options: ConvertOptions,
+    /// Results of the first inputs of the queue, in queue order.
+    pub results: Vec<ConvertResult>,
+}
+
+/// Reads the saved session. A missing or unreadable file means there is none.
+pub fn load(file: &Path) -> Option<Session> {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+}
+
+/// Written to a temporary file first so a crash mid-write keeps the previous session.
+pub fn save(file: &Path, session: &Session) -> Result<(), Message> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(failed("error.create_folder"))?;
+    }
+
+    let temp = file.with_extension("tmp");
+    fs::write(&temp, serde_json::to_string(session).unwrap()).map_err(failed("error.write_session"))?;
+    fs::rename(&temp, file).map_err(failed("error.write_session"))
+}
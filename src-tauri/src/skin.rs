@@ -0,0 +1,98 @@
+use glam::Vec3;
+
+use crate::messages::Message;
+use crate::{McBone, McFaceUv, McFaceUvs, McUv};
+
+// ================= SKIN TEXTURE LAYOUT =================
+//
+// Maps humanoid bones onto the standard 64×64 skin layout, so the texture can
+// be painted in any skin editor. Each bone owns the box-UV region of its skin
+// part; every cube face samples the matching face of that region, in
+// proportion to where the cube sits inside the bone's bounds. Faces use
+// per-face UVs since voxel cubes are far smaller than a skin part.
+//
+// Box-UV layout of a part at (u, v) with size (w, h, d):
+//   up (u+d, v) · down (u+d+w, v)
+//   east (u, v+d) · north (u+d, v+d) · west (u+d+w, v+d) · south (u+2d+w, v+d)
+
+struct SkinPart {
+    /// Bone names (lowercase, without separators) that use this part.
+    names: &'static [&'static str],
+    uv: [f32; 2],
+    size: [f32; 3],
+}
+
+const SKIN_PARTS: &[SkinPart] = &[
+    SkinPart { names: &["head"], uv: [0.0, 0.0], size: [8.0, 8.0, 8.0] },
+    SkinPart { names: &["hat", "headwear"], uv: [32.0, 0.0], size: [8.0, 8.0, 8.0] },
+    SkinPart { names: &["body", "torso"], uv: [16.0, 16.0], size: [8.0, 12.0, 4.0] },
+    SkinPart { names: &["jacket"], uv: [16.0, 32.0], size: [8.0, 12.0, 4.0] },
+    SkinPart { names: &["rightarm", "armright"], uv: [40.0, 16.0], size: [4.0, 12.0, 4.0] },
+    SkinPart { names: &["rightsleeve"], uv: [40.0, 32.0], size: [4.0, 12.0, 4.0] },
+    SkinPart { names: &["leftarm", "armleft"], uv: [32.0, 48.0], size: [4.0, 12.0, 4.0] },
+    SkinPart { names: &["leftsleeve"], uv: [48.0, 48.0], size: [4.0, 12.0, 4.0] },
+    SkinPart { names: &["rightleg", "legright"], uv: [0.0, 16.0], size: [4.0, 12.0, 4.0] },
+    SkinPart { names: &["rightpants"], uv: [0.0, 32.0], size: [4.0, 12.0, 4.0] },
+    SkinPart { names: &["leftleg", "legleft"], uv: [16.0, 48.0], size: [4.0, 12.0, 4.0] },
+    SkinPart { names: &["leftpants"], uv: [0.0, 48.0], size: [4.0, 12.0, 4.0] },
+];
+
+fn skin_part(bone_name: &str) -> Option<&'static SkinPart> {
+    let key: String = bone_name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    SKIN_PARTS.iter().find(|part| part.names.contains(&key.as_str()))
+}
+
+/// Rectangle of the part's texture covered by the fraction ranges `u` and `v` (0..1).
+fn face(origin: [f32; 2], size: [f32; 2], u: [f32; 2], v: [f32; 2]) -> Option<McFaceUv> {
+    Some(McFaceUv {
+        uv: [origin[0] + u[0] * size[0], origin[1] + v[0] * size[1]],
+        uv_size: [(u[1] - u[0]) * size[0], (v[1] - v[0]) * size[1]],
+    })
+}
+
+/// Assigns skin-layout UVs to every bone with a humanoid name.
+pub fn apply_skin_layout(bones: &mut [McBone]) -> Result<(), Message> {
+    let mut mapped = 0;
+
+    for bone in bones.iter_mut() {
+        let Some(part) = skin_part(&bone.name) else { continue };
+        if bone.cubes.is_empty() { continue; }
+        mapped += 1;
+
+        let min = bone.cubes.iter().map(|c| Vec3::from(c.origin)).fold(Vec3::INFINITY, Vec3::min);
+        let max = bone.cubes.iter().map(|c| Vec3::from(c.origin) + Vec3::from(c.size)).fold(Vec3::NEG_INFINITY, Vec3::max);
+        let extent = (max - min).max(Vec3::splat(f32::EPSILON));
+
+        let [u, v] = part.uv;
+        let [w, h, d] = part.size;
+
+        for cube in &mut bone.cubes {
+            let lo = (Vec3::from(cube.origin) - min) / extent;
+            let hi = (Vec3::from(cube.origin) + Vec3::from(cube.size) - min) / extent;
+
+            // Fractions as seen from outside each face, texture v running downwards
+            let x = [lo.x, hi.x];
+            let flip_x = [1.0 - hi.x, 1.0 - lo.x];
+            let z = [lo.z, hi.z];
+            let flip_z = [1.0 - hi.z, 1.0 - lo.z];
+            let y = [1.0 - hi.y, 1.0 - lo.y];
+
+            cube.uv = McUv::PerFace(McFaceUvs {
+                up: face([u + d, v], [w, d], x, z),
+                down: face([u + d + w, v], [w, d], x, flip_z),
+                east: face([u, v + d], [d, h], flip_z, y),
+                north: face([u + d, v + d], [w, h], flip_x, y),
+                west: face([u + d + w, v + d], [d, h], z, y),
+                south: face([u + 2.0 * d + w, v + d], [w, h], x, y),
+            });
+        }
+    }
+
+    if mapped == 0 {
+        return Err(Message::new("error.skin_needs_humanoid"));
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+
+use ahash::RandomState;
+use glam::IVec3;
+
+use crate::McCube;
+
+// ================= ROTATED SLOPE FITTING =================
+//
+// Looks for stair-stepped top surfaces (roofs, ramps) along X and along Z.
+// Within one row of columns, a run whose top heights stay within
+// MAX_DEVIATION of a line at 22.5° or 45° is replaced by a single rotated
+// slab, one voxel thick, laid along that line. Identical runs in neighbouring
+// rows are merged into one wider slab.
+//
+// Bedrock rotations are clockwise, so a slab rising towards +X uses a negative
+// Z rotation and a slab rising towards +Z a positive X rotation.
+
+const MIN_RUN: usize = 4;
+const MAX_DEVIATION: f32 = 0.6;
+const SLOPE_STEPS: [i32; 4] = [1, 2, -1, -2];
+const STEP_DEGREES: f32 = 22.5;
+
+#[derive(Clone, Copy, PartialEq)]
+enum RunAxis {
+    X,
+    Z,
+}
+
+impl RunAxis {
+    /// (along, across) coordinates of a voxel for this axis.
+    fn split(self, v: IVec3) -> (i32, i32) {
+        match self {
+            RunAxis::X => (v.x, v.z),
+            RunAxis::Z => (v.z, v.x),
+        }
+    }
+
+    fn join(self, along: i32, across: i32, y: i32) -> IVec3 {
+        match self {
+            RunAxis::X => IVec3::new(along, y, across),
+            RunAxis::Z => IVec3::new(across, y, along),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Run {
+    start: i32,
+    end: i32,
+    step: i32,
+    /// Line through voxel centers: y = tan(step) * x + intercept, in voxel units.
+    intercept: f32,
+}
+
+impl Run {
+    fn slope(&self) -> f32 {
+        (self.step as f32 * STEP_DEGREES).to_radians().tan()
+    }
+
+    fn line(&self, along: i32) -> f32 {
+        self.slope() * (along as f32 + 0.5) + self.intercept
+    }
+}
+
+/// Best-fitting intercept and worst deviation of `heights` (top voxel y per
+/// column, starting at `start`) from a line with the given slope.
+fn fit(start: i32, heights: &[i32], slope: f32) -> (f32, f32) {
+    let offsets = heights.iter().enumerate()
+        .map(|(i, &h)| h as f32 + 0.5 - slope * ((start + i as i32) as f32 + 0.5));
+    let intercept = offsets.clone().sum::<f32>() / heights.len() as f32;
+    let deviation = offsets.map(|o| (o - intercept).abs()).fold(0.0, f32::max);
+    (intercept, deviation)
+}
+
+fn find_runs(start: i32, heights: &[i32]) -> Vec<Run> {
+    let mut runs = Vec::new();
+    let mut i = 0;
+
+    while i + MIN_RUN <= heights.len() {
+        let mut best: Option<(usize, Run)> = None;
+
+        for step in SLOPE_STEPS {
+            let slope = (step as f32 * STEP_DEGREES).to_radians().tan();
+            let mut len = 0;
+            let mut intercept = 0.0;
+
+            for j in i + 2..=heights.len() {
+                let (c, deviation) = fit(start + i as i32, &heights[i..j], slope);
+                if deviation > MAX_DEVIATION { break; }
+                len = j - i;
+                intercept = c;
+            }
+
+            let rises = len > 0 && heights[i] != heights[i + len - 1];
+            if len >= MIN_RUN && rises && best.is_none_or(|(best_len, _)| len > best_len) {
+                let start = start + i as i32;
+                best = Some((len, Run { start, end: start + len as i32 - 1, step, intercept }));
+            }
+        }
+
+        match best {
+            Some((len, run)) => {
+                runs.push(run);
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+
+    runs
+}
+
+fn slab(axis: RunAxis, run: &Run, across_start: i32, across_len: i32) -> McCube {
+    let slope = run.slope();
+    let columns = (run.end - run.start + 1) as f32;
+    let length = columns * (1.0 + slope * slope).sqrt();
+    let center_along = (run.start + run.end + 1) as f32 / 2.0;
+    let center_y = slope * center_along + run.intercept;
+    let center_across = across_start as f32 + across_len as f32 / 2.0;
+    let angle = run.step as f32 * STEP_DEGREES;
+
+    let (origin, size, pivot, rotation) = match axis {
+        RunAxis::X => (
+            [center_along - length / 2.0, center_y - 0.5, across_start as f32],
+            [length, 1.0, across_len as f32],
+            [center_along, center_y, center_across],
+            [0.0, 0.0, -angle],
+        ),
+        RunAxis::Z => (
+            [across_start as f32, center_y - 0.5, center_along - length / 2.0],
+            [across_len as f32, 1.0, length],
+            [center_across, center_y, center_along],
+            [angle, 0.0, 0.0],
+        ),
+    };
+
+    McCube {
+        pivot: Some(pivot),
+        rotation: Some(rotation),
+        ..McCube::new(origin, size)
+    }
+}
+
+fn fit_axis(
+    voxels: &mut HashSet<IVec3, RandomState>,
+    axis: RunAxis,
+    claimed: &mut HashSet<(i32, i32), RandomState>,
+) -> Vec<McCube> {
+    // Top of every column not already replaced by the other axis
+    let mut tops: HashMap<(i32, i32), i32, RandomState> = HashMap::default();
+    for &v in voxels.iter() {
+        if claimed.contains(&(v.x, v.z)) { continue; }
+        let (along, across) = axis.split(v);
+        let top = tops.entry((across, along)).or_insert(v.y);
+        *top = (*top).max(v.y);
+    }
+
+    let mut rows: HashMap<i32, Vec<(i32, i32)>, RandomState> = HashMap::default();
+    for (&(across, along), &y) in &tops {
+        rows.entry(across).or_default().push((along, y));
+    }
+
+    // (start, end, step, intercept bits) -> rows where that run occurs
+    let mut found: HashMap<(i32, i32, i32, u32), Vec<i32>, RandomState> = HashMap::default();
+    let mut runs_by_key: HashMap<(i32, i32, i32, u32), Run, RandomState> = HashMap::default();
+
+    for (&across, columns) in rows.iter_mut() {
+        columns.sort_unstable();
+
+        // Split the row into contiguous stretches of columns
+        let mut begin = 0;
+        for k in 1..=columns.len() {
+            if k < columns.len() && columns[k].0 == columns[k - 1].0 + 1 { continue; }
+
+            let heights: Vec<i32> = columns[begin..k].iter().map(|&(_, y)| y).collect();
+            for run in find_runs(columns[begin].0, &heights) {
+                let key = (run.start, run.end, run.step, run.intercept.to_bits());
+                found.entry(key).or_default().push(across);
+                runs_by_key.insert(key, run);
+            }
+            begin = k;
+        }
+    }
+
+    // In key order, so the slabs come out the same on every run of an input
+    let mut found: Vec<_> = found.into_iter().collect();
+    found.sort_unstable_by_key(|&(key, _)| key);
+
+    let mut cubes = Vec::new();
+    for (key, mut rows) in found {
+        let run = runs_by_key[&key];
+        rows.sort_unstable();
+
+        let mut begin = 0;
+        for k in 1..=rows.len() {
+            if k < rows.len() && rows[k] == rows[k - 1] + 1 { continue; }
+
+            for &across in &rows[begin..k] {
+                for along in run.start..=run.end {
+                    // Drop the step voxels the slab now covers
+                    let line = run.line(along);
+                    let mut y = tops[&(across, along)];
+                    while y as f32 + 0.5 >= line - 1.0 && voxels.remove(&axis.join(along, across, y)) {
+                        y -= 1;
+                    }
+                    let v = axis.join(along, across, 0);
+                    claimed.insert((v.x, v.z));
+                }
+            }
+
+            cubes.push(slab(axis, &run, rows[begin], (k - begin) as i32));
+            begin = k;
+        }
+    }
+
+    cubes
+}
+
+/// Replaces stair-stepped slopes in `voxels` with rotated slabs and returns the slabs.
+pub fn fit_slopes(voxels: &mut HashSet<IVec3, RandomState>) -> Vec<McCube> {
+    let mut claimed = HashSet::default();
+    let mut cubes = fit_axis(voxels, RunAxis::X, &mut claimed);
+    cubes.extend(fit_axis(voxels, RunAxis::Z, &mut claimed));
+    cubes
+}
@@ -0,0 +1,60 @@
+use glam::{IVec3, Vec3};
+
+use crate::McBone;
+
+// ================= CUBE ORDER =================
+//
+// Greedy meshing emits cubes in scan order and objects finish in parallel, so
+// the order of bones and cubes says little and changes between runs. With
+// `sort_cubes`, bones are ordered parents first and then by name, and each
+// bone's cubes by the block-sized region they sit in, along a Z-order curve
+// so neighbouring regions stay close in the list; within a region, larger
+// cubes come first. Renderers and Blockbench cope better with huge models
+// ordered like this, and converting again gives the same file.
+
+/// Edge of a sorting region, in geometry units.
+const REGION: f32 = 16.0;
+
+/// Interleaves the low 21 bits of each coordinate.
+fn morton(cell: IVec3) -> u64 {
+    let spread = |v: u32| {
+        let mut x = (v & 0x1f_ffff) as u64;
+        x = (x | x << 32) & 0x1f_0000_0000_ffff;
+        x = (x | x << 16) & 0x1f_0000_ff00_00ff;
+        x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+        x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+        x = (x | x << 2) & 0x1249_2492_4924_9249;
+        x
+    };
+    spread(cell.x as u32) | spread(cell.y as u32) << 1 | spread(cell.z as u32) << 2
+}
+
+fn depth(bones: &[McBone], bone: &McBone) -> usize {
+    let mut depth = 0;
+    let mut parent = bone.parent.as_deref();
+    // Bounded by the bone count in case of a cycle
+    while let Some(name) = parent.filter(|_| depth < bones.len()) {
+        depth += 1;
+        parent = bones.iter().find(|b| b.name == name).and_then(|b| b.parent.as_deref());
+    }
+    depth
+}
+
+/// Orders `bones` and their cubes for stable, render-friendly output.
+pub fn sort_cubes(bones: &mut Vec<McBone>) {
+    let depths: Vec<usize> = bones.iter().map(|b| depth(bones, b)).collect();
+    let mut keyed: Vec<(usize, McBone)> = depths.into_iter().zip(bones.drain(..)).collect();
+    keyed.sort_by(|(da, a), (db, b)| da.cmp(db).then_with(|| a.name.cmp(&b.name)));
+    bones.extend(keyed.into_iter().map(|(_, b)| b));
+
+    for bone in bones.iter_mut() {
+        let lowest = bone.cubes.iter()
+            .map(|c| (Vec3::from(c.origin) / REGION).floor().as_ivec3())
+            .fold(IVec3::MAX, IVec3::min);
+        bone.cubes.sort_by_cached_key(|c| {
+            let region = (Vec3::from(c.origin) / REGION).floor().as_ivec3() - lowest;
+            let volume = Vec3::from(c.size).element_product();
+            (morton(region), std::cmp::Reverse(volume.to_bits()), c.origin.map(f32::to_bits))
+        });
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+
+use crate::messages::{failed, Message};
+use crate::{run_greedy_meshing, triangle_cells, McCube};
+
+// ================= OUT-OF-CORE VOXELS =================
+//
+// At scales where one object's voxels don't fit in memory, `spill_slab`
+// voxelizes it a triangle at a time, appending each cell to a temporary file
+// for its slab of `spill_slab` layers along Y. The slabs are then read back
+// one by one, meshed and deleted, so only one slab's cells are held at once.
+// At most MAX_OPEN_SLABS slab files are open at a time; the one written
+// longest ago is closed to make room and reopened for appending when needed.
+// Cubes stop at slab boundaries, so thinner slabs give more cubes. Steps
+// that need the whole grid (interior fill, gap welding, scripts, detail
+// regions, slope fitting, repeated parts and normal offset) are skipped for
+// a spilled object; it is slow, but it finishes.
+
+/// Bytes of one cell in a slab file: X, Y and Z as little-endian i32.
+const CELL_BYTES: usize = 12;
+/// Slab files kept open at once.
+const MAX_OPEN_SLABS: usize = 64;
+
+/// Numbers the folders of objects spilled at the same time.
+static NEXT_FOLDER: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary folder removed with everything in it when dropped.
+struct SpillFolder(PathBuf);
+
+impl SpillFolder {
+    fn new() -> Result<Self, Message> {
+        let name = format!("obj2mc-spill-{}-{}", std::process::id(), NEXT_FOLDER.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        fs::create_dir_all(&path).map_err(failed("error.spill"))?;
+        Ok(Self(path))
+    }
+
+    fn slab(&self, slab: i32) -> PathBuf {
+        self.0.join(format!("slab_{}.bin", slab))
+    }
+}
+
+impl Drop for SpillFolder {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The open slab files, each with the write count when it was last used.
+struct SlabWriters<'a> {
+    folder: &'a SpillFolder,
+    open: HashMap<i32, (BufWriter<File>, u64)>,
+    written: BTreeSet<i32>,
+    writes: u64,
+}
+
+impl<'a> SlabWriters<'a> {
+    fn new(folder: &'a SpillFolder) -> Self {
+        Self { folder, open: HashMap::new(), written: BTreeSet::new(), writes: 0 }
+    }
+
+    fn write(&mut self, slab: i32, bytes: &[u8]) -> io::Result<()> {
+        self.writes += 1;
+        if !self.open.contains_key(&slab) {
+            if self.open.len() >= MAX_OPEN_SLABS {
+                let oldest = self.open.iter().min_by_key(|(_, (_, used))| *used).map(|(&s, _)| s);
+                if let Some((mut writer, _)) = oldest.and_then(|s| self.open.remove(&s)) {
+                    writer.flush()?;
+                }
+            }
+            let file = OpenOptions::new().create(true).append(true).open(self.folder.slab(slab))?;
+            self.open.insert(slab, (BufWriter::new(file), 0));
+            self.written.insert(slab);
+        }
+        let (writer, used) = self.open.get_mut(&slab).unwrap();
+        *used = self.writes;
+        writer.write_all(bytes)
+    }
+
+    /// Flushes the open files and returns every slab written, bottom up.
+    fn finish(self) -> io::Result<BTreeSet<i32>> {
+        for (_, (mut writer, _)) in self.open {
+            writer.flush()?;
+        }
+        Ok(self.written)
+    }
+}
+
+/// Cubes of the mesh's surface voxels, spilled to disk in slabs of `layers`;
+/// also returns the voxel count.
+pub fn voxelize_spilled(
+    vertices: &[Vec3],
+    indices: &[u32],
+    scale: f32,
+    epsilon: f32,
+    layers: u32,
+) -> Result<(Vec<McCube>, usize), Message> {
+    let folder = SpillFolder::new()?;
+    let layers = layers.max(1) as i32;
+
+    let mut writers = SlabWriters::new(&folder);
+    let mut error = None;
+    for t in indices.chunks(3) {
+        let v = [0, 1, 2].map(|k| vertices[t[k] as usize]);
+        triangle_cells(v, scale, epsilon, |cell, _, _| {
+            if error.is_some() {
+                return;
+            }
+            let mut bytes = [0; CELL_BYTES];
+            for (chunk, c) in bytes.chunks_exact_mut(4).zip(cell.to_array()) {
+                chunk.copy_from_slice(&c.to_le_bytes());
+            }
+            if let Err(e) = writers.write(cell.y.div_euclid(layers), &bytes) {
+                error = Some(e);
+            }
+        });
+        if let Some(e) = error {
+            return Err(failed("error.spill")(e));
+        }
+    }
+    let slabs = writers.finish().map_err(failed("error.spill"))?;
+
+    let mut cubes = Vec::new();
+    let mut voxel_count = 0;
+    for slab in slabs {
+        let path = folder.slab(slab);
+        let mut bytes = Vec::new();
+        BufReader::new(File::open(&path).map_err(failed("error.spill"))?)
+            .read_to_end(&mut bytes)
+            .map_err(failed("error.spill"))?;
+        fs::remove_file(&path).map_err(failed("error.spill"))?;
+
+        let cells: HashSet<IVec3, RandomState> = bytes.chunks_exact(CELL_BYTES)
+            .map(|c| IVec3::from_array([0, 4, 8].map(|at| i32::from_le_bytes(c[at..at + 4].try_into().unwrap()))))
+            .collect();
+        drop(bytes);
+        voxel_count += cells.len();
+        cubes.extend(run_greedy_meshing(&cells));
+    }
+    Ok((cubes, voxel_count))
+}
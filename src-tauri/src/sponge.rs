@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::{BlockGrid, BlockShape, EmptyCells};
+use crate::mcfunction::block_state;
+use crate::messages::{failed, Message};
+use crate::nbt::{self, compound, Compression, Endian, Tag};
+use crate::structure_nbt::DATA_VERSION;
+use crate::ConvertOptions;
+
+// ================= SPONGE SCHEMATIC (.SCHEM) =================
+//
+// Gzipped big-endian NBT in the Sponge format WorldEdit and its relatives
+// read and write. Blocks are a palette of block state strings (the ones the
+// datapack commands use, `block` for plain voxels) and a byte array of
+// varint palette indices in YZX order (x fastest). Empty cells are air, or
+// structure voids when `empty_cells` keeps them.
+//
+// Version 3 nests the blocks in a `Blocks` compound and keeps the anchor as
+// `Offset`; version 2 has them at the top and the anchor as WorldEdit's
+// paste offset, for older tools.
+
+/// Width, height and length are unsigned shorts.
+const MAX_SIZE: i32 = u16::MAX as i32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpongeVersion {
+    V2,
+    #[default]
+    V3,
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Writes `grid` as a Sponge schematic.
+pub fn write_schem(grid: &BlockGrid, options: &ConvertOptions, path: &Path) -> Result<(), Message> {
+    let size = grid.size;
+    if size.max_element() > MAX_SIZE {
+        return Err(Message::new("error.schematic_too_large").with("max", MAX_SIZE));
+    }
+
+    let mut palette: Vec<(String, Tag)> = vec![("minecraft:air".to_string(), Tag::Int(0))];
+    let mut indices: HashMap<BlockShape, u32> = HashMap::from([(BlockShape::Air, 0)]);
+    let empty = match options.empty_cells {
+        EmptyCells::Air => 0,
+        EmptyCells::Keep | EmptyCells::DryInterior => {
+            palette.push(("minecraft:structure_void".to_string(), Tag::Int(1)));
+            1
+        }
+    };
+    let mut data = Vec::with_capacity(grid.volume());
+    for y in 0..size.y {
+        for z in 0..size.z {
+            for x in 0..size.x {
+                let index = match grid.get(IVec3::new(x, y, z)) {
+                    Some(shape) => *indices.entry(shape).or_insert_with(|| {
+                        palette.push((block_state(options, shape), Tag::Int(palette.len() as i32)));
+                        palette.len() as u32 - 1
+                    }),
+                    None => empty,
+                };
+                write_varint(&mut data, index);
+            }
+        }
+    }
+
+    let dimensions = [
+        ("Width", Tag::Short(size.x as u16 as i16)),
+        ("Height", Tag::Short(size.y as u16 as i16)),
+        ("Length", Tag::Short(size.z as u16 as i16)),
+    ];
+    let offset = grid.origin;
+    let (name, root) = match options.sponge_version {
+        SpongeVersion::V3 => {
+            let [width, height, length] = dimensions;
+            let schematic = compound([
+                ("Version", Tag::Int(3)),
+                ("DataVersion", Tag::Int(DATA_VERSION)),
+                width,
+                height,
+                length,
+                ("Offset", Tag::IntArray(offset.to_array().to_vec())),
+                ("Blocks", compound([
+                    ("Palette", Tag::Compound(palette)),
+                    ("Data", Tag::ByteArray(data)),
+                    ("BlockEntities", Tag::List(vec![])),
+                ])),
+            ]);
+            ("", compound([("Schematic", schematic)]))
+        }
+        SpongeVersion::V2 => {
+            let [width, height, length] = dimensions;
+            let root = compound([
+                ("Version", Tag::Int(2)),
+                ("DataVersion", Tag::Int(DATA_VERSION)),
+                width,
+                height,
+                length,
+                ("Offset", Tag::IntArray(vec![0, 0, 0])),
+                ("Metadata", compound([
+                    ("WEOffsetX", Tag::Int(offset.x)),
+                    ("WEOffsetY", Tag::Int(offset.y)),
+                    ("WEOffsetZ", Tag::Int(offset.z)),
+                ])),
+                ("PaletteMax", Tag::Int(palette.len() as i32)),
+                ("Palette", Tag::Compound(palette)),
+                ("BlockData", Tag::ByteArray(data)),
+                ("BlockEntities", Tag::List(vec![])),
+            ]);
+            ("Schematic", root)
+        }
+    };
+
+    let file = File::create(path).map_err(failed("error.create_file"))?;
+    let mut writer = BufWriter::new(file);
+    nbt::write(&mut writer, name, &root, Endian::Big, Compression::Gzip)
+        .and_then(|_| writer.flush())
+        .map_err(failed("error.write_schematic"))
+}
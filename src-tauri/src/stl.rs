@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::messages::{failed, Message};
+
+// ================= STL IMPORT =================
+//
+// STL files (binary or ASCII), common for 3D-print models, hold one triangle
+// soup without objects or materials, so the whole mesh becomes one object
+// named after the file. Corners at the same position are merged so the mesh
+// is connected for filling and part detection, and facet normals are
+// dropped. STL models are Z-up like the printers they come from; they are
+// turned Y-up as they load.
+
+/// Header and triangle count of a binary STL, then 50 bytes per triangle.
+const HEADER_BYTES: usize = 84;
+const TRIANGLE_BYTES: usize = 50;
+
+pub fn is_stl(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("stl"))
+}
+
+pub fn load_stl(path: &str) -> Result<tobj::Model, Message> {
+    let bytes = fs::read(path).map_err(failed("error.load_stl"))?;
+    let corners = if is_binary(&bytes) { binary_corners(&bytes) } else { ascii_corners(&bytes)? };
+
+    let mut mesh = tobj::Mesh::default();
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    for [x, y, z] in corners {
+        // Adding zero turns -0.0 into 0.0 so both weld
+        let position = [x, z, -y].map(|c| c + 0.0);
+        let next = welded.len() as u32;
+        let index = *welded.entry(position.map(f32::to_bits)).or_insert_with(|| {
+            mesh.positions.extend(position);
+            next
+        });
+        mesh.indices.push(index);
+    }
+    let name = Path::new(path).file_stem().map_or("model".to_string(), |s| s.to_string_lossy().to_string());
+    Ok(tobj::Model::new(mesh, name))
+}
+
+/// ASCII files start with `solid` too, but only a binary one has exactly the size its count says.
+fn is_binary(bytes: &[u8]) -> bool {
+    let Some(count) = bytes.get(80..84) else { return false };
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    bytes.len() == HEADER_BYTES + count * TRIANGLE_BYTES
+}
+
+fn binary_corners(bytes: &[u8]) -> Vec<[f32; 3]> {
+    let float = |b: &[u8]| f32::from_le_bytes(b.try_into().unwrap());
+    bytes[HEADER_BYTES..]
+        .chunks_exact(TRIANGLE_BYTES)
+        // Skip the normal; then three corners
+        .flat_map(|t| (0..3).map(move |k| {
+            let at = 12 + k * 12;
+            [float(&t[at..at + 4]), float(&t[at + 4..at + 8]), float(&t[at + 8..at + 12])]
+        }))
+        .collect()
+}
+
+fn ascii_corners(bytes: &[u8]) -> Result<Vec<[f32; 3]>, Message> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut corners = Vec::new();
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("vertex") {
+            continue;
+        }
+        let coords: Vec<f32> = words.map(str::parse).collect::<Result<_, _>>().map_err(failed("error.load_stl"))?;
+        let [x, y, z] = coords[..] else {
+            return Err(Message::new("error.load_stl").with("error", format!("bad vertex line '{}'", line.trim())));
+        };
+        corners.push([x, y, z]);
+    }
+    // Facets are three vertices each
+    corners.truncate(corners.len() / 3 * 3);
+    Ok(corners)
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use glam::IVec3;
+use serde::Serialize;
+
+use crate::{McBone, McCube};
+
+// ================= PREVIEW STREAMING =================
+//
+// A 3D preview of a model with a six-figure cube count stalls for seconds
+// parsing one JSON payload. `stream_preview` meshes the file as a conversion
+// would and sends each model's cubes as soon as the model is done, as
+// `preview-cubes` events of at most BATCH_SIZE cubes, so the preview fills in
+// while the rest is still voxelizing and no single message is large. Bones
+// made from the whole scene (rigid copies, detail regions) follow at the end.
+// Batches stay where the voxels are, before `voxel_origin` moves the model;
+// the summary reports that offset so the preview can line up with what a
+// conversion writes.
+// Cubes carry their material color and nothing the preview can't draw (UVs,
+// surfaces).
+//
+// `preview_buffer` skips JSON altogether: all cubes in one binary response
+// laid out as typed arrays the viewer uploads straight to GPU buffers. All
+// numbers are little-endian, and every array starts at a multiple of 4:
+//
+//   header     u32 × 4   version (1), cube count n, bone count, name bytes
+//   origins    f32 × 3n
+//   sizes      f32 × 3n
+//   pivots     f32 × 3n  the origin for cubes without one
+//   rotations  f32 × 3n  degrees, zero for cubes without one
+//   bones      u32 × n   index of the cube's bone in the names
+//   colors     u8  × 4n  RGBA, zero for cubes without a material color
+//   names      UTF-8     bone names, one per line
+
+/// Most cubes in one `preview-cubes` event.
+pub const BATCH_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewCube {
+    pub origin: [f32; 3],
+    pub size: [f32; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pivot: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<[f32; 3]>,
+    /// RGBA material color, if the cube has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 4]>,
+}
+
+impl From<&McCube> for PreviewCube {
+    fn from(cube: &McCube) -> Self {
+        Self { origin: cube.origin, size: cube.size, pivot: cube.pivot, rotation: cube.rotation, color: cube.color }
+    }
+}
+
+/// Emitted as `preview-cubes`: the next cubes of a bone.
+#[derive(Debug, Clone, Serialize)]
+pub struct CubeBatch {
+    pub bone: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub pivot: [i32; 3],
+    pub cubes: Vec<PreviewCube>,
+    /// Cubes sent so far, this batch included.
+    pub sent: usize,
+}
+
+/// Returned by `stream_preview` once every batch is out.
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewSummary {
+    pub cube_count: usize,
+    pub bone_count: usize,
+    pub batches: usize,
+    /// What a conversion moves the model by (`voxel_origin`, or a far model
+    /// brought back); add it to every batch to match the written geometry.
+    pub origin_offset: [i32; 3],
+}
+
+/// Batches bones as they come and counts what went out.
+#[derive(Default)]
+pub struct CubeStream {
+    bones: HashSet<String>,
+    sent: usize,
+    batches: usize,
+}
+
+impl CubeStream {
+    /// Hands the cubes of `bone` to `emit` in batches; a bone without cubes is skipped.
+    pub fn send(&mut self, bone: &McBone, mut emit: impl FnMut(CubeBatch)) {
+        if bone.cubes.is_empty() {
+            return;
+        }
+        self.bones.insert(bone.name.clone());
+        for chunk in bone.cubes.chunks(BATCH_SIZE) {
+            self.sent += chunk.len();
+            self.batches += 1;
+            emit(CubeBatch {
+                bone: bone.name.clone(),
+                parent: bone.parent.clone(),
+                pivot: bone.pivot,
+                cubes: chunk.iter().map(PreviewCube::from).collect(),
+                sent: self.sent,
+            });
+        }
+    }
+
+    pub fn has_sent(&self, bone: &str) -> bool {
+        self.bones.contains(bone)
+    }
+
+    pub fn summary(&self, origin_offset: IVec3) -> PreviewSummary {
+        PreviewSummary {
+            cube_count: self.sent,
+            bone_count: self.bones.len(),
+            batches: self.batches,
+            origin_offset: origin_offset.to_array(),
+        }
+    }
+}
+
+/// Version of the `encode_cubes` layout, the first number of the buffer.
+pub const BUFFER_VERSION: u32 = 1;
+
+/// The cubes of `bones` in the binary layout above.
+pub fn encode_cubes(bones: &[McBone]) -> Vec<u8> {
+    let cubes = || bones.iter().flat_map(|b| &b.cubes);
+    let names = bones.iter().map(|b| b.name.as_str()).collect::<Vec<_>>().join("\n");
+    let count = cubes().count();
+
+    let mut buffer = Vec::with_capacity(16 + count * 56 + names.len());
+    for n in [BUFFER_VERSION, count as u32, bones.len() as u32, names.len() as u32] {
+        buffer.extend(n.to_le_bytes());
+    }
+    let mut floats = |field: fn(&McCube) -> [f32; 3]| {
+        for cube in cubes() {
+            buffer.extend(field(cube).iter().flat_map(|v| v.to_le_bytes()));
+        }
+    };
+    floats(|c| c.origin);
+    floats(|c| c.size);
+    floats(|c| c.pivot.unwrap_or(c.origin));
+    floats(|c| c.rotation.unwrap_or_default());
+    for (index, bone) in bones.iter().enumerate() {
+        for _ in &bone.cubes {
+            buffer.extend((index as u32).to_le_bytes());
+        }
+    }
+    buffer.extend(cubes().flat_map(|c| c.color.unwrap_or_default()));
+    buffer.extend(names.as_bytes());
+    buffer
+}
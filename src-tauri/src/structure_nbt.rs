@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use glam::IVec3;
+
+use crate::blocks::{BlockGrid, BlockShape, EmptyCells};
+use crate::mcfunction::block_state;
+use crate::messages::{failed, Message};
+use crate::nbt::{self, compound, Compression, Endian, Tag};
+use crate::ConvertOptions;
+
+// ================= JAVA STRUCTURE (.NBT) =================
+//
+// Gzipped big-endian NBT, as saved by structure blocks and loaded by them or
+// `/place template`. Each block lists its position and palette index; cells
+// left out are structure voids, so with empty cells kept only the model's
+// blocks are listed. Block states are the ones the datapack commands use.
+//
+// A structure block saves and loads at most 48 blocks per side, so larger
+// models are cut into chunk-aligned columns and those into 48-block layers.
+
+/// Largest structure a structure block handles along each axis.
+pub const MAX_SIZE: i32 = 48;
+/// Data version of Java 1.21; the game upgrades older structures on load.
+pub const DATA_VERSION: i32 = 3953;
+
+/// Palette entry for a command block state (`name[key=value,...]`).
+fn palette_entry(state: &str) -> Tag {
+    let (name, properties) = match state.split_once('[') {
+        Some((name, properties)) => (name, properties.trim_end_matches(']')),
+        None => (state, ""),
+    };
+    let properties: Vec<(String, Tag)> = properties
+        .split(',')
+        .filter_map(|p| p.split_once('='))
+        .map(|(key, value)| (key.to_string(), Tag::String(value.to_string())))
+        .collect();
+
+    let mut entry = vec![("Name".to_string(), Tag::String(name.to_string()))];
+    if !properties.is_empty() {
+        entry.push(("Properties".to_string(), Tag::Compound(properties)));
+    }
+    Tag::Compound(entry)
+}
+
+/// Encodes `grid` as the contents of a Java structure file.
+pub fn encode_structure(grid: &BlockGrid, options: &ConvertOptions) -> Vec<u8> {
+    let mut states: HashMap<String, i32> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut state_index = |state: String| {
+        *states.entry(state).or_insert_with_key(|state| {
+            palette.push(palette_entry(state));
+            palette.len() as i32 - 1
+        })
+    };
+
+    let mut blocks = Vec::new();
+    for y in 0..grid.size.y {
+        for z in 0..grid.size.z {
+            for x in 0..grid.size.x {
+                let shape = match grid.get(IVec3::new(x, y, z)) {
+                    Some(shape) => shape,
+                    None if options.empty_cells == EmptyCells::Air => BlockShape::Air,
+                    None => continue,
+                };
+                blocks.push(compound([
+                    ("pos", Tag::IntList(vec![x, y, z])),
+                    ("state", Tag::Int(state_index(block_state(options, shape)))),
+                ]));
+            }
+        }
+    }
+
+    let root = compound([
+        ("DataVersion", Tag::Int(DATA_VERSION)),
+        ("size", Tag::IntList(grid.size.to_array().to_vec())),
+        ("palette", Tag::List(palette)),
+        ("blocks", Tag::List(blocks)),
+        ("entities", Tag::List(vec![])),
+    ]);
+
+    nbt::to_bytes("", &root, Endian::Big, Compression::Gzip)
+}
+
+/// Writes `grid` as a Java structure file.
+pub fn write_structure(grid: &BlockGrid, options: &ConvertOptions, path: &Path) -> Result<(), Message> {
+    fs::write(path, encode_structure(grid, options)).map_err(failed("error.write_structure"))
+}
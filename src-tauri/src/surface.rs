@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use ahash::RandomState;
+use glam::{IVec3, Vec2, Vec3, Vec4};
+
+use crate::texture::Texture;
+use crate::triangle_aabb_intersect;
+
+// ================= SURFACE SAMPLING =================
+//
+// With `sample_textures`, atlas texels are painted from the material texture
+// through the mesh UVs, so painted details (eyes, signs, decals) survive
+// voxelization. Each texel finds the nearest point of the object's surface
+// (triangles are bucketed by voxel cell, and only the cells around the texel
+// are searched), interpolates the UV there and reads the texture.
+//
+// Vertex colors (PLY scans, OBJ `v x y z r g b`) paint the same way,
+// interpolating the colors of the nearest triangle's corners instead of its
+// UVs; for them no texture or `sample_textures` is needed.
+//
+// An object with several materials keeps a layer per material and the layer
+// of each triangle, so a texel reads the texture of the triangle under it, or
+// that material's color when it has no texture.
+//
+// The surface stays in the voxel space it was voxelized in. Cubes keep a
+// per-axis map from their final coordinates back to it, updated when the
+// geometry is moved or rescaled, so cutting or re-parenting cubes needs no
+// care.
+
+pub struct Surface {
+    triangles: Vec<[Vec3; 3]>,
+    paint: Paint,
+    /// Triangles whose bounds touch each voxel cell.
+    cells: HashMap<IVec3, Vec<u32>, RandomState>,
+}
+
+/// What colors the surface, by triangle corner.
+enum Paint {
+    /// Each triangle's layer, through its UVs (none without them).
+    Layers { uvs: Vec<[Vec2; 3]>, layers: Vec<Layer>, layer_of: Vec<Option<u32>> },
+    /// RGBA from 0 to 1.
+    Vertex(Vec<[Vec4; 3]>),
+}
+
+/// A material of the surface: its texture, else its color.
+pub struct Layer {
+    pub image: Option<Texture>,
+    pub color: Option<[u8; 4]>,
+}
+
+/// Closest point to `p` on triangle `t` as barycentric weights (Ericson,
+/// Real-Time Collision Detection 5.1.5).
+fn closest_barycentric(p: Vec3, [a, b, c]: [Vec3; 3]) -> Vec3 {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 { return Vec3::X; }
+
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 { return Vec3::Y; }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return Vec3::new(1.0 - v, v, 0.0);
+    }
+
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 { return Vec3::Z; }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return Vec3::new(1.0 - w, 0.0, w);
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return Vec3::new(0.0, 1.0 - w, w);
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    let (v, w) = (vb * denom, vc * denom);
+    Vec3::new(1.0 - v - w, v, w)
+}
+
+impl Surface {
+    /// Surface of `mesh` scaled into voxel space; `None` without UVs.
+    pub fn new(mesh: &tobj::Mesh, scale: f32, image: Texture) -> Option<Self> {
+        if mesh.texcoords.is_empty() { return None; }
+        let layer_of = vec![Some(0); mesh.indices.len() / 3];
+        Some(Self::with_layers(mesh, scale, vec![Layer { image: Some(image), color: None }], layer_of))
+    }
+
+    /// Surface of `mesh` whose triangles are painted by the layers `layer_of` names.
+    pub fn with_layers(mesh: &tobj::Mesh, scale: f32, layers: Vec<Layer>, layer_of: Vec<Option<u32>>) -> Self {
+        let uv = |i: u32| Vec2::from_slice(&mesh.texcoords[i as usize * 2..]);
+        let uvs = match !mesh.texcoords.is_empty() {
+            true => mesh.indices.chunks_exact(3).map(|t| [uv(t[0]), uv(t[1]), uv(t[2])]).collect(),
+            false => vec![],
+        };
+        Self::build(mesh, scale, Paint::Layers { uvs, layers, layer_of })
+    }
+
+    /// Surface of `mesh` painted by its vertex colors; `None` without them.
+    pub fn from_vertex_colors(mesh: &tobj::Mesh, scale: f32) -> Option<Self> {
+        if mesh.vertex_color.len() < mesh.positions.len() { return None; }
+        let color = |i: u32| Vec3::from_slice(&mesh.vertex_color[i as usize * 3..]).clamp(Vec3::ZERO, Vec3::ONE).extend(1.0);
+        let colors = mesh.indices.chunks_exact(3).map(|t| [color(t[0]), color(t[1]), color(t[2])]).collect();
+        Some(Self::build(mesh, scale, Paint::Vertex(colors)))
+    }
+
+    fn build(mesh: &tobj::Mesh, scale: f32, paint: Paint) -> Self {
+        let position = |i: u32| Vec3::from_slice(&mesh.positions[i as usize * 3..]) * scale;
+
+        let mut triangles = Vec::with_capacity(mesh.indices.len() / 3);
+        let mut cells: HashMap<IVec3, Vec<u32>, RandomState> = HashMap::default();
+        for chunk in mesh.indices.chunks_exact(3) {
+            let triangle = [position(chunk[0]), position(chunk[1]), position(chunk[2])];
+            let lo = triangle[0].min(triangle[1]).min(triangle[2]).floor().as_ivec3();
+            let hi = triangle[0].max(triangle[1]).max(triangle[2]).floor().as_ivec3();
+            // Only the cells the triangle crosses, not every cell of its bounds
+            for x in lo.x..=hi.x {
+                for y in lo.y..=hi.y {
+                    for z in lo.z..=hi.z {
+                        let cell = IVec3::new(x, y, z);
+                        if triangle_aabb_intersect(triangle[0], triangle[1], triangle[2], cell.as_vec3() + 0.5, 0.5) {
+                            cells.entry(cell).or_default().push(triangles.len() as u32);
+                        }
+                    }
+                }
+            }
+            triangles.push(triangle);
+        }
+
+        Self { triangles, paint, cells }
+    }
+
+    /// Mean color of the surface's corners, for cubes too deep inside to sample it.
+    pub fn mean_color(&self) -> Option<[u8; 4]> {
+        let Paint::Vertex(colors) = &self.paint else { return None };
+        if colors.is_empty() { return None; }
+        let sum: Vec4 = colors.iter().flatten().sum();
+        Some(to_rgba(sum / (colors.len() * 3) as f32))
+    }
+
+    /// The triangle nearest to `point` and the barycentric weights of the
+    /// nearest point on it, if any triangle is within a cell.
+    fn nearest(&self, point: Vec3) -> Option<(usize, Vec3)> {
+        let cell = point.floor().as_ivec3();
+        let mut best: Option<(f32, usize, Vec3)> = None;
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let Some(candidates) = self.cells.get(&(cell + IVec3::new(x, y, z))) else { continue };
+                    for &i in candidates {
+                        let t = self.triangles[i as usize];
+                        let weights = closest_barycentric(point, t);
+                        let closest = t[0] * weights.x + t[1] * weights.y + t[2] * weights.z;
+                        let distance = closest.distance_squared(point);
+                        if best.is_none_or(|(d, ..)| distance < d) {
+                            best = Some((distance, i as usize, weights));
+                        }
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, i, weights)| (i, weights))
+    }
+
+    /// Index in the mesh of the triangle nearest to `point`, if any is within a cell.
+    pub fn nearest_triangle(&self, point: Vec3) -> Option<usize> {
+        self.nearest(point).map(|(i, _)| i)
+    }
+
+    /// Color at the surface point nearest to `point`, if any is within a cell.
+    fn sample(&self, point: Vec3) -> Option<[u8; 4]> {
+        let (i, weights) = self.nearest(point)?;
+        let (uvs, layer) = match &self.paint {
+            Paint::Layers { uvs, layers, layer_of } => (uvs, layers.get(layer_of[i]? as usize)?),
+            Paint::Vertex(colors) => {
+                let [a, b, c] = colors[i];
+                return Some(to_rgba(a * weights.x + b * weights.y + c * weights.z));
+            }
+        };
+        let (Some(image), Some(&[a, b, c])) = (&layer.image, uvs.get(i)) else { return layer.color };
+        let uv = a * weights.x + b * weights.y + c * weights.z;
+        // OBJ texture v runs upwards; UVs outside 0-1 repeat
+        let wrap = |t: f32| if (0.0..=1.0).contains(&t) { t } else { t.rem_euclid(1.0) };
+        let x = (wrap(uv.x) * image.width as f32) as u32;
+        let y = ((1.0 - wrap(uv.y)) * image.height as f32) as u32;
+        let (x, y) = (x.min(image.width - 1), y.min(image.height - 1));
+        Some(image.pixels[(y * image.width + x) as usize])
+    }
+}
+
+fn to_rgba(color: Vec4) -> [u8; 4] {
+    color.to_array().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// A cube's link to the surface it was voxelized from.
+#[derive(Clone)]
+pub struct SurfaceRef {
+    surface: Arc<Surface>,
+    /// Surface point = `offset + point * scale`, per axis.
+    offset: Vec3,
+    scale: Vec3,
+}
+
+impl fmt::Debug for SurfaceRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SurfaceRef").field("offset", &self.offset).field("scale", &self.scale).finish_non_exhaustive()
+    }
+}
+
+impl SurfaceRef {
+    pub fn new(surface: Arc<Surface>) -> Self {
+        Self { surface, offset: Vec3::ZERO, scale: Vec3::ONE }
+    }
+
+    /// Follows the cube moving by `shift`.
+    pub fn translate(&mut self, shift: Vec3) {
+        self.offset -= shift * self.scale;
+    }
+
+    /// Follows the cube being scaled by `factor`.
+    pub fn rescale(&mut self, factor: Vec3) {
+        self.scale /= factor;
+    }
+
+    pub fn sample(&self, point: Vec3) -> Option<[u8; 4]> {
+        self.surface.sample(self.offset + point * self.scale)
+    }
+}
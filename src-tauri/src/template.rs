@@ -0,0 +1,81 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::messages::Message;
+use crate::OutputFormat;
+
+// ================= OUTPUT TEMPLATES =================
+//
+// `output_template` organizes batch exports into folders, e.g.
+// `{output_dir}/{model}/{format}`. Variables are written in braces:
+//
+//   {output_dir}  the output folder chosen for the conversion
+//   {model}       model name (the input file name without extension)
+//   {scale}       voxels per block
+//   {format}      output format, as named in the options
+//   {date}        conversion date (UTC), as YYYY-MM-DD
+//
+// The expanded path is the folder the output files are written to. A
+// template without `{output_dir}` is taken from the output folder, unless it
+// is absolute. File names stay as the format names them.
+
+/// Civil date of `days` since 1970-01-01 (Howard Hinnant's algorithm).
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn today() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let (year, month, day) = civil_date(seconds.div_euclid(86_400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn format_name(format: OutputFormat) -> String {
+    serde_json::to_value(format).ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Folder the outputs of `model` in `format` go to.
+pub fn output_folder(
+    template: &str,
+    output_dir: &str,
+    model: &str,
+    scale: f32,
+    format: OutputFormat,
+) -> Result<String, Message> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            return Err(Message::new("error.template_unclosed").with("template", template));
+        };
+        let name = &rest[start + 1..start + len];
+        let value = match name {
+            "output_dir" => output_dir.to_string(),
+            "model" => model.to_string(),
+            "scale" => scale.to_string(),
+            "format" => format_name(format),
+            "date" => today(),
+            _ => return Err(Message::new("error.unknown_template_variable").with("name", name)),
+        };
+        expanded.push_str(&value);
+        rest = &rest[start + len + 1..];
+    }
+    expanded.push_str(rest);
+
+    if template.contains("{output_dir}") {
+        return Ok(expanded);
+    }
+    Ok(Path::new(output_dir).join(expanded).to_string_lossy().to_string())
+}
@@ -0,0 +1,452 @@
+use std::collections::{HashMap, HashSet};
+
+use ahash::RandomState;
+use glam::{EulerRot, IVec3, Quat, Vec3};
+
+use crate::blocks::rasterize;
+use crate::messages::{failed, Message};
+use crate::projection::{ColorSources, Projector};
+use crate::surface::SurfaceRef;
+use crate::{clay, ConvertOptions, McBone, McCube, McFaceUv, McFaceUvs, McUv, TextureLayout, TEXTURE_SIZE};
+
+// ================= TEXTURE ATLAS =================
+//
+// Bakes a texture for `texture_layout = "atlas"`. Every cube face gets its own
+// patch, one texel per geometry unit, filled with the cube's material color;
+// the patches are packed in rows into a power-of-two atlas and each face's
+// per-face UV points at its patch.
+//
+// With `uv_sharing`, faces whose patches look the same share one region: a
+// patch of a single color (within `uv_share_tolerance` per channel) shrinks to
+// one texel, and patches with identical texels are stored once. Flat-shaded
+// models end up with one texel per color.
+//
+// Ambient occlusion darkens each texel by how many of the eight voxels around
+// the cell in front of it are filled: creases and corners get darker, open
+// surfaces stay as they are. Directional lighting shades each face by the
+// angle between its normal (rotated cubes included) and the light, lambert
+// style, over a fixed ambient floor so faces turned away aren't black. Texels
+// follow the face orientation of the skin
+// layout (east is +X, texture v runs downwards).
+//
+// With a projected image or a material texture, texels take their color from
+// the image rather than the material color, before shading. `texel_density`
+// gives each geometry unit several texels per side, for detail sampled from
+// textures.
+//
+// With `atlas_budget` the atlas has a fixed size instead, and one density for
+// the whole model is picked so the patches fill it: each face gets texels in
+// proportion to its size, so large faces keep detail while tiny ones shrink
+// to a single texel. The density starts from the faces' total area and steps
+// down until the patch sizes pack within the budget; the patches are then
+// baked once at that density.
+
+const WHITE: [u8; 4] = [255; 4];
+/// Light reaching faces turned away from the baked light.
+const AMBIENT_LIGHT: f32 = 0.4;
+pub const MAX_TEXEL_DENSITY: u32 = 16;
+/// Largest `atlas_budget` side.
+pub const MAX_ATLAS_BUDGET: u32 = 8192;
+/// Share of the budget the patches are first sized to fill, leaving room for
+/// packing gaps.
+const BUDGET_FILL: f32 = 0.85;
+/// Factor the density shrinks by each time the patches don't fit the budget.
+const BUDGET_STEP: f32 = 0.9;
+
+#[derive(Clone)]
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA texels, row by row from the top.
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Texture {
+    /// Plain white texture for models without a baked one.
+    pub fn blank() -> Self {
+        Self::filled(TEXTURE_SIZE, TEXTURE_SIZE, WHITE)
+    }
+
+    fn filled(width: u32, height: u32, color: [u8; 4]) -> Self {
+        Self { width, height, pixels: vec![color; (width * height) as usize] }
+    }
+
+    /// Reads a PNG of any color type as RGBA.
+    pub fn load_png(path: &str) -> Result<Self, Message> {
+        let file = std::fs::File::open(path).map_err(failed("error.read_image"))?;
+        let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(failed("error.read_image"))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(failed("error.read_image"))?;
+        let bytes = &buffer[..info.buffer_size()];
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => bytes.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]).collect(),
+            png::ColorType::Rgb => bytes.chunks(3).map(|c| [c[0], c[1], c[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => bytes.chunks(2).map(|c| [c[0], c[0], c[0], c[1]]).collect(),
+            png::ColorType::Grayscale => bytes.iter().map(|&g| [g, g, g, 255]).collect(),
+            png::ColorType::Indexed => unreachable!("expanded by normalize_to_color8"),
+        };
+        Ok(Self { width: info.width, height: info.height, pixels })
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(self.pixels.as_flattened()).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Face {
+    North,
+    East,
+    South,
+    West,
+    Up,
+    Down,
+}
+
+const FACES: [Face; 6] = [Face::North, Face::East, Face::South, Face::West, Face::Up, Face::Down];
+
+impl Face {
+    fn normal(self) -> IVec3 {
+        match self {
+            Face::North => IVec3::NEG_Z,
+            Face::East => IVec3::X,
+            Face::South => IVec3::Z,
+            Face::West => IVec3::NEG_X,
+            Face::Up => IVec3::Y,
+            Face::Down => IVec3::NEG_Y,
+        }
+    }
+
+    /// Voxel cell of `cube` behind texel (u, v) of this face.
+    fn texel_cell(self, cube: &McCube, u: i32, v: i32) -> IVec3 {
+        let o = IVec3::from_array(cube.origin.map(|c| c.floor() as i32));
+        let s = IVec3::from_array(cube.size.map(|c| c.ceil().max(1.0) as i32)) - IVec3::ONE;
+        let top = o.y + s.y - v;
+        match self {
+            Face::North => IVec3::new(o.x + s.x - u, top, o.z),
+            Face::East => IVec3::new(o.x + s.x, top, o.z + s.z - u),
+            Face::South => IVec3::new(o.x + u, top, o.z + s.z),
+            Face::West => IVec3::new(o.x, top, o.z + u),
+            Face::Up => IVec3::new(o.x + u, o.y + s.y, o.z + v),
+            Face::Down => IVec3::new(o.x + u, o.y, o.z + s.z - v),
+        }
+    }
+
+    /// Point on this face at (u, v) geometry units from its texture corner.
+    fn texel_point(self, cube: &McCube, u: f32, v: f32) -> Vec3 {
+        let (o, s) = (Vec3::from(cube.origin), Vec3::from(cube.size));
+        let top = o.y + s.y - v;
+        match self {
+            Face::North => Vec3::new(o.x + s.x - u, top, o.z),
+            Face::East => Vec3::new(o.x + s.x, top, o.z + s.z - u),
+            Face::South => Vec3::new(o.x + u, top, o.z + s.z),
+            Face::West => Vec3::new(o.x, top, o.z + u),
+            Face::Up => Vec3::new(o.x + u, o.y + s.y, o.z + v),
+            Face::Down => Vec3::new(o.x + u, o.y, o.z + s.z - v),
+        }
+    }
+
+    /// The face's extent in whole units along its horizontal and vertical axes.
+    fn extent(self, cube: &McCube) -> (f32, f32) {
+        let [x, y, z] = cube.size.map(|s| s.ceil().max(1.0));
+        match self {
+            Face::North | Face::South => (x, y),
+            Face::East | Face::West => (z, y),
+            Face::Up | Face::Down => (x, z),
+        }
+    }
+
+    /// Patch size in texels, `density` texels per unit and at least one.
+    fn patch_size(self, cube: &McCube, density: f32) -> (u32, u32) {
+        let (u, v) = self.extent(cube);
+        ((u * density).round().max(1.0) as u32, (v * density).round().max(1.0) as u32)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Patch {
+    width: u32,
+    height: u32,
+    texels: Vec<[u8; 4]>,
+}
+
+impl Patch {
+    fn solid(width: u32, height: u32, color: [u8; 4]) -> Self {
+        Self { width, height, texels: vec![color; (width * height) as usize] }
+    }
+
+    /// Geometry units of `face` each texel spans, along u and v.
+    fn texel_units(&self, face: Face, cube: &McCube) -> (f32, f32) {
+        let (u, v) = face.extent(cube);
+        (u / self.width as f32, v / self.height as f32)
+    }
+
+    /// Unit of `face` texel (u, v) lies in, counted from the texture corner.
+    fn texel_unit(&self, face: Face, cube: &McCube, u: u32, v: u32) -> (i32, i32) {
+        let (du, dv) = self.texel_units(face, cube);
+        (((u as f32 + 0.5) * du) as i32, ((v as f32 + 0.5) * dv) as i32)
+    }
+
+    /// The patch's color when every texel is within `tolerance` of the first.
+    fn uniform_color(&self, tolerance: u8) -> Option<[u8; 4]> {
+        let first = self.texels[0];
+        let close = |t: &[u8; 4]| t.iter().zip(first).all(|(&a, b)| a.abs_diff(b) <= tolerance);
+        self.texels.iter().all(close).then_some(first)
+    }
+}
+
+/// Fraction of the eight cells around `front` (in the plane across `normal`) that are filled.
+fn occlusion(cells: &HashSet<IVec3, RandomState>, front: IVec3, normal: IVec3) -> f32 {
+    let (a, b) = match normal.abs() {
+        IVec3 { x: 1, .. } => (IVec3::Y, IVec3::Z),
+        IVec3 { y: 1, .. } => (IVec3::X, IVec3::Z),
+        _ => (IVec3::X, IVec3::Y),
+    };
+    let mut filled = 0;
+    for i in -1..=1 {
+        for j in -1..=1 {
+            if (i, j) != (0, 0) && cells.contains(&(front + a * i + b * j)) {
+                filled += 1;
+            }
+        }
+    }
+    filled as f32 / 8.0
+}
+
+fn shade_ambient_occlusion(
+    patch: &mut Patch,
+    face: Face,
+    cube: &McCube,
+    cells: &HashSet<IVec3, RandomState>,
+    strength: f32,
+) {
+    for v in 0..patch.height {
+        for u in 0..patch.width {
+            let (cu, cv) = patch.texel_unit(face, cube, u, v);
+            let front = face.texel_cell(cube, cu, cv) + face.normal();
+            let light = 1.0 - strength * occlusion(cells, front, face.normal());
+            light_texel(&mut patch.texels[(v * patch.width + u) as usize], light);
+        }
+    }
+}
+
+fn project(patch: &mut Patch, face: Face, cube: &McCube, projector: &Projector) {
+    for v in 0..patch.height {
+        for u in 0..patch.width {
+            let (cu, cv) = patch.texel_unit(face, cube, u, v);
+            let cell = face.texel_cell(cube, cu, cv);
+            patch.texels[(v * patch.width + u) as usize] = projector.sample(cell, face.normal());
+        }
+    }
+}
+
+/// Paints texels from the surface under them, keeping the color where there is none.
+fn sample_surface(patch: &mut Patch, face: Face, cube: &McCube, surface: &SurfaceRef) {
+    let (du, dv) = patch.texel_units(face, cube);
+    for v in 0..patch.height {
+        for u in 0..patch.width {
+            let point = face.texel_point(cube, (u as f32 + 0.5) * du, (v as f32 + 0.5) * dv);
+            if let Some(color) = surface.sample(point) {
+                patch.texels[(v * patch.width + u) as usize] = color;
+            }
+        }
+    }
+}
+
+/// Normal of `face`, turned with the cube.
+fn face_normal(face: Face, cube: &McCube) -> Vec3 {
+    let normal = face.normal().as_vec3();
+    match cube.rotation {
+        // Bedrock rotations are clockwise
+        Some([x, y, z]) => Quat::from_euler(EulerRot::ZYX, -z.to_radians(), -y.to_radians(), -x.to_radians()) * normal,
+        None => normal,
+    }
+}
+
+fn shade_directional(patch: &mut Patch, face: Face, cube: &McCube, light: Vec3) {
+    let brightness = AMBIENT_LIGHT + (1.0 - AMBIENT_LIGHT) * face_normal(face, cube).dot(light).max(0.0);
+    for texel in &mut patch.texels {
+        light_texel(texel, brightness);
+    }
+}
+
+/// Shades by which way the face points, blending the axis brightnesses for turned faces.
+fn shade_clay(patch: &mut Patch, face: Face, cube: &McCube) {
+    let n = face_normal(face, cube);
+    let vertical = if n.y > 0.0 { clay::TOP } else { clay::BOTTOM };
+    // Squared components of a unit normal add up to 1
+    let brightness = n.x * n.x * clay::SIDE_X + n.y * n.y * vertical + n.z * n.z * clay::SIDE_Z;
+    for texel in &mut patch.texels {
+        light_texel(texel, brightness);
+    }
+}
+
+fn light_texel(texel: &mut [u8; 4], light: f32) {
+    for c in &mut texel[..3] {
+        *c = (*c as f32 * light).round() as u8;
+    }
+}
+
+/// Key grouping near-identical colors: each channel bucketed by `tolerance + 1`.
+fn color_key(color: [u8; 4], tolerance: u8) -> [u8; 4] {
+    color.map(|c| c / tolerance.saturating_add(1))
+}
+
+/// Places patches of the given sizes in rows; returns each patch's corner and the atlas size.
+fn pack(sizes: &[(u32, u32)]) -> (Vec<[u32; 2]>, u32, u32) {
+    let area: u32 = sizes.iter().map(|&(w, h)| w * h).sum();
+    let widest = sizes.iter().map(|&(w, _)| w).max().unwrap_or(1);
+    let width = ((area as f64).sqrt().ceil() as u32).max(widest).next_power_of_two().max(16);
+
+    // Tallest first keeps rows tight
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| (std::cmp::Reverse(sizes[i].1), std::cmp::Reverse(sizes[i].0)));
+
+    let mut corners = vec![[0, 0]; sizes.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for i in order {
+        let (w, h) = sizes[i];
+        if x + w > width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        corners[i] = [x, y];
+        x += w;
+        row_height = row_height.max(h);
+    }
+
+    let height = (y + row_height).next_power_of_two().max(16);
+    (corners, width, height)
+}
+
+fn patch_sizes(patches: &[Patch]) -> Vec<(u32, u32)> {
+    patches.iter().map(|p| (p.width, p.height)).collect()
+}
+
+fn face_uv(corner: [u32; 2], patch: &Patch) -> McFaceUv {
+    McFaceUv {
+        uv: [corner[0] as f32, corner[1] as f32],
+        uv_size: [patch.width as f32, patch.height as f32],
+    }
+}
+
+/// Patches of the cube faces of `bones` at `density` texels per unit, and the
+/// patch of each face, cube by cube.
+fn bake_patches(bones: &[McBone], options: &ConvertOptions, sources: &ColorSources, density: f32) -> (Vec<Patch>, Vec<[usize; 6]>) {
+    let tolerance = options.uv_share_tolerance;
+    let ao_strength = options.ambient_occlusion.clamp(0.0, 1.0);
+    let cells = (ao_strength > 0.0).then(|| rasterize(bones));
+    let light = options.light_direction.map(|d| Vec3::from(d).normalize_or_zero());
+    let mut patches: Vec<Patch> = Vec::new();
+    let mut shared: HashMap<Patch, usize> = HashMap::new();
+    let mut shared_colors: HashMap<[u8; 4], usize> = HashMap::new();
+
+    let mut faces: Vec<[usize; 6]> = Vec::new();
+    for cube in bones.iter().flat_map(|b| &b.cubes) {
+        let color = cube.color.unwrap_or(WHITE);
+        let indices = FACES.map(|face| {
+            let (width, height) = face.patch_size(cube, density);
+            let mut patch = Patch::solid(width, height, color);
+            if let Some(projector) = sources.for_cube(cube) {
+                project(&mut patch, face, cube, projector);
+            } else if let Some(surface) = &cube.surface {
+                sample_surface(&mut patch, face, cube, surface);
+            }
+            // Rotated cubes don't line up with the voxel grid
+            if let (Some(cells), None) = (&cells, cube.rotation) {
+                shade_ambient_occlusion(&mut patch, face, cube, cells, ao_strength);
+            }
+            if options.texture_layout == TextureLayout::Clay {
+                shade_clay(&mut patch, face, cube);
+            }
+            if let Some(light) = light {
+                shade_directional(&mut patch, face, cube, light);
+            }
+            if !options.uv_sharing {
+                patches.push(patch);
+                return patches.len() - 1;
+            }
+            if let Some(color) = patch.uniform_color(tolerance) {
+                return *shared_colors.entry(color_key(color, tolerance)).or_insert_with(|| {
+                    patches.push(Patch::solid(1, 1, color));
+                    patches.len() - 1
+                });
+            }
+            *shared.entry(patch.clone()).or_insert_with(|| {
+                patches.push(patch);
+                patches.len() - 1
+            })
+        });
+        faces.push(indices);
+    }
+    (patches, faces)
+}
+
+/// Density whose patches pack into a `side` by `side` atlas, judged from
+/// the unshared patch sizes. Gives up shrinking once every face is a single
+/// texel, even if that doesn't fit.
+fn budget_density(bones: &[McBone], side: u32) -> f32 {
+    let cubes = || bones.iter().flat_map(|b| &b.cubes);
+    let area: f32 = cubes()
+        .flat_map(|cube| FACES.map(|face| face.extent(cube)))
+        .map(|(u, v)| u * v)
+        .sum();
+    let largest = cubes().flat_map(|c| c.size).fold(1.0f32, |a, s| a.max(s.ceil()));
+    let mut density = ((side as f32).powi(2) * BUDGET_FILL / area.max(1.0)).sqrt().min(MAX_TEXEL_DENSITY as f32);
+    loop {
+        let sizes: Vec<(u32, u32)> = cubes().flat_map(|cube| FACES.map(|face| face.patch_size(cube, density))).collect();
+        let (_, width, height) = pack(&sizes);
+        // Below this every patch is one texel and shrinking changes nothing
+        if (width <= side && height <= side) || density * largest < 0.5 {
+            return density;
+        }
+        density *= BUDGET_STEP;
+    }
+}
+
+/// Warns when `texture` came out larger than the `atlas_budget` it was baked for.
+pub fn budget_warning(texture: &Texture, options: &ConvertOptions) -> Option<Message> {
+    let side = options.atlas_budget?.clamp(16, MAX_ATLAS_BUDGET);
+    (texture.width > side || texture.height > side).then(|| {
+        Message::new("warning.atlas_over_budget")
+            .with("budget", side)
+            .with("width", texture.width)
+            .with("height", texture.height)
+    })
+}
+
+/// Bakes the atlas and points every cube face of `bones` at its patch.
+pub fn bake_atlas(bones: &mut [McBone], options: &ConvertOptions, sources: &ColorSources) -> Texture {
+    let (patches, faces) = match options.atlas_budget {
+        Some(side) => bake_patches(bones, options, sources, budget_density(bones, side.clamp(16, MAX_ATLAS_BUDGET))),
+        None => bake_patches(bones, options, sources, options.texel_density.clamp(1, MAX_TEXEL_DENSITY) as f32),
+    };
+    let (corners, width, height) = pack(&patch_sizes(&patches));
+
+    let mut texture = Texture::filled(width, height, [0; 4]);
+    for (patch, corner) in patches.iter().zip(&corners) {
+        for row in 0..patch.height {
+            let start = ((corner[1] + row) * width + corner[0]) as usize;
+            let texels = &patch.texels[(row * patch.width) as usize..((row + 1) * patch.width) as usize];
+            texture.pixels[start..start + patch.width as usize].copy_from_slice(texels);
+        }
+    }
+
+    let cubes = bones.iter_mut().flat_map(|b| &mut b.cubes);
+    for (cube, indices) in cubes.zip(faces) {
+        let [north, east, south, west, up, down] = indices.map(|i| Some(face_uv(corners[i], &patches[i])));
+        cube.uv = McUv::PerFace(McFaceUvs { north, east, south, west, up, down });
+    }
+
+    texture
+}
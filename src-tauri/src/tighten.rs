@@ -0,0 +1,53 @@
+use std::collections::HashSet;
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+use rayon::prelude::*;
+
+use crate::triangle_cells;
+
+// ================= NORMAL OFFSET =================
+//
+// The box-overlap test fills every cell a triangle touches, so surfaces come
+// out up to a voxel fatter on the outside, which shows on small models. With
+// an offset, a touched cell is also sampled against the plane of the triangle
+// at its center: cells whose center lies further out along the normal than
+// the allowed distance are dropped. The distance shrinks from half the cell's
+// extent across the plane (what the overlap test keeps) to half its largest
+// axis, the thinnest layer that still covers every row of cells the surface
+// crosses, so no holes open. The inside is kept as is; a cell touched by
+// several triangles stays if any of them keeps it. Double-sided surfaces are
+// trimmed on both sides.
+
+/// Cells of the mesh like `voxelize_mesh`, trimming `offset` (0-1) of the outward inflation.
+pub fn voxelize_offset(
+    vertices: &[Vec3],
+    indices: &[u32],
+    scale: f32,
+    epsilon: f32,
+    offset: f32,
+    double_sided: bool,
+) -> HashSet<IVec3, RandomState> {
+    let offset = offset.clamp(0.0, 1.0);
+
+    indices.par_chunks(3)
+        .map(|chunk| {
+            let mut local_voxels = Vec::new();
+            let v = [0, 1, 2].map(|k| vertices[chunk[k] as usize]);
+            let normal = (v[1] - v[0]).cross(v[2] - v[0]).normalize_or_zero();
+            // In voxels from the plane
+            let across = normal.abs();
+            let allowed = 0.5 * (across.element_sum() * (1.0 - offset) + across.max_element() * offset);
+
+            triangle_cells(v, scale, epsilon, |cell, local, center| {
+                let distance = (center - local[0]).dot(normal);
+                let distance = if double_sided { distance.abs() } else { distance };
+                if distance <= allowed {
+                    local_voxels.push(cell);
+                }
+            });
+            local_voxels
+        })
+        .flatten()
+        .collect()
+}
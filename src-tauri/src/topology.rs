@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use ahash::RandomState;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::locators;
+use crate::messages::Message;
+
+// ================= MESH TOPOLOGY =================
+//
+// Per-object statistics for analysis. Vertices are welded by exact position
+// (OBJ files split them for UVs and normals), and an object is closed when
+// every edge is shared by exactly two triangles: only closed objects enclose
+// an interior that `fill_interior` can fill.
+//
+// Edges used by a single triangle are the rims of holes. Connected rims are
+// grouped into one hole each, located by the center and extent of its rim
+// vertices, so a leaking fill can be traced to the gap. Edges shared by three
+// or more triangles (internal faces, touching parts) are counted separately.
+
+/// Holes listed per object; the count covers all of them.
+const MAX_LISTED_HOLES: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectStats {
+    pub name: String,
+    pub triangles: usize,
+    /// Surface area in square blocks (OBJ units).
+    pub surface_area: f32,
+    /// Watertight: no holes, no edges shared by more than two triangles.
+    pub closed: bool,
+    pub hole_count: usize,
+    /// The largest holes, largest first.
+    pub holes: Vec<Hole>,
+    /// Edges shared by more than two triangles.
+    pub non_manifold_edges: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hole {
+    /// Edges around the rim.
+    pub edges: usize,
+    /// Center of the rim in model coordinates.
+    pub center: [f32; 3],
+    /// Size of the rim's bounding box.
+    pub extent: [f32; 3],
+}
+
+/// Canonical index of every vertex, shared by vertices at the same position.
+pub fn weld(positions: &[f32]) -> Vec<u32> {
+    let mut welded: HashMap<[u32; 3], u32, RandomState> = HashMap::default();
+    positions.chunks(3)
+        .map(|p| {
+            let len = welded.len() as u32;
+            *welded.entry([p[0].to_bits(), p[1].to_bits(), p[2].to_bits()]).or_insert(len)
+        })
+        .collect()
+}
+
+fn position(positions: &[f32], i: u32) -> Vec3 {
+    let i = i as usize * 3;
+    Vec3::new(positions[i], positions[i + 1], positions[i + 2])
+}
+
+/// Triangles (as welded corners) of `mesh` that aren't collapsed to a line or
+/// point, and the position of every welded vertex.
+fn welded_triangles(mesh: &tobj::Mesh) -> (Vec<[u32; 3]>, Vec<Vec3>) {
+    let canonical = weld(&mesh.positions);
+    let mut points = vec![Vec3::ZERO; canonical.iter().max().map_or(0, |&i| i as usize + 1)];
+    for (i, &c) in canonical.iter().enumerate() {
+        points[c as usize] = position(&mesh.positions, i as u32);
+    }
+
+    let triangles = mesh.indices.chunks(3)
+        .map(|t| [t[0], t[1], t[2]].map(|i| canonical[i as usize]))
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect();
+    (triangles, points)
+}
+
+/// Number of triangles using each undirected edge.
+fn edge_uses(triangles: &[[u32; 3]]) -> HashMap<(u32, u32), usize, RandomState> {
+    let mut uses: HashMap<(u32, u32), usize, RandomState> = HashMap::default();
+    for &[a, b, c] in triangles {
+        for (x, y) in [(a, b), (b, c), (c, a)] {
+            *uses.entry((x.min(y), x.max(y))).or_default() += 1;
+        }
+    }
+    uses
+}
+
+/// Whether every edge of `triangles` (welded corners) is shared by exactly
+/// two of them, so they enclose a volume.
+pub fn is_closed(triangles: &[[u32; 3]]) -> bool {
+    !triangles.is_empty() && edge_uses(triangles).values().all(|&n| n == 2)
+}
+
+fn surface_area(mesh: &tobj::Mesh) -> f32 {
+    mesh.indices.chunks(3)
+        .map(|t| {
+            let [a, b, c] = [t[0], t[1], t[2]].map(|i| position(&mesh.positions, i));
+            (b - a).cross(c - a).length() / 2.0
+        })
+        .sum()
+}
+
+fn find(parents: &mut [u32], mut i: u32) -> u32 {
+    while parents[i as usize] != i {
+        parents[i as usize] = parents[parents[i as usize] as usize];
+        i = parents[i as usize];
+    }
+    i
+}
+
+/// Rims of the holes in the surface, largest first.
+fn holes(edge_uses: &HashMap<(u32, u32), usize, RandomState>, points: &[Vec3]) -> Vec<Hole> {
+    let rims: Vec<(u32, u32)> = edge_uses.iter().filter(|(_, &n)| n == 1).map(|(&e, _)| e).collect();
+
+    let mut parents: Vec<u32> = (0..points.len() as u32).collect();
+    for &(a, b) in &rims {
+        let (a, b) = (find(&mut parents, a), find(&mut parents, b));
+        parents[a as usize] = b;
+    }
+
+    // Rim edges and vertices of each hole, keyed by its root vertex
+    let mut groups: HashMap<u32, (usize, Vec<u32>), RandomState> = HashMap::default();
+    for &(a, b) in &rims {
+        let group = groups.entry(find(&mut parents, a)).or_default();
+        group.0 += 1;
+        group.1.extend([a, b]);
+    }
+
+    let mut holes: Vec<Hole> = groups.into_values()
+        .map(|(edges, mut vertices)| {
+            vertices.sort_unstable();
+            vertices.dedup();
+            let (min, max) = vertices.iter()
+                .map(|&v| points[v as usize])
+                .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), p| (min.min(p), max.max(p)));
+            Hole { edges, center: ((min + max) / 2.0).to_array(), extent: (max - min).to_array() }
+        })
+        .collect();
+    holes.sort_by(|a, b| b.edges.cmp(&a.edges).then(a.center.partial_cmp(&b.center).unwrap_or(std::cmp::Ordering::Equal)));
+    holes
+}
+
+/// Stats of every object with triangles, locator markers left out.
+pub fn object_stats(models: &[tobj::Model]) -> Vec<ObjectStats> {
+    models.iter()
+        .filter(|m| !m.mesh.indices.is_empty() && !locators::is_marker(&m.name))
+        .map(|model| {
+            let (triangles, points) = welded_triangles(&model.mesh);
+            let uses = edge_uses(&triangles);
+            let mut holes = holes(&uses, &points);
+            let hole_count = holes.len();
+            holes.truncate(MAX_LISTED_HOLES);
+            let non_manifold_edges = uses.values().filter(|&&n| n > 2).count();
+
+            ObjectStats {
+                name: model.name.clone(),
+                triangles: model.mesh.indices.len() / 3,
+                surface_area: surface_area(&model.mesh),
+                closed: !triangles.is_empty() && hole_count == 0 && non_manifold_edges == 0,
+                hole_count,
+                holes,
+                non_manifold_edges,
+            }
+        })
+        .collect()
+}
+
+/// Warnings for objects with holes, which `fill_interior` may leak out of.
+pub fn fill_warnings(objects: &[ObjectStats]) -> Vec<Message> {
+    objects.iter()
+        .filter_map(|object| {
+            let hole = object.holes.first()?;
+            let [x, y, z] = hole.center.map(|c| (c * 100.0).round() / 100.0);
+            Some(Message::new("warning.fill_leaks")
+                .with("object", &object.name)
+                .with("holes", object.hole_count)
+                .with("x", x)
+                .with("y", y)
+                .with("z", z))
+        })
+        .collect()
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::messages::{failed, Message};
+
+// ================= GEOMETRY VALIDATION =================
+//
+// Every geo.json is checked against a bundled schema of the Bedrock geometry
+// format (1.12.0) before it is written, so an exporter bug fails the
+// conversion with a JSON pointer to the bad field instead of a model the game
+// silently refuses to load. NaN and infinite coordinates turn into nulls and
+// fail the number checks. The validator knows the schema keywords the schema
+// uses: type, properties, required, additionalProperties, items, minItems,
+// maxItems, minimum, minLength and oneOf. Bone names must also be unique
+// (ignoring case, as the game does), parents must name a bone of the same
+// geometry without forming a loop and poly mesh corners must name positions
+// that exist, which a schema can't say.
+//
+// The schema only rejects what the game refuses to load; `inspect` checks
+// existing files with it and adds warnings for loadable oddities, such as a
+// missing texture size, negative sizes or UVs outside the texture.
+
+fn typed(kind: &str) -> Value {
+    json!({ "type": kind })
+}
+
+fn numbers(count: usize) -> Value {
+    json!({ "type": "array", "items": typed("number"), "minItems": count, "maxItems": count })
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({ "type": "object", "properties": properties, "required": required, "additionalProperties": false })
+}
+
+fn geometry_schema() -> Value {
+    let face = object(json!({ "uv": numbers(2), "uv_size": numbers(2), "material_instance": typed("string") }), &["uv"]);
+    let faces = ["north", "east", "south", "west", "up", "down"].map(|name| (name.to_string(), face.clone()));
+    let per_face = json!({ "type": "object", "properties": serde_json::Map::from_iter(faces) });
+
+    let cube = object(
+        json!({
+            "origin": numbers(3),
+            "size": numbers(3),
+            "uv": { "oneOf": [numbers(2), per_face] },
+            "pivot": numbers(3),
+            "rotation": numbers(3),
+            "inflate": typed("number"),
+            "mirror": typed("boolean"),
+        }),
+        &["origin", "size", "uv"],
+    );
+    let poly_mesh = object(
+        json!({
+            "normalized_uvs": typed("boolean"),
+            "positions": { "type": "array", "items": numbers(3) },
+            "normals": { "type": "array", "items": numbers(3) },
+            "uvs": { "type": "array", "items": numbers(2) },
+            "polys": {
+                "type": "array",
+                "items": {
+                    "type": "array",
+                    "items": { "type": "array", "items": { "type": "integer", "minimum": 0 }, "minItems": 3, "maxItems": 3 },
+                    "minItems": 3,
+                    "maxItems": 4,
+                },
+            },
+        }),
+        &["positions", "polys"],
+    );
+    let bone = object(
+        json!({
+            "name": { "type": "string", "minLength": 1 },
+            "parent": { "type": "string", "minLength": 1 },
+            "pivot": numbers(3),
+            "rotation": numbers(3),
+            "binding": typed("string"),
+            "mirror": typed("boolean"),
+            "inflate": typed("number"),
+            "cubes": { "type": "array", "items": cube },
+            "poly_mesh": poly_mesh,
+            "locators": { "type": "object", "additionalProperties": numbers(3) },
+        }),
+        &["name", "pivot"],
+    );
+    let description = object(
+        json!({
+            "identifier": { "type": "string", "minLength": 1 },
+            "texture_width": { "type": "integer", "minimum": 1 },
+            "texture_height": { "type": "integer", "minimum": 1 },
+            "visible_bounds_width": typed("number"),
+            "visible_bounds_height": typed("number"),
+            "visible_bounds_offset": numbers(3),
+        }),
+        &["identifier"],
+    );
+    let geometry = object(
+        json!({ "description": description, "bones": { "type": "array", "items": bone } }),
+        &["description"],
+    );
+    object(
+        json!({
+            "format_version": { "type": "string", "minLength": 1 },
+            "minecraft:geometry": { "type": "array", "items": geometry, "minItems": 1 },
+        }),
+        &["format_version", "minecraft:geometry"],
+    )
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// JSON pointer segment, escaped.
+fn segment(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// First place `value` breaks `schema`, as a pointer and what is wrong.
+fn check(value: &Value, schema: &Value, pointer: &str) -> Result<(), (String, String)> {
+    let fail = |problem: String| Err((pointer.to_string(), problem));
+
+    if let Some(expected) = schema["type"].as_str() {
+        let found = kind(value);
+        if found != expected && !(expected == "number" && found == "integer") {
+            return fail(format!("expected {}, found {}", expected, found));
+        }
+    }
+    if let Some(options) = schema["oneOf"].as_array() {
+        let matching = options.iter().filter(|s| check(value, s, pointer).is_ok()).count();
+        if matching != 1 {
+            return fail(format!("matches {} of {} allowed shapes", matching, options.len()));
+        }
+    }
+    if let (Some(minimum), Some(n)) = (schema["minimum"].as_f64(), value.as_f64()) {
+        if n < minimum {
+            return fail(format!("{} is below the minimum {}", n, minimum));
+        }
+    }
+    if let (Some(min), Some(s)) = (schema["minLength"].as_u64(), value.as_str()) {
+        if (s.chars().count() as u64) < min {
+            return fail(format!("shorter than {} characters", min));
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        if schema["minItems"].as_u64().is_some_and(|min| (items.len() as u64) < min) {
+            return fail(format!("{} items, fewer than {}", items.len(), schema["minItems"]));
+        }
+        if schema["maxItems"].as_u64().is_some_and(|max| items.len() as u64 > max) {
+            return fail(format!("{} items, more than {}", items.len(), schema["maxItems"]));
+        }
+        if schema.get("items").is_some() {
+            for (i, item) in items.iter().enumerate() {
+                check(item, &schema["items"], &segment(pointer, &i.to_string()))?;
+            }
+        }
+    }
+
+    if let Some(fields) = value.as_object() {
+        for name in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+            if !fields.contains_key(name) {
+                return fail(format!("missing required field '{}'", name));
+            }
+        }
+        for (name, field) in fields {
+            match (schema["properties"].get(name), &schema["additionalProperties"]) {
+                (Some(property), _) => check(field, property, &segment(pointer, name))?,
+                (None, Value::Bool(false)) => return fail(format!("unknown field '{}'", name)),
+                (None, extra @ Value::Object(_)) => check(field, extra, &segment(pointer, name))?,
+                (None, _) => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bone names repeated, parents missing or looping and poly mesh corners
+/// out of range within each geometry.
+fn check_bones(root: &Value) -> Result<(), (String, String)> {
+    for (g, geometry) in root["minecraft:geometry"].as_array().into_iter().flatten().enumerate() {
+        let bones = geometry["bones"].as_array().map(Vec::as_slice).unwrap_or_default();
+        let at = |b: usize, field: &str| format!("/minecraft:geometry/{}/bones/{}/{}", g, b, field);
+        let name = |bone: &Value| bone["name"].as_str().unwrap_or_default().to_lowercase();
+        let mut parents = HashMap::new();
+        for (b, bone) in bones.iter().enumerate() {
+            let parent = bone["parent"].as_str().map(str::to_lowercase);
+            if parents.insert(name(bone), parent).is_some() {
+                return Err((at(b, "name"), format!("bone '{}' appears twice", name(bone))));
+            }
+        }
+        for (b, bone) in bones.iter().enumerate() {
+            // Walk up the chain; coming back to the bone means a loop
+            let mut current = name(bone);
+            for _ in 0..=bones.len() {
+                match &parents[&current] {
+                    None => break,
+                    Some(parent) if !parents.contains_key(parent) => {
+                        return Err((at(b, "parent"), format!("no bone named '{}'", parent)));
+                    }
+                    Some(parent) if *parent == name(bone) => {
+                        return Err((at(b, "parent"), "bone hierarchy has a cycle".to_string()));
+                    }
+                    Some(parent) => current = parent.clone(),
+                }
+            }
+        }
+        for (b, bone) in bones.iter().enumerate() {
+            let positions = bone["poly_mesh"]["positions"].as_array().map_or(0, Vec::len);
+            let corners = bone["poly_mesh"]["polys"].as_array().into_iter().flatten().filter_map(Value::as_array).flatten();
+            if corners.filter_map(|corner| corner[0].as_u64()).any(|i| i as usize >= positions) {
+                return Err((at(b, "poly_mesh/polys"), "a corner names a position that doesn't exist".to_string()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// First place `root`, a geo.json as JSON, breaks the format, as a JSON
+/// pointer and what is wrong there.
+pub fn check_json(root: &Value) -> Result<(), (String, String)> {
+    check(root, &geometry_schema(), "").and_then(|()| check_bones(root))
+}
+
+/// Fails when `geometry` isn't a valid Bedrock geo.json.
+pub fn check_geometry(geometry: &impl Serialize) -> Result<(), Message> {
+    let value = serde_json::to_value(geometry).map_err(failed("error.write_json"))?;
+    check_json(&value)
+        .map_err(|(pointer, problem)| Message::new("error.invalid_geometry").with("pointer", pointer).with("problem", problem))
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+use ahash::RandomState;
+use glam::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::{cell_noise, rasterize};
+use crate::{run_greedy_meshing, ConvertOptions, McBone, McCube};
+
+// ================= GEOMETRY VARIANTS =================
+//
+// Extra geometries written into the same geo.json for entities that swap
+// them through render controllers, such as a damaged version of a model. A
+// variant is the converted model with erosion applied: the surface is
+// divided into chunks of cells and a share of the chunks is eaten away a few
+// layers deep, the same chunks for the same seed. A variant can also be
+// mirrored across the YZ plane, for left and right versions of vehicle and
+// building parts. Cubes keep their colors and texture sources; the atlas
+// covers every variant.
+
+const CHUNK: i32 = 4;
+const DEPTH: usize = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    /// Added to the identifier: `geometry.<model>.<name>`.
+    pub name: String,
+    /// Share (0-1) of the surface eaten away.
+    #[serde(default)]
+    pub erosion: f32,
+    #[serde(default)]
+    pub seed: u32,
+    /// Mirror along X.
+    #[serde(default)]
+    pub mirror: bool,
+}
+
+/// Name of the variant `mirrored_copy` adds.
+const MIRRORED: &str = "mirrored";
+
+const NEIGHBORS: [IVec3; 6] = [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z];
+
+/// Cells of `cells` removed by `variant`.
+fn eroded_cells(cells: &HashSet<IVec3, RandomState>, variant: &Variant) -> HashSet<IVec3, RandomState> {
+    let erosion = variant.erosion.clamp(0.0, 1.0);
+    let mut removed = HashSet::default();
+    if erosion == 0.0 {
+        return removed;
+    }
+
+    for _ in 0..DEPTH {
+        let exposed: Vec<IVec3> = cells.iter()
+            .filter(|&&c| !removed.contains(&c))
+            .filter(|&&c| NEIGHBORS.iter().any(|&n| !cells.contains(&(c + n)) || removed.contains(&(c + n))))
+            .filter(|&&c| cell_noise(c.div_euclid(IVec3::splat(CHUNK)), variant.seed) < erosion)
+            .copied()
+            .collect();
+        removed.extend(exposed);
+    }
+    removed
+}
+
+/// `cube` without the `removed` cells.
+fn erode_cube(cube: &McCube, removed: &HashSet<IVec3, RandomState>) -> Vec<McCube> {
+    let min = Vec3::from(cube.origin);
+    if cube.rotation.is_some() {
+        let center = (min + Vec3::from(cube.size) / 2.0).floor().as_ivec3();
+        return if removed.contains(&center) { vec![] } else { vec![cube.clone()] };
+    }
+
+    let single = McBone {
+        name: String::new(),
+        parent: None,
+        pivot: [0, 0, 0],
+        binding: None,
+        cubes: vec![cube.clone()],
+        poly_mesh: None,
+        locators: Default::default(),
+    };
+    let cells = rasterize(&[single]);
+    if !cells.iter().any(|c| removed.contains(c)) {
+        return vec![cube.clone()];
+    }
+    let kept: HashSet<IVec3, RandomState> = cells.into_iter().filter(|c| !removed.contains(c)).collect();
+    run_greedy_meshing(&kept).into_iter()
+        .map(|c| McCube { origin: c.origin, size: c.size, ..cube.clone() })
+        .collect()
+}
+
+fn mirror(bone: &mut McBone) {
+    // Subtracting keeps 0 from turning into -0 in the JSON
+    let flip = |p: [f32; 3]| [0.0 - p[0], p[1], p[2]];
+    bone.pivot[0] = -bone.pivot[0];
+    for cube in &mut bone.cubes {
+        cube.origin[0] = 0.0 - (cube.origin[0] + cube.size[0]);
+        cube.pivot = cube.pivot.map(flip);
+        // Reflecting turns rotations about Y and Z the other way
+        cube.rotation = cube.rotation.map(|[x, y, z]| [x, -y, -z]);
+        if let Some(surface) = &mut cube.surface {
+            surface.rescale(Vec3::new(-1.0, 1.0, 1.0));
+        }
+    }
+    if let Some(mesh) = &mut bone.poly_mesh {
+        mesh.mirror_x();
+    }
+    for position in bone.locators.values_mut() {
+        *position = flip(*position);
+    }
+}
+
+/// The bones of each variant in `options`, converted from the voxelized `bones`.
+pub fn build_variants(bones: &[McBone], options: &ConvertOptions) -> Vec<(String, Vec<McBone>)> {
+    let mirrored = options.mirrored_copy.then(|| Variant { name: MIRRORED.to_string(), erosion: 0.0, seed: 0, mirror: true });
+    options.variants.iter().chain(&mirrored)
+        .map(|variant| {
+            let bones = bones.iter()
+                .map(|bone| {
+                    let removed = eroded_cells(&rasterize(std::slice::from_ref(bone)), variant);
+                    let cubes = bone.cubes.iter().flat_map(|c| erode_cube(c, &removed)).collect();
+                    let mut bone = McBone { cubes, ..bone.clone() };
+                    if variant.mirror {
+                        mirror(&mut bone);
+                    }
+                    bone
+                })
+                .collect();
+            (variant.name.clone(), bones)
+        })
+        .collect()
+}
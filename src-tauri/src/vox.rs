@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use ahash::RandomState;
+use glam::{IVec3, Mat3, Vec3};
+
+use crate::messages::{failed, Message};
+use crate::{run_greedy_meshing, McCube};
+
+// ================= MAGICAVOXEL IMPORT =================
+//
+// A `.vox` file is already voxels, so nothing is voxelized: each shape's
+// cells go straight to greedy meshing, once per palette color so every cube
+// keeps one color for the atlas. One vox voxel is one cell whatever the
+// scale, and steps that work on triangles or the voxel grid (fill, scripts,
+// detail regions, slopes) don't apply. Shapes placed by the scene graph
+// become bones named after their node, in their place; MagicaVoxel is Z-up
+// and turned Y-up as it loads.
+
+/// A model of the file in its place, as cells and their RGBA colors.
+pub struct VoxShape {
+    pub name: String,
+    pub cells: HashMap<IVec3, [u8; 4], RandomState>,
+}
+
+pub fn is_vox(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("vox"))
+}
+
+fn error(reason: impl Into<String>) -> Message {
+    Message::new("error.load_vox").with("error", reason.into())
+}
+
+struct Chunk<'a> {
+    id: &'a [u8],
+    content: &'a [u8],
+    children: &'a [u8],
+}
+
+/// The chunks in `bytes`, one after the other.
+fn chunks(mut bytes: &[u8]) -> Result<Vec<Chunk<'_>>, Message> {
+    let mut chunks = Vec::new();
+    while bytes.len() >= 12 {
+        let size = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        let (content, children) = (size(4), size(8));
+        let end = 12 + content + children;
+        if bytes.len() < end {
+            return Err(error("chunk runs past the end of the file"));
+        }
+        chunks.push(Chunk { id: &bytes[..4], content: &bytes[12..12 + content], children: &bytes[12 + content..end] });
+        bytes = &bytes[end..];
+    }
+    Ok(chunks)
+}
+
+/// Reads the little-endian integers, strings and dictionaries of chunk contents.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn u32(&mut self) -> Result<u32, Message> {
+        let (head, rest) = self.0.split_at_checked(4).ok_or_else(|| error("chunk ends early"))?;
+        self.0 = rest;
+        Ok(u32::from_le_bytes(head.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, Message> {
+        let len = self.u32()? as usize;
+        let (head, rest) = self.0.split_at_checked(len).ok_or_else(|| error("chunk ends early"))?;
+        self.0 = rest;
+        Ok(String::from_utf8_lossy(head).to_string())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, Message> {
+        (0..self.u32()?).map(|_| Ok((self.string()?, self.string()?))).collect()
+    }
+}
+
+/// Scene graph node, by id.
+enum Node {
+    Transform { name: Option<String>, child: u32, rotation: Mat3, translation: Vec3 },
+    Group(Vec<u32>),
+    Shape(Vec<u32>),
+}
+
+pub fn load_vox(path: &str) -> Result<Vec<VoxShape>, Message> {
+    let bytes = fs::read(path).map_err(failed("error.load_vox"))?;
+    if bytes.get(..4) != Some(b"VOX ") {
+        return Err(error("not a MagicaVoxel file"));
+    }
+    let main = chunks(bytes.get(8..).unwrap_or_default())?;
+    let main = main.iter().find(|c| c.id == b"MAIN").ok_or_else(|| error("no MAIN chunk"))?;
+
+    let mut sizes: Vec<IVec3> = Vec::new();
+    let mut models: Vec<Vec<(IVec3, u8)>> = Vec::new();
+    let mut palette = default_palette();
+    let mut nodes: HashMap<u32, Node> = HashMap::new();
+    for chunk in chunks(main.children)? {
+        let mut r = Reader(chunk.content);
+        match chunk.id {
+            b"SIZE" => sizes.push(IVec3::new(r.u32()? as i32, r.u32()? as i32, r.u32()? as i32)),
+            b"XYZI" => {
+                let count = r.u32()? as usize;
+                let data = r.0.get(..count * 4).ok_or_else(|| error("chunk ends early"))?;
+                models.push(data.chunks_exact(4).map(|v| (IVec3::new(v[0] as i32, v[1] as i32, v[2] as i32), v[3])).collect());
+            }
+            b"RGBA" => {
+                // Entry i holds color index i + 1
+                for i in 0..255 {
+                    let c = r.0.get(i * 4..i * 4 + 4).ok_or_else(|| error("chunk ends early"))?;
+                    palette[i + 1] = [c[0], c[1], c[2], c[3]];
+                }
+            }
+            b"nTRN" => {
+                let id = r.u32()?;
+                let attributes = r.dict()?;
+                let child = r.u32()?;
+                r.u32()?; // reserved
+                r.u32()?; // layer
+                let frames = r.u32()?;
+                let frame = if frames > 0 { r.dict()? } else { HashMap::new() };
+                let translation = frame.get("_t").map_or(Vec3::ZERO, |t| {
+                    let values: Vec<f32> = t.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+                    Vec3::from_slice(&[values.as_slice(), &[0.0; 3]].concat()[..3])
+                });
+                let rotation = frame.get("_r").and_then(|r| r.parse().ok()).map_or(Mat3::IDENTITY, rotation);
+                nodes.insert(id, Node::Transform { name: attributes.get("_name").cloned(), child, rotation, translation });
+            }
+            b"nGRP" => {
+                let id = r.u32()?;
+                r.dict()?;
+                let children = (0..r.u32()?).map(|_| r.u32()).collect::<Result<_, _>>()?;
+                nodes.insert(id, Node::Group(children));
+            }
+            b"nSHP" => {
+                let id = r.u32()?;
+                r.dict()?;
+                let count = r.u32()?;
+                let mut shapes = Vec::new();
+                for _ in 0..count {
+                    shapes.push(r.u32()?);
+                    r.dict()?;
+                }
+                nodes.insert(id, Node::Shape(shapes));
+            }
+            _ => {}
+        }
+    }
+    if models.len() != sizes.len() {
+        return Err(error("SIZE and XYZI chunks don't pair up"));
+    }
+
+    // Without a scene graph (old files) every model stands at the origin
+    let mut placed = Vec::new();
+    if nodes.contains_key(&0) {
+        place(&nodes, 0, Mat3::IDENTITY, Vec3::ZERO, None, &mut placed, 0)?;
+    } else {
+        placed = (0..models.len() as u32).map(|m| (m, Mat3::IDENTITY, Vec3::ZERO, None)).collect();
+    }
+
+    let stem = Path::new(path).file_stem().map_or("model".to_string(), |s| s.to_string_lossy().to_string());
+    let mut used: HashSet<String> = HashSet::new();
+    let mut shapes = Vec::new();
+    for (i, (model, rotation, translation, name)) in placed.into_iter().enumerate() {
+        let voxels = models.get(model as usize).ok_or_else(|| error("shape names a missing model"))?;
+        // MagicaVoxel centers each model on its node, rounding down; cells
+        // turn about their centers, so a rotated cell lands on whole cells
+        let half = sizes[model as usize] / 2;
+        let cells = voxels.iter()
+            .map(|&(p, index)| {
+                let center = (p - half).as_vec3() + Vec3::splat(0.5);
+                let [x, y, z] = (rotation * center + translation).floor().as_ivec3().to_array();
+                // Cell y spans y to y + 1, so it becomes -y - 1 to -y
+                (IVec3::new(x, z, -y - 1), palette[index as usize])
+            })
+            .collect();
+
+        let base = name.unwrap_or_else(|| if i == 0 { stem.clone() } else { format!("{}_{}", stem, i) });
+        let mut name = base.clone();
+        let mut n = 1;
+        while !used.insert(name.clone()) {
+            n += 1;
+            name = format!("{}_{}", base, n);
+        }
+        shapes.push(VoxShape { name, cells });
+    }
+    Ok(shapes)
+}
+
+/// The models under `node` with their accumulated rotation and translation
+/// and the name of their nearest named transform.
+fn place(
+    nodes: &HashMap<u32, Node>,
+    node: u32,
+    rotation: Mat3,
+    translation: Vec3,
+    name: Option<&str>,
+    placed: &mut Vec<(u32, Mat3, Vec3, Option<String>)>,
+    depth: usize,
+) -> Result<(), Message> {
+    // Scene graphs are trees; a cycle would never end
+    if depth > 64 {
+        return Err(error("scene graph too deep"));
+    }
+    match nodes.get(&node) {
+        Some(Node::Transform { name: own, child, rotation: r, translation: t }) => {
+            let name = own.as_deref().or(name);
+            place(nodes, *child, rotation * *r, rotation * *t + translation, name, placed, depth + 1)
+        }
+        Some(Node::Group(children)) => {
+            children.iter().try_for_each(|&c| place(nodes, c, rotation, translation, name, placed, depth + 1))
+        }
+        Some(Node::Shape(models)) => {
+            placed.extend(models.iter().map(|&m| (m, rotation, translation, name.map(str::to_string))));
+            Ok(())
+        }
+        None => Err(error(format!("missing scene node {}", node))),
+    }
+}
+
+/// Rotation packed in a byte: the column of the non-zero entry in rows 0 and
+/// 1 (2 bits each), then the sign of each row.
+fn rotation(packed: u8) -> Mat3 {
+    let first = (packed & 3) as usize;
+    let second = ((packed >> 2) & 3) as usize;
+    let third = 3usize.saturating_sub(first + second).min(2);
+    let mut rows = [[0.0; 3]; 3];
+    for (row, column) in [first, second, third].into_iter().enumerate() {
+        rows[row][column.min(2)] = if packed >> (4 + row) & 1 == 1 { -1.0 } else { 1.0 };
+    }
+    Mat3::from_cols_array_2d(&rows).transpose()
+}
+
+/// Palette of files without an RGBA chunk, by color index.
+fn default_palette() -> [[u8; 4]; 256] {
+    let mut palette = [[0; 4]; 256];
+    const STEPS: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    let mut i = 1;
+    for r in STEPS {
+        for g in STEPS {
+            for b in STEPS {
+                if i < 216 {
+                    palette[i] = [r, g, b, 0xff];
+                    i += 1;
+                }
+            }
+        }
+    }
+    // Ramps of blue, green and red, as the file's ABGR table lists them
+    for channel in [2, 1, 0] {
+        for v in RAMP {
+            let mut color = [0, 0, 0, 0xff];
+            color[channel] = v;
+            palette[i] = color;
+            i += 1;
+        }
+    }
+    for v in RAMP {
+        palette[i] = [v, v, v, 0xff];
+        i += 1;
+    }
+    palette
+}
+
+/// Cubes of `shape`, meshed per color.
+pub fn mesh_shape(shape: &VoxShape) -> Vec<McCube> {
+    let mut by_color: HashMap<[u8; 4], HashSet<IVec3, RandomState>> = HashMap::new();
+    for (&cell, &color) in &shape.cells {
+        by_color.entry(color).or_default().insert(cell);
+    }
+    let mut colors: Vec<_> = by_color.into_iter().collect();
+    // Same cube order every run
+    colors.sort_unstable_by_key(|(color, _)| *color);
+    colors.into_iter()
+        .flat_map(|(color, cells)| {
+            run_greedy_meshing(&cells).into_iter().map(move |mut cube| {
+                cube.color = Some(color);
+                cube
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,25 @@
+use std::collections::HashSet;
+
+use ahash::RandomState;
+use glam::IVec3;
+
+// ================= GAP WELDING =================
+//
+// Thin double-walled meshes and near-touching parts voxelize with hairline
+// cracks: rows of empty cells one cell wide between filled ones, which show
+// as seams and let the interior fill leak out. An empty cell with filled
+// cells on both sides along an axis is filled. Only such one-cell gaps
+// close; wider openings and the outline of the model stay as they are, unlike
+// a dilation that grows every surface.
+
+const AXES: [IVec3; 3] = [IVec3::X, IVec3::Y, IVec3::Z];
+
+/// Fills the empty cells of `voxels` between two filled ones.
+pub fn weld_gaps(voxels: &mut HashSet<IVec3, RandomState>) {
+    let gaps: HashSet<IVec3, RandomState> = voxels.iter()
+        .flat_map(|&cell| AXES.map(|axis| (cell, axis)))
+        .filter(|&(cell, axis)| !voxels.contains(&(cell + axis)) && voxels.contains(&(cell + axis * 2)))
+        .map(|(cell, axis)| cell + axis)
+        .collect();
+    voxels.extend(gaps);
+}
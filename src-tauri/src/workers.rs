@@ -0,0 +1,115 @@
+use crate::messages::{failed, Message};
+use crate::ConvertOptions;
+
+// ================= WORKER THREADS =================
+//
+// Conversions spread over every core by default, which leaves little for
+// Blender or a game running alongside. `threads` caps the worker threads and
+// `low_priority` runs them at background priority, so the OS hands the cores
+// to interactive programs first. Either runs the conversion in a pool of its
+// own; with neither, rayon's shared pool is used as before.
+//
+// Only the pool's threads are lowered, each as it starts, and put back as it
+// exits; the app's own threads keep their priority. Platforms without a
+// per-thread priority (Unix other than Linux and macOS) run the pool at
+// normal priority.
+
+#[cfg(target_os = "linux")]
+mod priority {
+    use std::cell::Cell;
+
+    /// Niceness added to low-priority workers (19 is the lowest priority).
+    const NICENESS: i32 = 10;
+
+    thread_local! {
+        static PREVIOUS: Cell<Option<i32>> = const { Cell::new(None) };
+    }
+
+    /// Lowers the calling thread's priority; nice is per thread on Linux.
+    pub fn lower() {
+        unsafe {
+            let thread = libc::gettid() as libc::id_t;
+            let previous = libc::getpriority(libc::PRIO_PROCESS, thread);
+            if libc::setpriority(libc::PRIO_PROCESS, thread, (previous + NICENESS).min(19)) == 0 {
+                PREVIOUS.set(Some(previous));
+            }
+        }
+    }
+
+    /// Puts back the priority `lower` replaced, as far as the system allows.
+    pub fn restore() {
+        if let Some(previous) = PREVIOUS.take() {
+            unsafe {
+                libc::setpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t, previous);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod priority {
+    /// Background priority for the calling thread's CPU and disk use.
+    pub fn lower() {
+        unsafe {
+            libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, libc::PRIO_DARWIN_BG);
+        }
+    }
+
+    pub fn restore() {
+        unsafe {
+            libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, 0);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod priority {
+    extern "system" {
+        fn GetCurrentThread() -> isize;
+        fn SetThreadPriority(thread: isize, priority: i32) -> i32;
+    }
+    /// Lowers CPU, disk and memory priority of the thread, and ends it.
+    const THREAD_MODE_BACKGROUND_BEGIN: i32 = 0x0001_0000;
+    const THREAD_MODE_BACKGROUND_END: i32 = 0x0002_0000;
+
+    pub fn lower() {
+        unsafe {
+            SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN);
+        }
+    }
+
+    pub fn restore() {
+        unsafe {
+            SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_END);
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod priority {
+    pub fn lower() {}
+    pub fn restore() {}
+}
+
+/// Runs `work` on the worker threads `options` ask for.
+pub fn with_workers<T: Send>(options: &ConvertOptions, work: impl FnOnce() -> T + Send) -> Result<T, Message> {
+    if options.threads == 0 && !options.low_priority {
+        return Ok(work());
+    }
+    let low_priority = options.low_priority;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads)
+        .start_handler(move |_| {
+            if low_priority {
+                priority::lower();
+            }
+        })
+        .exit_handler(move |_| {
+            if low_priority {
+                priority::restore();
+            }
+        })
+        .build()
+        .map_err(failed("error.worker_threads"))?;
+    Ok(pool.install(work))
+}